@@ -0,0 +1,63 @@
+use std::{fmt::Write, hint::black_box};
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use oxc_toml::{Formatter, Options, format};
+
+/// A lockfile with many arrays of tables, like the large generated documents.
+fn lockfile(packages: usize) -> String {
+    let mut source = String::from("# This file is generated.\nversion = 4\n");
+    for idx in 0..packages {
+        let _ = write!(
+            source,
+            "\n[[package]]\nname=\"package-{idx}\"\nversion = \"1.{}.{}\"\n\
+             source = \"registry+https://github.com/rust-lang/crates.io-index\"\n\
+             checksum = \"{idx:064x}\"\ndependencies = [\n \"package-{}\",\n \"package-{}\",\n]\n",
+            idx % 10,
+            idx % 7,
+            idx / 2,
+            idx / 3,
+        );
+    }
+    source
+}
+
+/// A configuration with comments, inline tables and nested arrays.
+fn config(sections: usize) -> String {
+    let mut source = String::new();
+    for idx in 0..sections {
+        let _ = write!(
+            source,
+            "# Section {idx}\n[section-{idx}]\nenabled=true # on\nlevel = {idx}\n\
+             ratio = 0.5\nname = 'section {idx}'\n\
+             point = {{x = {idx}, y = -{idx},   label = \"p\"}}\n\
+             matrix = [[1,2,3],[4,5,6], [7, 8, 9]]\ntags = [\"a\",\"b\", # b\n  \"c\"]\n\
+             long = [\"{}\", \"{}\", \"{}\"]\n\n",
+            "x".repeat(20),
+            "y".repeat(30),
+            "z".repeat(40),
+        );
+    }
+    source
+}
+
+fn bench_format(c: &mut Criterion) {
+    let documents = [("lockfile", lockfile(2000)), ("config", config(1000))];
+    let aligned = Options::builder().align_entries(true).align_comments(true).build().unwrap();
+
+    let mut group = c.benchmark_group("format");
+    for (name, source) in &documents {
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_function(*name, |b| b.iter(|| format(black_box(source), Options::default())));
+        group.bench_function(format!("{name}/aligned"), |b| {
+            b.iter(|| format(black_box(source), aligned.clone()));
+        });
+        group.bench_function(format!("{name}/reused"), |b| {
+            let mut formatter = Formatter::new(Options::default());
+            b.iter(|| formatter.format(black_box(source)).len());
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_format);
+criterion_main!(benches);
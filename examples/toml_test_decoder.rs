@@ -0,0 +1,34 @@
+//! A decoder for the toml-test suite.
+//!
+//! It reads a TOML document from stdin and writes its value in the tagged JSON encoding
+//! of toml-test to stdout, or writes the errors to stderr and exits with 1 if it is invalid.
+//!
+//! Usage:
+//!   cargo build --example toml_test_decoder
+//!   toml-test target/debug/examples/toml_test_decoder
+
+use std::io::Read;
+use std::process::ExitCode;
+
+use oxc_toml::{ParseOptions, parse_with};
+
+fn main() -> ExitCode {
+    let mut source = String::new();
+    if let Err(error) = std::io::stdin().read_to_string(&mut source) {
+        eprintln!("failed to read stdin: {error}");
+        return ExitCode::FAILURE;
+    }
+
+    let mut options = ParseOptions::default();
+    options.validate = true;
+    let parse = parse_with(&source, options);
+    if !parse.errors.is_empty() {
+        for error in &parse.errors {
+            eprintln!("{error}");
+        }
+        return ExitCode::FAILURE;
+    }
+
+    println!("{}", parse.into_syntax().to_value().to_tagged_json());
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,33 @@
+//! An encoder for the toml-test suite.
+//!
+//! It reads a value in the tagged JSON encoding of toml-test from stdin
+//! and writes it as a TOML document to stdout, or writes the error to stderr
+//! and exits with 1 if the JSON is invalid.
+//!
+//! Usage:
+//!   cargo build --example toml_test_encoder
+//!   toml-test -encoder target/debug/examples/toml_test_encoder
+
+use std::io::Read;
+use std::process::ExitCode;
+
+use oxc_toml::{Options, SyntaxTree, Value};
+
+fn main() -> ExitCode {
+    let mut json = String::new();
+    if let Err(error) = std::io::stdin().read_to_string(&mut json) {
+        eprintln!("failed to read stdin: {error}");
+        return ExitCode::FAILURE;
+    }
+
+    match Value::from_tagged_json(&json) {
+        Ok(value) => {
+            print!("{}", SyntaxTree::from_value(&value, Options::default()).source);
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
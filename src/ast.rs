@@ -0,0 +1,236 @@
+//! Typed views of the nodes of a syntax tree, see [`Root::cast`].
+//!
+//! The views borrow the tree, their text is read from the source like the text of the nodes.
+
+use crate::{
+    syntax::{SyntaxKind::*, SyntaxNode, SyntaxToken},
+    tree::{Element, TextRange},
+    util::unescape,
+};
+
+/// A typed view of a syntax node of one kind.
+pub trait AstNode<'a>: Sized {
+    /// The view of the node, `None` if it has another kind.
+    fn cast(node: &'a SyntaxNode) -> Option<Self>;
+
+    /// The viewed node.
+    fn syntax(&self) -> &'a SyntaxNode;
+
+    /// The view of the element, `None` if it is a token or a node of another kind.
+    fn cast_element(element: &'a Element) -> Option<Self> {
+        element.as_node().and_then(Self::cast)
+    }
+
+    /// The span of the node.
+    fn span(&self) -> TextRange {
+        self.syntax().span.clone()
+    }
+
+    /// The text of the node.
+    fn text<'s>(&self, source: &'s str) -> &'s str {
+        self.syntax().text(source)
+    }
+}
+
+macro_rules! ast_nodes {
+    ($($(#[$doc:meta])* $name:ident: $kind:ident,)*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Debug, Clone, Copy)]
+            pub struct $name<'a>(&'a SyntaxNode);
+
+            impl<'a> AstNode<'a> for $name<'a> {
+                fn cast(node: &'a SyntaxNode) -> Option<Self> {
+                    (node.kind() == $kind).then_some(Self(node))
+                }
+
+                fn syntax(&self) -> &'a SyntaxNode {
+                    self.0
+                }
+            }
+        )*
+    };
+}
+
+ast_nodes! {
+    /// The whole document.
+    Root: ROOT,
+
+    /// A table header, e.g. `[table]`.
+    ///
+    /// The entries of the table follow the header in the [`Root`].
+    Table: TABLE_HEADER,
+
+    /// A header of a table of an array of tables, e.g. `[[table]]`.
+    ArrayOfTables: TABLE_ARRAY_HEADER,
+
+    /// A key with its value, e.g. `key = "value"`.
+    Entry: ENTRY,
+
+    /// A key of an entry or a header, its parts are separated by periods.
+    Key: KEY,
+
+    /// An array, e.g. `[1, 2]`.
+    Array: ARRAY,
+
+    /// An inline table, e.g. `{ key = "value" }`.
+    InlineTable: INLINE_TABLE,
+}
+
+/// The views of the children of a node that have the kind of `T`.
+fn children<'a, T: AstNode<'a> + 'a>(node: &'a SyntaxNode) -> impl Iterator<Item = T> + 'a {
+    node.children().iter().filter_map(T::cast_element)
+}
+
+/// An item directly in the document.
+#[derive(Debug, Clone, Copy)]
+pub enum RootItem<'a> {
+    Table(Table<'a>),
+    ArrayOfTables(ArrayOfTables<'a>),
+    Entry(Entry<'a>),
+}
+
+impl<'a> Root<'a> {
+    /// The table headers and entries in the order of the document.
+    pub fn items(&self) -> impl Iterator<Item = RootItem<'a>> + use<'a> {
+        self.0.children().iter().filter_map(Element::as_node).filter_map(|node| {
+            Table::cast(node)
+                .map(RootItem::Table)
+                .or_else(|| ArrayOfTables::cast(node).map(RootItem::ArrayOfTables))
+                .or_else(|| Entry::cast(node).map(RootItem::Entry))
+        })
+    }
+}
+
+impl<'a> Table<'a> {
+    /// The key of the table, `None` if the header is invalid.
+    pub fn key(&self) -> Option<Key<'a>> {
+        children(self.0).next()
+    }
+}
+
+impl<'a> ArrayOfTables<'a> {
+    /// The key of the array, `None` if the header is invalid.
+    pub fn key(&self) -> Option<Key<'a>> {
+        children(self.0).next()
+    }
+}
+
+impl<'a> Entry<'a> {
+    /// The key of the entry, `None` if it is missing.
+    pub fn key(&self) -> Option<Key<'a>> {
+        children(self.0).next()
+    }
+
+    /// The value of the entry, `None` if it is missing or invalid.
+    pub fn value(&self) -> Option<Value<'a>> {
+        self.0.children().iter().find_map(Value::cast_element)
+    }
+}
+
+impl<'a> Key<'a> {
+    /// The tokens of the parts of the key, quoted parts are written with their quotes.
+    pub fn idents(&self) -> impl Iterator<Item = &'a SyntaxToken> + use<'a> {
+        self.0.children().iter().filter_map(Element::as_token).filter(|t| t.kind() == IDENT)
+    }
+
+    /// The parts of the key without quotes and with their escapes replaced,
+    /// e.g. `a."b.c"` has the parts `a` and `b.c`.
+    pub fn parts(&self, source: &str) -> Vec<String> {
+        self.idents().map(|ident| key_part(ident.text(source))).collect()
+    }
+}
+
+impl<'a> Array<'a> {
+    /// The valid values of the array.
+    pub fn items(&self) -> impl Iterator<Item = Value<'a>> + use<'a> {
+        self.0.children().iter().filter_map(Value::cast_element)
+    }
+}
+
+impl<'a> InlineTable<'a> {
+    /// The entries of the inline table.
+    pub fn entries(&self) -> impl Iterator<Item = Entry<'a>> + use<'a> {
+        children(self.0)
+    }
+}
+
+/// A value of an entry or an array item, the scalars are single tokens.
+#[derive(Debug, Clone, Copy)]
+pub enum Value<'a> {
+    /// A basic or literal string, either single-line or multi-line.
+    String(&'a SyntaxToken),
+
+    /// A decimal, hexadecimal, octal or binary integer.
+    Integer(&'a SyntaxToken),
+
+    Float(&'a SyntaxToken),
+
+    Bool(&'a SyntaxToken),
+
+    /// An offset or local date-time, a local date or a local time.
+    DateTime(&'a SyntaxToken),
+
+    Array(Array<'a>),
+
+    InlineTable(InlineTable<'a>),
+}
+
+impl<'a> Value<'a> {
+    /// The view of a `VALUE` node, `None` if it has another kind or the value is invalid.
+    pub fn cast(node: &'a SyntaxNode) -> Option<Self> {
+        if node.kind() != VALUE {
+            return None;
+        }
+        node.children().iter().find_map(|child| match child {
+            Element::Node(node) => Array::cast(node)
+                .map(Self::Array)
+                .or_else(|| InlineTable::cast(node).map(Self::InlineTable)),
+            Element::Token(token) => match token.kind() {
+                STRING | MULTI_LINE_STRING | STRING_LITERAL | MULTI_LINE_STRING_LITERAL => {
+                    Some(Self::String(token))
+                }
+                INTEGER | INTEGER_HEX | INTEGER_OCT | INTEGER_BIN => Some(Self::Integer(token)),
+                FLOAT => Some(Self::Float(token)),
+                BOOL => Some(Self::Bool(token)),
+                DATE_TIME_OFFSET | DATE_TIME_LOCAL | DATE | TIME => Some(Self::DateTime(token)),
+                _ => None,
+            },
+        })
+    }
+
+    /// The view of an element that is a `VALUE` node, see [`Value::cast`].
+    pub fn cast_element(element: &'a Element) -> Option<Self> {
+        element.as_node().and_then(Self::cast)
+    }
+
+    /// The span of the value without the whitespace and comments around it.
+    pub fn span(&self) -> TextRange {
+        match self {
+            Self::String(token)
+            | Self::Integer(token)
+            | Self::Float(token)
+            | Self::Bool(token)
+            | Self::DateTime(token) => token.span.clone(),
+            Self::Array(array) => array.span(),
+            Self::InlineTable(table) => table.span(),
+        }
+    }
+
+    /// The text of the value.
+    pub fn text<'s>(&self, source: &'s str) -> &'s str {
+        let span = self.span();
+        &source[span.start as usize..span.end as usize]
+    }
+}
+
+/// The unquoted and unescaped text of a part of a key.
+pub(crate) fn key_part(text: &str) -> String {
+    if let Some(quoted) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        unescape(quoted).unwrap_or_else(|_| quoted.to_string())
+    } else if let Some(literal) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+        literal.to_string()
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,93 @@
+//! The comments that belong to nodes, see [`SyntaxTree::comments`].
+
+use crate::{
+    syntax::SyntaxKind::*,
+    tree::{Element, Node, SyntaxTree, Token},
+};
+
+/// The comments of a node, see [`SyntaxTree::comments`].
+#[derive(Debug, Clone, Default)]
+pub struct Comments<'a> {
+    /// The comments on their own lines right above the node, without a blank line
+    /// between them and the node.
+    pub leading: Vec<&'a Token>,
+
+    /// The comment at the end of the last line of the node, after a comma for the items
+    /// of arrays and inline tables.
+    pub trailing: Option<&'a Token>,
+
+    /// The comments on their own lines above the leading ones that are separated from them,
+    /// or from the node, by a blank line. They go up to the previous line with something else.
+    pub detached: Vec<&'a Token>,
+}
+
+impl SyntaxTree<'_> {
+    /// The comments that belong to a node of the tree, based on the lines and the blank lines
+    /// around it. Nodes that don't start on their own line have no leading or detached comments.
+    pub fn comments(&self, node: &Node) -> Comments<'_> {
+        let source = self.source();
+        let mut tokens = Vec::new();
+        comment_tokens(&self.root, &mut tokens);
+
+        let mut comments = Comments::default();
+        let end = content_end(node).unwrap_or(node.span.end) as usize;
+        comments.trailing = tokens.iter().copied().find(|token| {
+            let start = token.span.start as usize;
+            let between = source.get(end..start).map(str::trim);
+            start >= end && matches!(between, Some("" | ","))
+        });
+
+        let start = node.span.start as usize;
+        let mut line = line_start(source, start);
+        if !source[line..start].trim().is_empty() {
+            return comments;
+        }
+
+        // The comments on the lines above, the leading ones until the first blank line.
+        let mut blank = false;
+        while line > 0 {
+            let prev = line_start(source, line - 1);
+            let text = source[prev..line].trim();
+            if text.is_empty() {
+                blank = true;
+                line = prev;
+                continue;
+            }
+            let offset = prev + source[prev..].len() - source[prev..].trim_start().len();
+            let Some(comment) = tokens.iter().copied().find(|t| t.span.start as usize == offset)
+            else {
+                break;
+            };
+            if blank { &mut comments.detached } else { &mut comments.leading }.push(comment);
+            line = prev;
+        }
+        comments.leading.reverse();
+        comments.detached.reverse();
+        comments
+    }
+}
+
+/// Add the comment tokens of a node and its descendants.
+fn comment_tokens<'a>(node: &'a Node, tokens: &mut Vec<&'a Token>) {
+    for child in &node.children {
+        match child {
+            Element::Node(node) => comment_tokens(node, tokens),
+            Element::Token(token) if token.kind == COMMENT => tokens.push(token),
+            Element::Token(_) => {}
+        }
+    }
+}
+
+/// The end of the last token of a node that is not whitespace or a comment.
+fn content_end(node: &Node) -> Option<u32> {
+    node.children.iter().rev().find_map(|child| match child {
+        Element::Node(node) => content_end(node),
+        Element::Token(token) if matches!(token.kind, WHITESPACE | NEWLINE | COMMENT) => None,
+        Element::Token(token) => Some(token.span.end),
+    })
+}
+
+/// The offset of the start of the line with the offset.
+fn line_start(source: &str, offset: usize) -> usize {
+    source[..offset].rfind('\n').map_or(0, |idx| idx + 1)
+}
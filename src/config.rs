@@ -0,0 +1,517 @@
+//! Loading formatter settings from config files.
+//!
+//! A config file has the glob patterns of the files to format at the top level
+//! and the options in the `formatting` table:
+//!
+//! ```toml
+//! include = ["**/*.toml"]
+//! exclude = ["target/**"]
+//!
+//! [formatting]
+//! align_entries = true
+//! column_width = 100
+//!
+//! [[rule]]
+//! keys = ["dependencies"]
+//! formatting = { reorder_keys = true }
+//! ```
+//!
+//! The options of a rule apply to the entries that match its key patterns,
+//! see [`FormatRule`].
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ast::key_part,
+    formatter::{
+        ArrayLayout, DateTimeSeparator, FormatRule, HexCase, IntegerUnderscores, LineEnding,
+        Options, for_each_option, is_valid_pattern,
+    },
+    parser::{ParseError, ParseOptions, TomlVersion, parse_root},
+    syntax::{SyntaxKind, SyntaxKind::*, SyntaxNode},
+    tree::{Element, TextRange},
+    util::unescape,
+};
+
+/// Names of config files in the order they are looked for in each directory.
+pub const CONFIG_FILE_NAMES: [&str; 2] = ["oxc-toml.toml", ".oxctomlrc.toml"];
+
+/// Names of Taplo config files, they are used if there is no other config file in a directory.
+pub const TAPLO_CONFIG_FILE_NAMES: [&str; 2] = ["taplo.toml", ".taplo.toml"];
+
+/// The formatting options of Taplo with the same meaning here, `crlf` is mapped to `line_ending`.
+const TAPLO_OPTIONS: [&str; 19] = [
+    "align_entries",
+    "align_comments",
+    "align_single_comments",
+    "array_trailing_comma",
+    "array_auto_expand",
+    "inline_table_expand",
+    "array_auto_collapse",
+    "compact_arrays",
+    "compact_inline_tables",
+    "compact_entries",
+    "column_width",
+    "indent_tables",
+    "indent_entries",
+    "indent_string",
+    "trailing_newline",
+    "reorder_keys",
+    "reorder_arrays",
+    "reorder_inline_tables",
+    "allowed_blank_lines",
+];
+
+/// Formatter settings from a config file.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Config {
+    /// The options from the `formatting` table.
+    pub options: Options,
+
+    /// Glob patterns of the files to format, relative to the directory of the config file.
+    pub include: Vec<String>,
+
+    /// Glob patterns of the files that are not formatted even if they are included.
+    pub exclude: Vec<String>,
+
+    /// The `rule` tables, their options start from the ones of the `formatting` table.
+    pub rules: Vec<FormatRule>,
+}
+
+/// An error that can occur while loading a config file.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config file could not be read.
+    Io { path: PathBuf, error: io::Error },
+
+    /// The config file is not valid TOML.
+    Syntax(Vec<ParseError>),
+
+    /// An unknown key or a value of the wrong type.
+    Invalid {
+        /// The span of the key or value.
+        range: TextRange,
+
+        /// Human-friendly error message.
+        message: String,
+    },
+}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, error } => write!(f, "failed to read {}: {error}", path.display()),
+            Self::Syntax(errors) => {
+                write!(f, "invalid config file")?;
+                for error in errors {
+                    write!(f, "\n{error}")?;
+                }
+                Ok(())
+            }
+            Self::Invalid { range, message } => write!(f, "{message} ({range:?})"),
+        }
+    }
+}
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Parse the contents of a config file.
+    pub fn parse(source: &str) -> Result<Self, ConfigError> {
+        parse_config(source, false)
+    }
+
+    /// Parse the contents of a Taplo config file.
+    ///
+    /// The formatting options that Taplo supports are used, other settings
+    /// such as schemas are ignored. Rules are only used if they have keys,
+    /// a rule with include or exclude patterns of its own is an error.
+    pub fn parse_taplo(source: &str) -> Result<Self, ConfigError> {
+        parse_config(source, true)
+    }
+
+    /// Read and parse a config file, Taplo config files are recognized by their name.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| ConfigError::Io { path: path.to_path_buf(), error })?;
+
+        let taplo = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| TAPLO_CONFIG_FILE_NAMES.contains(&name));
+        parse_config(&source, taplo)
+    }
+}
+
+fn parse_config(source: &str, taplo: bool) -> Result<Config, ConfigError> {
+    // Validate so that a key set twice is an error instead of silently using
+    // one of the values.
+    let (root, errors) =
+        parse_root(source, ParseOptions { validate: true, ..ParseOptions::default() });
+    if !errors.is_empty() {
+        return Err(ConfigError::Syntax(errors));
+    }
+
+    let (entries, rule_headers) = entries(&root, source, taplo)?;
+
+    let mut config = Config::default();
+    let mut rules = rule_headers.iter().map(|_| RuleEntries::default()).collect::<Vec<_>>();
+    // The keys of the options in the `formatting` table.
+    let mut option_keys = Vec::new();
+    for entry in entries {
+        let ConfigEntry { key, key_range, value, rule } = entry;
+        let unknown_key = || ConfigError::Invalid {
+            range: key_range.clone(),
+            message: format!("unknown key `{}`", key.join(".")),
+        };
+        let result = match (rule, &key.iter().map(String::as_str).collect::<Vec<_>>()[..]) {
+            (None, ["include"]) => FromValue::from_value(value, source).map(|v| config.include = v),
+            (None, ["exclude"]) => FromValue::from_value(value, source).map(|v| config.exclude = v),
+            (None, ["formatting", name]) => {
+                option_keys.push((name.to_string(), key_range.clone()));
+                set_config_option(&mut config.options, name, value, source, taplo)
+                    .ok_or_else(unknown_key)?
+            }
+            (Some(rule), ["rule", "keys"]) => {
+                Vec::<String>::from_value(value, source).and_then(|keys| {
+                    if let Some(key) = keys.iter().find(|key| !is_valid_pattern(key)) {
+                        return Err(format!("invalid key pattern `{key}`"));
+                    }
+                    rules[rule].keys = Some(keys);
+                    Ok(())
+                })
+            }
+            // The options are set after the ones of the `formatting` table.
+            (Some(rule), ["rule", "formatting", name]) => {
+                rules[rule].formatting.push((name.to_string(), key_range, value));
+                Ok(())
+            }
+            // Taplo rules can also apply to files, the rule would apply to other files too.
+            (Some(rule), ["rule", name @ ("include" | "exclude")]) if taplo => {
+                return Err(ConfigError::Invalid {
+                    range: key_range,
+                    message: format!("`{name}` in rule {} is not supported", rule + 1),
+                });
+            }
+            _ if taplo => continue,
+            _ => return Err(unknown_key()),
+        };
+        result.map_err(|message| ConfigError::Invalid { range: value.span.clone(), message })?;
+    }
+    validate_options(&config.options, &option_keys, &(0..0))?;
+
+    for (rule, header) in rules.into_iter().zip(rule_headers) {
+        let keys = match rule.keys {
+            Some(keys) => keys,
+            None if taplo => continue,
+            None => {
+                return Err(ConfigError::Invalid {
+                    range: header,
+                    message: "missing `keys` in rule".into(),
+                });
+            }
+        };
+
+        let mut options = config.options.clone();
+        let mut option_keys = option_keys.clone();
+        for (name, key_range, value) in rule.formatting {
+            option_keys.push((name.clone(), key_range.clone()));
+            let result =
+                set_config_option(&mut options, &name, value, source, taplo).ok_or_else(|| {
+                    ConfigError::Invalid {
+                        range: key_range,
+                        message: format!("unknown key `rule.formatting.{name}`"),
+                    }
+                })?;
+            result
+                .map_err(|message| ConfigError::Invalid { range: value.span.clone(), message })?;
+        }
+        validate_options(&options, &option_keys, &header)?;
+        config.rules.push(FormatRule { keys, options });
+    }
+
+    Ok(config)
+}
+
+/// Check the options with [`Options::validate`], the error is at the key of the option
+/// that is invalid, or at `fallback` if the option isn't set in the file.
+fn validate_options(
+    options: &Options,
+    keys: &[(String, TextRange)],
+    fallback: &TextRange,
+) -> Result<(), ConfigError> {
+    let Err(errors) = options.validate() else {
+        return Ok(());
+    };
+    let error = &errors[0];
+    let range =
+        keys.iter().rev().find(|(name, _)| name == error.option).map_or(fallback, |key| &key.1);
+    Err(ConfigError::Invalid { range: range.clone(), message: error.to_string() })
+}
+
+/// The entries of a `rule` table.
+#[derive(Default)]
+struct RuleEntries<'a> {
+    keys: Option<Vec<String>>,
+    formatting: Vec<(String, TextRange, &'a SyntaxNode)>,
+}
+
+/// Set an option of the `formatting` table, `None` if there is no such option.
+///
+/// Only the options of Taplo and `crlf` are allowed in Taplo config files.
+fn set_config_option(
+    options: &mut Options,
+    name: &str,
+    value: &SyntaxNode,
+    source: &str,
+    taplo: bool,
+) -> Option<Result<(), String>> {
+    match name {
+        "crlf" if taplo => Some(bool::from_value(value, source).map(|crlf| {
+            options.line_ending = if crlf { LineEnding::Crlf } else { LineEnding::Lf };
+        })),
+        _ if taplo && !TAPLO_OPTIONS.contains(&name) => None,
+        _ => set_option(options, name, value, source),
+    }
+}
+
+impl Options {
+    /// Find the config file for the file or directory at `path` and load it.
+    ///
+    /// The directory of `path` and its ancestors are searched for one of the
+    /// [`CONFIG_FILE_NAMES`] or [`TAPLO_CONFIG_FILE_NAMES`], the closest one is used.
+    /// Returns the path of the config file along with its contents.
+    pub fn discover(path: impl AsRef<Path>) -> Result<Option<(PathBuf, Config)>, ConfigError> {
+        let path = path.as_ref();
+        let start = if path.is_dir() { Some(path) } else { path.parent() };
+
+        for dir in start.into_iter().flat_map(Path::ancestors) {
+            for name in CONFIG_FILE_NAMES.into_iter().chain(TAPLO_CONFIG_FILE_NAMES) {
+                let candidate = dir.join(name);
+                if candidate.is_file() {
+                    let config = Config::load(&candidate)?;
+                    return Ok(Some((candidate, config)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// An entry of a config file.
+struct ConfigEntry<'a> {
+    /// The full key including the table.
+    key: Vec<String>,
+
+    /// The span of the key without the surrounding whitespace.
+    key_range: TextRange,
+
+    value: &'a SyntaxNode,
+
+    /// The index of the `[[rule]]` table the entry belongs to.
+    rule: Option<usize>,
+}
+
+/// Collect the entries, the entries of inline tables are collected separately.
+/// The spans of the `[[rule]]` headers are returned as well.
+///
+/// Other arrays of tables are an error unless `skip_array_tables` is set.
+fn entries<'a>(
+    root: &'a SyntaxNode,
+    source: &str,
+    skip_array_tables: bool,
+) -> Result<(Vec<ConfigEntry<'a>>, Vec<TextRange>), ConfigError> {
+    let mut table = Vec::new();
+    let mut in_array_table = false;
+    let mut rule = None;
+    let mut rule_headers = Vec::new();
+    let mut entries = Vec::new();
+
+    for node in root.children().iter().filter_map(Element::as_node) {
+        match node.kind() {
+            TABLE_HEADER => {
+                table = key_parts(node, source);
+                in_array_table = false;
+                // Subtables of a rule such as `[rule.formatting]` belong to it.
+                if table.len() == 1 || table.first().is_none_or(|first| first != "rule") {
+                    rule = None;
+                }
+            }
+            TABLE_ARRAY_HEADER if key_parts(node, source) == ["rule"] => {
+                table = vec!["rule".to_string()];
+                in_array_table = false;
+                rule = Some(rule_headers.len());
+                rule_headers.push(node.span.clone());
+            }
+            TABLE_ARRAY_HEADER if skip_array_tables => in_array_table = true,
+            TABLE_ARRAY_HEADER => {
+                return Err(ConfigError::Invalid {
+                    range: node.span.clone(),
+                    message: "arrays of tables are not supported".into(),
+                });
+            }
+            ENTRY if in_array_table => {}
+            ENTRY => add_entry(node, &table, rule, source, &mut entries),
+            _ => {}
+        }
+    }
+
+    Ok((entries, rule_headers))
+}
+
+fn add_entry<'a>(
+    node: &'a SyntaxNode,
+    table: &[String],
+    rule: Option<usize>,
+    source: &str,
+    entries: &mut Vec<ConfigEntry<'a>>,
+) {
+    let (Some(key_node), Some(value)) = (child(node, KEY), child(node, VALUE)) else {
+        return;
+    };
+    let mut key = table.to_vec();
+    key.extend(key_parts(node, source));
+
+    if let Some(inline_table) = child(value, INLINE_TABLE) {
+        for entry in inline_table.children().iter().filter_map(Element::as_node) {
+            if entry.kind() == ENTRY {
+                add_entry(entry, &key, rule, source, entries);
+            }
+        }
+        return;
+    }
+
+    let mut idents = key_node.children().iter().filter(|c| c.kind() == IDENT);
+    let first = idents.next().map(Element::text_range);
+    let last = idents.next_back().map(Element::text_range).or(first.clone());
+    let start = first.map_or(key_node.span.start, |range| range.start);
+    let end = last.map_or(key_node.span.end, |range| range.end);
+    entries.push(ConfigEntry { key, key_range: start..end, value, rule });
+}
+
+fn child(node: &SyntaxNode, kind: SyntaxKind) -> Option<&SyntaxNode> {
+    node.children().iter().filter_map(Element::as_node).find(|n| n.kind() == kind)
+}
+
+/// The parts of the key of a table header or an entry, unquoted and unescaped.
+fn key_parts(node: &SyntaxNode, source: &str) -> Vec<String> {
+    child(node, KEY)
+        .into_iter()
+        .flat_map(|key| key.children())
+        .filter(|c| c.kind() == IDENT)
+        .map(|ident| key_part(ident.text(source)))
+        .collect()
+}
+
+/// Conversion of a `VALUE` node in a config file.
+trait FromValue: Sized {
+    fn from_value(value: &SyntaxNode, source: &str) -> Result<Self, String>;
+}
+
+/// The single token of a value, `None` for arrays and inline tables.
+fn value_token<'s>(value: &SyntaxNode, source: &'s str) -> Option<(SyntaxKind, &'s str)> {
+    match value.children().first()? {
+        Element::Token(token) => Some((token.kind(), token.text(source))),
+        Element::Node(_) => None,
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &SyntaxNode, source: &str) -> Result<Self, String> {
+        match value_token(value, source) {
+            Some((BOOL, text)) => Ok(text == "true"),
+            _ => Err("expected a boolean".into()),
+        }
+    }
+}
+
+impl FromValue for usize {
+    fn from_value(value: &SyntaxNode, source: &str) -> Result<Self, String> {
+        match value_token(value, source) {
+            Some((INTEGER, text)) => {
+                text.replace('_', "").parse().map_err(|_| "expected a positive integer".into())
+            }
+            _ => Err("expected a positive integer".into()),
+        }
+    }
+}
+
+impl FromValue for Option<usize> {
+    fn from_value(value: &SyntaxNode, source: &str) -> Result<Self, String> {
+        usize::from_value(value, source).map(Some)
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &SyntaxNode, source: &str) -> Result<Self, String> {
+        match value_token(value, source) {
+            Some((STRING, text)) => {
+                unescape(&text[1..text.len() - 1]).map_err(|_| "invalid escape sequence".into())
+            }
+            Some((STRING_LITERAL, text)) => Ok(text[1..text.len() - 1].to_string()),
+            _ => Err("expected a string".into()),
+        }
+    }
+}
+
+impl FromValue for Vec<String> {
+    fn from_value(value: &SyntaxNode, source: &str) -> Result<Self, String> {
+        let array = child(value, ARRAY).ok_or("expected an array of strings")?;
+        array
+            .children()
+            .iter()
+            .filter_map(Element::as_node)
+            .filter(|n| n.kind() == VALUE)
+            .map(|item| String::from_value(item, source))
+            .collect()
+    }
+}
+
+macro_rules! enum_values {
+    ($($ty:ident { $($name:literal => $variant:ident,)* })*) => {
+        $(
+            impl FromValue for $ty {
+                fn from_value(value: &SyntaxNode, source: &str) -> Result<Self, String> {
+                    match String::from_value(value, source)?.as_str() {
+                        $($name => Ok($ty::$variant),)*
+                        _ => Err(concat!("expected one of", $(" `", $name, "`",)*).into()),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+enum_values! {
+    ArrayLayout { "auto" => Auto, "preserve" => Preserve, }
+    IntegerUnderscores { "preserve" => Preserve, "group" => Group, "remove" => Remove, }
+    HexCase { "preserve" => Preserve, "lower" => Lower, "upper" => Upper, }
+    DateTimeSeparator { "preserve" => Preserve, "t" => T, "space" => Space, }
+    LineEnding { "lf" => Lf, "crlf" => Crlf, "preserve" => Preserve, }
+    TomlVersion { "1.0" => V1_0, "1.1" => V1_1, }
+}
+
+macro_rules! options {
+    ($($name:ident: $ty:ty,)*) => {
+        /// Set the option with the given name from a config value,
+        /// `None` if there is no such option.
+        fn set_option(
+            options: &mut Options,
+            name: &str,
+            value: &SyntaxNode,
+            source: &str,
+        ) -> Option<Result<(), String>> {
+            let result = match name {
+                $(stringify!($name) => FromValue::from_value(value, source).map(|v| options.$name = v),)*
+                _ => return None,
+            };
+            Some(result)
+        }
+    };
+}
+
+for_each_option!(options);
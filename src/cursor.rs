@@ -0,0 +1,138 @@
+//! Navigation of a syntax tree in all directions, see [`SyntaxTree::cursor`].
+
+use std::rc::Rc;
+
+use crate::{
+    syntax::SyntaxKind,
+    tree::{Element, Node, SyntaxTree, TextRange, Token},
+};
+
+/// A node or token of a tree that knows its parent and its position in it.
+///
+/// The nodes and tokens of the tree don't link to their parents, so the cursor
+/// keeps the path from the root to them. Cloning it is cheap, the path is shared.
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    element: CursorElement<'a>,
+    parent: Option<Rc<Cursor<'a>>>,
+
+    /// The index in the children of the parent.
+    index: usize,
+}
+
+/// The node or token of a [`Cursor`].
+#[derive(Debug, Clone, Copy)]
+pub enum CursorElement<'a> {
+    Node(&'a Node),
+    Token(&'a Token),
+}
+
+impl SyntaxTree<'_> {
+    /// A cursor at the root node.
+    pub fn cursor(&self) -> Cursor<'_> {
+        Cursor { element: CursorElement::Node(&self.root), parent: None, index: 0 }
+    }
+
+    /// A cursor at the token at a byte offset, see [`SyntaxTree::element_at`].
+    pub fn cursor_at(&self, offset: u32) -> Option<Cursor<'_>> {
+        let mut cursor = self.cursor();
+        while let CursorElement::Node(node) = cursor.element {
+            let last = node.children.len().checked_sub(1)?;
+            let index = match node.children.iter().position(|c| c.span().contains(&offset)) {
+                Some(index) => index,
+                None if node.children[last].span().end == offset => last,
+                None => return None,
+            };
+            cursor = Cursor::child(Rc::new(cursor), index)?;
+        }
+        Some(cursor)
+    }
+}
+
+impl<'a> Cursor<'a> {
+    /// The cursor at a child of a node.
+    fn child(parent: Rc<Self>, index: usize) -> Option<Self> {
+        let CursorElement::Node(node) = parent.element else {
+            return None;
+        };
+        let element = match node.children.get(index)? {
+            Element::Node(node) => CursorElement::Node(node),
+            Element::Token(token) => CursorElement::Token(token),
+        };
+        Some(Self { element, parent: Some(parent), index })
+    }
+
+    pub const fn element(&self) -> CursorElement<'a> {
+        self.element
+    }
+
+    /// The node, `None` for tokens.
+    pub const fn as_node(&self) -> Option<&'a Node> {
+        match self.element {
+            CursorElement::Node(node) => Some(node),
+            CursorElement::Token(_) => None,
+        }
+    }
+
+    /// The token, `None` for nodes.
+    pub const fn as_token(&self) -> Option<&'a Token> {
+        match self.element {
+            CursorElement::Node(_) => None,
+            CursorElement::Token(token) => Some(token),
+        }
+    }
+
+    pub const fn kind(&self) -> SyntaxKind {
+        match self.element {
+            CursorElement::Node(node) => node.kind,
+            CursorElement::Token(token) => token.kind,
+        }
+    }
+
+    pub fn span(&self) -> TextRange {
+        match self.element {
+            CursorElement::Node(node) => node.span.clone(),
+            CursorElement::Token(token) => token.span.clone(),
+        }
+    }
+
+    pub fn text<'s>(&self, source: &'s str) -> &'s str {
+        &source[self.span().start as usize..self.span().end as usize]
+    }
+
+    /// The node that contains this element, `None` for the root.
+    pub fn parent(&self) -> Option<Self> {
+        self.parent.as_deref().cloned()
+    }
+
+    /// The nodes that contain this element from its parent up to the root.
+    pub fn ancestors(&self) -> impl Iterator<Item = Self> + use<'a> {
+        std::iter::successors(self.parent(), Self::parent)
+    }
+
+    /// The children of a node, none for tokens.
+    pub fn children(&self) -> impl Iterator<Item = Self> + use<'a> {
+        let len = self.as_node().map_or(0, |node| node.children.len());
+        let parent = Rc::new(self.clone());
+        (0..len).filter_map(move |index| Self::child(Rc::clone(&parent), index))
+    }
+
+    pub fn first_child(&self) -> Option<Self> {
+        Self::child(Rc::new(self.clone()), 0)
+    }
+
+    pub fn last_child(&self) -> Option<Self> {
+        let len = self.as_node()?.children.len();
+        Self::child(Rc::new(self.clone()), len.checked_sub(1)?)
+    }
+
+    /// The element before this one in its parent.
+    pub fn prev_sibling(&self) -> Option<Self> {
+        Self::child(Rc::clone(self.parent.as_ref()?), self.index.checked_sub(1)?)
+    }
+
+    /// The element after this one in its parent.
+    pub fn next_sibling(&self) -> Option<Self> {
+        Self::child(Rc::clone(self.parent.as_ref()?), self.index + 1)
+    }
+}
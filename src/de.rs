@@ -0,0 +1,332 @@
+//! Deserialization of documents with serde, see [`from_str`].
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+    value::StrDeserializer,
+};
+
+use crate::{
+    ast::AstNode,
+    dom::{Document, Item, Table},
+    parser::{ParseOptions, parse_with},
+    tree::TextRange,
+    value::{Value, scalar},
+};
+
+/// Deserialize a type from a TOML document.
+///
+/// The document is parsed once and deserialized from its syntax tree.
+/// It is validated as well, so keys that are defined more than once are errors.
+pub fn from_str<T: DeserializeOwned>(source: &str) -> Result<T, DeserializeError> {
+    let parse = parse_with(source, ParseOptions { validate: true, ..ParseOptions::default() });
+    if let Some(error) = parse.errors.first() {
+        return Err(DeserializeError {
+            span: Some(error.span.clone()),
+            message: error.message.clone(),
+        });
+    }
+
+    let document = Document::new(&parse.tree);
+    T::deserialize(TableDeserializer { table: document.root(), source })
+}
+
+/// An error that can occur while deserializing a document, see [`from_str`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DeserializeError {
+    /// The span of the value or table that couldn't be deserialized, or of the syntax error.
+    pub span: Option<TextRange>,
+
+    /// Human-friendly error message.
+    pub message: String,
+}
+
+impl DeserializeError {
+    /// Point the error at the span if it doesn't have one yet, the innermost span is kept.
+    fn with_span(mut self, span: Option<TextRange>) -> Self {
+        if self.span.is_none() {
+            self.span = span;
+        }
+        self
+    }
+}
+
+impl core::fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.span {
+            Some(span) => write!(f, "{} ({span:?})", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+impl std::error::Error for DeserializeError {}
+
+impl de::Error for DeserializeError {
+    fn custom<T: core::fmt::Display>(msg: T) -> Self {
+        Self { span: None, message: msg.to_string() }
+    }
+}
+
+/// Deserializes an item of a document.
+struct ItemDeserializer<'a, 'b> {
+    item: &'a Item<'b>,
+    source: &'a str,
+}
+
+impl ItemDeserializer<'_, '_> {
+    fn span(&self) -> Option<TextRange> {
+        match self.item {
+            Item::Value(value) => Some(value.span()),
+            Item::Array(array) => Some(array.syntax().span()),
+            Item::Table(table) => table.span(),
+            Item::ArrayOfTables(tables) => {
+                tables.first().and_then(Item::as_table).and_then(Table::span)
+            }
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ItemDeserializer<'_, '_> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let span = self.span();
+        let result = match self.item {
+            Item::Value(value) => match scalar(*value, self.source) {
+                Some(Value::String(s)) => visitor.visit_string(s),
+                Some(Value::Integer(i)) => visitor.visit_i64(i),
+                Some(Value::Float(f)) => visitor.visit_f64(f),
+                Some(Value::Bool(b)) => visitor.visit_bool(b),
+                // Date-times are deserialized as strings, see `Datetime`.
+                Some(Value::Datetime(datetime)) => visitor.visit_string(datetime.to_string()),
+                _ => Err(de::Error::custom("invalid value")),
+            },
+            Item::Array(array) => visitor
+                .visit_seq(SeqDeserializer { items: array.items().iter(), source: self.source }),
+            Item::ArrayOfTables(tables) => {
+                visitor.visit_seq(SeqDeserializer { items: tables.iter(), source: self.source })
+            }
+            Item::Table(table) => {
+                return TableDeserializer { table, source: self.source }.deserialize_any(visitor);
+            }
+        };
+        result.map_err(|error| error.with_span(span))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let span = self.span();
+        let result = match self.item {
+            Item::Value(value) => match scalar(*value, self.source) {
+                Some(Value::String(s)) => {
+                    visitor.visit_enum(IntoDeserializer::<DeserializeError>::into_deserializer(s))
+                }
+                _ => Err(de::Error::custom(format!("expected a variant of `{name}`"))),
+            },
+            Item::Table(table) => {
+                return TableDeserializer { table, source: self.source }
+                    .deserialize_enum(name, variants, visitor);
+            }
+            Item::Array(_) | Item::ArrayOfTables(_) => {
+                Err(de::Error::custom(format!("expected a variant of `{name}`")))
+            }
+        };
+        result.map_err(|error| error.with_span(span))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// Deserializes a table of a document, including the root table.
+struct TableDeserializer<'a, 'b> {
+    table: &'a Table<'b>,
+    source: &'a str,
+}
+
+impl<'de> de::Deserializer<'de> for TableDeserializer<'_, '_> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let span = self.table.span();
+        let map = MapDeserializer { items: self.table.iter(), value: None, source: self.source };
+        visitor.visit_map(map).map_err(|error| error.with_span(span))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// A table with a single key is a variant with data, e.g. `{ point = { x = 1, y = 2 } }`.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let span = self.table.span();
+        let mut items = self.table.iter();
+        let result = match (items.next(), items.next()) {
+            (Some((variant, item)), None) => {
+                visitor.visit_enum(EnumDeserializer { variant, item, source: self.source })
+            }
+            _ => Err(de::Error::custom(format!(
+                "expected a variant of `{name}`, a table with a single key"
+            ))),
+        };
+        result.map_err(|error| error.with_span(span))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+struct SeqDeserializer<'a, 'b: 'a, I: Iterator<Item = &'a Item<'b>>> {
+    items: I,
+    source: &'a str,
+}
+
+impl<'de, 'a, 'b: 'a, I: Iterator<Item = &'a Item<'b>>> SeqAccess<'de>
+    for SeqDeserializer<'a, 'b, I>
+{
+    type Error = DeserializeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        self.items
+            .next()
+            .map(|item| seed.deserialize(ItemDeserializer { item, source: self.source }))
+            .transpose()
+    }
+}
+
+struct MapDeserializer<'a, 'b: 'a, I: Iterator<Item = (&'a str, &'a Item<'b>)>> {
+    items: I,
+
+    /// The item of the last key.
+    value: Option<&'a Item<'b>>,
+
+    source: &'a str,
+}
+
+impl<'de, 'a, 'b: 'a, I: Iterator<Item = (&'a str, &'a Item<'b>)>> MapAccess<'de>
+    for MapDeserializer<'a, 'b, I>
+{
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, item)) = self.items.next() else {
+            return Ok(None);
+        };
+        self.value = Some(item);
+        let deserializer: StrDeserializer<'_, DeserializeError> = key.into_deserializer();
+        seed.deserialize(deserializer).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let item = self.value.take().ok_or_else(|| de::Error::custom("value without a key"))?;
+        seed.deserialize(ItemDeserializer { item, source: self.source })
+    }
+}
+
+/// The variant of a table with a single key.
+struct EnumDeserializer<'a, 'b> {
+    variant: &'a str,
+    item: &'a Item<'b>,
+    source: &'a str,
+}
+
+impl<'de, 'a, 'b> de::EnumAccess<'de> for EnumDeserializer<'a, 'b> {
+    type Error = DeserializeError;
+    type Variant = ItemDeserializer<'a, 'b>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let deserializer: StrDeserializer<'_, DeserializeError> = self.variant.into_deserializer();
+        let variant = seed.deserialize(deserializer)?;
+        Ok((variant, ItemDeserializer { item: self.item, source: self.source }))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for ItemDeserializer<'_, '_> {
+    type Error = DeserializeError;
+
+    /// A unit variant in a table has an empty table, e.g. `{ variant = {} }`.
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.item {
+            Item::Table(table) if table.iter().next().is_none() => Ok(()),
+            _ => Err(DeserializeError {
+                span: self.span(),
+                message: "expected an empty table for a unit variant".into(),
+            }),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
@@ -0,0 +1,150 @@
+//! The differences between the keys of documents, see [`diff`].
+
+use crate::{
+    ast::AstNode,
+    dom::{Document, Item, Table, TableKind},
+    emit::key_text,
+    tree::{SyntaxTree, TextRange},
+    value::item_value,
+};
+
+/// A key that is added, removed or changed, see [`diff`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Change {
+    pub kind: ChangeKind,
+
+    /// The parts of the key separated by periods, with the indices of the tables
+    /// of arrays of tables, see [`Document::get`].
+    pub path: String,
+
+    /// The value in the old document, `None` if the key is added.
+    pub old: Option<ChangeValue>,
+
+    /// The value in the new document, `None` if the key is removed.
+    pub new: Option<ChangeValue>,
+}
+
+/// How a key changed between two documents, see [`Change`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// The value of a [`Change`] in one of the documents.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ChangeValue {
+    /// The text of the value, or of the header of an empty table.
+    pub text: String,
+
+    /// The span of the text in its document.
+    pub span: TextRange,
+}
+
+/// The keys that are added, removed or changed from one document to another,
+/// in the order of the keys of the old document followed by the new ones.
+///
+/// The documents are compared by their values, so the layout, the comments and the way
+/// values and tables are written don't matter, e.g. `0x10` is the same as `16` and an inline
+/// table is the same as a table with a header. Tables are compared key by key and the tables
+/// of arrays of tables by their indices, other arrays are compared as a whole.
+pub fn diff(old: &SyntaxTree, new: &SyntaxTree) -> Vec<Change> {
+    let (old_document, new_document) = (Document::new(old), Document::new(new));
+    let mut differ =
+        Differ { old: old.source(), new: new.source(), path: Vec::new(), changes: Vec::new() };
+    differ.tables(Some(old_document.root()), Some(new_document.root()));
+    differ.changes
+}
+
+struct Differ<'s> {
+    old: &'s str,
+    new: &'s str,
+    path: Vec<String>,
+    changes: Vec<Change>,
+}
+
+impl Differ<'_> {
+    /// Compare the items of tables, a missing table has no items.
+    fn tables(&mut self, old: Option<&Table<'_>>, new: Option<&Table<'_>>) {
+        let mut keys =
+            old.into_iter().flat_map(Table::iter).map(|(key, _)| key).collect::<Vec<_>>();
+        for (key, _) in new.into_iter().flat_map(Table::iter) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        for key in keys {
+            self.path.push(key.to_string());
+            self.item(old.and_then(|table| table.get(key)), new.and_then(|table| table.get(key)));
+            self.path.pop();
+        }
+    }
+
+    fn item(&mut self, old: Option<&Item<'_>>, new: Option<&Item<'_>>) {
+        match (old, new) {
+            (None, None) => {}
+            (Some(Item::Table(old)), Some(Item::Table(new))) => self.tables(Some(old), Some(new)),
+            (Some(Item::Table(old)), None) if old.iter().next().is_some() => {
+                self.tables(Some(old), None);
+            }
+            (None, Some(Item::Table(new))) if new.iter().next().is_some() => {
+                self.tables(None, Some(new));
+            }
+            (Some(Item::ArrayOfTables(old)), Some(Item::ArrayOfTables(new))) => {
+                self.arrays_of_tables(old, new);
+            }
+            (Some(Item::ArrayOfTables(old)), None) => self.arrays_of_tables(old, &[]),
+            (None, Some(Item::ArrayOfTables(new))) => self.arrays_of_tables(&[], new),
+            _ => {
+                let old_value = old.and_then(|item| change_value(item, self.old));
+                let new_value = new.and_then(|item| change_value(item, self.new));
+                // A value and a table without text of its own, which is compared key by key.
+                if old.is_some() && old_value.is_none() || new.is_some() && new_value.is_none() {
+                    if old.is_some() && new.is_some() {
+                        self.item(old, None);
+                        self.item(None, new);
+                    }
+                    return;
+                }
+                if let (Some(old), Some(new)) = (old, new) {
+                    let same = item_value(old, self.old) == item_value(new, self.new);
+                    let same_text = old_value.as_ref().map(|value| &value.text)
+                        == new_value.as_ref().map(|value| &value.text);
+                    if same || same_text {
+                        return;
+                    }
+                }
+                let kind = match (&old_value, &new_value) {
+                    (None, _) => ChangeKind::Added,
+                    (_, None) => ChangeKind::Removed,
+                    _ => ChangeKind::Changed,
+                };
+                let path = key_text(&self.path);
+                self.changes.push(Change { kind, path, old: old_value, new: new_value });
+            }
+        }
+    }
+
+    fn arrays_of_tables(&mut self, old: &[Item<'_>], new: &[Item<'_>]) {
+        for idx in 0..old.len().max(new.len()) {
+            self.path.push(idx.to_string());
+            self.item(old.get(idx), new.get(idx));
+            self.path.pop();
+        }
+    }
+}
+
+/// The text of the value of an item, or of the header of a table without items.
+fn change_value(item: &Item<'_>, source: &str) -> Option<ChangeValue> {
+    let span = match item {
+        Item::Table(table) if table.iter().next().is_none() => match table.kind() {
+            TableKind::Header(header) => header.span(),
+            TableKind::ArrayOfTables(header) => header.span(),
+            _ => item.value()?.span(),
+        },
+        _ => item.value()?.span(),
+    };
+    let text = source[span.start as usize..span.end as usize].to_string();
+    Some(ChangeValue { text, span })
+}
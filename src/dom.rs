@@ -0,0 +1,486 @@
+//! A document model that resolves the keys of a syntax tree, see [`Document::get`].
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::{self, AstNode, RootItem, key_part},
+    parser::Parser,
+    tree::{SyntaxTree, TextRange},
+};
+
+/// The tables and values of a document by their keys.
+///
+/// Tables defined by headers, dotted keys and inline tables are all [`Table`]s,
+/// the values keep their nodes so their spans are known.
+/// Keys that are defined more than once keep their first definition.
+#[derive(Debug, Clone)]
+pub struct Document<'a> {
+    root: Table<'a>,
+    syntax: Option<ast::Root<'a>>,
+    source: &'a str,
+}
+
+/// A table with its items in the order they are defined.
+#[derive(Debug, Clone)]
+pub struct Table<'a> {
+    kind: TableKind<'a>,
+    items: Vec<(String, Item<'a>)>,
+}
+
+/// How a [`Table`] is defined.
+#[derive(Debug, Clone, Copy)]
+pub enum TableKind<'a> {
+    /// The document.
+    Root,
+
+    /// A table header, e.g. `[table]`.
+    Header(ast::Table<'a>),
+
+    /// A table of an array of tables, e.g. `[[table]]`.
+    ArrayOfTables(ast::ArrayOfTables<'a>),
+
+    /// An inline table, e.g. `{ key = "value" }`.
+    Inline(ast::InlineTable<'a>),
+
+    /// A table defined by dotted keys, e.g. `table` in `table.key = "value"`.
+    Dotted,
+
+    /// A table that only contains other tables, e.g. `a` in `[a.b]`.
+    Implicit,
+}
+
+/// An array value with its items.
+#[derive(Debug, Clone)]
+pub struct Array<'a> {
+    syntax: ast::Array<'a>,
+    items: Vec<Item<'a>>,
+}
+
+/// A value or a table of a [`Document`].
+#[derive(Debug, Clone)]
+pub enum Item<'a> {
+    /// A string, number, boolean or date-time.
+    Value(ast::Value<'a>),
+
+    Array(Array<'a>),
+
+    Table(Table<'a>),
+
+    /// The tables of an array of tables, they are all [`Item::Table`]s.
+    ArrayOfTables(Vec<Item<'a>>),
+}
+
+impl<'a> Document<'a> {
+    /// Resolve the keys of the tree, syntax errors are skipped.
+    pub fn new(tree: &'a SyntaxTree<'_>) -> Self {
+        let mut root = Table::new(TableKind::Root);
+        let source = tree.source();
+        let Some(syntax) = ast::Root::cast(tree.root()) else {
+            return Self { root, syntax: None, source };
+        };
+
+        // The path of the current table, `None` if its header is invalid.
+        let mut current = Some(Vec::new());
+        for item in syntax.items() {
+            match item {
+                RootItem::Table(header) => {
+                    current = header.key().map(|key| key.parts(source));
+                    if let Some(path) = &current {
+                        root.define_table(path, TableKind::Header(header));
+                    }
+                }
+                RootItem::ArrayOfTables(header) => {
+                    current = header.key().map(|key| key.parts(source));
+                    if let Some(path) = &current {
+                        root.define_table(path, TableKind::ArrayOfTables(header));
+                    }
+                }
+                RootItem::Entry(entry) => {
+                    if let Some(table) = current.as_ref().and_then(|path| root.table_mut(path)) {
+                        table.define_entry(entry, source);
+                    }
+                }
+            }
+        }
+
+        Self { root, syntax: Some(syntax), source }
+    }
+
+    /// The table of the document.
+    pub const fn root(&self) -> &Table<'a> {
+        &self.root
+    }
+
+    /// The item at a path of keys separated by periods, e.g. `package.metadata.docs`.
+    ///
+    /// The parts of the path are written like the parts of TOML keys, so they can be quoted.
+    /// Parts that are numbers are the indices of the items of arrays and arrays of tables.
+    pub fn get(&self, path: &str) -> Option<&Item<'a>> {
+        let parts = path_parts(path)?;
+        let (first, rest) = parts.split_first()?;
+
+        let mut item = self.root.get(first)?;
+        for part in rest {
+            item = match item {
+                Item::Table(table) => table.get(part)?,
+                Item::Array(Array { items, .. }) | Item::ArrayOfTables(items) => {
+                    items.get(part.parse::<usize>().ok()?)?
+                }
+                Item::Value(_) => return None,
+            };
+        }
+        Some(item)
+    }
+
+    /// The keys of the entries in the order they are written, with the keys of
+    /// their tables and the indices of their arrays in front of them.
+    ///
+    /// The keys of the entries of inline tables are included, and so are the keys
+    /// that are defined more than once, unlike the items of the tables.
+    pub fn keys(&self) -> impl Iterator<Item = DocumentKey<'a>> + use<'a> {
+        let mut keys = Vec::new();
+        let Some(syntax) = self.syntax else {
+            return keys.into_iter();
+        };
+
+        // The number of tables of each array of tables by its full key.
+        let mut arrays = HashMap::<Vec<String>, usize>::new();
+        // The parts of the key of the current table, `None` if its header is invalid.
+        let mut table = Some(Vec::new());
+        for item in syntax.items() {
+            let (key, array) = match item {
+                RootItem::Table(header) => (header.key(), false),
+                RootItem::ArrayOfTables(header) => (header.key(), true),
+                RootItem::Entry(entry) => {
+                    if let Some(table) = &table {
+                        entry_keys(entry, table, self.source, &mut keys);
+                    }
+                    continue;
+                }
+            };
+            table = key.map(|key| self.table_parts(key, array, &mut arrays));
+        }
+        keys.into_iter()
+    }
+
+    /// The table headers and entries of the root node.
+    pub(crate) fn syntax_items(&self) -> impl Iterator<Item = RootItem<'a>> + use<'a> {
+        self.syntax.into_iter().flat_map(|syntax| syntax.items())
+    }
+
+    /// The spans of the table headers with the names of the parts of their keys,
+    /// including the indices of the arrays of tables like [`Document::keys`].
+    pub(crate) fn headers(&self) -> Vec<(TextRange, Vec<String>)> {
+        let headers = self.header_keys().into_iter();
+        headers
+            .map(|(span, parts)| (span, parts.into_iter().map(|part| part.name).collect()))
+            .collect()
+    }
+
+    /// The spans of the table headers with the parts of their keys, see [`Document::headers`].
+    pub(crate) fn header_keys(&self) -> Vec<(TextRange, Vec<KeyPart>)> {
+        let mut headers = Vec::new();
+        let Some(syntax) = self.syntax else {
+            return headers;
+        };
+
+        let mut arrays = HashMap::new();
+        for item in syntax.items() {
+            let (node, key, array) = match item {
+                RootItem::Table(header) => (header.span(), header.key(), false),
+                RootItem::ArrayOfTables(header) => (header.span(), header.key(), true),
+                RootItem::Entry(_) => continue,
+            };
+            let parts =
+                key.map(|key| self.table_parts(key, array, &mut arrays)).unwrap_or_default();
+            headers.push((node, parts));
+        }
+        headers
+    }
+
+    /// The parts of the key of a header with the indices of the arrays of tables.
+    fn table_parts(
+        &self,
+        key: ast::Key<'a>,
+        array: bool,
+        arrays: &mut HashMap<Vec<String>, usize>,
+    ) -> Vec<KeyPart> {
+        let mut table = Vec::new();
+        let idents = key.idents().collect::<Vec<_>>();
+        let (Some(first), Some(last)) = (idents.first(), idents.last()) else {
+            return table;
+        };
+        let span = first.span.start..last.span.end;
+        for (idx, ident) in idents.iter().enumerate() {
+            table.push(KeyPart {
+                name: key_part(ident.text(self.source)),
+                span: ident.span.clone(),
+            });
+            let path = table.iter().map(|part| part.name.clone()).collect::<Vec<_>>();
+            let count = if array && idx == idents.len() - 1 {
+                let count = arrays.entry(path).or_default();
+                *count += 1;
+                *count
+            } else {
+                match arrays.get(&path) {
+                    Some(&count) => count,
+                    None => continue,
+                }
+            };
+            table.push(KeyPart { name: (count - 1).to_string(), span: span.clone() });
+        }
+        table
+    }
+}
+
+/// The parts of a path of [`Document::get`], `None` if it is not a valid key.
+pub(crate) fn path_parts(path: &str) -> Option<Vec<String>> {
+    let parse = Parser::new(path).parse_key_only();
+    if !parse.errors.is_empty() {
+        return None;
+    }
+    Some(ast::Key::cast(parse.tree.root())?.parts(path))
+}
+
+/// A key of an entry with the keys in front of it, see [`Document::keys`].
+#[derive(Debug, Clone)]
+pub struct DocumentKey<'a> {
+    /// The parts of the key from the root, e.g. `bin`, `0` and `name` for `name = "a"`
+    /// in the first table of `[[bin]]`.
+    pub parts: Vec<KeyPart>,
+
+    /// The value of the entry.
+    pub value: ast::Value<'a>,
+}
+
+impl DocumentKey<'_> {
+    /// The names of the parts separated by periods, without quotes.
+    pub fn path(&self) -> String {
+        self.parts.iter().map(|part| part.name.as_str()).collect::<Vec<_>>().join(".")
+    }
+}
+
+/// A part of a [`DocumentKey`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct KeyPart {
+    /// The unquoted and unescaped name of the part, or the index in an array.
+    pub name: String,
+
+    /// The span of the part, the span of the key of the header for the indices
+    /// of arrays of tables and the span of the item for the indices of arrays.
+    pub span: TextRange,
+}
+
+/// Add the keys of an entry and of the entries in its value after the parts of `prefix`.
+fn entry_keys<'a>(
+    entry: ast::Entry<'a>,
+    prefix: &[KeyPart],
+    source: &str,
+    keys: &mut Vec<DocumentKey<'a>>,
+) {
+    let (Some(key), Some(value)) = (entry.key(), entry.value()) else {
+        return;
+    };
+    let mut parts = prefix.to_vec();
+    parts.extend(
+        key.idents()
+            .map(|ident| KeyPart { name: key_part(ident.text(source)), span: ident.span.clone() }),
+    );
+    keys.push(DocumentKey { parts: parts.clone(), value });
+    value_keys(value, &parts, source, keys);
+}
+
+/// Add the keys of the entries in a value after the parts of `prefix`.
+fn value_keys<'a>(
+    value: ast::Value<'a>,
+    prefix: &[KeyPart],
+    source: &str,
+    keys: &mut Vec<DocumentKey<'a>>,
+) {
+    match value {
+        ast::Value::InlineTable(table) => {
+            for entry in table.entries() {
+                entry_keys(entry, prefix, source, keys);
+            }
+        }
+        ast::Value::Array(array) => {
+            for (idx, item) in array.items().enumerate() {
+                let mut parts = prefix.to_vec();
+                parts.push(KeyPart { name: idx.to_string(), span: item.span() });
+                value_keys(item, &parts, source, keys);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl<'a> Table<'a> {
+    const fn new(kind: TableKind<'a>) -> Self {
+        Self { kind, items: Vec::new() }
+    }
+
+    /// How the table is defined.
+    pub const fn kind(&self) -> TableKind<'a> {
+        self.kind
+    }
+
+    /// The item with the key in this table.
+    pub fn get(&self, key: &str) -> Option<&Item<'a>> {
+        self.items.iter().find(|(k, _)| k == key).map(|(_, item)| item)
+    }
+
+    /// The keys and items of the table in the order they are defined.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Item<'a>)> {
+        self.items.iter().map(|(key, item)| (key.as_str(), item))
+    }
+
+    /// The span of the key of the header or of the inline table,
+    /// `None` for tables without either.
+    pub fn span(&self) -> Option<TextRange> {
+        match self.kind {
+            TableKind::Header(header) => header.key().map(|key| key.span()),
+            TableKind::ArrayOfTables(header) => header.key().map(|key| key.span()),
+            TableKind::Inline(table) => Some(table.span()),
+            TableKind::Root | TableKind::Dotted | TableKind::Implicit => None,
+        }
+    }
+
+    /// The table with the key, which is created with the given kind if it doesn't exist,
+    /// the last table of an array of tables. `None` if the key is a value.
+    fn child(&mut self, key: &str, kind: TableKind<'a>) -> Option<&mut Table<'a>> {
+        let idx = match self.items.iter().position(|(k, _)| k == key) {
+            Some(idx) => idx,
+            None => {
+                self.items.push((key.to_string(), Item::Table(Table::new(kind))));
+                self.items.len() - 1
+            }
+        };
+        self.items[idx].1.table_mut()
+    }
+
+    /// The table at the path below this one, `None` if it doesn't exist.
+    fn table_mut(&mut self, path: &[String]) -> Option<&mut Table<'a>> {
+        let mut table = self;
+        for part in path {
+            let item = table.items.iter_mut().find(|(k, _)| k == part).map(|(_, item)| item)?;
+            table = item.table_mut()?;
+        }
+        Some(table)
+    }
+
+    /// Define the table of a header at the path below this one.
+    fn define_table(&mut self, path: &[String], kind: TableKind<'a>) {
+        let Some((last, parents)) = path.split_last() else {
+            return;
+        };
+        let mut table = self;
+        for part in parents {
+            match table.child(part, TableKind::Implicit) {
+                Some(child) => table = child,
+                None => return,
+            }
+        }
+
+        let existing = table.items.iter_mut().find(|(k, _)| k == last).map(|(_, item)| item);
+        match (existing, kind) {
+            (None, TableKind::ArrayOfTables(_)) => {
+                let tables = vec![Item::Table(Table::new(kind))];
+                table.items.push((last.clone(), Item::ArrayOfTables(tables)));
+            }
+            (None, _) => table.items.push((last.clone(), Item::Table(Table::new(kind)))),
+            (Some(Item::ArrayOfTables(tables)), TableKind::ArrayOfTables(_)) => {
+                tables.push(Item::Table(Table::new(kind)));
+            }
+            // A table that was only created by the headers of its subtables.
+            (Some(Item::Table(existing)), TableKind::Header(_))
+                if matches!(existing.kind, TableKind::Implicit) =>
+            {
+                existing.kind = kind;
+            }
+            (Some(_), _) => {}
+        }
+    }
+
+    /// Define the value of an entry below this table.
+    fn define_entry(&mut self, entry: ast::Entry<'a>, source: &str) {
+        let (Some(key), Some(value)) = (entry.key(), entry.value()) else {
+            return;
+        };
+        let parts = key.parts(source);
+        let Some((last, parents)) = parts.split_last() else {
+            return;
+        };
+
+        let mut table = self;
+        for part in parents {
+            match table.child(part, TableKind::Dotted) {
+                Some(child) => table = child,
+                None => return,
+            }
+        }
+        if table.get(last).is_none() {
+            table.items.push((last.clone(), Item::from_value(value, source)));
+        }
+    }
+}
+
+impl<'a> Array<'a> {
+    /// The node of the array.
+    pub const fn syntax(&self) -> ast::Array<'a> {
+        self.syntax
+    }
+
+    /// The items of the array.
+    pub fn items(&self) -> &[Item<'a>] {
+        &self.items
+    }
+}
+
+impl<'a> Item<'a> {
+    fn from_value(value: ast::Value<'a>, source: &str) -> Self {
+        match value {
+            ast::Value::Array(syntax) => Self::Array(Array {
+                syntax,
+                items: syntax.items().map(|item| Self::from_value(item, source)).collect(),
+            }),
+            ast::Value::InlineTable(syntax) => {
+                let mut table = Table::new(TableKind::Inline(syntax));
+                for entry in syntax.entries() {
+                    table.define_entry(entry, source);
+                }
+                Self::Table(table)
+            }
+            _ => Self::Value(value),
+        }
+    }
+
+    /// The value node of the item, `None` for tables that are not inline tables
+    /// and arrays of tables.
+    pub fn value(&self) -> Option<ast::Value<'a>> {
+        match self {
+            Self::Value(value) => Some(*value),
+            Self::Array(array) => Some(ast::Value::Array(array.syntax)),
+            Self::Table(Table { kind: TableKind::Inline(table), .. }) => {
+                Some(ast::Value::InlineTable(*table))
+            }
+            Self::Table(_) | Self::ArrayOfTables(_) => None,
+        }
+    }
+
+    /// The table of the item, if it is one.
+    pub const fn as_table(&self) -> Option<&Table<'a>> {
+        match self {
+            Self::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    /// The table of the item, the last one of an array of tables.
+    fn table_mut(&mut self) -> Option<&mut Table<'a>> {
+        match self {
+            Self::Table(table) => Some(table),
+            Self::ArrayOfTables(tables) => tables.last_mut()?.table_mut(),
+            Self::Value(_) | Self::Array(_) => None,
+        }
+    }
+}
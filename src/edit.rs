@@ -0,0 +1,1539 @@
+//! Editing documents in place, see [`DocumentMut`].
+
+use std::{
+    cell::OnceCell,
+    ops::{Index, IndexMut, Range},
+};
+
+use crate::{
+    ast::{self, AstNode, RootItem, key_part},
+    dom::{Document, Item, KeyPart, TableKind, path_parts},
+    emit::{inline_value, key_text, write_string},
+    formatter::{Options, format, is_valid_pattern, matches_pattern},
+    parser::{ParseError, ParseOptions, parse_with},
+    syntax::SyntaxKind::*,
+    tree::{SyntaxTree, TextRange, text_range},
+    value::{Value, item_value},
+};
+
+/// A document that is edited by the paths of its keys.
+///
+/// The parts of the document that are not edited are kept as they are,
+/// the entries and values that are written are formatted with the options.
+///
+/// The values can also be read and set by indexing, e.g.
+/// `doc["dependencies"]["serde"] = Value::from("1")`. The keys that are indexed are added as
+/// tables, and the values that are set are written like with [`DocumentMut::set`] when the text
+/// is read or the document is edited otherwise, empty tables that don't exist are not written.
+/// The number of tables of an array of tables can't be changed this way.
+#[derive(Debug, Clone)]
+pub struct DocumentMut {
+    source: String,
+    options: Options,
+
+    /// The value of the document for indexing, see [`DocumentMut::value`].
+    value: OnceCell<Value>,
+
+    /// Whether the value is edited by indexing and not written to the source yet.
+    edited: bool,
+
+    /// The source with the edited value written, see [`DocumentMut::text`].
+    written: OnceCell<String>,
+}
+
+/// An error that can occur while editing a [`DocumentMut`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum EditError {
+    /// The path is not a valid key, or an index of an array is out of bounds.
+    InvalidPath,
+
+    /// The key is a value, so no keys can be defined in it.
+    NotATable {
+        /// The parts of the key separated by periods.
+        path: String,
+    },
+
+    /// The key is a table with a header or an array of tables, which can't be replaced
+    /// by a value. It can be removed first.
+    NotAValue {
+        /// The parts of the key separated by periods.
+        path: String,
+    },
+
+    /// A key that is inserted or that a key is renamed to already exists.
+    Exists {
+        /// The parts of the key separated by periods.
+        path: String,
+    },
+
+    /// A key that is renamed or edited doesn't exist.
+    Missing {
+        /// The parts of the key separated by periods.
+        path: String,
+    },
+
+    /// The key of an [`ArrayEdit`] is not an array.
+    NotAnArray {
+        /// The parts of the key separated by periods.
+        path: String,
+    },
+
+    /// The edit would make the document invalid, so it is not made.
+    InvalidDocument,
+}
+
+impl core::fmt::Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidPath => f.write_str("invalid path"),
+            Self::NotATable { path } => write!(f, "`{path}` is a value, not a table"),
+            Self::NotAValue { path } => write!(f, "`{path}` is a table with a header"),
+            Self::Exists { path } => write!(f, "`{path}` already exists"),
+            Self::Missing { path } => write!(f, "`{path}` doesn't exist"),
+            Self::NotAnArray { path } => write!(f, "`{path}` is not an array"),
+            Self::InvalidDocument => f.write_str("the edit would make the document invalid"),
+        }
+    }
+}
+impl std::error::Error for EditError {}
+
+/// Where the entries of a table are written.
+enum Section<'a> {
+    /// The entries before the first table header.
+    Root,
+
+    /// The entries after a table header, by the span of the header.
+    Header(TextRange),
+
+    Inline(ast::InlineTable<'a>),
+
+    /// A table that only contains tables, a header is written for it.
+    New {
+        /// The key of the header.
+        key: Vec<String>,
+
+        /// The key with the indices of arrays of tables, see [`Document::keys`].
+        resolved: Vec<String>,
+    },
+}
+
+impl DocumentMut {
+    /// Parse a document to edit it, it must not have syntax or semantic errors.
+    pub fn parse(source: &str, options: Options) -> Result<Self, Vec<ParseError>> {
+        let parse = parse_with(source, parse_options(&options));
+        if !parse.errors.is_empty() {
+            return Err(parse.errors);
+        }
+        Ok(Self::new(source.to_string(), options))
+    }
+
+    const fn new(source: String, options: Options) -> Self {
+        Self { source, options, value: OnceCell::new(), edited: false, written: OnceCell::new() }
+    }
+
+    /// The text of the document.
+    pub fn as_str(&self) -> &str {
+        self.text()
+    }
+
+    /// The value at a path of keys, see [`Document::get`].
+    pub fn get(&self, path: &str) -> Option<Value> {
+        let tree = self.tree();
+        let document = Document::new(&tree);
+        item_value(document.get(path)?, tree.source())
+    }
+
+    /// Set the value at a path of keys, see [`Document::get`].
+    ///
+    /// Only the value of an existing entry is replaced, its key and the whitespace and
+    /// comments around the value are kept. The new value is formatted with the options
+    /// after the key, e.g. a long array is written on several lines.
+    /// Missing entries are added like with [`DocumentMut::insert`].
+    /// Tables are written as inline tables.
+    pub fn set(&mut self, path: &str, value: &Value) -> Result<(), EditError> {
+        self.commit();
+        let parts = path_parts(path).filter(|parts| !parts.is_empty());
+        let parts = parts.ok_or(EditError::InvalidPath)?;
+
+        let tree = self.tree();
+        let document = Document::new(&tree);
+        let change = self.plan_set(&document, &parts, value)?;
+        self.apply(change);
+        Ok(())
+    }
+
+    /// Add an entry for a path of keys that doesn't exist yet, returns the span of the entry.
+    ///
+    /// The entry is added to the closest table that exists, with a dotted key for the tables
+    /// that don't, or to a new table header if that table only has headers itself, which
+    /// includes the root table.
+    /// It is written like the entries around it: at its position if they are sorted by their
+    /// keys, with their indentation and spacing around `=`, with quotes around the key if
+    /// they are all quoted, and strings as literal strings if theirs are.
+    pub fn insert(&mut self, path: &str, value: &Value) -> Result<TextRange, EditError> {
+        self.commit();
+        let parts = path_parts(path).filter(|parts| !parts.is_empty());
+        let parts = parts.ok_or(EditError::InvalidPath)?;
+
+        let tree = self.tree();
+        let document = Document::new(&tree);
+        if document.get(path).is_some() {
+            return Err(EditError::Exists { path: parts.join(".") });
+        }
+        let change = self.plan_set(&document, &parts, value)?;
+        Ok(self.apply(change))
+    }
+
+    /// Remove the entries and tables at a path of keys, including the tables in them,
+    /// returns whether anything was removed.
+    ///
+    /// The comments on the lines right above them and at the end of their lines are
+    /// removed with them, and so is a blank line that would be left next to another one
+    /// or at the start or the end of the document.
+    pub fn remove(&mut self, path: &str) -> bool {
+        self.commit();
+        let Some(parts) = path_parts(path).filter(|parts| !parts.is_empty()) else {
+            return false;
+        };
+
+        let tree = self.tree();
+        let document = Document::new(&tree);
+        let mut ranges = Vec::new();
+
+        let headers = document.headers();
+        for (idx, (span, names)) in headers.iter().enumerate() {
+            if names.starts_with(&parts) {
+                let end = headers.get(idx + 1).map_or(self.source.len(), |(next, _)| {
+                    comments_start(&self.source, line_start(&self.source, next.start as usize))
+                });
+                let start = line_start(&self.source, span.start as usize);
+                ranges.push(comments_start(&self.source, start)..end);
+            }
+        }
+        for key in document.keys() {
+            let names = key.parts.iter().map(|part| part.name.as_str());
+            if names.clone().count() >= parts.len() && names.zip(&parts).all(|(a, b)| a == b) {
+                ranges.extend(self.entry_range(&tree, key.value));
+            }
+        }
+        if ranges.is_empty() {
+            return false;
+        }
+
+        // The ranges of the entries in removed tables and inline tables are contained in theirs.
+        ranges.sort_by_key(|range| (range.start, usize::MAX - range.end));
+        let mut removed: Vec<Range<usize>> = Vec::new();
+        for range in ranges {
+            match removed.last_mut() {
+                Some(last) if range.start < last.end => last.end = last.end.max(range.end),
+                _ => removed.push(range),
+            }
+        }
+        for range in removed.into_iter().rev() {
+            let range = self.with_blank_lines(range);
+            self.source.replace_range(range, "");
+        }
+        true
+    }
+
+    /// Rename the last part of a path of keys, e.g. `b` for `a.b`, everywhere it is written:
+    /// in the table headers, in the dotted keys and in the inline tables.
+    ///
+    /// Only the parts of the keys are replaced, quoted parts stay quoted with their quotes.
+    pub fn rename(&mut self, path: &str, name: &str) -> Result<(), EditError> {
+        self.commit();
+        let parts = path_parts(path).filter(|parts| !parts.is_empty());
+        let parts = parts.ok_or(EditError::InvalidPath)?;
+        let old = &parts[parts.len() - 1];
+
+        let tree = self.tree();
+        let document = Document::new(&tree);
+        let mut renamed = parts.clone();
+        renamed[parts.len() - 1] = name.to_string();
+        if old != name && document.get(&key_text(&renamed)).is_some() {
+            return Err(EditError::Exists { path: renamed.join(".") });
+        }
+
+        let headers = document.header_keys().into_iter().map(|(_, key)| key);
+        let mut spans = Vec::new();
+        for key in headers.chain(document.keys().map(|key| key.parts)) {
+            if key.len() < parts.len() || !key.iter().zip(&parts).all(|(a, b)| a.name == *b) {
+                continue;
+            }
+            // The indices of arrays have the spans of the keys of the arrays.
+            let span = key[parts.len() - 1].span.clone();
+            if key_part(&self.source[span.start as usize..span.end as usize]) == *old {
+                spans.push(span);
+            }
+        }
+        if spans.is_empty() {
+            return Err(match document.get(path) {
+                Some(_) => EditError::InvalidPath,
+                None => EditError::Missing { path: parts.join(".") },
+            });
+        }
+
+        spans.sort_by_key(|span| span.start);
+        spans.dedup();
+        for span in spans.into_iter().rev() {
+            let range = span.start as usize..span.end as usize;
+            let text = match self.source[range.clone()].chars().next() {
+                Some('"') => {
+                    let mut text = String::new();
+                    write_string(&mut text, name);
+                    text
+                }
+                Some('\'') if is_literal(name) => format!("'{name}'"),
+                _ => key_text(std::slice::from_ref(&name.to_string())),
+            };
+            self.source.replace_range(range, &text);
+        }
+        Ok(())
+    }
+
+    /// Sort the entries of the tables with a header whose keys match a pattern by their keys,
+    /// returns the number of tables that match. Nothing else is changed.
+    ///
+    /// The pattern is a key whose bare parts can contain the `*` and `?` wildcards like the
+    /// patterns of [`FormatRule`](crate::FormatRule), it matches the whole key of the header,
+    /// e.g. `*dependencies` matches `[dev-dependencies]` but not `[target.x.dependencies]`.
+    /// The comments above the entries move with them and the groups of entries that are
+    /// separated by blank lines are sorted separately.
+    pub fn sort_table(&mut self, pattern: &str) -> Result<usize, EditError> {
+        self.commit();
+        if !is_valid_pattern(pattern) {
+            return Err(EditError::InvalidPath);
+        }
+
+        let tree = self.tree();
+        let document = Document::new(&tree);
+        // The entries of the tables that match.
+        let mut sections: Vec<Vec<ast::Entry<'_>>> = Vec::new();
+        let mut matched = false;
+        for item in document.syntax_items() {
+            let key = match item {
+                RootItem::Table(header) => header.key(),
+                RootItem::ArrayOfTables(header) => header.key(),
+                RootItem::Entry(entry) => {
+                    if let Some(section) = sections.last_mut().filter(|_| matched) {
+                        section.push(entry);
+                    }
+                    continue;
+                }
+            };
+            let key = key.map(|key| key.parts(&self.source)).unwrap_or_default();
+            matched = matches_pattern(pattern, &key);
+            if matched {
+                sections.push(Vec::new());
+            }
+        }
+
+        let source = &self.source;
+        let mut changes = Vec::new();
+        for entries in &sections {
+            // The entries with the comments above them, in groups separated by blank lines.
+            let mut groups: Vec<Vec<(Vec<String>, Range<usize>)>> = Vec::new();
+            let mut prev_end = None;
+            for entry in entries {
+                let (Some(key), Some(value)) = (entry.key(), entry.value()) else {
+                    continue;
+                };
+                let start = comments_start(source, line_start(source, entry.span().start as usize));
+                let end = line_end(source, value.span().end as usize);
+                let end = if source[..end].ends_with('\n') { end - 1 } else { end };
+                match groups.last_mut() {
+                    Some(group) if prev_end == Some(start.saturating_sub(1)) => {
+                        group.push((key.parts(source), start..end));
+                    }
+                    _ => groups.push(vec![(key.parts(source), start..end)]),
+                }
+                prev_end = Some(end);
+            }
+
+            for mut group in groups {
+                let range = group[0].1.start..group[group.len() - 1].1.end;
+                group.sort_by(|(a, _), (b, _)| a.cmp(b));
+                let blocks = group.iter().map(|(_, block)| &source[block.clone()]);
+                let text = blocks.collect::<Vec<_>>().join("\n");
+                if text != source[range.clone()] {
+                    changes.push((range, text));
+                }
+            }
+        }
+        for (range, text) in changes.into_iter().rev() {
+            self.source.replace_range(range, &text);
+        }
+        Ok(sections.len())
+    }
+
+    /// Move the section of a table header, with its entries and the comments above it,
+    /// before or after the section of another one. The sections are found by their keys
+    /// with the indices of the arrays of tables, e.g. `bin.1` for the second `[[bin]]`.
+    ///
+    /// The document is left as it is if the move would make it invalid, e.g. if a table
+    /// is moved before the table of an array of tables that it belongs to.
+    pub fn move_table(&mut self, path: &str, position: TablePosition) -> Result<(), EditError> {
+        self.commit();
+        let (target, after) = match &position {
+            TablePosition::Before(target) => (target.as_str(), false),
+            TablePosition::After(target) => (target.as_str(), true),
+        };
+        let parts = path_parts(path).ok_or(EditError::InvalidPath)?;
+        let target = path_parts(target).ok_or(EditError::InvalidPath)?;
+        let missing = |parts: &[String]| EditError::Missing { path: parts.join(".") };
+        let section = self.section(&parts).ok_or_else(|| missing(&parts))?;
+        self.section(&target).ok_or_else(|| missing(&target))?;
+        if parts == target {
+            return Ok(());
+        }
+
+        let mut moved = self.clone();
+        let text = moved.source[section.clone()].trim_end().to_string();
+        let range = moved.with_blank_lines(section);
+        moved.source.replace_range(range, "");
+        let section = moved.section(&target).ok_or_else(|| missing(&target))?;
+        if after && section.end == moved.source.len() {
+            if !moved.source.ends_with('\n') {
+                moved.source.push('\n');
+            }
+            moved.source += &format!("\n{text}\n");
+        } else {
+            let pos = if after { section.end } else { section.start };
+            moved.source.insert_str(pos, &format!("{text}\n\n"));
+        }
+
+        if !parse_with(&moved.source, parse_options(&self.options)).errors.is_empty() {
+            return Err(EditError::InvalidDocument);
+        }
+        *self = moved;
+        Ok(())
+    }
+
+    /// The range of the section of a table header with the comments above it
+    /// and the blank lines after it.
+    fn section(&self, parts: &[String]) -> Option<Range<usize>> {
+        let tree = self.tree();
+        let headers = Document::new(&tree).headers();
+        let source = &self.source;
+        let idx = headers.iter().position(|(_, names)| names == parts)?;
+        let start = comments_start(source, line_start(source, headers[idx].0.start as usize));
+        let end = headers.get(idx + 1).map_or(source.len(), |(next, _)| {
+            comments_start(source, line_start(source, next.start as usize))
+        });
+        Some(start..end)
+    }
+
+    /// A document with the table or the entry at a path of keys, with the comments above its
+    /// entries and tables. Its keys are relative to the table that contains it, e.g. it has
+    /// a `[dependencies]` table for `target.x.dependencies`. See [`DocumentMut::graft`].
+    ///
+    /// `None` if nothing is at the path or if it is in an array of tables.
+    pub fn extract(&self, path: &str) -> Option<Self> {
+        let parts = path_parts(path).filter(|parts| !parts.is_empty())?;
+        let tree = self.tree();
+        let document = Document::new(&tree);
+        let parent = parts.len() - 1;
+        if !is_table_path(&document, &parts[..parent]) {
+            return None;
+        }
+        let item = document.get(path)?;
+
+        let source = tree.source();
+        let headers = document.header_keys();
+        let mut entries = String::new();
+        for key in document.keys() {
+            if !is_prefix(&parts, &key.parts) {
+                continue;
+            }
+            // The entries of the tables at the path are in their sections.
+            let Some(entry) = root_entry(&tree, key.value) else {
+                continue;
+            };
+            let section = headers.iter().rev().find(|(span, _)| span.start < entry.start);
+            if section.is_some_and(|(_, names)| is_prefix(&parts, names)) {
+                continue;
+            }
+
+            let start = comments_start(source, line_start(source, entry.start as usize));
+            let key_end = key.parts[key.parts.len() - 1].span.end as usize;
+            let names =
+                key.parts[parent..].iter().map(|part| part.name.clone()).collect::<Vec<_>>();
+            let end = line_end(source, key.value.span().end as usize);
+            entries += &source[start..entry.start as usize];
+            entries += &key_text(&names);
+            entries += source[key_end..end].trim_end();
+            entries.push('\n');
+        }
+
+        let mut sections = Vec::new();
+        let syntax_headers = document.syntax_items().filter_map(|item| match item {
+            RootItem::Table(header) => Some(header.syntax()),
+            RootItem::ArrayOfTables(header) => Some(header.syntax()),
+            RootItem::Entry(_) => None,
+        });
+        for (idx, ((span, names), header)) in headers.iter().zip(syntax_headers).enumerate() {
+            if !is_prefix(&parts, names) {
+                continue;
+            }
+            let start = comments_start(source, line_start(source, span.start as usize));
+            let end = headers.get(idx + 1).map_or(source.len(), |(next, _)| {
+                comments_start(source, line_start(source, next.start as usize))
+            });
+            let key = header.children().iter().find_map(|child| ast::Key::cast(child.as_node()?));
+            let Some(key) = key else {
+                continue;
+            };
+            let idents = key.idents().collect::<Vec<_>>();
+            let names = key.parts(source)[parent..].to_vec();
+            let (key_start, key_end) =
+                (idents[0].span.start as usize, idents[idents.len() - 1].span.end as usize);
+            sections.push(format!(
+                "{}{}{}",
+                &source[start..key_start],
+                key_text(&names),
+                source[key_end..end].trim_end()
+            ));
+        }
+
+        let mut fragment = entries;
+        if fragment.is_empty() && sections.is_empty() {
+            // A value in an inline table or an array.
+            let value = item_value(item, source)?;
+            fragment = format!("{} = {}\n", key_text(&parts[parent..]), inline_value(&value));
+        }
+        for section in sections {
+            if !fragment.is_empty() {
+                fragment.push('\n');
+            }
+            fragment += &section;
+            fragment.push('\n');
+        }
+        Some(Self::new(fragment, self.options.clone()))
+    }
+
+    /// Add the entries and the tables of another document to the table at a path of keys,
+    /// e.g. a document from [`DocumentMut::extract`]. An empty path adds them to the root.
+    /// Returns the spans of the text that is added.
+    ///
+    /// The entries are added after the ones of the table, in a new table header if it doesn't
+    /// have one, and the tables are added at the end of the document. Only the added text is
+    /// formatted with the options. The document is left as it is if the keys that are added
+    /// already exist or if the table can't have headers, e.g. because it is an inline table.
+    pub fn graft(&mut self, path: &str, other: &Self) -> Result<Vec<TextRange>, EditError> {
+        self.commit();
+        let parts = if path.is_empty() {
+            Vec::new()
+        } else {
+            path_parts(path).filter(|parts| !parts.is_empty()).ok_or(EditError::InvalidPath)?
+        };
+        let tree = self.tree();
+        let document = Document::new(&tree);
+        if !is_table_path(&document, &parts) {
+            return Err(EditError::InvalidPath);
+        }
+
+        // The entries and the sections of the other document, with the path in front of the keys.
+        let other_tree = other.tree();
+        let other_document = Document::new(&other_tree);
+        let other_source = other_tree.source();
+        let headers = other_document.headers();
+        let first = headers.first().map_or(other_source.len(), |(span, _)| {
+            comments_start(other_source, line_start(other_source, span.start as usize))
+        });
+        let entries = other_source[..first].trim();
+        let mut sections = Vec::new();
+        for item in other_document.syntax_items() {
+            let (header, open, close) = match item {
+                RootItem::Table(header) => (header.syntax(), "[", "]"),
+                RootItem::ArrayOfTables(header) => (header.syntax(), "[[", "]]"),
+                RootItem::Entry(_) => continue,
+            };
+            let key = header.children().iter().find_map(|child| ast::Key::cast(child.as_node()?));
+            let mut names = parts.clone();
+            names.extend(key.map(|key| key.parts(other_source)).unwrap_or_default());
+            let start =
+                comments_start(other_source, line_start(other_source, header.span.start as usize));
+            let end = headers.iter().find(|(span, _)| span.start > header.span.start).map_or(
+                other_source.len(),
+                |(next, _)| {
+                    comments_start(other_source, line_start(other_source, next.start as usize))
+                },
+            );
+            let comments = &other_source[start..header.span.start as usize];
+            let body = &other_source[line_end(other_source, header.span.end as usize)..end];
+            sections.push(format!(
+                "{comments}{open}{}{close}\n{}",
+                key_text(&names),
+                body.trim_end()
+            ));
+        }
+
+        let header = match parts.is_empty() {
+            true => Some(None),
+            false => match document.get(&key_text(&parts)) {
+                Some(Item::Table(table)) => match table.kind() {
+                    TableKind::Header(header) => Some(Some(header.span())),
+                    _ => None,
+                },
+                _ => None,
+            },
+        };
+        if !entries.is_empty() && header.is_none() {
+            sections.insert(0, format!("[{}]\n{entries}", key_text(&parts)));
+        }
+
+        let original = self.source.clone();
+        let mut spans = Vec::new();
+        if let Some(header) = header.filter(|_| !entries.is_empty()) {
+            let (start, section) = self.section_entries(&document, header);
+            let pos = section
+                .last()
+                .and_then(ast::Entry::value)
+                .map_or(start, |value| line_end(&self.source, value.span().end as usize));
+            let formatted = self.format_entries(&format!("{entries}\n"));
+            let mut text = String::new();
+            if pos > 0 && !self.source[..pos].ends_with('\n') {
+                text.push('\n');
+            }
+            let written = text.len()..text.len() + formatted.len();
+            text += &formatted;
+            if section.is_empty() && pos == 0 && !self.source.is_empty() {
+                text.push('\n');
+            }
+            spans.push(self.apply(Change::written(pos..pos, text, written)));
+        }
+        if !sections.is_empty() {
+            let formatted = sections
+                .iter()
+                .map(|section| self.format_entries(&format!("{section}\n")))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let end = self.source.len();
+            let mut text = String::new();
+            if end > 0 && !self.source.ends_with('\n') {
+                text.push('\n');
+            }
+            if !self.source.trim().is_empty() {
+                text.push('\n');
+            }
+            let written = text.len()..text.len() + formatted.len();
+            text += &formatted;
+            spans.push(self.apply(Change::written(end..end, text, written)));
+        }
+
+        if !parse_with(&self.source, parse_options(&self.options)).errors.is_empty() {
+            self.source = original;
+            return Err(EditError::InvalidDocument);
+        }
+        Ok(spans)
+    }
+
+    /// Edit the items of the array at a path of keys, see [`ArrayEdit`].
+    pub fn array(&mut self, path: &str) -> Result<ArrayEdit<'_>, EditError> {
+        self.commit();
+        let parts = path_parts(path).filter(|parts| !parts.is_empty());
+        let parts = parts.ok_or(EditError::InvalidPath)?;
+
+        let tree = self.tree();
+        let document = Document::new(&tree);
+        match document.get(path) {
+            Some(Item::Array(_)) => {}
+            Some(_) => return Err(EditError::NotAnArray { path: parts.join(".") }),
+            None => return Err(EditError::Missing { path: parts.join(".") }),
+        }
+        Ok(ArrayEdit { document: self, path: path.to_string() })
+    }
+
+    pub(crate) const fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub(crate) fn tree(&self) -> SyntaxTree<'static> {
+        parse_with(self.text(), parse_options(&self.options)).into_syntax()
+    }
+
+    /// The source with the value that is edited by indexing written to it.
+    fn text(&self) -> &str {
+        match self.value.get() {
+            Some(value) if self.edited => self.written.get_or_init(|| {
+                let mut document = Self::new(self.source.clone(), self.options.clone());
+                let old = document.tree().to_value();
+                document.write_value(&mut Vec::new(), Some(&old), value);
+                document.source
+            }),
+            _ => &self.source,
+        }
+    }
+
+    /// The value of the document that is read and edited by indexing.
+    fn value(&self) -> &Value {
+        self.value.get_or_init(|| self.tree().to_value())
+    }
+
+    /// Write the value that is edited by indexing to the source, before it is edited
+    /// otherwise. The value is read from the source again after that.
+    fn commit(&mut self) {
+        if self.edited {
+            self.text();
+            self.source = self.written.take().unwrap_or_default();
+            self.edited = false;
+        }
+        self.value.take();
+    }
+
+    /// Write the differences between the old and the new value at a path of keys, the keys
+    /// of tables are written one by one, so that new tables get headers like with
+    /// [`DocumentMut::insert`], and so are the tables of arrays of tables.
+    fn write_value(&mut self, path: &mut Vec<String>, old: Option<&Value>, new: &Value) {
+        match (old, new) {
+            (Some(old), new) if old == new => {}
+            (None | Some(Value::Table(_)), Value::Table(new))
+                if !new.is_empty() || old.is_some() =>
+            {
+                let old = old.and_then(Value::as_table);
+                for key in old.into_iter().flat_map(|old| old.keys()) {
+                    if !new.contains_key(key) {
+                        path.push(key.clone());
+                        self.remove(&key_text(path));
+                        path.pop();
+                    }
+                }
+                for (key, value) in new {
+                    path.push(key.clone());
+                    self.write_value(path, old.and_then(|old| old.get(key)), value);
+                    path.pop();
+                }
+            }
+            (Some(Value::Array(old)), Value::Array(new))
+                if old.len() == new.len() && self.is_array_of_tables(&key_text(path)) =>
+            {
+                for (idx, (old, new)) in old.iter().zip(new).enumerate() {
+                    path.push(idx.to_string());
+                    self.write_value(path, Some(old), new);
+                    path.pop();
+                }
+            }
+            (None, Value::Table(_)) => {}
+            (_, new) => {
+                // Only values can be set, e.g. an array of tables with more tables can't.
+                let _ = self.set(&key_text(path), new);
+            }
+        }
+    }
+
+    /// Apply a change to the text, returns the span of what it writes.
+    fn apply(&mut self, change: Change) -> TextRange {
+        let start = change.range.start;
+        self.source.replace_range(change.range, &change.text);
+        text_range(start + change.written.start, start + change.written.end)
+    }
+
+    /// The change that sets the value at the path.
+    fn plan_set(
+        &self,
+        document: &Document<'_>,
+        parts: &[String],
+        value: &Value,
+    ) -> Result<Change, EditError> {
+        let mut table = document.root();
+        let mut section = Section::Root;
+        // The parts of the key in front of the new entry in its section.
+        let mut prefix = Vec::new();
+        let mut key = Vec::new();
+        let mut resolved = Vec::new();
+
+        let mut idx = 0;
+        while idx < parts.len() {
+            let part = &parts[idx];
+            let last = idx == parts.len() - 1;
+            let Some(item) = table.get(part) else {
+                prefix.extend_from_slice(&parts[idx..]);
+                return Ok(self.plan_insert(document, section, &prefix, value));
+            };
+            key.push(part.clone());
+            resolved.push(part.clone());
+            let path = || parts[..=idx].join(".");
+
+            let item = match item {
+                Item::Array(array) if !last => {
+                    idx += 1;
+                    let index = parts[idx].parse::<usize>().ok();
+                    let item = index.and_then(|index| array.items().get(index));
+                    let item = item.ok_or(EditError::InvalidPath)?;
+                    if idx == parts.len() - 1 {
+                        return Ok(self.plan_replace(document, item, value));
+                    }
+                    resolved.push(parts[idx].clone());
+                    item
+                }
+                Item::ArrayOfTables(tables) if !last => {
+                    idx += 1;
+                    let index = parts[idx].parse::<usize>().ok();
+                    let item = index.and_then(|index| tables.get(index));
+                    let item = item.ok_or(EditError::InvalidPath)?;
+                    if idx == parts.len() - 1 {
+                        return Err(EditError::NotAValue { path: parts[..=idx].join(".") });
+                    }
+                    resolved.push(parts[idx].clone());
+                    item
+                }
+                Item::ArrayOfTables(_) => return Err(EditError::NotAValue { path: path() }),
+                item if last => {
+                    return match item.value() {
+                        Some(_) => Ok(self.plan_replace(document, item, value)),
+                        None => Err(EditError::NotAValue { path: path() }),
+                    };
+                }
+                item => item,
+            };
+
+            let Some(child) = item.as_table() else {
+                return Err(EditError::NotATable { path: parts[..=idx].join(".") });
+            };
+            match child.kind() {
+                TableKind::Header(header) => {
+                    section = Section::Header(header.span());
+                    prefix.clear();
+                }
+                TableKind::ArrayOfTables(header) => {
+                    section = Section::Header(header.span());
+                    prefix.clear();
+                }
+                TableKind::Inline(inline) => {
+                    section = Section::Inline(inline);
+                    prefix.clear();
+                }
+                TableKind::Implicit => {
+                    section = Section::New { key: key.clone(), resolved: resolved.clone() };
+                    prefix.clear();
+                }
+                TableKind::Dotted => prefix.push(part.clone()),
+                TableKind::Root => {}
+            }
+            table = child;
+            idx += 1;
+        }
+        Err(EditError::InvalidPath)
+    }
+
+    /// Replace the value of an item, the values of entries are formatted after their keys.
+    fn plan_replace(&self, document: &Document<'_>, item: &Item<'_>, value: &Value) -> Change {
+        let span = item.value().map_or(0..0, |value| value.span());
+        let range = span.start as usize..span.end as usize;
+        let key = document.syntax_items().find_map(|item| match item {
+            RootItem::Entry(entry) if entry.value().is_some_and(|v| v.span() == span) => {
+                let mut idents = entry.key()?.idents();
+                let first = idents.next()?;
+                let last = idents.last().unwrap_or(first);
+                Some(&self.source[first.span.start as usize..last.span.end as usize])
+            }
+            _ => None,
+        });
+        let Some(key) = key else {
+            return Change::new(range, self.format_value(value));
+        };
+
+        // The key is formatted with the value, so that its width is taken into account.
+        let formatted = self.format_entries(&format!("{key} = {}\n", inline_value(value)));
+        let tree = parse_with(&formatted, ParseOptions::default()).into_syntax();
+        let entry = Document::new(&tree).syntax_items().find_map(|item| match item {
+            RootItem::Entry(entry) => entry.value(),
+            _ => None,
+        });
+        let Some(text) = entry.map(|value| value.text(&formatted)) else {
+            return Change::new(range, self.format_value(value));
+        };
+
+        // The lines of a value that is written on several lines are indented like the entry.
+        let line = &self.source[line_start(&self.source, range.start)..range.start];
+        let indent = &line[..line.len() - line.trim_start().len()];
+        Change::new(range, text.replace('\n', &format!("\n{indent}")))
+    }
+
+    /// Add an entry to a section.
+    fn plan_insert(
+        &self,
+        document: &Document<'_>,
+        section: Section<'_>,
+        key: &[String],
+        value: &Value,
+    ) -> Change {
+        let source = &self.source;
+
+        // A root without entries of its own gets a new table after the others, the way
+        // `cargo add` writes `[dependencies]`, rather than a dotted key above the headers.
+        let section = match section {
+            Section::Root
+                if key.len() > 1
+                    && !document.headers().is_empty()
+                    && self.section_entries(document, None).1.is_empty() =>
+            {
+                Section::New { key: Vec::new(), resolved: Vec::new() }
+            }
+            section => section,
+        };
+
+        let header = match section {
+            Section::Inline(table) => {
+                let entries: Vec<_> =
+                    table.entries().filter(|entry| entry.value().is_some()).collect();
+                let entry = Style::of(&entries, source).entry(self, key, value);
+                return match entries.last().and_then(ast::Entry::value) {
+                    Some(last) => {
+                        let end = last.span().end as usize;
+                        Change::written(end..end, format!(", {entry}"), 2..2 + entry.len())
+                    }
+                    None => {
+                        let span = table.span();
+                        let text = format!("{{ {entry} }}");
+                        Change::written(
+                            span.start as usize..span.end as usize,
+                            text,
+                            2..2 + entry.len(),
+                        )
+                    }
+                };
+            }
+            Section::New { key: mut header, resolved } => {
+                // The tables that don't exist are written in the header, not as dotted keys.
+                let tables = &key[..key.len() - 1];
+                header.extend_from_slice(tables);
+                let entry = Style::default().entry(self, &key[tables.len()..], value);
+
+                // After the tables in the table, which can be in a table of an array of tables.
+                let headers = document.headers();
+                let anchor = headers.iter().rposition(|(_, names)| names.starts_with(&resolved));
+                let end = anchor
+                    .and_then(|idx| headers.get(idx + 1))
+                    .map_or(source.len(), |next| line_start(source, next.0.start as usize));
+                let mut text = String::new();
+                if !source[..end].ends_with('\n') && end > 0 {
+                    text.push('\n');
+                }
+                if end > 0 {
+                    text.push('\n');
+                }
+                text += &self.format_entries(&format!("[{}]\n", key_text(&header)));
+                let written = text.len()..text.len() + entry.len();
+                text += &entry;
+                text.push('\n');
+                if end < source.len() {
+                    text.push('\n');
+                }
+                return Change::written(end..end, text, written);
+            }
+            Section::Root => None,
+            Section::Header(span) => Some(span),
+        };
+        let (start, entries) = self.section_entries(document, header);
+        let style = Style::of(&entries, source);
+        let entry = style.entry(self, key, value);
+
+        // In order if the entries are sorted, otherwise after the last one.
+        let keys: Vec<_> = entries
+            .iter()
+            .map(|entry| entry.key().map(|key| key.parts(source)).unwrap_or_default())
+            .collect();
+        let next = if keys.len() > 1 && keys.is_sorted() {
+            keys.iter().position(|other| other.as_slice() > key)
+        } else {
+            None
+        };
+        if let Some(next) = next {
+            let pos =
+                comments_start(source, line_start(source, entries[next].span().start as usize));
+            let text = format!("{}{entry}\n", style.indent);
+            let written = style.indent.len()..style.indent.len() + entry.len();
+            return Change::written(pos..pos, text, written);
+        }
+
+        let last = entries.last().and_then(ast::Entry::value);
+        let pos = last.map_or(start, |value| line_end(source, value.span().end as usize));
+        let mut text = String::new();
+        if pos > 0 && !source[..pos].ends_with('\n') {
+            text.push('\n');
+        }
+        text += &style.indent;
+        let written = text.len()..text.len() + entry.len();
+        text += &entry;
+        text.push('\n');
+        // A blank line before the first table header.
+        if last.is_none() && pos == 0 && !source.is_empty() {
+            text.push('\n');
+        }
+        Change::written(pos..pos, text, written)
+    }
+
+    /// The start of the entries of the root table or of a table header, after the line of
+    /// the header, and its entries.
+    fn section_entries<'t>(
+        &self,
+        document: &Document<'t>,
+        header: Option<TextRange>,
+    ) -> (usize, Vec<ast::Entry<'t>>) {
+        let source = &self.source;
+        let headers = document.headers();
+        let (start, end) = match header {
+            None => (0, headers.first().map(|(span, _)| span.start as usize)),
+            Some(span) => {
+                let next = headers.iter().find(|(header, _)| header.start > span.start);
+                (line_end(source, span.end as usize), next.map(|(header, _)| header.start as usize))
+            }
+        };
+
+        let end = end.map_or(source.len(), |end| line_start(source, end));
+        let entries = document
+            .syntax_items()
+            .filter_map(|item| match item {
+                RootItem::Entry(entry) => Some(entry),
+                _ => None,
+            })
+            .filter(|entry| {
+                entry.value().is_some_and(|value| {
+                    let pos = line_end(source, value.span().end as usize);
+                    pos > start && pos <= end
+                })
+            })
+            .collect();
+        (start, entries)
+    }
+
+    /// The text of a value formatted as the value of an entry.
+    fn format_value(&self, value: &Value) -> String {
+        let formatted = self.format_entries(&format!("x = {}\n", inline_value(value)));
+        let formatted = formatted.trim_end();
+        formatted.strip_prefix("x = ").unwrap_or(formatted).to_string()
+    }
+
+    /// Format entries that are added, they end with a line break.
+    fn format_entries(&self, text: &str) -> String {
+        let mut formatted = format(text, self.options.clone());
+        if !formatted.ends_with('\n') {
+            formatted.push('\n');
+        }
+        formatted
+    }
+
+    /// The range of an entry with its line, or with a comma next to it in an inline table.
+    fn entry_range(&self, tree: &SyntaxTree, value: ast::Value<'_>) -> Option<Range<usize>> {
+        let cursor = tree.cursor_at(value.span().start)?;
+        let entry = cursor.ancestors().find(|cursor| cursor.kind() == ENTRY)?;
+        let (start, end) = (entry.span().start as usize, value.span().end as usize);
+        if entry.parent()?.kind() != INLINE_TABLE {
+            let start = comments_start(&self.source, line_start(&self.source, start));
+            return Some(start..line_end(&self.source, end));
+        }
+
+        let is_trivia = |kind| matches!(kind, WHITESPACE | NEWLINE | COMMENT);
+        let mut next = entry.next_sibling();
+        while let Some(sibling) = next.as_ref().filter(|sibling| is_trivia(sibling.kind())) {
+            next = sibling.next_sibling();
+        }
+        if let Some(comma) = next.filter(|sibling| sibling.kind() == COMMA) {
+            let rest = &self.source[comma.span().end as usize..];
+            let end = comma.span().end as usize + rest.len() - rest.trim_start().len();
+            return Some(start..end);
+        }
+
+        let mut prev = entry.prev_sibling();
+        while let Some(sibling) = prev.as_ref().filter(|sibling| is_trivia(sibling.kind())) {
+            prev = sibling.prev_sibling();
+        }
+        match prev.filter(|sibling| sibling.kind() == COMMA) {
+            Some(comma) => Some(comma.span().start as usize..end),
+            None => Some(start..end),
+        }
+    }
+}
+
+/// Where [`DocumentMut::move_table`] moves a section, by the key of another section.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum TablePosition {
+    Before(String),
+    After(String),
+}
+
+/// Edits the items of an array of a [`DocumentMut`], see [`DocumentMut::array`].
+///
+/// The layout of the array is kept: in arrays that are written on several lines, items
+/// are added on their own lines and removed with their lines and comments, otherwise they
+/// are added with the separators of the other items. A trailing comma is kept if there is
+/// one and not added otherwise.
+#[derive(Debug)]
+pub struct ArrayEdit<'a> {
+    document: &'a mut DocumentMut,
+    path: String,
+}
+
+/// An item of an [`ArrayEdit`].
+struct ArrayItem {
+    span: Range<usize>,
+
+    /// The span of the comma after the item.
+    comma: Option<Range<usize>>,
+
+    value: Option<Value>,
+}
+
+impl ArrayEdit<'_> {
+    /// The number of items of the array.
+    pub fn len(&self) -> usize {
+        self.items().1.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The values of the items of the array.
+    pub fn values(&self) -> Vec<Value> {
+        self.items().1.into_iter().filter_map(|item| item.value).collect()
+    }
+
+    /// Add an item at the end of the array.
+    pub fn push(&mut self, value: &Value) {
+        let len = self.len();
+        self.insert_item(len, value);
+    }
+
+    /// Add an item at an index, [`EditError::InvalidPath`] if it is after the end.
+    pub fn insert(&mut self, index: usize, value: &Value) -> Result<(), EditError> {
+        if index > self.len() {
+            return Err(EditError::InvalidPath);
+        }
+        self.insert_item(index, value);
+        Ok(())
+    }
+
+    /// Remove the items that the predicate returns `true` for, returns how many were removed.
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&Value) -> bool) -> usize {
+        let (span, items) = self.items();
+        let removed: Vec<bool> =
+            items.iter().map(|item| item.value.as_ref().is_some_and(&mut predicate)).collect();
+        let count = removed.iter().filter(|&&removed| removed).count();
+        if count == 0 {
+            return 0;
+        }
+        if count == items.len() {
+            self.document.source.replace_range(span, "[]");
+            return count;
+        }
+
+        let source = &self.document.source;
+        let multiline = source[span.clone()].contains('\n');
+        let mut ranges = Vec::new();
+        for (item, _) in items.iter().zip(&removed).filter(|(_, removed)| **removed) {
+            let end = item.comma.as_ref().map_or(item.span.end, |comma| comma.end);
+            if multiline && own_line(source, item.span.start, end) {
+                let start = comments_start(source, line_start(source, item.span.start));
+                ranges.push(start..line_end(source, end));
+            } else if item.comma.is_some() {
+                let rest = &source[end..];
+                ranges.push(
+                    item.span.start..end + rest.len() - rest.trim_start_matches([' ', '\t']).len(),
+                );
+            } else {
+                let before = source[..item.span.start].trim_end_matches([' ', '\t']);
+                ranges.push(before.len()..item.span.end);
+            }
+        }
+        // Without a trailing comma, the item that is last now loses its comma.
+        let last_kept = removed.iter().rposition(|removed| !removed);
+        if items.last().is_some_and(|item| item.comma.is_none())
+            && let Some(comma) = last_kept.and_then(|idx| items[idx].comma.clone())
+            && last_kept != Some(items.len() - 1)
+        {
+            ranges.push(comma);
+        }
+
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::new();
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        for range in merged.into_iter().rev() {
+            self.document.source.replace_range(range, "");
+        }
+        count
+    }
+
+    /// The span of the array and its items.
+    fn items(&self) -> (Range<usize>, Vec<ArrayItem>) {
+        let source = &self.document.source;
+        let tree = self.document.tree();
+        let document = Document::new(&tree);
+        let Some(Item::Array(array)) = document.get(&self.path) else {
+            return (0..0, Vec::new());
+        };
+
+        let is_trivia = |kind| matches!(kind, WHITESPACE | NEWLINE | COMMENT);
+        let items = array.items().iter().filter_map(|item| {
+            let span = item.value()?.span();
+            let cursor = tree.cursor_at(span.start)?;
+            let node = cursor.ancestors().find(|cursor| cursor.kind() == VALUE)?;
+            let mut next = node.next_sibling();
+            while let Some(sibling) = next.as_ref().filter(|sibling| is_trivia(sibling.kind())) {
+                next = sibling.next_sibling();
+            }
+            let comma = next.filter(|sibling| sibling.kind() == COMMA).map(|comma| {
+                let span = comma.span();
+                span.start as usize..span.end as usize
+            });
+            Some(ArrayItem {
+                span: span.start as usize..span.end as usize,
+                comma,
+                value: item_value(item, source),
+            })
+        });
+        let span = array.syntax().span();
+        (span.start as usize..span.end as usize, items.collect())
+    }
+
+    fn insert_item(&mut self, index: usize, value: &Value) {
+        let (span, items) = self.items();
+        let source = &self.document.source;
+        let text = self.document.format_value(value);
+        let Some(last) = items.last() else {
+            self.document.source.replace_range(span, &format!("[{text}]"));
+            return;
+        };
+
+        let multiline = source[span.clone()].contains('\n');
+        let indent = items
+            .iter()
+            .map(|item| &source[line_start(source, item.span.start)..item.span.start])
+            .find(|indent| indent.trim().is_empty());
+        // The separator of the items on the same line.
+        let separator = items
+            .first()
+            .zip(items.get(1))
+            .and_then(|(first, second)| {
+                Some(&source[first.comma.as_ref()?.start..second.span.start])
+            })
+            .filter(|separator| !separator.contains('\n'))
+            .unwrap_or(", ");
+
+        let mut changes = Vec::new();
+        if let Some(next) = items.get(index) {
+            let end = next.comma.as_ref().map_or(next.span.end, |comma| comma.end);
+            match indent {
+                Some(indent) if multiline && own_line(source, next.span.start, end) => {
+                    let pos = comments_start(source, line_start(source, next.span.start));
+                    changes.push((pos, format!("{indent}{text},\n")));
+                }
+                _ => changes.push((next.span.start, format!("{text}{separator}"))),
+            }
+        } else {
+            let end = last.comma.as_ref().map_or(last.span.end, |comma| comma.end);
+            match indent {
+                Some(indent) if multiline && own_line(source, last.span.start, end) => {
+                    let comma = if last.comma.is_some() { "," } else { "" };
+                    if last.comma.is_none() {
+                        changes.push((last.span.end, ",".to_string()));
+                    }
+                    changes.push((line_end(source, end), format!("{indent}{text}{comma}\n")));
+                }
+                _ => changes.push((last.span.end, format!("{separator}{text}"))),
+            }
+        }
+        for (pos, text) in changes.into_iter().rev() {
+            self.document.source.insert_str(pos, &text);
+        }
+    }
+}
+
+/// Whether an item of an array that ends at `end` is the only thing on its lines,
+/// apart from comments after it.
+fn own_line(source: &str, start: usize, end: usize) -> bool {
+    let rest = source[end..line_end(source, end)].trim();
+    source[line_start(source, start)..start].trim().is_empty()
+        && (rest.is_empty() || rest.starts_with('#'))
+}
+
+/// A replacement of a range of the text.
+struct Change {
+    range: Range<usize>,
+    text: String,
+
+    /// The range of the entry or value that is written in the text.
+    written: Range<usize>,
+}
+
+impl Change {
+    fn new(range: Range<usize>, text: String) -> Self {
+        let written = 0..text.len();
+        Self { range, text, written }
+    }
+
+    const fn written(range: Range<usize>, text: String, written: Range<usize>) -> Self {
+        Self { range, text, written }
+    }
+}
+
+/// How the entries of a section are written, new entries are written the same way.
+struct Style {
+    indent: String,
+
+    /// The text between the keys and the values, e.g. ` = `.
+    separator: String,
+
+    /// The quote of the keys if they are all quoted.
+    quote: Option<char>,
+
+    /// Whether the strings are literal strings.
+    literal_strings: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self { indent: String::new(), separator: " = ".into(), quote: None, literal_strings: false }
+    }
+}
+
+impl Style {
+    /// The style that all the entries share, the default for what they don't.
+    fn of(entries: &[ast::Entry<'_>], source: &str) -> Self {
+        let default = Self::default();
+        let indent = common(entries.iter().map(|entry| {
+            let start = entry.span().start as usize;
+            &source[line_start(source, start)..start]
+        }))
+        .filter(|indent| indent.trim().is_empty());
+        let separator = common(entries.iter().filter_map(|entry| {
+            let key = entry.key()?.idents().last()?.span.end as usize;
+            source.get(key..entry.value()?.span().start as usize)
+        }));
+        let quote = common(
+            entries
+                .iter()
+                .filter_map(ast::Entry::key)
+                .flat_map(|key| key.idents())
+                .map(|ident| ident.text(source).chars().next()),
+        )
+        .flatten()
+        .filter(|c| matches!(c, '"' | '\''));
+        let strings = entries.iter().filter_map(|entry| match entry.value()? {
+            ast::Value::String(token) => Some(token.kind()),
+            _ => None,
+        });
+
+        Self {
+            indent: indent.map_or(default.indent, str::to_string),
+            separator: separator.map_or(default.separator, str::to_string),
+            quote,
+            literal_strings: common(strings) == Some(STRING_LITERAL),
+        }
+    }
+
+    /// The text of an entry, without its indentation.
+    fn entry(&self, document: &DocumentMut, key: &[String], value: &Value) -> String {
+        let mut text = String::new();
+        for (idx, part) in key.iter().enumerate() {
+            if idx > 0 {
+                text.push('.');
+            }
+            match self.quote {
+                Some('"') => write_string(&mut text, part),
+                Some('\'') if is_literal(part) => text += &format!("'{part}'"),
+                _ => text += &key_text(std::slice::from_ref(part)),
+            }
+        }
+        text += &self.separator;
+        match value {
+            Value::String(s) if self.literal_strings && is_literal(s) => text += &format!("'{s}'"),
+            value => text += &document.format_value(value),
+        }
+        text
+    }
+}
+
+/// The item that all items are equal to, `None` if there are none or they differ.
+fn common<T: PartialEq>(mut items: impl Iterator<Item = T>) -> Option<T> {
+    let first = items.next()?;
+    items.all(|item| item == first).then_some(first)
+}
+
+/// Whether a string can be written as a single-line literal string.
+fn is_literal(s: &str) -> bool {
+    !s.chars().any(|c| c == '\'' || (c.is_control() && c != '\t'))
+}
+
+/// The start of the comments on the lines right above a line.
+fn comments_start(source: &str, mut start: usize) -> usize {
+    while start > 0 {
+        let prev = line_start(source, start - 1);
+        if !source[prev..start].trim_start().starts_with('#') {
+            break;
+        }
+        start = prev;
+    }
+    start
+}
+
+impl DocumentMut {
+    /// Whether the key at a path of keys is an array of tables.
+    pub(crate) fn is_array_of_tables(&self, path: &str) -> bool {
+        let tree = self.tree();
+        matches!(Document::new(&tree).get(path), Some(Item::ArrayOfTables(_)))
+    }
+
+    /// Remove the comments on the lines right above the entry at a path of keys
+    /// and at the end of its line, the entries of inline tables are left as they are.
+    pub(crate) fn remove_comments(&mut self, path: &str) {
+        self.commit();
+        let Some(parts) = path_parts(path) else {
+            return;
+        };
+        let tree = self.tree();
+        let document = Document::new(&tree);
+        let key = document.keys().find(|key| key.parts.iter().map(|part| &part.name).eq(&parts));
+        let Some(value) = key.map(|key| key.value.span()) else {
+            return;
+        };
+        let entry = tree
+            .cursor_at(value.start)
+            .and_then(|cursor| cursor.ancestors().find(|cursor| cursor.kind() == ENTRY));
+        let Some(entry) = entry.filter(|entry| entry.parent().is_some_and(|p| p.kind() == ROOT))
+        else {
+            return;
+        };
+
+        let source = &self.source;
+        let end = value.end as usize;
+        let rest = &source[end..line_end(source, end)];
+        let comment = rest
+            .trim_start()
+            .starts_with('#')
+            .then(|| end..end + rest.trim_end_matches(['\n', '\r']).len());
+        let start = line_start(source, entry.span().start as usize);
+        let comments = comments_start(source, start)..start;
+        if let Some(comment) = comment {
+            self.source.replace_range(comment, "");
+        }
+        self.source.replace_range(comments, "");
+    }
+
+    /// Add the table headers of another document whose keys start with the parts at the end,
+    /// with their entries and the comments above them.
+    pub(crate) fn append_tables(&mut self, other: &Self, parts: &[String]) {
+        self.commit();
+        let tree = other.tree();
+        let headers = Document::new(&tree).headers();
+        let source = tree.source();
+        let mut sections = Vec::new();
+        for (idx, (span, names)) in headers.iter().enumerate() {
+            if !names.starts_with(parts) {
+                continue;
+            }
+            let start = comments_start(source, line_start(source, span.start as usize));
+            let end = headers.get(idx + 1).map_or(source.len(), |(next, _)| {
+                comments_start(source, line_start(source, next.start as usize))
+            });
+            sections.push(source[start..end].trim_end());
+        }
+        if sections.is_empty() {
+            return;
+        }
+
+        if !self.source.is_empty() && !self.source.ends_with('\n') {
+            self.source.push('\n');
+        }
+        if !self.source.trim().is_empty() {
+            self.source.push('\n');
+        }
+        self.source += &sections.join("\n\n");
+        self.source.push('\n');
+    }
+
+    /// Extend a range of lines that are removed over the blank lines after it if there is
+    /// a blank line before it, or over the ones before it at the end of the document.
+    fn with_blank_lines(&self, mut range: Range<usize>) -> Range<usize> {
+        let source = &self.source;
+        let is_lines = range.start == line_start(source, range.start)
+            && (range.end == source.len() || source[..range.end].ends_with('\n'));
+        if !is_lines || range.is_empty() {
+            return range;
+        }
+
+        let blank_before =
+            range.start == 0 || is_blank_line(source, line_start(source, range.start - 1));
+        if blank_before {
+            while range.end < source.len() && is_blank_line(source, range.end) {
+                range.end = line_end(source, range.end);
+            }
+        }
+        if range.end == source.len() {
+            while range.start > 0 && is_blank_line(source, line_start(source, range.start - 1)) {
+                range.start = line_start(source, range.start - 1);
+            }
+        }
+        range
+    }
+}
+
+impl core::fmt::Display for DocumentMut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.text())
+    }
+}
+
+/// The value of a key of the root table, panics if it doesn't exist.
+impl Index<&str> for DocumentMut {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        &self.value()[key]
+    }
+}
+
+/// The value of a key of the root table to set it, see [`DocumentMut`].
+impl IndexMut<&str> for DocumentMut {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        self.value();
+        self.edited = true;
+        self.written.take();
+        let value = self.value.get_mut().expect("the value is read before it is edited");
+        &mut value[key]
+    }
+}
+
+fn parse_options(options: &Options) -> ParseOptions {
+    ParseOptions { toml_version: options.toml_version, validate: true, ..ParseOptions::default() }
+}
+
+/// The offset of the start of the line with the offset.
+fn line_start(source: &str, offset: usize) -> usize {
+    source[..offset].rfind('\n').map_or(0, |idx| idx + 1)
+}
+
+/// Whether the parts of a key start with the parts of a path.
+fn is_prefix(prefix: &[String], parts: &[KeyPart]) -> bool {
+    parts.len() >= prefix.len() && prefix.iter().zip(parts).all(|(name, part)| *name == part.name)
+}
+
+/// Whether the keys in front of the last part of a path are tables that are not in arrays,
+/// or don't exist.
+fn is_table_path(document: &Document<'_>, parts: &[String]) -> bool {
+    (1..=parts.len())
+        .all(|len| matches!(document.get(&key_text(&parts[..len])), None | Some(Item::Table(_))))
+}
+
+/// The span of an entry of the root node with the value.
+fn root_entry(tree: &SyntaxTree, value: ast::Value<'_>) -> Option<TextRange> {
+    let cursor = tree.cursor_at(value.span().start)?;
+    let entry = cursor.ancestors().find(|cursor| cursor.kind() == ENTRY)?;
+    (entry.parent()?.kind() == ROOT).then(|| entry.span())
+}
+
+/// Whether the line that starts at the offset only has whitespace.
+fn is_blank_line(source: &str, start: usize) -> bool {
+    source[start..line_end(source, start)].trim().is_empty()
+}
+
+/// The offset after the line break of the line with the offset.
+fn line_end(source: &str, offset: usize) -> usize {
+    source[offset..].find('\n').map_or(source.len(), |idx| offset + idx + 1)
+}
@@ -0,0 +1,209 @@
+//! Writing documents from values, see [`SyntaxTree::from_value`].
+//!
+//! The documents are written plainly and then formatted,
+//! so they follow the same style as the documents that are formatted by hand.
+
+use crate::{
+    formatter::{Options, format},
+    parser::parse,
+    tree::SyntaxTree,
+    value::Value,
+};
+
+impl SyntaxTree<'_> {
+    /// Write a table value as a document formatted with the options.
+    ///
+    /// Tables are written as table sections and arrays that only contain tables
+    /// as arrays of tables, except inside other arrays where they are inline tables.
+    /// Values other than tables give an empty document.
+    pub fn from_value(value: &Value, options: Options) -> Self {
+        let document = match value_node(value) {
+            Node::Table(entries) => write_document(&entries),
+            _ => String::new(),
+        };
+        parse(&format(&document, options)).into_syntax()
+    }
+}
+
+/// The node of a value, the keys of its tables are sorted.
+fn value_node(value: &Value) -> Node {
+    match value {
+        Value::String(s) => Node::String(s.clone()),
+        Value::Integer(i) => Node::Value(i.to_string()),
+        Value::Float(f) => float(*f),
+        Value::Bool(b) => Node::Value(b.to_string()),
+        Value::Datetime(datetime) => Node::Value(datetime.to_string()),
+        Value::Array(array) => Node::Array(array.iter().map(value_node).collect()),
+        Value::Table(table) => {
+            Node::Table(table.iter().map(|(key, value)| (key.clone(), value_node(value))).collect())
+        }
+    }
+}
+
+/// The text of a value as the value of an entry, tables are inline tables.
+pub(crate) fn inline_value(value: &Value) -> String {
+    let mut text = String::new();
+    write_inline(&mut text, &value_node(value));
+    text
+}
+
+/// The text of a key with the parts, quoted where they need to be.
+pub(crate) fn key_text(parts: &[String]) -> String {
+    let mut text = String::new();
+    for (idx, part) in parts.iter().enumerate() {
+        if idx > 0 {
+            text.push('.');
+        }
+        write_key(&mut text, part);
+    }
+    text
+}
+
+/// Write the entries of the root table as a document that is not formatted yet.
+pub(crate) fn write_document(entries: &[(String, Node)]) -> String {
+    let mut document = String::new();
+    write_table(&mut document, "", entries);
+    document
+}
+
+/// A value before it is written.
+#[derive(Debug)]
+pub(crate) enum Node {
+    /// A string that is not quoted yet, it can also be a key.
+    String(String),
+
+    /// A number, boolean or date-time as it is written.
+    Value(String),
+
+    Array(Vec<Node>),
+
+    /// The keys and values in the order they are written.
+    Table(Vec<(String, Node)>),
+
+    /// A `None` of serde, entries with it are left out.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    None,
+}
+
+impl Node {
+    /// Whether the node is written as tables with headers rather than as the value of an entry.
+    fn is_table(&self) -> bool {
+        match self {
+            Self::Table(_) => true,
+            Self::Array(items) => is_array_of_tables(items),
+            _ => false,
+        }
+    }
+}
+
+fn is_array_of_tables(items: &[Node]) -> bool {
+    !items.is_empty() && items.iter().all(|item| matches!(item, Node::Table(_)))
+}
+
+/// Write the values of a table and then its tables, `path` is the key of its header.
+fn write_table(out: &mut String, path: &str, entries: &[(String, Node)]) {
+    for (key, node) in entries.iter().filter(|(_, node)| !node.is_table()) {
+        write_key(out, key);
+        out.push_str(" = ");
+        write_inline(out, node);
+        out.push('\n');
+    }
+
+    for (key, node) in entries {
+        let mut child = path.to_string();
+        if !child.is_empty() {
+            child.push('.');
+        }
+        write_key(&mut child, key);
+
+        match node {
+            Node::Table(table) => {
+                // Tables that only contain tables are defined by the headers of their tables.
+                if table.is_empty() || table.iter().any(|(_, node)| !node.is_table()) {
+                    out.push_str(&format!("\n[{child}]\n"));
+                }
+                write_table(out, &child, table);
+            }
+            Node::Array(items) if is_array_of_tables(items) => {
+                for item in items {
+                    if let Node::Table(table) = item {
+                        out.push_str(&format!("\n[[{child}]]\n"));
+                        write_table(out, &child, table);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Write a node as the value of an entry or an array item.
+fn write_inline(out: &mut String, node: &Node) {
+    match node {
+        Node::String(s) => write_string(out, s),
+        Node::Value(value) => out.push_str(value),
+        Node::Array(items) => {
+            out.push('[');
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(", ");
+                }
+                write_inline(out, item);
+            }
+            out.push(']');
+        }
+        Node::Table(entries) if entries.is_empty() => out.push_str("{}"),
+        Node::Table(entries) => {
+            out.push_str("{ ");
+            for (idx, (key, node)) in entries.iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(", ");
+                }
+                write_key(out, key);
+                out.push_str(" = ");
+                write_inline(out, node);
+            }
+            out.push_str(" }");
+        }
+        Node::None => {}
+    }
+}
+
+/// Write a key, quoted unless it only has the characters of bare keys in TOML 1.0.
+fn write_key(out: &mut String, key: &str) {
+    if !key.is_empty() && key.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-') {
+        out.push_str(key);
+    } else {
+        write_string(out, key);
+    }
+}
+
+/// Write a basic string with the escapes that all TOML versions support.
+pub(crate) fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_ascii_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Write a float so that it is never read as an integer.
+pub(crate) fn float(f: f64) -> Node {
+    Node::Value(if f.is_nan() {
+        "nan".to_string()
+    } else if f.is_infinite() {
+        if f > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        format!("{f:?}")
+    })
+}
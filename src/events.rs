@@ -0,0 +1,108 @@
+//! Parsing without building a tree, see [`tokenize`] and [`parse_events`].
+
+use crate::{
+    lexer::Lexer,
+    parser::{BOM, ParseError, ParseOptions, Parser, validate},
+    syntax::SyntaxKind,
+    tree::{SyntaxTree, TextRange, TreeBuilder, TreeSink, text_range},
+};
+
+/// The tokens of a TOML document in the order of the source, without parsing it.
+///
+/// The tokens cover the whole source, the text that is not a token is an `ERROR` token.
+/// They are the tokens of the lexer, so the parser may split or join some of them,
+/// e.g. `1.2` is a `FLOAT` here but two keys in `1.2 = true`.
+pub fn tokenize(source: &str) -> Vec<(SyntaxKind, TextRange)> {
+    let mut lexer = Lexer::<SyntaxKind>::new(source);
+    let mut tokens = Vec::with_capacity(source.len() / 4);
+    // A byte order mark is whitespace before the first token.
+    if lexer.skip_prefix(BOM) {
+        tokens.push((SyntaxKind::WHITESPACE, text_range(0, BOM.len())));
+    }
+    while let Some(token) = lexer.next() {
+        let span = lexer.span();
+        tokens.push((token.unwrap_or(SyntaxKind::ERROR), text_range(span.start, span.end)));
+    }
+    tokens
+}
+
+/// A step of the parser, see [`parse_events`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ParseEvent {
+    /// A node starts, the events until the matching [`ParseEvent::Finish`] are its children.
+    Start(SyntaxKind),
+
+    /// A token of the current node.
+    Token(SyntaxKind, TextRange),
+
+    /// The current node ends.
+    Finish,
+}
+
+/// The result of [`parse_events`].
+#[derive(Debug, Clone)]
+pub struct EventParse {
+    /// The events in the order of the source, starting with the `ROOT` node.
+    pub events: Vec<ParseEvent>,
+
+    /// The syntax errors like the ones of [`parse_with`](crate::parse_with).
+    pub errors: Vec<ParseError>,
+
+    /// The options the document was parsed with.
+    pub options: ParseOptions,
+}
+
+impl EventParse {
+    /// Build the tree that [`parse_with`](crate::parse_with) returns from the events.
+    pub fn build_tree(&self, source: &str) -> SyntaxTree<'static> {
+        let mut builder = TreeBuilder::new();
+        for event in &self.events {
+            match event {
+                ParseEvent::Start(kind) => builder.start_node(*kind),
+                ParseEvent::Token(kind, span) => builder.token(*kind, span.end as usize),
+                ParseEvent::Finish => builder.finish_node(),
+            }
+        }
+        SyntaxTree {
+            root: builder.finish_root(),
+            source: source.to_string().into(),
+            options: self.options,
+        }
+    }
+}
+
+/// Parse a TOML document like [`parse_with`](crate::parse_with), but return the nodes
+/// and tokens as a flat list of events instead of a tree.
+///
+/// The tree can be built later with [`EventParse::build_tree`]. With
+/// [`ParseOptions::validate`] the tree is built anyway to check the document.
+pub fn parse_events(source: &str, options: ParseOptions) -> EventParse {
+    let sink = EventSink { events: Vec::with_capacity(source.len() / 2), current_pos: 0 };
+    let (sink, mut errors) = Parser::with_sink(source, sink).with_options(options).parse_into();
+    let parse = EventParse { events: sink.events, errors: Vec::new(), options };
+    if options.validate {
+        validate::validate(&parse.build_tree(source).root, source, &mut errors);
+    }
+    EventParse { errors, ..parse }
+}
+
+struct EventSink {
+    events: Vec<ParseEvent>,
+    current_pos: usize,
+}
+
+impl TreeSink for EventSink {
+    fn start_node(&mut self, kind: SyntaxKind) {
+        self.events.push(ParseEvent::Start(kind));
+    }
+
+    fn token(&mut self, kind: SyntaxKind, end: usize) {
+        debug_assert!(end >= self.current_pos, "tokens must be added in the order of the source");
+        self.events.push(ParseEvent::Token(kind, text_range(self.current_pos, end)));
+        self.current_pos = end;
+    }
+
+    fn finish_node(&mut self) {
+        self.events.push(ParseEvent::Finish);
+    }
+}
@@ -0,0 +1,122 @@
+//! Fluent construction of [`Options`].
+
+use super::{
+    ArrayLayout, DateTimeSeparator, HexCase, IntegerUnderscores, LineEnding, Options,
+    for_each_option,
+};
+use crate::parser::TomlVersion;
+
+/// An invalid value of one of the [`Options`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OptionsError {
+    /// The name of the option.
+    pub option: &'static str,
+
+    /// Human-friendly error message.
+    pub message: String,
+}
+
+impl core::fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid `{}`: {}", self.option, self.message)
+    }
+}
+impl std::error::Error for OptionsError {}
+
+/// Builder for [`Options`] starting from the defaults, see [`Options::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct OptionsBuilder {
+    options: Options,
+}
+
+impl From<Options> for OptionsBuilder {
+    fn from(options: Options) -> Self {
+        Self { options }
+    }
+}
+
+macro_rules! setters {
+    ($($name:ident: $ty:ty,)*) => {
+        $(setter!($name: $ty);)*
+    };
+}
+
+macro_rules! setter {
+    (indent_string: $ty:ty) => {
+        /// See [`Options::indent_string`].
+        #[must_use]
+        pub fn indent_string(mut self, indent_string: impl Into<String>) -> Self {
+            self.options.indent_string = indent_string.into();
+            self
+        }
+    };
+    (table_priority: $ty:ty) => {
+        /// See [`Options::table_priority`].
+        #[must_use]
+        pub fn table_priority<I>(mut self, table_priority: I) -> Self
+        where
+            I: IntoIterator,
+            I::Item: Into<String>,
+        {
+            self.options.table_priority = table_priority.into_iter().map(Into::into).collect();
+            self
+        }
+    };
+    ($name:ident: $ty:ty) => {
+        #[doc = concat!("See [`Options::", stringify!($name), "`].")]
+        #[must_use]
+        pub fn $name(mut self, $name: $ty) -> Self {
+            self.options.$name = $name;
+            self
+        }
+    };
+}
+
+impl OptionsBuilder {
+    for_each_option!(setters);
+
+    /// Check the options and return them if they are valid.
+    ///
+    /// The first error of [`Options::validate`] is returned otherwise.
+    pub fn build(self) -> Result<Options, OptionsError> {
+        match self.options.validate() {
+            Ok(()) => Ok(self.options),
+            Err(mut errors) => Err(errors.swap_remove(0)),
+        }
+    }
+}
+
+impl Options {
+    /// Check for invalid values and settings that conflict with each other.
+    ///
+    /// The formatter accepts any options, but these produce surprising output.
+    pub fn validate(&self) -> Result<(), Vec<OptionsError>> {
+        let mut errors = Vec::new();
+        let mut error = |option, message: &str| {
+            errors.push(OptionsError { option, message: message.into() });
+        };
+
+        if self.column_width == 0 {
+            error("column_width", "must be greater than zero");
+        }
+
+        if !self.indent_string.chars().all(|c| matches!(c, ' ' | '\t')) {
+            error("indent_string", "must only contain spaces and tabs");
+        }
+
+        if self.collapse_tables && self.expand_dotted_keys.is_some() {
+            // The collapsed tables would be expanded again and the other way around.
+            error("collapse_tables", "conflicts with `expand_dotted_keys`");
+        }
+
+        if self.blank_lines_before_table.is_some_and(|lines| lines > self.allowed_blank_lines) {
+            error("blank_lines_before_table", "must not be more than `allowed_blank_lines`");
+        }
+
+        if !self.table_priority.is_empty() && !self.reorder_tables {
+            error("table_priority", "has no effect without `reorder_tables`");
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
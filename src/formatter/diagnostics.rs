@@ -0,0 +1,103 @@
+//! Reports of what the formatter left as it is, see [`format_with_diagnostics`](super::format_with_diagnostics).
+
+use super::sections::header_key;
+use crate::{
+    syntax::{SyntaxKind::*, SyntaxNode},
+    tree::{Element, TextRange},
+};
+
+/// Something that the formatter didn't format or change.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FormatDiagnostic {
+    /// The span in the source, `None` if it is about the entire document.
+    pub range: Option<TextRange>,
+
+    /// What was left as it is.
+    pub kind: FormatDiagnosticKind,
+
+    /// Human-friendly message.
+    pub message: String,
+}
+
+/// The reason of a [`FormatDiagnostic`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum FormatDiagnosticKind {
+    /// The region around a syntax error is copied as it is.
+    SyntaxError,
+
+    /// The region between `fmt off` and `fmt on` comments is not formatted.
+    FormattingDisabled,
+
+    /// The entry or table section after an `ignore` comment is not formatted.
+    Ignored,
+
+    /// Tables are not moved or converted because parts of the document are not formatted.
+    TablesNotRestructured,
+
+    /// A key pattern of a rule is not a valid key, so it doesn't match anything.
+    InvalidRulePattern,
+
+    /// The output of [`Options::whitespace_only`](super::Options::whitespace_only)
+    /// would change more than whitespace, so the source is returned unchanged.
+    TokensChanged,
+
+    /// An entry after a table header whose key starts like an entry of the root table,
+    /// so it may be meant for the root table. It stays in its table, moving it above
+    /// the first header would change the document.
+    MisplacedRootEntry,
+}
+
+impl core::fmt::Display for FormatDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.range {
+            Some(range) => write!(f, "{} ({range:?})", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// The entries after table headers whose first key part is the first part of the key of an
+/// entry of the root table, e.g. `edition` after `[dependencies]` if the root table has an
+/// `edition` entry. Tables are not counted, `features` is a common key in the subtables
+/// of `[package]` and `[dependencies]` next to a `[features]` table.
+pub(super) fn misplaced_root_entries(root: &SyntaxNode, source: &str) -> Vec<FormatDiagnostic> {
+    let nodes = root.children().iter().filter_map(Element::as_node);
+    let root_keys = nodes
+        .clone()
+        .take_while(|node| !matches!(node.kind(), TABLE_HEADER | TABLE_ARRAY_HEADER))
+        .filter(|node| node.kind() == ENTRY)
+        .filter_map(|node| header_key(node, source).into_iter().next())
+        .collect::<Vec<_>>();
+
+    let mut diagnostics = Vec::new();
+    let mut table = None;
+    for node in nodes {
+        match node.kind() {
+            TABLE_HEADER | TABLE_ARRAY_HEADER => table = Some(header_key(node, source)),
+            ENTRY => {
+                let Some(table) = &table else {
+                    continue;
+                };
+                let key = header_key(node, source);
+                let Some(first) = key.first() else {
+                    continue;
+                };
+                // A key that is part of the name of its own table is likely meant for it.
+                if root_keys.contains(first) && !table.contains(first) {
+                    diagnostics.push(FormatDiagnostic {
+                        range: Some(node.span.clone()),
+                        kind: FormatDiagnosticKind::MisplacedRootEntry,
+                        message: format!(
+                            "`{}` is in the table `{}`, not in the root table with `{first}`",
+                            key.join("."),
+                            table.join("."),
+                        ),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    diagnostics
+}
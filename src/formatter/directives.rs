@@ -0,0 +1,74 @@
+//! Comments that control the formatter, e.g. `# oxc-toml: fmt off`.
+
+use crate::{
+    syntax::{SyntaxKind::*, SyntaxNode},
+    tree::TextRange,
+};
+
+/// The directive of a comment, e.g. `fmt off` for `# oxc-toml: fmt off`.
+pub(super) fn directive(comment: &str) -> Option<&str> {
+    comment.strip_prefix('#')?.trim().strip_prefix("oxc-toml:").map(str::trim)
+}
+
+/// Find the regions between `fmt off` and `fmt on` comments including the comments.
+///
+/// A region that is not turned back on extends to the end of the document.
+pub(super) fn fmt_off_ranges(root: &SyntaxNode, source: &str) -> Vec<TextRange> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+
+    for comment in root.descendants_with_tokens().filter(|c| c.kind() == COMMENT) {
+        let range = comment.text_range();
+        match directive(comment.text(source)) {
+            Some("fmt off") if start.is_none() => start = Some(range.start),
+            Some("fmt on") => {
+                if let Some(start) = start.take() {
+                    ranges.push(start..range.end);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(start) = start {
+        ranges.push(start..root.span.end);
+    }
+
+    ranges
+}
+
+/// Find the entries and table sections after `ignore` comments including the comments.
+pub(super) fn ignored_ranges(root: &SyntaxNode, source: &str) -> Vec<TextRange> {
+    let children = root.children();
+    let mut ranges = Vec::new();
+
+    for (idx, comment) in children.iter().enumerate() {
+        if comment.kind() != COMMENT || directive(comment.text(source)) != Some("ignore") {
+            continue;
+        }
+
+        let Some(item_idx) = (idx + 1..children.len()).find(|&i| children[i].as_node().is_some())
+        else {
+            continue;
+        };
+        let item = &children[item_idx];
+        let end = if matches!(item.kind(), TABLE_HEADER | TABLE_ARRAY_HEADER) {
+            // The section ends with the last entry or comment before the next header.
+            let section_end = children[item_idx + 1..]
+                .iter()
+                .position(|c| matches!(c.kind(), TABLE_HEADER | TABLE_ARRAY_HEADER))
+                .map_or(children.len(), |pos| item_idx + 1 + pos);
+            children[item_idx..section_end]
+                .iter()
+                .rfind(|c| !matches!(c.kind(), NEWLINE | WHITESPACE))
+                .unwrap_or(item)
+                .text_range()
+                .end
+        } else {
+            item.text_range().end
+        };
+        ranges.push(comment.text_range().start..end);
+    }
+
+    ranges
+}
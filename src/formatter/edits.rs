@@ -0,0 +1,144 @@
+//! The changes of the formatter as text edits, see [`format_edits`].
+
+use crate::tree::{TextRange, text_range};
+
+use super::{Options, format};
+
+/// A replacement of a range of the source, see [`format_edits`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TextEdit {
+    /// The span in the source that is replaced.
+    pub range: TextRange,
+
+    /// The text that replaces it.
+    pub new_text: String,
+}
+
+/// The number of differences between the lines above which the changed lines are
+/// replaced by a single edit, to bound the time and memory of the diff.
+const MAX_DIFFERENCES: usize = 1000;
+
+/// Format a document like [`format()`], but return the edits that turn the source
+/// into the formatted document rather than the formatted document.
+///
+/// The edits are sorted and don't overlap, their ranges are in the source. Only the lines
+/// that change are replaced, without the characters at their start and end that stay the same.
+pub fn format_edits(source: &str, options: Options) -> Vec<TextEdit> {
+    text_edits(source, &format(source, options))
+}
+
+/// The edits that turn the source into the formatted text, see [`format_edits`].
+pub(super) fn text_edits(source: &str, formatted: &str) -> Vec<TextEdit> {
+    if formatted == source {
+        return Vec::new();
+    }
+
+    let old = source.split_inclusive('\n').collect::<Vec<_>>();
+    let new = formatted.split_inclusive('\n').collect::<Vec<_>>();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_lines, new_lines) =
+        (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    // The offsets of the lines in the source, with the end of the last one.
+    let mut old_offsets = vec![old[..prefix].iter().map(|line| line.len()).sum::<usize>()];
+    for line in old_lines {
+        old_offsets.push(old_offsets[old_offsets.len() - 1] + line.len());
+    }
+
+    // The same lines of the source and the formatted document, with the ends as the last pair.
+    let mut same = common_lines(old_lines, new_lines).unwrap_or_default();
+    same.push((old_lines.len(), new_lines.len()));
+
+    let mut edits = Vec::new();
+    let (mut old_start, mut new_start) = (0, 0);
+    for (old_end, new_end) in same {
+        if old_start < old_end || new_start < new_end {
+            let range = old_offsets[old_start]..old_offsets[old_end];
+            edits.push(edit(source, range, &new_lines[new_start..new_end].concat()));
+        }
+        (old_start, new_start) = (old_end + 1, new_end + 1);
+    }
+    edits
+}
+
+/// The edit that replaces a range of the source, without the characters at the start
+/// and the end of the range that the new text has as well.
+fn edit(source: &str, range: std::ops::Range<usize>, new_text: &str) -> TextEdit {
+    let old = &source[range.clone()];
+    let prefix = old
+        .char_indices()
+        .zip(new_text.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(old.len().min(new_text.len()), |((idx, _), _)| idx);
+    let (old_rest, new_rest) = (&old[prefix..], &new_text[prefix..]);
+    let suffix = old_rest
+        .chars()
+        .rev()
+        .zip(new_rest.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum::<usize>();
+
+    TextEdit {
+        range: text_range(range.start + prefix, range.end - suffix),
+        new_text: new_rest[..new_rest.len() - suffix].to_string(),
+    }
+}
+
+/// The pairs of indices of the lines that are the same in both, in order,
+/// with the diff algorithm of Myers. `None` if there are too many differences.
+fn common_lines(old: &[&str], new: &[&str]) -> Option<Vec<(usize, usize)>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    let offset = max + 1;
+    let index = |k: isize| (k + offset) as usize;
+
+    // The furthest position in the old lines on each diagonal, for each number of differences.
+    let mut furthest = vec![0isize; 2 * max as usize + 3];
+    let mut trace = Vec::new();
+    'search: for d in 0..=max {
+        if d as usize > MAX_DIFFERENCES {
+            return None;
+        }
+        trace.push(furthest.clone());
+        for k in (-d..=d).step_by(2) {
+            let down = k == -d || (k != d && furthest[index(k - 1)] < furthest[index(k + 1)]);
+            let mut x = if down { furthest[index(k + 1)] } else { furthest[index(k - 1)] + 1 };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, furthest) in trace.iter().enumerate().rev() {
+        let (d, k) = (d as isize, x - y);
+        let down = k == -d || (k != d && furthest[index(k - 1)] < furthest[index(k + 1)]);
+        let prev_k = if down { k + 1 } else { k - 1 };
+        let prev_x = furthest[index(prev_k)];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            pairs.push((x as usize, y as usize));
+        }
+        if d > 0 {
+            (x, y) = (prev_x, prev_y);
+        }
+    }
+    pairs.reverse();
+    Some(pairs)
+}
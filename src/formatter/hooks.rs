@@ -0,0 +1,145 @@
+//! Rewriting values by their keys while formatting, see [`format_with_hooks`].
+
+use std::collections::HashMap;
+
+use super::{Keys, Options, format_full, rules::matches_pattern, sections};
+use crate::{
+    syntax::{SyntaxKind::*, SyntaxNode},
+    tree::Element,
+};
+
+/// The function of a hook, it gets the parts of the key separated by periods and the text
+/// of the value and returns the text to write instead, or `None` to format it as usual.
+type Hook<'a> = dyn Fn(&str, &str) -> Option<String> + 'a;
+
+/// Functions that rewrite the values of the entries whose keys match key patterns,
+/// see [`format_with_hooks`].
+///
+/// The patterns are written like the ones of a [`FormatRule`](super::FormatRule), but they
+/// must match all the parts of the key, e.g. `*.token` matches `server.token` but neither
+/// `token` nor `server.token.value`. Patterns that are not valid match nothing.
+#[derive(Default)]
+pub struct ValueHooks<'a> {
+    hooks: Vec<(String, Box<Hook<'a>>)>,
+}
+
+impl<'a> ValueHooks<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a hook for the values of the keys that match the pattern.
+    /// If several hooks match a key, the first one that returns a text is used.
+    #[must_use]
+    pub fn hook(
+        mut self,
+        pattern: impl Into<String>,
+        hook: impl Fn(&str, &str) -> Option<String> + 'a,
+    ) -> Self {
+        self.hooks.push((pattern.into(), Box::new(hook)));
+        self
+    }
+}
+
+impl std::fmt::Debug for ValueHooks<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let patterns = self.hooks.iter().map(|(pattern, _)| pattern);
+        f.debug_struct("ValueHooks").field("patterns", &patterns.collect::<Vec<_>>()).finish()
+    }
+}
+
+/// Like [`format`](super::format), but the values of the entries whose keys match a hook
+/// are written with the text that the hook returns.
+///
+/// Hooks get the text of values from the source before they are formatted and what they
+/// return is written as it is. They are called for strings, numbers, booleans and date-times,
+/// the items of arrays have the key of the array. Values that are not formatted,
+/// e.g. because of syntax errors or directives, are not rewritten.
+pub fn format_with_hooks(src: &str, options: Options, hooks: &ValueHooks<'_>) -> String {
+    format_full(src, options, &[], hooks).0
+}
+
+/// The texts that the hooks return by the start of the `VALUE` nodes.
+pub(super) fn hooked_values(
+    root: &SyntaxNode,
+    source: &str,
+    hooks: &ValueHooks<'_>,
+) -> HashMap<u32, String> {
+    let mut values = HashMap::new();
+    if hooks.hooks.is_empty() {
+        return values;
+    }
+
+    let mut table = Vec::new();
+    for node in root.children().iter().filter_map(Element::as_node) {
+        match node.kind() {
+            TABLE_HEADER | TABLE_ARRAY_HEADER => table = sections::header_key(node, source),
+            ENTRY => add_entry(node, &table, source, hooks, &mut values),
+            _ => {}
+        }
+    }
+    values
+}
+
+fn add_entry(
+    entry: &SyntaxNode,
+    parent: &[String],
+    source: &str,
+    hooks: &ValueHooks<'_>,
+    values: &mut HashMap<u32, String>,
+) {
+    let mut key = parent.to_vec();
+    key.extend(Keys::from_header(entry, source).map(|keys| keys.keys).unwrap_or_default());
+    let nodes = entry.children().iter().filter_map(Element::as_node);
+    add_values(nodes, &key, source, hooks, values);
+}
+
+fn add_values<'a>(
+    nodes: impl Iterator<Item = &'a SyntaxNode>,
+    key: &[String],
+    source: &str,
+    hooks: &ValueHooks<'_>,
+    values: &mut HashMap<u32, String>,
+) {
+    for value in nodes.filter(|n| n.kind() == VALUE) {
+        let Some(node) = value.children().iter().find_map(Element::as_node) else {
+            add_scalar(value, key, source, hooks, values);
+            continue;
+        };
+
+        let children = node.children().iter().filter_map(Element::as_node);
+        match node.kind() {
+            INLINE_TABLE => {
+                for entry in children.filter(|n| n.kind() == ENTRY) {
+                    add_entry(entry, key, source, hooks, values);
+                }
+            }
+            // The items of arrays have the key of the array.
+            ARRAY => add_values(children, key, source, hooks, values),
+            _ => {}
+        }
+    }
+}
+
+/// Call the hooks that match the key with the text of a value that is a single token.
+fn add_scalar(
+    value: &SyntaxNode,
+    key: &[String],
+    source: &str,
+    hooks: &ValueHooks<'_>,
+    values: &mut HashMap<u32, String>,
+) {
+    let mut tokens = value.children().iter().filter_map(Element::as_token);
+    let Some(token) = tokens.find(|t| !matches!(t.kind(), WHITESPACE | NEWLINE | COMMENT)) else {
+        return;
+    };
+    let path = key.join(".");
+    let text = hooks
+        .hooks
+        .iter()
+        .filter(|(pattern, _)| matches_pattern(pattern, key))
+        .find_map(|(_, hook)| hook(&path, token.text(source)));
+    if let Some(text) = text {
+        values.insert(value.span.start, text);
+    }
+}
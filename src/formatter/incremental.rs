@@ -0,0 +1,108 @@
+//! Formatting only the part of a document that an edit changes, see [`format_incremental`].
+
+use crate::{
+    reparse::{header_line_end, section_starts},
+    tree::{Element, SyntaxTree, text_range},
+};
+
+use super::{
+    LineEnding, Options, TextEdit,
+    edits::{format_edits, text_edits},
+    format,
+};
+
+/// Apply the edit to the tree of a formatted document and return the edits that format
+/// the result, like [`format_edits`] would, but only format the table sections that the
+/// edit touches.
+///
+/// The tree is updated with [`SyntaxTree::reparse`], then the sections it parsed again are
+/// formatted together with the section before them, whose blank lines and comments depend
+/// on the header after it. The sections outside of them are assumed to be formatted already
+/// with the same options. The tree is updated with the returned edits as well, so it is the
+/// tree of the formatted document again and can be passed with the next edit.
+///
+/// The ranges of the returned edits are in the document after the edit. The whole document
+/// is formatted if the options move tables or entries between sections, or if it contains
+/// formatter directives.
+///
+/// # Panics
+///
+/// If the range of the edit is not in the document or not on character boundaries.
+pub fn format_incremental(
+    tree: &mut SyntaxTree<'_>,
+    edit: TextEdit,
+    options: Options,
+) -> Vec<TextEdit> {
+    let reparse = tree.reparse(edit);
+    let edits =
+        format_reparsed(tree, reparse.range.start as usize, reparse.range.end as usize, options);
+
+    // The edits are applied to the tree as a single edit, so the sections are parsed once.
+    if let (Some(first), Some(last)) = (edits.first(), edits.last()) {
+        let range = first.range.start..last.range.end;
+        let mut new_text = String::new();
+        let mut pos = range.start as usize;
+        for edit in &edits {
+            new_text += &tree.source[pos..edit.range.start as usize];
+            new_text += &edit.new_text;
+            pos = edit.range.end as usize;
+        }
+        tree.reparse(TextEdit { range, new_text });
+    }
+    edits
+}
+
+/// The edits that format the sections around the range that was parsed again.
+fn format_reparsed(
+    tree: &SyntaxTree<'_>,
+    changed_start: usize,
+    changed_end: usize,
+    mut options: Options,
+) -> Vec<TextEdit> {
+    let source = tree.source();
+    if options.line_ending == LineEnding::Preserve {
+        options.line_ending = LineEnding::detect(source);
+    }
+
+    let formats_whole_document = options.reorder_tables
+        || options.collapse_tables
+        || options.inline_small_tables.is_some()
+        || options.inline_array_tables
+        || options.expand_dotted_keys.is_some()
+        || options.expand_inline_tables
+        || options.indent_tables
+        || options.whitespace_only
+        || source.contains("oxc-toml:");
+    if formats_whole_document {
+        return format_edits(source, options);
+    }
+
+    let root = tree.root();
+    let sections = section_starts(root);
+    let start = sections.iter().copied().filter(|&idx| idx < changed_start).max().unwrap_or(0);
+    let end = sections
+        .iter()
+        .copied()
+        .find(|&idx| idx >= changed_end && idx > start)
+        .unwrap_or(source.len());
+
+    // The header after the region is formatted with it for the blank lines before it,
+    // then its line is removed again.
+    let next_header = root.children.iter().find_map(|child| match child {
+        Element::Node(node) if node.span.start as usize == end => Some(node),
+        _ => None,
+    });
+    let line_end = next_header.map_or(end, |header| header_line_end(root, header));
+    let mut formatted = format(&source[start..line_end], options);
+    if next_header.is_some() {
+        let without_header =
+            formatted.trim_end_matches(['\r', '\n']).rfind('\n').map_or(0, |idx| idx + 1);
+        formatted.truncate(without_header);
+    }
+
+    let mut edits = text_edits(&source[start..end], &formatted);
+    for edit in &mut edits {
+        edit.range = text_range(edit.range.start as usize + start, edit.range.end as usize + start);
+    }
+    edits
+}
@@ -0,0 +1,133 @@
+//! Normalization of literal values.
+
+use super::{DateTimeSeparator, HexCase, IntegerUnderscores, Options};
+use crate::syntax::SyntaxKind::{self, *};
+
+/// Write an integer token with its digit separators and hexadecimal digits normalized
+/// according to [`Options::integer_underscores`] and [`Options::hex_case`].
+pub(super) fn format_integer(
+    kind: SyntaxKind,
+    text: &str,
+    options: &Options,
+    formatted: &mut String,
+) {
+    let (prefix, digits) = match kind {
+        INTEGER_HEX | INTEGER_OCT | INTEGER_BIN => text.split_at(2),
+        _ => text.split_at(usize::from(text.starts_with(['+', '-']))),
+    };
+    formatted.push_str(prefix);
+
+    let cased;
+    let digits = match options.hex_case {
+        HexCase::Lower if kind == INTEGER_HEX => {
+            cased = digits.to_ascii_lowercase();
+            &cased
+        }
+        HexCase::Upper if kind == INTEGER_HEX => {
+            cased = digits.to_ascii_uppercase();
+            &cased
+        }
+        _ => digits,
+    };
+
+    match options.integer_underscores {
+        IntegerUnderscores::Preserve => formatted.push_str(digits),
+        IntegerUnderscores::Remove => formatted.extend(digits.chars().filter(|&c| c != '_')),
+        IntegerUnderscores::Group => {
+            let digits = digits.chars().filter(|&c| c != '_').collect::<Vec<_>>();
+            if digits.len() <= 4 {
+                formatted.extend(digits);
+                return;
+            }
+
+            let group = match kind {
+                INTEGER_HEX | INTEGER_BIN => 4,
+                _ => 3,
+            };
+            for (idx, c) in digits.iter().enumerate() {
+                if idx > 0 && (digits.len() - idx) % group == 0 {
+                    formatted.push('_');
+                }
+                formatted.push(*c);
+            }
+        }
+    }
+}
+
+/// Write a date-time or time token normalized according to
+/// [`Options::normalize_date_times`] and [`Options::date_time_separator`].
+pub(super) fn format_date_time(
+    kind: SyntaxKind,
+    text: &str,
+    options: &Options,
+    formatted: &mut String,
+) {
+    let time = if matches!(kind, DATE_TIME_OFFSET | DATE_TIME_LOCAL) {
+        // The date is always `YYYY-MM-DD`.
+        let (date, rest) = text.split_at(10);
+        let (separator, time) = rest.split_at(1);
+        formatted.push_str(date);
+        formatted.push_str(match options.date_time_separator {
+            DateTimeSeparator::T => "T",
+            DateTimeSeparator::Space => " ",
+            DateTimeSeparator::Preserve if options.normalize_date_times && separator == "t" => "T",
+            DateTimeSeparator::Preserve => separator,
+        });
+        time
+    } else {
+        text
+    };
+
+    if !options.normalize_date_times {
+        formatted.push_str(time);
+        return;
+    }
+
+    let offset_start = time.find(['Z', 'z', '+', '-']).unwrap_or(time.len());
+    let (time, offset) = time.split_at(offset_start);
+
+    formatted.push_str(time);
+    // Pad the fraction to milli-, micro- or nanoseconds.
+    if let Some((_, fraction)) = time.split_once('.') {
+        let padding = fraction.len().next_multiple_of(3) - fraction.len();
+        formatted.extend(std::iter::repeat_n('0', padding));
+    }
+    formatted.push_str(&offset.to_ascii_uppercase());
+}
+
+/// Write a multi-line basic string with the whitespace of its line-ending backslashes
+/// normalized according to [`Options::normalize_line_continuations`].
+///
+/// The whitespace after a line-ending backslash is not part of the value,
+/// so it is replaced by a single line break and the indentation of the next line,
+/// which is one level deeper than `indent` unless the line only closes the string.
+pub(super) fn format_line_continuations(
+    text: &str,
+    indent: &str,
+    options: &Options,
+    formatted: &mut String,
+) {
+    let mut rest = text;
+    while let Some(idx) = rest.find('\\') {
+        formatted.push_str(&rest[..=idx]);
+        rest = &rest[idx + 1..];
+
+        let skipped = rest.trim_start_matches([' ', '\t']);
+        if !skipped.starts_with(['\n', '\r']) {
+            // An escape sequence, its character can be another backslash.
+            if let Some(c) = rest.chars().next() {
+                formatted.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+            continue;
+        }
+
+        rest = skipped.trim_start_matches([' ', '\t', '\n', '\r']);
+        formatted.push('\n');
+        formatted.push_str(indent);
+        if !rest.bytes().all(|b| b == b'"') {
+            formatted.push_str(&options.indent_string);
+        }
+    }
+    formatted.push_str(rest);
+}
@@ -0,0 +1,197 @@
+//! The minimal formatting mode, see [`Options::minimal`].
+
+use crate::{
+    syntax::{SyntaxKind::*, SyntaxNode, SyntaxToken},
+    tree::Element,
+};
+
+use super::{Context, Keys, Options, table_indent_level};
+
+/// Write the source to `formatted` with the spacing around `=` and after commas and the
+/// indentation at the starts of lines fixed, the rest of the source is copied as it is.
+pub(super) fn format_minimal(
+    root: &SyntaxNode,
+    source: &str,
+    options: &Options,
+    context: &Context,
+    formatted: &mut String,
+) {
+    let tokens = leveled_tokens(root, source, options);
+    let verbatim = |token: &SyntaxToken| context.verbatim_at(token.span.clone());
+
+    // The replacements of the whitespace between tokens, sorted by their offsets.
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+    let around_eq = if options.compact_entries { "" } else { " " };
+    for (idx, &(token, level)) in tokens.iter().enumerate() {
+        let prev = idx.checked_sub(1).map(|idx| tokens[idx].0);
+        let next = tokens.get(idx + 1).map(|(token, _)| *token);
+        let start = token.span.start as usize;
+        if verbatim(token) {
+            continue;
+        }
+
+        // The indentation of the first token of a line.
+        let line_start = match prev {
+            None => Some(0),
+            Some(prev) if prev.kind() == NEWLINE => Some(start),
+            Some(prev) if prev.kind() == WHITESPACE && idx == 1 => Some(0),
+            Some(prev) if prev.kind() == WHITESPACE && tokens[idx - 2].0.kind() == NEWLINE => {
+                Some(prev.span.start as usize)
+            }
+            Some(_) => None,
+        };
+        if let Some(line_start) = line_start
+            && !matches!(token.kind(), WHITESPACE | NEWLINE)
+        {
+            replacements.push((line_start, start, options.indent_string.repeat(level)));
+            continue;
+        }
+
+        match token.kind() {
+            EQ => {
+                let before = prev.filter(|prev| prev.kind() == WHITESPACE);
+                let after = next.filter(|next| next.kind() == WHITESPACE);
+                if prev.is_some_and(|prev| prev.kind() != NEWLINE) {
+                    replacements.push(space_before(token, before, around_eq));
+                }
+                if next.is_some_and(|next| next.kind() != NEWLINE) {
+                    replacements.push(space_after(token, after, around_eq));
+                }
+            }
+            COMMA => {
+                if let Some(before) = prev.filter(|prev| prev.kind() == WHITESPACE) {
+                    replacements.push(space_before(token, Some(before), ""));
+                }
+                let after = next.filter(|next| next.kind() == WHITESPACE);
+                let following = match after {
+                    Some(_) => tokens.get(idx + 2).map(|(token, _)| *token),
+                    None => next,
+                };
+                match following.map(SyntaxToken::kind) {
+                    Some(COMMENT) => {}
+                    None | Some(NEWLINE) => replacements.push(space_after(token, after, "")),
+                    Some(_) => replacements.push(space_after(token, after, " ")),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    formatted.reserve(source.len());
+    let mut last_end = 0;
+    for (start, end, text) in replacements {
+        if start < last_end {
+            continue;
+        }
+        *formatted += &source[last_end..start];
+        *formatted += &text;
+        last_end = end;
+    }
+    *formatted += &source[last_end..];
+}
+
+/// The replacement of the whitespace before a token, which is inserted if there is none.
+fn space_before(
+    token: &SyntaxToken,
+    whitespace: Option<&SyntaxToken>,
+    text: &str,
+) -> (usize, usize, String) {
+    let end = token.span.start as usize;
+    let start = whitespace.map_or(end, |whitespace| whitespace.span.start as usize);
+    (start, end, text.to_string())
+}
+
+/// The replacement of the whitespace after a token, which is inserted if there is none.
+fn space_after(
+    token: &SyntaxToken,
+    whitespace: Option<&SyntaxToken>,
+    text: &str,
+) -> (usize, usize, String) {
+    let start = token.span.end as usize;
+    let end = whitespace.map_or(start, |whitespace| whitespace.span.end as usize);
+    (start, end, text.to_string())
+}
+
+/// The tokens of the document in order with the indentation level of the lines they start.
+///
+/// Headers and entries are indented like the formatter does with the options, comments
+/// like the header or entry after them, and the items of arrays and inline tables on their
+/// own lines one level deeper than the line that opens them.
+fn leveled_tokens<'a>(
+    root: &'a SyntaxNode,
+    source: &str,
+    options: &Options,
+) -> Vec<(&'a SyntaxToken, usize)> {
+    let mut tokens = Vec::new();
+    // The comments that get the level of the next header or entry.
+    let mut comments = Vec::new();
+    let mut level = 0;
+    let mut history = Vec::new();
+
+    for child in root.children() {
+        let node = match child {
+            Element::Token(token) => {
+                if token.kind() == COMMENT {
+                    comments.push(tokens.len());
+                }
+                tokens.push((token, level));
+                continue;
+            }
+            Element::Node(node) => node,
+        };
+
+        let node_level = if matches!(node.kind(), TABLE_HEADER | TABLE_ARRAY_HEADER) {
+            if options.indent_entries && level == 0 {
+                level = 1;
+            }
+            if let Some(key) = Keys::from_header(node, source) {
+                if options.indent_tables {
+                    let default = usize::from(options.indent_entries);
+                    level = table_indent_level(&history, &key, default);
+                }
+                history.push((key, level));
+            }
+            if options.indent_entries { level.saturating_sub(1) } else { level }
+        } else {
+            level
+        };
+        for idx in comments.drain(..) {
+            tokens[idx].1 = node_level;
+        }
+        node_tokens(node, node_level, None, source, &mut tokens);
+    }
+    tokens
+}
+
+/// Add the tokens of a node, `bracket_line` is the line of the bracket of the array
+/// or inline table that contains it.
+fn node_tokens<'a>(
+    node: &'a SyntaxNode,
+    level: usize,
+    bracket_line: Option<usize>,
+    source: &str,
+    tokens: &mut Vec<(&'a SyntaxToken, usize)>,
+) {
+    let is_bracket = matches!(node.kind(), ARRAY | INLINE_TABLE);
+    let (inner, bracket_line) = if is_bracket {
+        let line = source[..node.span.start as usize].matches('\n').count();
+        // Brackets on the same line, e.g. `[[`, open a single level.
+        if bracket_line == Some(line) { (level, bracket_line) } else { (level + 1, Some(line)) }
+    } else {
+        (level, bracket_line)
+    };
+    for child in node.children() {
+        match child {
+            Element::Node(child) => node_tokens(child, inner, bracket_line, source, tokens),
+            Element::Token(token) if is_bracket => {
+                let level = match token.kind() {
+                    BRACKET_START | BRACE_START => level,
+                    BRACKET_END | BRACE_END => inner.saturating_sub(1),
+                    _ => inner,
+                };
+                tokens.push((token, level));
+            }
+            Element::Token(token) => tokens.push((token, level)),
+        }
+    }
+}
@@ -4,12 +4,40 @@
 //! contain invalid syntax. In that case the invalid part is skipped.
 
 use crate::{
-    syntax::{SyntaxElement, SyntaxKind::*, SyntaxNode, SyntaxToken},
+    ast::key_part,
+    lexer::Lexer,
+    parser::{BOM, EncodingError, ParseOptions, TomlVersion},
+    syntax::{SyntaxKind, SyntaxKind::*, SyntaxNode, SyntaxToken, is_bare_key_char},
     tree::{Element, TextRange},
-    util::overlaps,
+    util::{overlaps, write_portable_escapes},
 };
 use std::cell::OnceCell;
-use std::{cmp, collections::VecDeque, ops::Range, rc::Rc};
+use std::{borrow::Cow, cmp, collections::HashMap, collections::VecDeque, ops::Range, rc::Rc};
+
+mod builder;
+mod diagnostics;
+mod directives;
+mod edits;
+mod hooks;
+mod incremental;
+mod literals;
+mod minimal;
+mod reuse;
+mod rules;
+mod sections;
+mod stream;
+
+pub use builder::{OptionsBuilder, OptionsError};
+pub use diagnostics::{FormatDiagnostic, FormatDiagnosticKind};
+pub use edits::{TextEdit, format_edits};
+pub use hooks::{ValueHooks, format_with_hooks};
+pub use incremental::format_incremental;
+pub use reuse::{Formatter, format_into};
+pub use rules::FormatRule;
+use rules::RuleScopes;
+pub(crate) use rules::{is_valid_pattern, matches_pattern};
+use sections::{CollapsedEntry, CollapsedTables};
+pub use stream::format_stream;
 
 /// Simplified Keys struct for tracking table paths (used for indentation)
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -18,16 +46,16 @@ struct Keys {
 }
 
 impl Keys {
-    fn from_syntax(syntax: &SyntaxElement, source: &str) -> Self {
-        let mut keys = Vec::new();
-        if let Some(node) = syntax.as_node() {
-            for child in node.children_with_tokens() {
-                if child.kind() == IDENT {
-                    keys.push(child.to_string(source));
-                }
-            }
-        }
-        Self { keys }
+    /// Keys of a table header, `None` if the header has no key.
+    fn from_header(header: &SyntaxNode, source: &str) -> Option<Self> {
+        let key = header.children().iter().find_map(|c| c.as_node().filter(|n| n.kind() == KEY))?;
+        let keys = key
+            .children()
+            .iter()
+            .filter(|c| c.kind() == IDENT)
+            .map(|ident| key_part(ident.text(source)))
+            .collect();
+        Some(Self { keys })
     }
 
     /// Check if current key contains (is nested under) another key
@@ -39,85 +67,359 @@ impl Keys {
     }
 }
 
-/// All the formatting options.
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct Options {
-    /// Align entries vertically.
-    ///
-    /// Entries that have table headers, comments,
-    /// or blank lines between them are not aligned.
-    pub align_entries: bool,
-
-    /// Align consecutive comments after entries and items vertically.
-    ///
-    /// This applies to comments that are after entries or array items.
-    pub align_comments: bool,
-
-    /// If `align_comments` is true, apply the alignment in cases where
-    /// there's only one comment.
-    pub align_single_comments: bool,
-
-    /// Put trailing commas for multiline
-    /// arrays.
-    pub array_trailing_comma: bool,
-
-    /// Automatically expand arrays to multiple lines once they
-    /// exceed the configured `column_width`.
-    pub array_auto_expand: bool,
+/// Defines [`Options`] and `for_each_option!`, which calls a macro with the names and the
+/// types of all options, so that the builder and config files don't list them again.
+macro_rules! options {
+    (
+        $(#[$meta:meta])*
+        pub struct Options {
+            $($(#[$attr:meta])* pub $name:ident: $ty:ty,)*
+        }
+    ) => {
+        $(#[$meta])*
+        pub struct Options {
+            $($(#[$attr])* pub $name: $ty,)*
+        }
 
-    /// Expand values (e.g.) inside inline tables
-    /// where possible.
-    pub inline_table_expand: bool,
+        macro_rules! for_each_option {
+            ($callback:ident) => {
+                $callback! { $($name: $ty,)* }
+            };
+        }
+        pub(crate) use for_each_option;
+    };
+}
 
-    /// Automatically collapse arrays if they
-    /// fit in one line.
+options! {
+    /// All the formatting options.
     ///
-    /// The array won't be collapsed if it
-    /// contains a comment.
-    pub array_auto_collapse: bool,
-
-    /// Omit whitespace padding inside single-line arrays.
-    pub compact_arrays: bool,
-
-    /// Omit whitespace padding inside inline tables.
-    pub compact_inline_tables: bool,
-
-    /// Omit whitespace around `=`.
-    pub compact_entries: bool,
+    /// New options are added over time, use [`Options::builder`] or
+    /// start from [`Options::default`] to set them.
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+    #[non_exhaustive]
+    pub struct Options {
+        /// Align entries vertically.
+        ///
+        /// Entries that have table headers, comments,
+        /// or blank lines between them are not aligned.
+        pub align_entries: bool,
+
+        /// Align consecutive comments after entries and items vertically.
+        ///
+        /// This applies to comments that are after entries or array items.
+        pub align_comments: bool,
+
+        /// If `align_comments` is true, apply the alignment in cases where
+        /// there's only one comment.
+        pub align_single_comments: bool,
+
+        /// Remove all comments, lines with nothing but a comment are removed entirely.
+        ///
+        /// Formatting directives such as `# oxc-toml: fmt off` are removed as well
+        /// and have no effect.
+        pub strip_comments: bool,
+
+        /// Put trailing commas for multiline
+        /// arrays.
+        pub array_trailing_comma: bool,
+
+        /// Automatically expand arrays to multiple lines once they
+        /// exceed the configured `column_width`.
+        pub array_auto_expand: bool,
+
+        /// Expand values (e.g.) inside inline tables
+        /// where possible.
+        pub inline_table_expand: bool,
+
+        /// Automatically collapse arrays if they
+        /// fit in one line.
+        ///
+        /// The array won't be collapsed if it
+        /// contains a comment.
+        pub array_auto_collapse: bool,
+
+        /// Whether arrays are expanded and collapsed automatically
+        /// or kept on a single or multiple lines as they are written.
+        ///
+        /// `array_auto_expand` and `array_auto_collapse` only apply to [`ArrayLayout::Auto`].
+        pub array_layout: ArrayLayout,
+
+        /// Omit whitespace padding inside single-line arrays.
+        pub compact_arrays: bool,
+
+        /// Omit whitespace padding inside inline tables.
+        pub compact_inline_tables: bool,
+
+        /// Omit whitespace around `=`.
+        pub compact_entries: bool,
+
+        /// Target maximum column width after which
+        /// arrays are expanded into new lines.
+        ///
+        /// This is best-effort and might not be accurate.
+        pub column_width: usize,
+
+        /// Indent subtables if they come in order.
+        pub indent_tables: bool,
+
+        /// Indent entries under tables by one level.
+        ///
+        /// With `indent_tables`, entries are indented one level deeper than their table header.
+        pub indent_entries: bool,
+
+        /// Indentation to use, should be tabs or spaces
+        /// but technically could be anything.
+        pub indent_string: String,
+
+        /// End the output with exactly one newline.
+        ///
+        /// Blank lines at the end of the document are removed either way.
+        pub trailing_newline: bool,
+
+        /// Keep the byte order mark at the start of the document, it is removed otherwise.
+        pub preserve_bom: bool,
+
+        /// Remove spaces and tabs at the ends of lines everywhere, also at the ends of comments
+        /// and in the parts of the document that are not formatted.
+        ///
+        /// Multi-line strings are never changed.
+        pub trim_trailing_whitespace: bool,
+
+        /// Alphabetically reorder keys that are not separated by blank lines.
+        pub reorder_keys: bool,
+
+        /// Alphabetically reorder array values that are not separated by blank lines.
+        pub reorder_arrays: bool,
+
+        /// Alphabetically reorder inline table values.
+        pub reorder_inline_tables: bool,
+
+        /// Alphabetically sort entries within tables and inline tables.
+        ///
+        /// Unlike `reorder_keys`, comments don't break the sorted groups,
+        /// instead comments directly above an entry are moved together with it.
+        /// Blank lines still separate the groups that are sorted.
+        pub sort_entries: bool,
+
+        /// Alphabetically reorder table sections.
+        ///
+        /// Each section is moved together with its entries and the comments
+        /// directly above its header. Subtables of an array of tables are kept
+        /// with the array element they belong to.
+        ///
+        /// Documents with syntax errors are never reordered.
+        pub reorder_tables: bool,
+
+        /// Convert inline tables that don't fit in `column_width` into standard table sections.
+        ///
+        /// Only inline tables that are the values of entries in tables are converted,
+        /// the new sections are added after the section the entry was in.
+        pub expand_inline_tables: bool,
+
+        /// Move entries with dotted keys of at least this many parts into table sections,
+        /// e.g. with `Some(3)`, `a.b.c = 1` becomes `c = 1` in a new `[a.b]` section.
+        ///
+        /// The new sections are added after the section the entries were in,
+        /// documents with syntax errors are never changed.
+        pub expand_dotted_keys: Option<usize>,
+
+        /// Collapse tables with a single entry and no comments into a dotted key in their
+        /// parent table, e.g. `[profile.release]` with `lto = true` becomes `profile.release.lto = true`.
+        ///
+        /// Tables with subtables are kept, documents with syntax errors are never changed.
+        pub collapse_tables: bool,
+
+        /// Convert tables with at most this many entries and no comments into inline tables
+        /// in their parent table if they fit in `column_width`,
+        /// e.g. `[server]` with `host = "a"` and `port = 1` becomes `server = { host = "a", port = 1 }`.
+        ///
+        /// Tables with subtables are kept, documents with syntax errors are never changed.
+        /// Tables with a single entry are collapsed into a dotted key instead if
+        /// `collapse_tables` is enabled.
+        pub inline_small_tables: Option<usize>,
+
+        /// Convert arrays of tables whose elements have a single entry and no comments into
+        /// an array of inline tables in their parent table if it fits in `column_width`,
+        /// e.g. `[[bin]]` sections with `name = "a"` and `name = "b"` become
+        /// `bin = [{ name = "a" }, { name = "b" }]`.
+        ///
+        /// Arrays with subtables are kept, documents with syntax errors are never changed.
+        pub inline_array_tables: bool,
+
+        /// Force a blank line between the elements of an array of tables.
+        ///
+        /// Subtables of an element stay with their element.
+        pub separate_array_tables: bool,
+
+        /// Table keys that are moved before the rest of the tables in the given order
+        /// if `reorder_tables` is enabled, e.g. `["package", "dependencies"]`.
+        ///
+        /// A key also matches all of its subtables.
+        pub table_priority: Vec<String>,
+
+        /// Remove the quotes from keys that are valid bare keys, e.g. `"key" = 1` becomes `key = 1`.
+        ///
+        /// Keys that can't be written as bare keys are kept quoted, e.g. the keys with non-ASCII
+        /// characters.
+        pub unquote_keys: bool,
+
+        /// How to write the `_` separators between the digits of integers.
+        pub integer_underscores: IntegerUnderscores,
+
+        /// The case of the digits of hexadecimal integers.
+        pub hex_case: HexCase,
+
+        /// Write dates and times consistently, the `T` and `Z` are uppercased
+        /// and fractional seconds are padded to milli-, micro- or nanoseconds.
+        pub normalize_date_times: bool,
+
+        /// The separator between the date and time of date-times.
+        pub date_time_separator: DateTimeSeparator,
+
+        /// Write the `\e` and `\xHH` escapes of string values, which were added in TOML 1.1,
+        /// as `\u001B` and `\u00HH` so that TOML 1.0 parsers can read them.
+        pub portable_escapes: bool,
+
+        /// Remove the whitespace after the backslashes at the ends of lines in multi-line
+        /// basic strings and indent the lines after them one level deeper than the entry.
+        ///
+        /// The whitespace after such a backslash is not part of the string,
+        /// so the value doesn't change.
+        pub normalize_line_continuations: bool,
+
+        /// The maximum amount of consecutive blank lines allowed.
+        pub allowed_blank_lines: usize,
+
+        /// The exact amount of blank lines before table headers,
+        /// the blank lines from the source are kept if not set.
+        ///
+        /// The blank lines are added before the comments directly above the header,
+        /// and never before a table at the start of the document.
+        pub blank_lines_before_table: Option<usize>,
+
+        /// The line endings used in the output.
+        pub line_ending: LineEnding,
+
+        /// Only change whitespace and line breaks, never the other tokens.
+        ///
+        /// The options that rewrite, move or remove tokens are ignored and trailing commas
+        /// of arrays are kept as they are. If the output would still differ in anything
+        /// other than whitespace, the source is returned unchanged.
+        pub whitespace_only: bool,
+
+        /// Only fix the spacing around `=` and after commas and the indentation at the starts
+        /// of lines, never change anything else.
+        ///
+        /// The other options are ignored, except for `compact_entries`, `indent_string`,
+        /// `indent_entries` and `indent_tables`. Line breaks, blank lines, comments and the
+        /// layout of arrays are kept as they are.
+        pub minimal: bool,
+
+        /// The version of TOML of the documents.
+        ///
+        /// Syntax that the version doesn't allow is a syntax error,
+        /// so it is left as it is.
+        pub toml_version: TomlVersion,
+    }
+}
 
-    /// Target maximum column width after which
-    /// arrays are expanded into new lines.
+/// Digit separators in integers, see [`Options::integer_underscores`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum IntegerUnderscores {
+    /// Keep the separators from the source.
+    #[default]
+    Preserve,
+    /// Separate the digits of integers with more than 4 digits into groups,
+    /// groups of 4 for hexadecimal and binary integers and 3 otherwise.
     ///
-    /// This is best-effort and might not be accurate.
-    pub column_width: usize,
-
-    /// Indent subtables if they come in order.
-    pub indent_tables: bool,
-
-    /// Indent entries under tables.
-    pub indent_entries: bool,
-
-    /// Indentation to use, should be tabs or spaces
-    /// but technically could be anything.
-    pub indent_string: String,
-
-    /// Add trailing newline to the source.
-    pub trailing_newline: bool,
+    /// E.g. `1000000` becomes `1_000_000` and `0xdeadbeef` becomes `0xdead_beef`.
+    Group,
+    /// Remove all separators.
+    Remove,
+}
 
-    /// Alphabetically reorder keys that are not separated by blank lines.
-    pub reorder_keys: bool,
+/// Layout of arrays, see [`Options::array_layout`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ArrayLayout {
+    /// Expand arrays that are too long and collapse arrays that fit in one line
+    /// according to `array_auto_expand` and `array_auto_collapse`.
+    #[default]
+    Auto,
+    /// Keep single-line arrays on a single line and multi-line arrays on multiple lines,
+    /// only the spacing and the indentation are normalized.
+    Preserve,
+}
 
-    /// Alphabetically reorder array values that are not separated by blank lines.
-    pub reorder_arrays: bool,
+/// Case of hexadecimal digits, see [`Options::hex_case`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum HexCase {
+    /// Keep the digits from the source.
+    #[default]
+    Preserve,
+    /// `0xdeadbeef`
+    Lower,
+    /// `0xDEADBEEF`
+    Upper,
+}
 
-    /// Alphabetically reorder inline table values.
-    pub reorder_inline_tables: bool,
+/// Separator between the date and the time, see [`Options::date_time_separator`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum DateTimeSeparator {
+    /// Keep the separator from the source.
+    #[default]
+    Preserve,
+    /// `1979-05-27T07:32:00`
+    T,
+    /// `1979-05-27 07:32:00`
+    Space,
+}
 
-    /// The maximum amount of consecutive blank lines allowed.
-    pub allowed_blank_lines: usize,
+/// Line endings of the formatted output.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`
+    Crlf,
+    /// Use the line ending of the first line in the source,
+    /// `\n` if the source has a single line.
+    Preserve,
+}
 
-    /// Use CRLF line endings
-    pub crlf: bool,
+impl LineEnding {
+    fn detect(src: &str) -> Self {
+        match src.find('\n') {
+            Some(idx) if src[..idx].ends_with('\r') => Self::Crlf,
+            _ => Self::Lf,
+        }
+    }
 }
 
 impl Default for Options {
@@ -126,9 +428,11 @@ impl Default for Options {
             align_entries: false,
             align_comments: false,
             align_single_comments: false,
+            strip_comments: false,
             array_trailing_comma: true,
             array_auto_expand: true,
             array_auto_collapse: true,
+            array_layout: ArrayLayout::Auto,
             compact_arrays: true,
             compact_inline_tables: false,
             compact_entries: false,
@@ -137,25 +441,135 @@ impl Default for Options {
             indent_entries: false,
             inline_table_expand: true,
             trailing_newline: true,
+            preserve_bom: true,
+            trim_trailing_whitespace: false,
             allowed_blank_lines: 2,
+            blank_lines_before_table: None,
             indent_string: "  ".into(),
             reorder_keys: false,
             reorder_arrays: false,
             reorder_inline_tables: false,
-            crlf: false,
+            sort_entries: false,
+            reorder_tables: false,
+            expand_inline_tables: false,
+            expand_dotted_keys: None,
+            collapse_tables: false,
+            inline_small_tables: None,
+            inline_array_tables: false,
+            separate_array_tables: false,
+            table_priority: Vec::new(),
+            unquote_keys: false,
+            integer_underscores: IntegerUnderscores::Preserve,
+            hex_case: HexCase::Preserve,
+            normalize_date_times: false,
+            date_time_separator: DateTimeSeparator::Preserve,
+            portable_escapes: false,
+            normalize_line_continuations: false,
+            line_ending: LineEnding::Lf,
+            whitespace_only: false,
+            minimal: false,
+            toml_version: TomlVersion::V1_1,
         }
     }
 }
 
+#[cfg(feature = "serde")]
+impl Options {
+    /// Deserialize the options like [`serde::Deserialize`] and also return the paths
+    /// of the unknown fields that were ignored, e.g. to warn about typos in config files.
+    pub fn deserialize_with_unknown_fields<'de, D>(
+        deserializer: D,
+    ) -> Result<(Self, Vec<String>), D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut unknown_fields = Vec::new();
+        let options = serde_ignored::deserialize(deserializer, |path| {
+            unknown_fields.push(path.to_string());
+        })?;
+        Ok((options, unknown_fields))
+    }
+}
+
 impl Options {
+    /// Create a builder starting from the default options.
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder::default()
+    }
+
+    /// The style of `Cargo.toml` files from the Rust style guide,
+    /// indented with four spaces and with one blank line before tables.
+    pub fn cargo() -> Self {
+        Self {
+            indent_string: "    ".into(),
+            allowed_blank_lines: 1,
+            blank_lines_before_table: Some(1),
+            ..Self::default()
+        }
+    }
+
+    /// As little whitespace as possible, without blank lines, trailing commas and
+    /// spaces around `=` or inside arrays and inline tables.
+    pub fn compact() -> Self {
+        Self {
+            array_trailing_comma: false,
+            compact_arrays: true,
+            compact_inline_tables: true,
+            compact_entries: true,
+            allowed_blank_lines: 0,
+            ..Self::default()
+        }
+    }
+
+    /// The defaults of Taplo, which only differ in the column width and comment alignment.
+    pub fn taplo_default() -> Self {
+        Self {
+            align_comments: true,
+            align_single_comments: true,
+            column_width: 80,
+            ..Self::default()
+        }
+    }
+
     const fn newline(&self) -> &'static str {
-        if self.crlf { "\r\n" } else { "\n" }
+        match self.line_ending {
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Lf | LineEnding::Preserve => "\n",
+        }
     }
 
     fn newlines(&self, count: usize) -> impl Iterator<Item = &'static str> {
         std::iter::repeat_n(self.newline(), usize::min(count, self.allowed_blank_lines + 1))
     }
 
+    /// Whether tables are collapsed into entries of their parent tables.
+    fn collapses_tables(&self) -> bool {
+        self.collapse_tables || self.inline_small_tables.is_some() || self.inline_array_tables
+    }
+
+    /// Disable the options that change anything other than whitespace.
+    fn restrict_to_whitespace(&mut self) {
+        self.strip_comments = false;
+        self.reorder_keys = false;
+        self.reorder_arrays = false;
+        self.reorder_inline_tables = false;
+        self.sort_entries = false;
+        self.reorder_tables = false;
+        self.expand_inline_tables = false;
+        self.expand_dotted_keys = None;
+        self.collapse_tables = false;
+        self.inline_small_tables = None;
+        self.inline_array_tables = false;
+        self.unquote_keys = false;
+        self.integer_underscores = IntegerUnderscores::Preserve;
+        self.hex_case = HexCase::Preserve;
+        self.normalize_date_times = false;
+        self.date_time_separator = DateTimeSeparator::Preserve;
+        self.portable_escapes = false;
+        self.normalize_line_continuations = false;
+        self.preserve_bom = true;
+    }
+
     const fn should_align_comments(&self, comment_count: usize) -> bool {
         (comment_count != 1 || self.align_single_comments) && self.align_comments
     }
@@ -166,6 +580,12 @@ struct Context {
     indent_level: usize,
     force_multiline: bool,
     errors: Rc<[TextRange]>,
+    /// Regions where formatting is disabled with `fmt off` and `fmt on` comments.
+    disabled: Rc<[TextRange]>,
+    /// The options of the entries that match a [`FormatRule`].
+    rules: Rc<RuleScopes>,
+    /// The texts of the values that hooks rewrite by the start of their nodes.
+    hooked: Rc<HashMap<u32, String>>,
 }
 
 impl Default for Context {
@@ -174,14 +594,19 @@ impl Default for Context {
             indent_level: Default::default(),
             force_multiline: Default::default(),
             errors: Rc::from([]),
+            disabled: Rc::from([]),
+            rules: Rc::default(),
+            hooked: Rc::default(),
         }
     }
 }
 
 impl Context {
-    fn error_at(&self, range: TextRange) -> bool {
-        for error_range in self.errors.iter() {
-            if overlaps(range.clone(), error_range.clone()) {
+    /// Whether the range has to be copied as it is because of syntax errors
+    /// or because formatting is disabled.
+    fn verbatim_at(&self, range: TextRange) -> bool {
+        for verbatim_range in self.errors.iter().chain(self.disabled.iter()) {
+            if overlaps(range.clone(), verbatim_range.clone()) {
                 return true;
             }
         }
@@ -189,48 +614,440 @@ impl Context {
         false
     }
 
+    /// Moving sections around is not safe if we don't understand the entire document
+    /// or parts of it must stay in place.
+    fn can_restructure(&self) -> bool {
+        self.errors.is_empty() && self.disabled.is_empty()
+    }
+
     fn indent<'o>(&self, opts: &'o Options) -> impl Iterator<Item = &'o str> {
         std::iter::repeat_n(opts.indent_string.as_ref(), self.indent_level)
     }
+
+    /// The rendered width of the current indentation in characters.
+    fn indent_width(&self, opts: &Options) -> usize {
+        self.indent_level * opts.indent_string.chars().count()
+    }
+}
+
+/// Measure a formatted value and check whether it would overflow `column_width`.
+///
+/// `prefix_width` is the width of everything printed before the value on its first line
+/// (indentation, key, `=`), `suffix_width` is the width of everything after its last line
+/// (comma, trailing comment).
+fn exceeds_column_width(
+    options: &Options,
+    prefix_width: usize,
+    value: &str,
+    suffix_width: usize,
+) -> bool {
+    let line_count = value.split('\n').count();
+
+    value.split('\n').enumerate().any(|(idx, line)| {
+        let mut width = line.strip_suffix('\r').unwrap_or(line).chars().count();
+        if idx == 0 {
+            width += prefix_width;
+        }
+        if idx == line_count - 1 {
+            width += suffix_width;
+        }
+        width > options.column_width
+    })
 }
 
 /// Parses then formats a TOML document, skipping ranges that contain syntax errors.
+///
+/// Regions between `# oxc-toml: fmt off` and `# oxc-toml: fmt on` comments are not formatted,
+/// neither is the entry or table section after a `# oxc-toml: ignore` comment.
 pub fn format(src: &str, options: Options) -> String {
-    let (root, errors) = crate::parser::parse_root(src);
+    format_with_rules(src, options, &[])
+}
+
+/// Like [`format()`] for a document that may not be valid UTF-8.
+///
+/// Returns the offset of the first invalid sequence if it is not valid UTF-8,
+/// the document is never formatted partially.
+pub fn format_bytes(src: &[u8], options: Options) -> Result<String, EncodingError> {
+    Ok(format(str::from_utf8(src)?, options))
+}
+
+/// Format a node of a syntax tree of the source on its own, e.g. an entry, a table header
+/// or a value, and return its text without a line break at the end.
+///
+/// The node is formatted like it is in the formatted document, with the indentation of its
+/// table section but without the indentation of its first line. The root node is formatted
+/// like [`format()`], the text of the nodes that are not formatted on their own and of the
+/// nodes that contain syntax errors or that formatting is disabled for is returned as it is.
+pub fn format_node(node: &SyntaxNode, source: &str, mut options: Options) -> String {
+    if node.kind() == ROOT {
+        return format(source, options);
+    }
+
+    if options.whitespace_only || options.minimal {
+        options.restrict_to_whitespace();
+    }
+    if options.line_ending == LineEnding::Preserve {
+        options.line_ending = LineEnding::detect(source);
+    }
+
+    // The tree of the source is only used to find the section and the parent of the node.
+    let parse_options = ParseOptions { toml_version: options.toml_version, ..Default::default() };
+    let (root, errors) = crate::parser::parse_root(source, parse_options);
+    let fmt_off = directives::fmt_off_ranges(&root, source);
+    let ignored = directives::ignored_ranges(&root, source);
+    let mut context = Context {
+        errors: errors.iter().map(|err| err.span.clone()).collect(),
+        disabled: fmt_off.into_iter().chain(ignored).collect(),
+        ..Context::default()
+    };
+    if context.verbatim_at(node.span.clone()) {
+        return node.text(source).to_string();
+    }
+
+    let mut history = Vec::new();
+    let mut header_context = context.clone();
+    let mut item = None;
+    for child in root.children().iter().filter_map(Element::as_node) {
+        if child.span.start > node.span.start {
+            break;
+        }
+        if matches!(child.kind(), TABLE_HEADER | TABLE_ARRAY_HEADER) {
+            header_context = enter_table(child, source, &options, &mut context, &mut history);
+        }
+        item = Some(child);
+    }
+
+    let mut formatted = String::new();
+    let comment = match node.kind() {
+        TABLE_HEADER | TABLE_ARRAY_HEADER => {
+            format_table_header(node, source, &options, &header_context, &mut formatted)
+        }
+        ENTRY => {
+            let mut entry = format_entry(node, source, &options, &context);
+            expand_entry(&mut entry, source, &options, &context);
+            write_entries(&[entry], &mut formatted, &options, &context);
+            formatted.drain(..context.indent(&options).map(str::len).sum::<usize>());
+            None
+        }
+        VALUE => {
+            // The value of an entry is expanded like in the entry.
+            let entry = item
+                .into_iter()
+                .flat_map(|item| {
+                    std::iter::once(item).chain(item.descendants().filter_map(Element::as_node))
+                })
+                .find(|n| {
+                    n.kind() == ENTRY
+                        && n.children().iter().any(|c| c.kind() == VALUE && c.span() == &node.span)
+                });
+            match entry {
+                Some(entry) => {
+                    let mut entry = format_entry(entry, source, &options, &context);
+                    expand_entry(&mut entry, source, &options, &context);
+                    formatted += &entry.value;
+                    entry.comment
+                }
+                None => format_value(node, source, &options, &context, &mut formatted),
+            }
+        }
+        ARRAY => format_array(node, source, &options, &context, &mut formatted),
+        INLINE_TABLE => format_inline_table(node, source, &options, &context, &mut formatted),
+        KEY => {
+            format_key(node, source, &mut formatted, &options, &context);
+            None
+        }
+        _ => return node.text(source).to_string(),
+    };
+    if let Some(comment) = comment {
+        formatted += " ";
+        formatted += comment;
+    }
+
+    formatted
+}
+
+/// Like [`format()`], but the entries that match one of the rules are formatted
+/// with the options of the rule.
+pub fn format_with_rules(src: &str, options: Options, rules: &[FormatRule]) -> String {
+    format_with_diagnostics(src, options, rules).0
+}
+
+/// Like [`format_with_rules`], but also report the parts of the document that were
+/// left as they are and why, e.g. because of syntax errors or directives.
+pub fn format_with_diagnostics(
+    src: &str,
+    options: Options,
+    rules: &[FormatRule],
+) -> (String, Vec<FormatDiagnostic>) {
+    format_full(src, options, rules, &ValueHooks::default())
+}
+
+/// Format with the rules and the hooks and report the diagnostics.
+fn format_full(
+    src: &str,
+    options: Options,
+    rules: &[FormatRule],
+    hooks: &ValueHooks<'_>,
+) -> (String, Vec<FormatDiagnostic>) {
+    let mut formatted = String::new();
+    let diagnostics =
+        format_full_into(src, options, rules, hooks, &mut formatted, &mut String::new());
+    (formatted, diagnostics)
+}
+
+/// Like [`format_full`], but the document is written to `out`, which is cleared first,
+/// and `scratch` holds the intermediate texts so their allocations can be reused.
+fn format_full_into(
+    src: &str,
+    mut options: Options,
+    rules: &[FormatRule],
+    hooks: &ValueHooks<'_>,
+    out: &mut String,
+    scratch: &mut String,
+) -> Vec<FormatDiagnostic> {
+    out.clear();
+    let parse_options = ParseOptions { toml_version: options.toml_version, ..Default::default() };
+    let (root, errors) = crate::parser::parse_root(src, parse_options);
+    let mut diagnostics = errors
+        .iter()
+        .map(|err| FormatDiagnostic {
+            range: Some(err.span.clone()),
+            kind: FormatDiagnosticKind::SyntaxError,
+            message: format!("not formatted because of a syntax error: {}", err.message),
+        })
+        .collect::<Vec<_>>();
+
+    if options.whitespace_only || options.minimal {
+        options.restrict_to_whitespace();
+    }
+
+    if options.strip_comments {
+        let stripped = strip_comments(&root, src);
+        options.strip_comments = false;
+        let stripped_diagnostics = format_full_into(&stripped, options, rules, hooks, out, scratch);
+        // The spans in the stripped document don't match the source.
+        diagnostics.extend(stripped_diagnostics.into_iter().filter(|d| d.range.is_none()));
+        return diagnostics;
+    }
+
+    if options.line_ending == LineEnding::Preserve {
+        options.line_ending = LineEnding::detect(src);
+    }
+
+    for (idx, rule) in rules.iter().enumerate() {
+        for key in rule.keys.iter().filter(|key| !is_valid_pattern(key)) {
+            diagnostics.push(FormatDiagnostic {
+                range: None,
+                kind: FormatDiagnosticKind::InvalidRulePattern,
+                message: format!("rule {idx} has an invalid key pattern `{key}`"),
+            });
+        }
+    }
+
+    // The line endings of the document are the same everywhere.
+    let rules = rules
+        .iter()
+        .map(|rule| {
+            let mut rule = rule.clone();
+            rule.options.line_ending = options.line_ending;
+            if options.whitespace_only {
+                rule.options.whitespace_only = true;
+                rule.options.restrict_to_whitespace();
+            }
+            rule
+        })
+        .collect::<Vec<_>>();
+
+    diagnostics.extend(diagnostics::misplaced_root_entries(&root, src));
+
+    let fmt_off = directives::fmt_off_ranges(&root, src);
+    let ignored = directives::ignored_ranges(&root, src);
+    for (ranges, kind, message) in [
+        (&fmt_off, FormatDiagnosticKind::FormattingDisabled, "formatting is disabled by `fmt off`"),
+        (&ignored, FormatDiagnosticKind::Ignored, "formatting is disabled by `ignore`"),
+    ] {
+        diagnostics.extend(ranges.iter().map(|range| FormatDiagnostic {
+            range: Some(range.clone()),
+            kind,
+            message: message.into(),
+        }));
+    }
 
     let ctx = Context {
-        errors: errors.iter().map(|err| err.range.clone()).collect(),
+        errors: errors.iter().map(|err| err.span.clone()).collect(),
+        disabled: fmt_off.into_iter().chain(ignored).collect(),
+        rules: RuleScopes::new(&root, src, &rules).into(),
+        hooked: hooks::hooked_values(&root, src, hooks).into(),
         ..Context::default()
     };
 
-    format_impl(&root, src, options, ctx)
+    if options.minimal {
+        minimal::format_minimal(&root, src, &options, &ctx, out);
+        return diagnostics;
+    }
+
+    let restructures_tables = options.reorder_tables
+        || options.collapse_tables
+        || options.inline_small_tables.is_some()
+        || options.inline_array_tables
+        || options.expand_dotted_keys.is_some();
+    if restructures_tables && !ctx.can_restructure() {
+        diagnostics.push(FormatDiagnostic {
+            range: None,
+            kind: FormatDiagnosticKind::TablesNotRestructured,
+            message: "tables are not moved because parts of the document are not formatted".into(),
+        });
+    }
+
+    let whitespace_only = options.whitespace_only;
+    // A table can be collapsed once its subtables are, so the output is formatted
+    // again until no more tables are collapsed.
+    let repeated_options = options.collapses_tables().then(|| options.clone());
+    let collapsed = format_impl(&root, src, options, ctx, out, scratch);
+    if whitespace_only && !same_tokens(src, out) {
+        diagnostics.push(FormatDiagnostic {
+            range: None,
+            kind: FormatDiagnosticKind::TokensChanged,
+            message: "formatting would change more than whitespace, the source is kept".into(),
+        });
+        out.clear();
+        *out += src;
+    }
+
+    if collapsed && let Some(options) = repeated_options {
+        // The values are already changed by the hooks and the diagnostics are reported
+        // for the source, so only the output is formatted again.
+        let collapsed = std::mem::take(out);
+        format_full_into(&collapsed, options, &rules, &ValueHooks::default(), out, scratch);
+    }
+
+    diagnostics
 }
 
-fn format_impl(node: &SyntaxNode, source: &str, options: Options, context: Context) -> String {
-    assert!(node.kind() == ROOT);
-    let mut formatted = format_root(node, source, &options, &context);
+/// Whether the documents only differ in whitespace, line breaks
+/// and whitespace at the end of comments.
+fn same_tokens(a: &str, b: &str) -> bool {
+    fn tokens(source: &str) -> impl Iterator<Item = (Result<SyntaxKind, ()>, &str)> {
+        let mut lexer = Lexer::<SyntaxKind>::new(source);
+        std::iter::from_fn(move || {
+            let token = lexer.next()?;
+            let text = match token {
+                Ok(COMMENT) => lexer.slice().trim_end(),
+                _ => lexer.slice(),
+            };
+            Some((token, text))
+        })
+        .filter(|(token, _)| !matches!(token, Ok(WHITESPACE | NEWLINE)))
+    }
 
-    if formatted.ends_with("\r\n") {
-        formatted.truncate(formatted.len() - 2);
-    } else if formatted.ends_with('\n') {
-        formatted.truncate(formatted.len() - 1);
+    tokens(a).eq(tokens(b))
+}
+
+/// Write the source without the spaces and tabs at the ends of lines, the whitespace
+/// in multi-line strings is part of their tokens, so it is kept.
+fn trim_trailing_whitespace(source: &str, trimmed: &mut String) {
+    trimmed.reserve(source.len());
+    let mut lexer = Lexer::<SyntaxKind>::new(source);
+    // The whitespace is only written if something other than a line break follows it.
+    let mut whitespace = "";
+
+    while let Some(token) = lexer.next() {
+        match token {
+            Ok(WHITESPACE) => {
+                whitespace = lexer.slice();
+                continue;
+            }
+            Ok(NEWLINE) => *trimmed += lexer.slice(),
+            Ok(COMMENT) => {
+                *trimmed += whitespace;
+                *trimmed += lexer.slice().trim_end_matches([' ', '\t']);
+            }
+            _ => {
+                *trimmed += whitespace;
+                *trimmed += lexer.slice();
+            }
+        }
+        whitespace = "";
     }
+}
+
+/// Remove the comments from the source together with the whitespace before them.
+///
+/// Lines with nothing but a comment are removed including their line break.
+fn strip_comments(root: &SyntaxNode, source: &str) -> String {
+    let mut stripped = String::with_capacity(source.len());
+    let mut last_end = 0;
+
+    for comment in root.descendants_with_tokens().filter(|c| c.kind() == COMMENT) {
+        let range = comment.text_range();
+        let (mut start, mut end) = (range.start as usize, range.end as usize);
+
+        let line_start = source[..start].rfind('\n').map_or(0, |idx| idx + 1);
+        let before = source[line_start..start].trim_end_matches([' ', '\t']);
+        if before.is_empty() {
+            start = line_start;
+            end += source[end..].find('\n').map_or(source.len() - end, |idx| idx + 1);
+        } else {
+            start = line_start + before.len();
+        }
+
+        stripped += &source[last_end..start];
+        last_end = end;
+    }
+    stripped += &source[last_end..];
+
+    // Don't leave the blank lines after comments at the start of the document.
+    if source.trim_start().starts_with('#') {
+        stripped = stripped.trim_start().to_string();
+    }
+
+    stripped
+}
+
+/// Returns whether tables were collapsed, see [`format_root`].
+fn format_impl(
+    node: &SyntaxNode,
+    source: &str,
+    options: Options,
+    context: Context,
+    formatted: &mut String,
+    scratch: &mut String,
+) -> bool {
+    assert!(node.kind() == ROOT);
+    let collapsed = format_root(node, source, &options, &context, formatted);
+
+    // Blank lines at the end of the document are never kept.
+    let len = formatted.trim_end_matches(['\r', '\n']).len();
+    formatted.truncate(len);
 
     if options.trailing_newline {
-        formatted += options.newline();
+        *formatted += options.newline();
     }
 
-    formatted
+    if options.trim_trailing_whitespace {
+        scratch.clear();
+        trim_trailing_whitespace(formatted, scratch);
+        std::mem::swap(formatted, scratch);
+    }
+
+    if options.preserve_bom && source.starts_with(BOM) {
+        formatted.insert_str(0, BOM);
+    }
+
+    collapsed
 }
 
 struct FormattedEntry<'a> {
     syntax: &'a SyntaxNode,
-    key: String,
+    /// The key and the value borrow the source if formatting doesn't change them.
+    key: Cow<'a, str>,
     /// This field is used to cache the "cleaned" version of the key and should only
     /// be accessed through the `cleaned_key` helpers method.
     cleaned_key: OnceCell<Vec<String>>,
-    value: String,
-    comment: Option<String>,
+    value: Cow<'a, str>,
+    comment: Option<&'a str>,
+    /// Comments directly above the entry, only collected if `sort_entries` is enabled.
+    leading_comments: Vec<&'a str>,
 }
 
 impl FormattedEntry<'_> {
@@ -273,10 +1090,22 @@ impl FormattedEntry<'_> {
     }
 }
 
-fn format_root(node: &SyntaxNode, source: &str, options: &Options, context: &Context) -> String {
+/// Write the formatted document to `out`, which must be empty.
+///
+/// Returns whether tables were collapsed, their parents may be collapsed as well
+/// if the output is formatted again.
+fn format_root(
+    node: &SyntaxNode,
+    source: &str,
+    options: &Options,
+    context: &Context,
+    out: &mut String,
+) -> bool {
     assert!(node.kind() == ROOT);
-    // Output size is roughly proportional to the input.
-    let mut formatted = String::with_capacity(source.len());
+    debug_assert!(out.is_empty());
+    // The allocation of the caller is reused, the output size is roughly proportional to the input.
+    let mut formatted = std::mem::take(out);
+    formatted.reserve(source.len());
 
     let mut entry_group: Vec<FormattedEntry> = Vec::new();
 
@@ -287,36 +1116,68 @@ fn format_root(node: &SyntaxNode, source: &str, options: &Options, context: &Con
 
     // We defer printing comments as well because we need to know
     // what comes after them for correct indentation.
-    let mut comment_group: Vec<String> = Vec::new();
+    let mut comment_group: Vec<&str> = Vec::new();
 
     let mut context = context.clone();
 
     // Table key for determining indents
     let mut table_key_indent_history: Vec<(Keys, usize)> = Vec::new();
 
-    fn add_comments(
-        comments: &mut Vec<String>,
-        formatted: &mut String,
-        context: &Context,
-        options: &Options,
-    ) -> bool {
-        let were_comments = !comments.is_empty();
+    let mut dangling_newline_count = 0;
 
-        for (idx, comment) in comments.drain(0..).enumerate() {
-            if idx != 0 {
-                *formatted += options.newline();
-            }
-            formatted.extend(context.indent(options));
-            *formatted += &comment;
-        }
+    // The header of the current table section.
+    let mut current_table: Option<&SyntaxNode> = None;
 
-        were_comments
-    }
+    // Inline tables and dotted keys that are written as sections after the current section.
+    let mut new_sections: Vec<NewSection> = Vec::new();
 
-    let mut dangling_newline_count = 0;
+    // The lines of entries and comments that were moved elsewhere,
+    // they are removed together with their line breaks.
+    let mut removed_lines = 0;
+
+    // The newlines of the last line break that were skipped because of pending entries,
+    // they are joined with the next line break if the line in between is removed.
+    let mut skipped_newlines = 0;
+
+    let restructure_tables = options.collapses_tables();
+    let mut collapsed = if restructure_tables && context.can_restructure() {
+        sections::collapse_tables(node, source, options, &context)
+    } else {
+        CollapsedTables::default()
+    };
+    let restructured =
+        (context.can_restructure() && (restructure_tables || options.reorder_tables)).then(|| {
+            let children = node
+                .children()
+                .iter()
+                .filter(|c| !collapsed.is_removed(&c.text_range()))
+                .collect::<Vec<_>>();
+            if options.reorder_tables {
+                sections::reorder_tables(&children, source, options)
+            } else {
+                children
+            }
+        });
+    let children: &mut dyn Iterator<Item = &Element> = match &restructured {
+        Some(children) => &mut children.iter().copied(),
+        None => &mut node.children_with_tokens(),
+    };
+
+    for c in children {
+        if context.verbatim_at(c.text_range()) {
+            // The pending lines come before the invalid part.
+            if add_entries_and_comments(
+                source,
+                &mut entry_group,
+                &mut comment_group,
+                &mut formatted,
+                options,
+                &context,
+            ) {
+                formatted += options.newline();
+            }
+            skip_newlines = 0;
 
-    for c in node.children_with_tokens() {
-        if context.error_at(c.text_range()) {
             formatted += c.text(source);
             continue;
         }
@@ -324,32 +1185,47 @@ fn format_root(node: &SyntaxNode, source: &str, options: &Options, context: &Con
         match c {
             Element::Node(node) => match node.kind() {
                 TABLE_ARRAY_HEADER | TABLE_HEADER => {
+                    if current_table.is_none() && !collapsed.root_entries.is_empty() {
+                        // The root table has no entries to follow, so the collapsed ones
+                        // are written before the first table.
+                        let entries = std::mem::take(&mut collapsed.root_entries);
+                        add_collapsed_entries(entries, &mut entry_group, source, options, &context);
+                        add_entries(source, &mut entry_group, &mut formatted, options, &context);
+                        formatted.extend(options.newlines(2));
+                        skip_newlines = 0;
+                    }
+
                     if add_entries(source, &mut entry_group, &mut formatted, options, &context) {
                         formatted += options.newline();
                         skip_newlines = 0;
                     }
 
-                    // We treat everything as indented other than table headers from now on.
-                    if options.indent_entries && context.indent_level == 0 {
-                        context.indent_level = 1;
+                    if let Some(blank_lines) = options.blank_lines_before_table {
+                        set_trailing_blank_lines(&mut formatted, blank_lines, options);
                     }
 
-                    if let Some(key) = node.first_child().map(|e| Keys::from_syntax(e, source)) {
-                        if options.indent_tables {
-                            context.indent_level = table_indent_level(
-                                &table_key_indent_history,
-                                &key,
-                                if options.indent_entries { 1 } else { 0 },
-                            );
-                        }
-                        table_key_indent_history.push((key.clone(), context.indent_level));
+                    add_new_sections(&mut new_sections, &mut formatted, source, options, &context);
+
+                    if options.separate_array_tables
+                        && node.kind() == TABLE_ARRAY_HEADER
+                        && let Some(previous) = current_table
+                        && (&formatted[formatted.trim_end_matches(['\r', '\n']).len()..])
+                            .newline_count()
+                            < 2
+                        && sections::header_key(previous, source)
+                            .starts_with(&sections::header_key(node, source))
+                    {
+                        set_trailing_blank_lines(&mut formatted, 1, options);
                     }
+                    current_table = Some(node);
 
-                    let mut header_context = context.clone();
-
-                    if options.indent_entries {
-                        header_context.indent_level = header_context.indent_level.saturating_sub(1);
-                    }
+                    let header_context = enter_table(
+                        node,
+                        source,
+                        options,
+                        &mut context,
+                        &mut table_key_indent_history,
+                    );
 
                     if add_comments(&mut comment_group, &mut formatted, &header_context, options) {
                         formatted += options.newline();
@@ -364,17 +1240,134 @@ fn format_root(node: &SyntaxNode, source: &str, options: &Options, context: &Con
                         format_table_header(node, source, options, &header_context, &mut formatted);
                     if let Some(c) = comment {
                         formatted += " ";
-                        formatted += &c;
+                        formatted += c;
+                    }
+
+                    if let Some(entries) = collapsed.entries.remove(&node.span.start) {
+                        // The table has no entries, the collapsed ones directly follow the header.
+                        formatted += options.newline();
+                        add_collapsed_entries(entries, &mut entry_group, source, options, &context);
+                        skip_newlines += 1;
                     }
                 }
                 ENTRY => {
-                    if add_comments(&mut comment_group, &mut formatted, &context, options) {
+                    let mut entry = format_entry(node, source, options, &context);
+                    let mut collapsed_entries = collapsed.entries.remove(&node.span.start);
+
+                    if options.expand_inline_tables
+                        && let Some(table) = long_inline_table(&entry, options, &context)
+                    {
+                        let mut key = String::new();
+                        if let Some(header) = current_table {
+                            table_header_key(header, source, &mut key, options, &context);
+                            key += ".";
+                        }
+                        key += &entry.key;
+
+                        // The lines of the moved comments are removed as well.
+                        skip_newlines -= comment_group.len();
+                        removed_lines += comment_group.len();
+                        if let Some(entries) = collapsed_entries.take() {
+                            // The collapsed entries take the place of the moved entry.
+                            add_collapsed_entries(
+                                entries,
+                                &mut entry_group,
+                                source,
+                                options,
+                                &context,
+                            );
+                            skip_newlines += 1;
+                        } else {
+                            removed_lines += 1;
+                        }
+                        new_sections.push(NewSection {
+                            key,
+                            content: SectionContent::InlineTable(table),
+                            comment: entry.comment,
+                            leading_comments: std::mem::take(&mut comment_group),
+                        });
+                        continue;
+                    }
+
+                    // Tables can't be moved safely if we don't understand the entire document.
+                    if context.can_restructure()
+                        && let Some((table_key, entry_key)) =
+                            split_dotted_key(&entry, source, options)
+                    {
+                        let mut key = String::new();
+                        let mut cleaned_key = Vec::new();
+                        if let Some(header) = current_table {
+                            table_header_key(header, source, &mut key, options, &context);
+                            key += ".";
+                            cleaned_key = sections::header_key(header, source);
+                        }
+                        key += &table_key
+                            .iter()
+                            .map(|part| format_ident(part, options))
+                            .collect::<Vec<_>>()
+                            .join(".");
+                        cleaned_key.extend(table_key.iter().map(|part| key_part(part)));
+
+                        entry.key = Cow::Owned(entry_key);
+                        skip_newlines -= comment_group.len();
+                        removed_lines += comment_group.len();
+                        if let Some(entries) = collapsed_entries.take() {
+                            // The collapsed entries take the place of the moved entry.
+                            add_collapsed_entries(
+                                entries,
+                                &mut entry_group,
+                                source,
+                                options,
+                                &context,
+                            );
+                            skip_newlines += 1;
+                        } else {
+                            removed_lines += 1;
+                        }
+                        entry.leading_comments.append(&mut comment_group);
+
+                        // All entries of the same table go to the same section,
+                        // the table would be defined twice otherwise.
+                        let section =
+                            new_sections.iter_mut().find_map(|section| {
+                                match &mut section.content {
+                                    SectionContent::Entries { cleaned_key: k, entries }
+                                        if *k == cleaned_key =>
+                                    {
+                                        Some(entries)
+                                    }
+                                    _ => None,
+                                }
+                            });
+                        match section {
+                            Some(entries) => entries.push(entry),
+                            None => new_sections.push(NewSection {
+                                key,
+                                content: SectionContent::Entries {
+                                    cleaned_key,
+                                    entries: vec![entry],
+                                },
+                                comment: None,
+                                leading_comments: Vec::new(),
+                            }),
+                        }
+                        continue;
+                    }
+
+                    if options.sort_entries {
+                        // The comments are moved together with the entry.
+                        entry.leading_comments.append(&mut comment_group);
+                    } else if add_comments(&mut comment_group, &mut formatted, &context, options) {
                         formatted += options.newline();
                         skip_newlines = 0;
                     }
 
-                    entry_group.push(format_entry(node, source, options, &context));
+                    entry_group.push(entry);
                     skip_newlines += 1;
+
+                    if let Some(entries) = collapsed_entries {
+                        add_collapsed_entries(entries, &mut entry_group, source, options, &context);
+                    }
                 }
                 _ => unreachable!(),
             },
@@ -382,6 +1375,12 @@ fn format_root(node: &SyntaxNode, source: &str, options: &Options, context: &Con
                 NEWLINE => {
                     let mut newline_count = token.text(source).newline_count();
 
+                    if removed_lines > 0 {
+                        newline_count =
+                            (newline_count + skipped_newlines).saturating_sub(removed_lines);
+                        removed_lines = 0;
+                    }
+
                     match dangling_newlines(token, source) {
                         Some(dnl) => {
                             dangling_newline_count += dnl;
@@ -394,19 +1393,28 @@ fn format_root(node: &SyntaxNode, source: &str, options: &Options, context: &Con
                     }
 
                     if newline_count > 1 {
-                        add_comments(&mut comment_group, &mut formatted, &context, options);
-                        add_entries(source, &mut entry_group, &mut formatted, options, &context);
+                        add_entries_and_comments(
+                            source,
+                            &mut entry_group,
+                            &mut comment_group,
+                            &mut formatted,
+                            options,
+                            &context,
+                        );
                         skip_newlines = 0;
                     }
 
+                    skipped_newlines = newline_count.min(skip_newlines);
                     formatted.extend(options.newlines(newline_count.saturating_sub(skip_newlines)));
                 }
                 COMMENT => {
-                    if add_entries(source, &mut entry_group, &mut formatted, options, &context) {
+                    if !options.sort_entries
+                        && add_entries(source, &mut entry_group, &mut formatted, options, &context)
+                    {
                         formatted += options.newline();
                         skip_newlines = 0;
                     }
-                    comment_group.push(token.text(source).to_string());
+                    comment_group.push(token.text(source));
                     skip_newlines += 1;
                 }
                 WHITESPACE => {}
@@ -415,12 +1423,378 @@ fn format_root(node: &SyntaxNode, source: &str, options: &Options, context: &Con
         }
     }
 
-    add_comments(&mut comment_group, &mut formatted, &context, options);
-    add_entries(source, &mut entry_group, &mut formatted, options, &context);
+    if !collapsed.root_entries.is_empty() {
+        // All the tables were collapsed, so the entries of the root table come last.
+        add_entries_and_comments(
+            source,
+            &mut entry_group,
+            &mut comment_group,
+            &mut formatted,
+            options,
+            &context,
+        );
+        let len = formatted.trim_end_matches(['\r', '\n']).len();
+        formatted.truncate(len);
+        if !formatted.is_empty() {
+            formatted.extend(options.newlines(2));
+        }
+
+        let entries = std::mem::take(&mut collapsed.root_entries);
+        add_collapsed_entries(entries, &mut entry_group, source, options, &context);
+    }
+
+    add_entries_and_comments(
+        source,
+        &mut entry_group,
+        &mut comment_group,
+        &mut formatted,
+        options,
+        &context,
+    );
+    if !new_sections.is_empty() {
+        // The trailing newlines are removed anyway, the sections are separated by a blank line.
+        let len = formatted.trim_end_matches(['\r', '\n']).len();
+        formatted.truncate(len);
+        add_new_sections(&mut new_sections, &mut formatted, source, options, &context);
+    }
+
+    *out = formatted;
+    !collapsed.removed.is_empty()
+}
+
+/// Add the entries of collapsed tables to the group with the keys of the tables prepended.
+fn add_collapsed_entries<'a>(
+    entries: Vec<CollapsedEntry<'a>>,
+    entry_group: &mut Vec<FormattedEntry<'a>>,
+    source: &'a str,
+    options: &Options,
+    context: &Context,
+) {
+    for entry in entries {
+        match entry {
+            CollapsedEntry::Dotted(key, node) => {
+                let mut entry = format_entry(node, source, options, context);
+                entry.key = Cow::Owned(format!("{key}.{}", entry.key));
+                entry_group.push(entry);
+            }
+            CollapsedEntry::Inline(entry) => entry_group.push(entry),
+        }
+    }
+}
+
+/// Format the entries of a table as the value of an inline table.
+fn format_entries_inline(
+    nodes: &[&SyntaxNode],
+    source: &str,
+    options: &Options,
+    context: &Context,
+) -> String {
+    let mut entries =
+        nodes.iter().map(|n| format_entry(n, source, options, context)).collect::<Vec<_>>();
+    if options.sort_entries {
+        entries.sort();
+    }
 
+    let mut formatted = String::from("{");
+    if !options.compact_inline_tables {
+        formatted += " ";
+    }
+    for (idx, entry) in entries.iter().enumerate() {
+        if idx != 0 {
+            formatted += ", ";
+        }
+        entry.write_to(&mut formatted, options);
+    }
+    if !options.compact_inline_tables {
+        formatted += " ";
+    }
+    formatted += "}";
     formatted
 }
 
+/// Replace the newlines at the end of the output with exactly `blank_lines` blank lines.
+///
+/// Nothing is changed if there is nothing but newlines in the output.
+fn set_trailing_blank_lines(formatted: &mut String, blank_lines: usize, options: &Options) {
+    let len = formatted.trim_end_matches(['\r', '\n']).len();
+    if len == 0 {
+        return;
+    }
+
+    formatted.truncate(len);
+    formatted.extend(options.newlines(blank_lines + 1));
+}
+
+/// A table section created by the formatter, see [`Options::expand_inline_tables`]
+/// and [`Options::expand_dotted_keys`].
+struct NewSection<'a> {
+    /// The full key of the new table.
+    key: String,
+    content: SectionContent<'a>,
+    /// Trailing comment of the original entry, written after the header.
+    comment: Option<&'a str>,
+    leading_comments: Vec<&'a str>,
+}
+
+enum SectionContent<'a> {
+    InlineTable(&'a SyntaxNode),
+    /// Entries with dotted keys with the table part of their keys removed.
+    Entries {
+        /// The full key of the table without quotes, `key` can be written differently.
+        cleaned_key: Vec<String>,
+        entries: Vec<FormattedEntry<'a>>,
+    },
+}
+
+/// Split the key of the entry into the key of the table it belongs to
+/// and the key within that table if it has enough parts to be expanded.
+///
+/// Returns the raw parts of the table key and the formatted key within the table.
+fn split_dotted_key<'s>(
+    entry: &FormattedEntry,
+    source: &'s str,
+    options: &Options,
+) -> Option<(Vec<&'s str>, String)> {
+    let depth = options.expand_dotted_keys?;
+
+    let key =
+        entry.syntax.children().iter().find_map(|c| c.as_node().filter(|n| n.kind() == KEY))?;
+    let mut parts = key
+        .children()
+        .iter()
+        .filter(|c| c.kind() == IDENT)
+        .map(|ident| ident.text(source))
+        .collect::<Vec<_>>();
+
+    if parts.len() < depth.max(2) {
+        return None;
+    }
+
+    let last = parts.pop()?;
+    Some((parts, format_ident(last, options).to_string()))
+}
+
+/// Return the inline table value of the entry if it doesn't fit in `column_width`.
+fn long_inline_table<'a>(
+    entry: &FormattedEntry<'a>,
+    options: &Options,
+    context: &Context,
+) -> Option<&'a SyntaxNode> {
+    let table = entry
+        .syntax
+        .children()
+        .iter()
+        .find(|c| c.kind() == VALUE)
+        .and_then(|value| value.as_node()?.first_child())
+        .and_then(|c| c.as_node())
+        .filter(|n| n.kind() == INLINE_TABLE)?;
+
+    let prefix_width = context.indent_width(options)
+        + entry.key.chars().count()
+        + if options.compact_entries { 1 } else { 3 };
+
+    exceeds_column_width(options, prefix_width, &entry.value, comment_width(entry.comment))
+        .then_some(table)
+}
+
+/// Write the inline tables collected in the current section as standard table sections.
+fn add_new_sections(
+    sections: &mut Vec<NewSection>,
+    formatted: &mut String,
+    source: &str,
+    options: &Options,
+    context: &Context,
+) {
+    let len = formatted.trim_end_matches(['\r', '\n']).len();
+    let at_start = len == 0;
+    if at_start {
+        formatted.clear();
+    }
+    if sections.is_empty() {
+        return;
+    }
+
+    // The sections are separated like tables, with the blank lines before the next
+    // header if there are more, so they look the same wherever they are written.
+    let trailing_newlines = (&formatted[len..]).newline_count();
+    let separator = trailing_newlines.max(options.blank_lines_before_table.unwrap_or(1) + 1);
+    let separator_after = trailing_newlines > 0 || at_start;
+    if separator_after && !at_start {
+        formatted.truncate(len);
+        formatted.extend(options.newlines(separator));
+    }
+
+    // Nested inline tables are written right after their parent.
+    let mut pending = sections.drain(0..).rev().collect::<Vec<_>>();
+    while let Some(section) = pending.pop() {
+        if !separator_after {
+            formatted.extend(options.newlines(separator));
+        }
+        let nested = write_new_section(section, formatted, source, options, context);
+        pending.extend(nested.into_iter().rev());
+        if separator_after {
+            formatted.extend(options.newlines(separator));
+        }
+    }
+}
+
+/// Write a single section and return the nested inline tables that are too long as well.
+fn write_new_section<'a>(
+    mut section: NewSection<'a>,
+    formatted: &mut String,
+    source: &'a str,
+    options: &Options,
+    context: &Context,
+) -> Vec<NewSection<'a>> {
+    let mut context = context.clone();
+    if options.indent_entries {
+        context.indent_level = context.indent_level.max(1);
+    }
+    let mut header_context = context.clone();
+    if options.indent_entries {
+        header_context.indent_level -= 1;
+    }
+
+    if add_comments(&mut section.leading_comments, formatted, &header_context, options) {
+        *formatted += options.newline();
+    }
+    formatted.extend(header_context.indent(options));
+    *formatted += "[";
+    *formatted += &section.key;
+    *formatted += "]";
+    if let Some(c) = section.comment {
+        *formatted += " ";
+        *formatted += c;
+    }
+
+    let table = match section.content {
+        SectionContent::InlineTable(table) => table,
+        SectionContent::Entries { mut entries, .. } => {
+            *formatted += options.newline();
+            add_entries(source, &mut entries, formatted, options, &context);
+            return Vec::new();
+        }
+    };
+
+    let mut entry_group = Vec::new();
+    let mut nested = Vec::new();
+    let mut newline_before = false;
+    for c in table.children_with_tokens() {
+        match c {
+            Element::Node(n) if n.kind() == ENTRY => {
+                let entry = format_entry(n, source, options, &context);
+                if let Some(table) = long_inline_table(&entry, options, &context) {
+                    nested.push(NewSection {
+                        key: format!("{}.{}", section.key, entry.key),
+                        content: SectionContent::InlineTable(table),
+                        comment: entry.comment,
+                        leading_comments: Vec::new(),
+                    });
+                } else {
+                    entry_group.push(entry);
+                }
+                newline_before = false;
+            }
+            Element::Token(t) if t.kind() == COMMENT => {
+                // Comments on the same line as an entry stay with the entry.
+                if !newline_before
+                    && let Some(entry) = entry_group.last_mut()
+                    && entry.comment.is_none()
+                {
+                    entry.comment = Some(t.text(source));
+                    continue;
+                }
+                *formatted += options.newline();
+                add_entries(source, &mut entry_group, formatted, options, &context);
+                if !formatted.ends_with('\n') {
+                    *formatted += options.newline();
+                }
+                formatted.extend(context.indent(options));
+                *formatted += t.text(source);
+            }
+            Element::Token(t) if t.kind() == NEWLINE => newline_before = true,
+            _ => {}
+        }
+    }
+
+    if !entry_group.is_empty() {
+        *formatted += options.newline();
+        add_entries(source, &mut entry_group, formatted, options, &context);
+    }
+
+    nested
+}
+
+fn add_comments(
+    comments: &mut Vec<&str>,
+    formatted: &mut String,
+    context: &Context,
+    options: &Options,
+) -> bool {
+    let were_comments = !comments.is_empty();
+
+    for (idx, comment) in comments.drain(0..).enumerate() {
+        if idx != 0 {
+            *formatted += options.newline();
+        }
+        formatted.extend(context.indent(options));
+        *formatted += comment;
+    }
+
+    were_comments
+}
+
+/// Flush both pending groups, the comments can only follow
+/// the entries if they are not attached to them.
+///
+/// Returns whether anything was written.
+fn add_entries_and_comments<'a>(
+    source: &'a str,
+    entry_group: &mut Vec<FormattedEntry<'a>>,
+    comment_group: &mut Vec<&str>,
+    formatted: &mut String,
+    options: &Options,
+    context: &Context,
+) -> bool {
+    let were_entries = add_entries(source, entry_group, formatted, options, context);
+    if were_entries && !comment_group.is_empty() {
+        *formatted += options.newline();
+    }
+    add_comments(comment_group, formatted, context, options) || were_entries
+}
+
+/// Set the indentation of the context to the one of the entries of the table section
+/// that starts with `header` and return the context of the header itself.
+fn enter_table(
+    header: &SyntaxNode,
+    source: &str,
+    options: &Options,
+    context: &mut Context,
+    history: &mut Vec<(Keys, usize)>,
+) -> Context {
+    // We treat everything as indented other than table headers from now on.
+    if options.indent_entries && context.indent_level == 0 {
+        context.indent_level = 1;
+    }
+
+    // The keys are only needed for the indentation of the tables.
+    if options.indent_tables
+        && let Some(key) = Keys::from_header(header, source)
+    {
+        context.indent_level =
+            table_indent_level(history, &key, if options.indent_entries { 1 } else { 0 });
+        history.push((key, context.indent_level));
+    }
+
+    let mut header_context = context.clone();
+
+    if options.indent_entries {
+        header_context.indent_level = header_context.indent_level.saturating_sub(1);
+    }
+
+    header_context
+}
+
 /// Determine the indentation level using the indentation history.
 ///
 /// The latest key that is a strict prefix is used and indented. If none is found, the default
@@ -441,116 +1815,131 @@ fn table_indent_level(
 }
 
 /// Add entries to the formatted string.
-fn add_entries(
-    source: &str,
-    entry_group: &mut Vec<FormattedEntry>,
+fn add_entries<'a>(
+    source: &'a str,
+    entry_group: &mut Vec<FormattedEntry<'a>>,
     formatted: &mut String,
     options: &Options,
     context: &Context,
 ) -> bool {
     let were_entries = !entry_group.is_empty();
 
-    if options.reorder_keys {
+    // The entries of a group belong to the same table, so the first one decides.
+    let options = match entry_group.first() {
+        Some(entry) => context.rules.options_for(entry.syntax, options),
+        None => options,
+    };
+
+    if options.reorder_keys || options.sort_entries {
         entry_group.sort();
     }
 
-    let indent_chars_count = context.indent_level * options.indent_string.chars().count();
-
     // We check for too long lines, and try to expand them if possible.
     // We don't take vertical alignment into account for simplicity.
-    if options.array_auto_expand {
-        for entry in entry_group.iter_mut() {
-            let comment_chars_count = entry
-                .comment
-                .as_ref()
-                .map(
-                    |c| c.chars().count() + 1, // account for the separator ' ' as well
-                )
-                .unwrap_or(0);
-
-            let line_count = entry.value.split('\n').count();
-
-            // check each line of the value
-            // for the first line we include the actual indent, key, and the eq parts as well
-            for (idx, line) in entry.value.split('\n').enumerate() {
-                let mut chars_count = line.chars().count();
-                if idx == 0 {
-                    chars_count += indent_chars_count;
-                    chars_count += entry.key.chars().count();
-                    chars_count += if options.compact_entries { 1 } else { 3 }; // " = "
-                }
+    for entry in entry_group.iter_mut() {
+        expand_entry(entry, source, options, context);
+    }
 
-                // Include comment in the last line.
-                if idx == line_count - 1 {
-                    chars_count += comment_chars_count;
-                }
+    // Comments break alignment, so every entry with leading comments starts a new group.
+    // The group is cleared instead of dropped to reuse its allocation.
+    let mut start = 0;
+    while start < entry_group.len() {
+        let end = entry_group[start + 1..]
+            .iter()
+            .position(|e| !e.leading_comments.is_empty())
+            .map_or(entry_group.len(), |i| start + 1 + i);
+
+        if start != 0 {
+            *formatted += options.newline();
+        }
+        if add_comments(&mut entry_group[start].leading_comments, formatted, context, options) {
+            *formatted += options.newline();
+        }
+        write_entries(&entry_group[start..end], formatted, options, context);
 
-                if chars_count > options.column_width {
-                    let mut context = context.clone();
-                    context.force_multiline = true;
+        start = end;
+    }
+    entry_group.clear();
 
-                    // too long, reformat the value of the entry
-                    entry.value.clear();
-                    let comment = format_value(
-                        entry
-                            .syntax
-                            .children()
-                            .iter()
-                            .find(|n| n.kind() == VALUE)
-                            .and_then(|e| e.as_node())
-                            .unwrap(),
-                        source,
-                        options,
-                        &context,
-                        &mut entry.value,
-                    );
+    were_entries
+}
 
-                    if let Some(c) = comment {
-                        debug_assert!(
-                            entry.comment.is_none() || entry.comment.as_ref().unwrap() == &c
-                        );
-                        entry.comment = Some(c);
-                    }
-                    break;
-                }
+/// Reformat the value of the entry on multiple lines if the entry is too long.
+fn expand_entry<'a>(
+    entry: &mut FormattedEntry<'a>,
+    source: &'a str,
+    options: &Options,
+    context: &Context,
+) {
+    let options = context.rules.options_for(entry.syntax, options);
+    let Some(value) =
+        entry.syntax.children().iter().find(|n| n.kind() == VALUE).and_then(|e| e.as_node())
+    else {
+        return;
+    };
+
+    if can_expand(value, options) {
+        // For the first line we include the actual indent, key, and the eq parts as well.
+        let prefix_width = context.indent_width(options)
+            + entry.key.chars().count()
+            + if options.compact_entries { 1 } else { 3 }; // " = "
+
+        if exceeds_column_width(options, prefix_width, &entry.value, comment_width(entry.comment)) {
+            let mut context = context.clone();
+            context.force_multiline = true;
+
+            // too long, reformat the value of the entry
+            let mut formatted_value = String::new();
+            let comment = format_value(value, source, options, &context, &mut formatted_value);
+            entry.value = Cow::Owned(formatted_value);
+
+            if let Some(c) = comment {
+                debug_assert!(entry.comment.is_none() || entry.comment == Some(c));
+                entry.comment = Some(c);
             }
         }
     }
+}
+
+/// Write a group of entries, aligning them if needed.
+fn write_entries(
+    entries: &[FormattedEntry],
+    formatted: &mut String,
+    options: &Options,
+    context: &Context,
+) {
+    // Pre-compute indent once per group instead of per entry.
+    let indent = context.indent(options).collect::<String>();
 
     // Fast path: when neither entry alignment nor comment alignment is enabled,
     // we can skip building intermediate rows and write directly.
     if !options.align_entries && !options.align_comments {
         let separator = if options.compact_entries { "=" } else { " = " };
-        // Pre-compute indent once per group instead of per entry.
-        let indent_str: String = context.indent(options).collect();
         let newline = options.newline();
-        for (i, e) in entry_group.drain(0..).enumerate() {
+        for (i, e) in entries.iter().enumerate() {
             if i != 0 {
                 *formatted += newline;
             }
-            *formatted += &indent_str;
+            *formatted += &indent;
             *formatted += &e.key;
             *formatted += separator;
             *formatted += &e.value;
-            if let Some(c) = e.comment {
+            if let Some(c) = &e.comment {
                 *formatted += " ";
-                *formatted += &c;
+                *formatted += c;
             }
         }
-        return were_entries;
+        return;
     }
 
     let mut comment_count = 0;
-    // Transform the entries into generic rows that can be aligned.
-    let rows = entry_group
-        .drain(0..)
+    // Transform the entries into generic rows that can be aligned, they borrow the entries.
+    let rows = entries
+        .iter()
         .map(|e| {
             let mut row = Vec::with_capacity(5);
 
-            row.push(context.indent(options).collect::<String>());
-            row.push(e.key);
-            row.push("=".to_string());
-            row.push(e.value);
+            row.extend([indent.as_str(), &e.key, "=", &e.value]);
             if let Some(c) = e.comment {
                 row.push(c);
                 comment_count += 1;
@@ -577,16 +1966,26 @@ fn add_entries(
         options.newline(),
         " ",
     );
-
-    were_entries
 }
 
 fn format_entry<'a>(
     node: &'a SyntaxNode,
-    source: &str,
+    source: &'a str,
     options: &Options,
     context: &Context,
 ) -> FormattedEntry<'a> {
+    let options = context.rules.options_for(node, options);
+    if let Some((key, value)) = unchanged_entry(node, source, options, context) {
+        return FormattedEntry {
+            syntax: node,
+            key: Cow::Borrowed(key),
+            cleaned_key: OnceCell::new(),
+            value: Cow::Borrowed(value),
+            comment: None,
+            leading_comments: Vec::new(),
+        };
+    }
+
     let mut key = String::new();
     let mut value = String::new();
     let mut comment = None;
@@ -610,21 +2009,73 @@ fn format_entry<'a>(
                 if let COMMENT = t.kind() {
                     // In TOML 1.1, entries can have comments - only take first
                     if comment.is_none() {
-                        comment = Some(t.text(source).into());
+                        comment = Some(t.text(source));
                     }
                 }
             }
         }
     }
 
-    FormattedEntry { syntax: node, key, cleaned_key: OnceCell::new(), value, comment }
+    FormattedEntry {
+        syntax: node,
+        key: Cow::Owned(key),
+        cleaned_key: OnceCell::new(),
+        value: Cow::Owned(value),
+        comment,
+        leading_comments: Vec::new(),
+    }
+}
+
+/// The key and the value of an entry if they are a single token each that is written
+/// as it is, which is the case for most entries, so they don't have to be copied.
+fn unchanged_entry<'a>(
+    node: &SyntaxNode,
+    source: &'a str,
+    options: &Options,
+    context: &Context,
+) -> Option<(&'a str, &'a str)> {
+    // A comment of the entry is taken from it as well.
+    if node.children().iter().any(|c| c.kind() == COMMENT) {
+        return None;
+    }
+    let mut nodes = node.children().iter().filter_map(Element::as_node);
+    let (key, value) = (nodes.next()?, nodes.next()?);
+    if key.kind() != KEY || value.kind() != VALUE || context.hooked.contains_key(&value.span.start)
+    {
+        return None;
+    }
+
+    fn single_token(node: &SyntaxNode) -> Option<&SyntaxToken> {
+        let mut tokens = node.children().iter().filter(|c| c.kind() != WHITESPACE);
+        let token = tokens.next()?.as_token()?;
+        tokens.next().is_none().then_some(token)
+    }
+    let key = single_token(key)?;
+    let value = single_token(value)?;
+    let unchanged_value = match value.kind() {
+        STRING => !options.portable_escapes,
+        STRING_LITERAL | BOOL | FLOAT => true,
+        INTEGER | INTEGER_OCT | INTEGER_BIN => {
+            matches!(options.integer_underscores, IntegerUnderscores::Preserve)
+        }
+        INTEGER_HEX => {
+            matches!(options.integer_underscores, IntegerUnderscores::Preserve)
+                && matches!(options.hex_case, HexCase::Preserve)
+        }
+        _ => false,
+    };
+    let key_text = key.text(source);
+    (key.kind() == IDENT
+        && format_ident(key_text, options).len() == key_text.len()
+        && unchanged_value)
+        .then(|| (key_text, value.text(source)))
 }
 
 fn format_key(
     node: &SyntaxNode,
     source: &str,
     formatted: &mut String,
-    _options: &Options,
+    options: &Options,
     _context: &Context,
 ) {
     // Idents and periods without whitespace
@@ -633,6 +2084,9 @@ fn format_key(
             Element::Node(_) => {}
             Element::Token(t) => match t.kind() {
                 WHITESPACE | NEWLINE => {}
+                IDENT => {
+                    *formatted += format_ident(t.text(source), options);
+                }
                 _ => {
                     *formatted += t.text(source);
                 }
@@ -641,14 +2095,31 @@ fn format_key(
     }
 }
 
+fn format_ident<'a>(ident: &'a str, options: &Options) -> &'a str {
+    if options.unquote_keys { unquote_key(ident) } else { ident }
+}
+
+/// Remove the quotes around a key if it is a valid bare key with only ASCII characters.
+fn unquote_key(key: &str) -> &str {
+    let unquoted = key
+        .strip_prefix('"')
+        .and_then(|k| k.strip_suffix('"'))
+        .or_else(|| key.strip_prefix('\'').and_then(|k| k.strip_suffix('\'')));
+
+    match unquoted {
+        Some(k) if !k.is_empty() && k.is_ascii() && k.chars().all(is_bare_key_char) => k,
+        _ => key,
+    }
+}
+
 /// Format a VALUE node by writing its value to `value` and returning its trailing comment, if any.
-fn format_value(
+fn format_value<'a>(
     node: &SyntaxNode,
-    source: &str,
+    source: &'a str,
     options: &Options,
     context: &Context,
     value: &mut String,
-) -> Option<String> {
+) -> Option<&'a str> {
     let mut comment = None;
     for c in node.children_with_tokens() {
         match c {
@@ -671,7 +2142,46 @@ fn format_value(
                 NEWLINE | WHITESPACE => {}
                 COMMENT => {
                     debug_assert!(comment.is_none());
-                    comment = Some(t.text(source).into());
+                    comment = Some(t.text(source));
+                }
+                _ if context.hooked.contains_key(&node.span.start) => {
+                    value.push_str(&context.hooked[&node.span.start]);
+                }
+                INTEGER | INTEGER_HEX | INTEGER_OCT | INTEGER_BIN => {
+                    literals::format_integer(t.kind(), t.text(source), options, value);
+                }
+                DATE_TIME_OFFSET | DATE_TIME_LOCAL | TIME => {
+                    literals::format_date_time(t.kind(), t.text(source), options, value);
+                }
+                STRING if options.portable_escapes => {
+                    write_portable_escapes(t.text(source), value);
+                }
+                MULTI_LINE_STRING | MULTI_LINE_STRING_LITERAL => {
+                    let mut string = String::new();
+                    let mut text = if t.kind() == MULTI_LINE_STRING && options.portable_escapes {
+                        write_portable_escapes(t.text(source), &mut string);
+                        &string
+                    } else {
+                        t.text(source)
+                    };
+                    let mut normalized = String::new();
+                    if t.kind() == MULTI_LINE_STRING && options.normalize_line_continuations {
+                        let indent = context.indent(options).collect::<String>();
+                        literals::format_line_continuations(
+                            text,
+                            &indent,
+                            options,
+                            &mut normalized,
+                        );
+                        text = &normalized;
+                    }
+                    // Line endings inside multi-line strings follow the output as well.
+                    for (idx, line) in text.split('\n').enumerate() {
+                        if idx > 0 {
+                            value.push_str(options.newline());
+                        }
+                        value.push_str(line.strip_suffix('\r').unwrap_or(line));
+                    }
                 }
                 _ => {
                     value.push_str(t.text(source));
@@ -683,13 +2193,13 @@ fn format_value(
     comment
 }
 
-fn format_inline_table(
+fn format_inline_table<'a>(
     node: &SyntaxNode,
-    source: &str,
+    source: &'a str,
     options: &Options,
     context: &Context,
     formatted: &mut String,
-) -> Option<String> {
+) -> Option<&'a str> {
     let mut context = context.clone();
     if context.force_multiline {
         context.force_multiline = options.inline_table_expand;
@@ -712,6 +2222,18 @@ fn format_inline_table(
         None
     };
 
+    let mut sorted_entries = options.sort_entries.then(|| {
+        let mut entries = node
+            .children()
+            .iter()
+            .filter_map(|c| c.as_node())
+            .filter(|n| n.kind() == ENTRY)
+            .map(|n| format_entry(n, source, options, context))
+            .collect::<Vec<_>>();
+        entries.sort();
+        VecDeque::from(entries)
+    });
+
     let mut node_index = 0;
     let mut last_was_comment = false;
     for c in node.children_with_tokens() {
@@ -725,7 +2247,11 @@ fn format_inline_table(
                 }
 
                 let sorted_child;
-                let entry = if options.reorder_inline_tables {
+                let entry = if let Some(entry) =
+                    sorted_entries.as_mut().and_then(|entries| entries.pop_front())
+                {
+                    entry
+                } else if options.reorder_inline_tables {
                     sorted_child = sorted_children
                         .as_mut()
                         .and_then(|children| children.pop_front())
@@ -738,7 +2264,7 @@ fn format_inline_table(
 
                 // In TOML 1.1, inline tables can have comments
                 entry.write_to(formatted, options);
-                if let Some(ref c) = entry.comment {
+                if let Some(c) = entry.comment {
                     *formatted += " ";
                     *formatted += c;
                 }
@@ -779,95 +2305,126 @@ fn format_inline_table(
 
     None
 }
+/// Whether a value can be expanded to multiple lines if it is too long.
+///
+/// Values that were written on multiple lines are only collapsed if they fit,
+/// so they can be expanded again even if `array_auto_expand` is disabled.
+fn can_expand(value: &SyntaxNode, options: &Options) -> bool {
+    options.array_auto_expand || (options.array_auto_collapse && is_array_multiline(value))
+}
+
 // Check whether the array spans multiple lines in its current form.
 fn is_array_multiline(node: &SyntaxNode) -> bool {
     node.descendants_with_tokens().any(|n| n.kind() == NEWLINE)
 }
 
+/// Whether the last value of the array is followed by a comma.
+fn has_trailing_comma(node: &SyntaxNode) -> bool {
+    node.children().iter().rev().take_while(|c| c.kind() != VALUE).any(|c| c.kind() == COMMA)
+}
+
 fn can_collapse_array(node: &SyntaxNode) -> bool {
     !node.descendants_with_tokens().any(|n| n.kind() == COMMENT)
 }
 
-fn format_array(
+fn format_array<'a>(
     node: &SyntaxNode,
-    source: &str,
+    source: &'a str,
     options: &Options,
     context: &Context,
     formatted: &mut String,
-) -> Option<String> {
-    let mut multiline = is_array_multiline(node) || context.force_multiline;
-
-    // We always try to collapse it if possible.
-    if can_collapse_array(node) && options.array_auto_collapse && !context.force_multiline {
-        multiline = false;
-    }
+) -> Option<&'a str> {
+    // The array is written after the values before it in the same buffer.
+    let start = formatted.len();
+    let multiline = match options.array_layout {
+        ArrayLayout::Preserve => is_array_multiline(node),
+        // We always try to collapse it if possible.
+        ArrayLayout::Auto
+            if can_collapse_array(node)
+                && options.array_auto_collapse
+                && !context.force_multiline =>
+        {
+            false
+        }
+        ArrayLayout::Auto => is_array_multiline(node) || context.force_multiline,
+    };
 
     // We use the same strategy as for entries, refer to [`format_root`].
     let mut skip_newlines = 0;
 
-    // Formatted value, optional trailing comment
-    // The value must not include the comma at the end.
-    let mut value_group: Vec<(String, Option<String>)> = Vec::new();
+    // The formatted values are written to a single buffer until they are added,
+    // the group holds their ranges in it and their trailing comments.
+    // The values must not include the comma at the end.
+    let mut values = String::new();
+    let mut value_group: Vec<(Range<usize>, Option<&str>)> = Vec::new();
     let mut commas_group: Vec<bool> = Vec::new();
 
-    let add_values = |value_group: &mut Vec<(String, Option<String>)>,
+    let add_values = |value_group: &mut Vec<(Range<usize>, Option<&str>)>,
                       commas_group: &mut Vec<bool>,
+                      values: &mut String,
                       formatted: &mut String,
                       context: &Context|
      -> bool {
         let were_values = !value_group.is_empty();
 
         if options.reorder_arrays {
-            value_group.sort_unstable_by(|x, y| x.0.cmp(&y.0));
+            value_group.sort_unstable_by(|x, y| values[x.0.clone()].cmp(&values[y.0.clone()]));
         }
 
-        for (has_comma, p) in commas_group.drain(0..).zip(value_group.iter_mut()) {
-            if has_comma {
-                p.0 += ","
-            };
-        }
+        let group = value_group.drain(0..).zip(commas_group.drain(0..));
 
         if !multiline {
-            for (idx, (val, comment)) in value_group.drain(0..).enumerate() {
+            for (idx, ((range, comment), has_comma)) in group.enumerate() {
                 debug_assert!(comment.is_none());
                 if idx != 0 {
                     *formatted += " "
                 }
 
-                *formatted += &val;
+                *formatted += &values[range];
+                if has_comma {
+                    *formatted += ",";
+                }
             }
 
+            values.clear();
             return were_values;
         }
 
         // Fast path: when comment alignment is disabled, skip building rows.
         if !options.align_comments {
-            let indent_str: String = context.indent(options).collect();
             let newline = options.newline();
-            for (i, (value, comment)) in value_group.drain(0..).enumerate() {
+            for (i, ((range, comment), has_comma)) in group.enumerate() {
                 if i != 0 {
                     *formatted += newline;
                 }
-                *formatted += &indent_str;
-                *formatted += &value;
+                formatted.extend(context.indent(options));
+                *formatted += &values[range];
+                if has_comma {
+                    *formatted += ",";
+                }
                 if let Some(c) = comment {
                     *formatted += " ";
-                    *formatted += &c;
+                    *formatted += c;
                 }
             }
+            values.clear();
             return were_values;
         }
 
         let mut comment_count = 0;
-        let rows = value_group
-            .drain(0..)
-            .map(|(value, comment)| {
+        let indent = context.indent(options).collect::<String>();
+        let rows = group
+            .map(|((range, comment), has_comma)| {
                 let mut row = Vec::with_capacity(5);
 
-                row.push(context.indent(options).collect::<String>());
-                row.push(value);
+                row.push(Cow::Borrowed(indent.as_str()));
+                row.push(if has_comma {
+                    Cow::Owned(format!("{},", &values[range]))
+                } else {
+                    Cow::Borrowed(&values[range])
+                });
                 if let Some(c) = comment {
-                    row.push(c);
+                    row.push(Cow::Borrowed(c));
                     comment_count += 1;
                 }
 
@@ -885,6 +2442,8 @@ fn format_array(
             " ",
         );
 
+        drop(rows);
+        values.clear();
         were_values
     };
 
@@ -895,6 +2454,9 @@ fn format_array(
 
     if multiline {
         inner_context.indent_level += 1;
+        // Every item gets its own line, so nested arrays are only
+        // expanded if they don't fit on that line, see below.
+        inner_context.force_multiline = false;
     }
 
     let mut dangling_newline_count = 0;
@@ -908,14 +2470,34 @@ fn format_array(
                         *formatted += options.newline();
                     }
 
-                    let mut val_string = String::new();
-                    let comment = format_value(n, source, options, &inner_context, &mut val_string);
-
-                    let has_comma =
-                        node_index < node_count - 1 || (multiline && options.array_trailing_comma);
+                    let has_comma = node_index < node_count - 1
+                        || if options.whitespace_only {
+                            has_trailing_comma(node)
+                        } else {
+                            multiline && options.array_trailing_comma
+                        };
+
+                    let start = values.len();
+                    let mut comment = format_value(n, source, options, &inner_context, &mut values);
+
+                    if multiline
+                        && can_expand(n, options)
+                        && exceeds_column_width(
+                            options,
+                            inner_context.indent_width(options),
+                            &values[start..],
+                            usize::from(has_comma) + comment_width(comment),
+                        )
+                    {
+                        let mut context = inner_context.clone();
+                        context.force_multiline = true;
+
+                        values.truncate(start);
+                        comment = format_value(n, source, options, &context, &mut values);
+                    }
                     commas_group.push(has_comma);
 
-                    value_group.push((val_string, comment));
+                    value_group.push((start..values.len(), comment));
                     skip_newlines += 1;
 
                     node_index += 1;
@@ -934,7 +2516,13 @@ fn format_array(
                     }
                 }
                 BRACKET_END => {
-                    add_values(&mut value_group, &mut commas_group, formatted, &inner_context);
+                    add_values(
+                        &mut value_group,
+                        &mut commas_group,
+                        &mut values,
+                        formatted,
+                        &inner_context,
+                    );
 
                     if multiline {
                         if !formatted.ends_with('\n') {
@@ -966,7 +2554,13 @@ fn format_array(
                     }
 
                     if newline_count > 1 {
-                        add_values(&mut value_group, &mut commas_group, formatted, &inner_context);
+                        add_values(
+                            &mut value_group,
+                            &mut commas_group,
+                            &mut values,
+                            formatted,
+                            &inner_context,
+                        );
                         skip_newlines = 0;
                     }
 
@@ -988,11 +2582,17 @@ fn format_array(
 
                     if !newline_before && !value_group.is_empty() {
                         // It's actually trailing comment, so we add it to the last value.
-                        value_group.last_mut().unwrap().1 = Some(t.text(source).to_string());
+                        value_group.last_mut().unwrap().1 = Some(t.text(source));
                         continue;
                     }
 
-                    if add_values(&mut value_group, &mut commas_group, formatted, &inner_context) {
+                    if add_values(
+                        &mut value_group,
+                        &mut commas_group,
+                        &mut values,
+                        formatted,
+                        &inner_context,
+                    ) {
                         *formatted += options.newline();
                         skip_newlines = 0;
                     }
@@ -1010,20 +2610,37 @@ fn format_array(
         }
     }
 
-    if formatted.is_empty() {
+    if formatted.len() == start {
         *formatted += "[]";
     }
 
     None
 }
 
-fn format_table_header(
-    node: &SyntaxNode,
+/// Write the key of a table header.
+fn table_header_key(
+    header: &SyntaxNode,
     source: &str,
+    formatted: &mut String,
+    options: &Options,
+    context: &Context,
+) {
+    for c in header.children() {
+        if let Element::Node(key) = c
+            && key.kind() == KEY
+        {
+            format_key(key, source, formatted, options, context);
+        }
+    }
+}
+
+fn format_table_header<'a>(
+    node: &SyntaxNode,
+    source: &'a str,
     options: &Options,
     context: &Context,
     formatted: &mut String,
-) -> Option<String> {
+) -> Option<&'a str> {
     let mut comment = None;
 
     for c in node.children_with_tokens() {
@@ -1036,7 +2653,7 @@ fn format_table_header(
                 WHITESPACE | NEWLINE => {}
                 COMMENT => {
                     debug_assert!(comment.is_none());
-                    comment = Some(t.text(source).to_string());
+                    comment = Some(t.text(source));
                 }
                 _ => *formatted += t.text(source),
             },
@@ -1046,6 +2663,11 @@ fn format_table_header(
     comment
 }
 
+/// Width of a trailing comment including the separating space.
+fn comment_width(comment: Option<&str>) -> usize {
+    comment.map(|c| c.chars().count() + 1).unwrap_or(0)
+}
+
 trait NewlineCount {
     fn newline_count(&self) -> usize;
 }
@@ -1068,13 +2690,22 @@ fn format_rows<R, S>(
     R: AsRef<[S]>,
     S: AsRef<str>,
 {
-    // We currently don't support vertical alignment of complex data.
-    let can_align = rows.iter().flat_map(|r| r.as_ref().iter()).all(|s| !s.as_ref().contains('\n'));
+    // We currently don't support vertical alignment of complex data,
+    // only the items before the first multi-line item of a row are aligned.
+    let align_limits = rows
+        .iter()
+        .map(|r| r.as_ref().iter().position(|s| s.as_ref().contains('\n')).unwrap_or(usize::MAX))
+        .collect::<Vec<_>>();
 
     let diff_widths = |range: Range<usize>, row: &R| -> usize {
         let mut max_width = 0_usize;
 
-        for row in rows {
+        for (row, &align_limit) in rows.iter().zip(&align_limits) {
+            // Rows that can't be aligned don't take up space either.
+            if align_limit < range.end {
+                continue;
+            }
+
             let row_len = row.as_ref().len();
 
             let range =
@@ -1109,7 +2740,7 @@ fn format_rows<R, S>(
 
             *out += item.as_ref();
 
-            if can_align
+            if item_idx < align_limits[row_idx]
                 && align_range.start <= item_idx
                 && align_range.end > item_idx
                 && item_idx < row.as_ref().len() - 1
@@ -1134,10 +2765,13 @@ fn format_rows<R, S>(
 /// So we check if the newlines are followed by whitespace,
 /// then newlines again, and return the count here,
 /// and we can add these values up.
-const fn dangling_newlines(t: &SyntaxToken, source: &str) -> Option<usize> {
-    // TODO: This function needs sibling traversal which we don't have yet
-    // For now, just return None to disable this optimization
-    // The formatter will still work correctly, just potentially with extra newlines
-    let _ = (t, source);
-    None
+fn dangling_newlines(t: &SyntaxToken, source: &str) -> Option<usize> {
+    let rest = &source[t.span.end as usize..];
+    let line = rest.trim_start_matches([' ', '\t']);
+
+    if line.len() != rest.len() && (line.starts_with('\n') || line.starts_with("\r\n")) {
+        Some(t.text(source).newline_count())
+    } else {
+        None
+    }
 }
@@ -0,0 +1,44 @@
+//! Formatting many documents without allocating new buffers for each,
+//! see [`format_into`] and [`Formatter`].
+
+use super::{Options, ValueHooks, format_full_into};
+
+/// Like [`format`](super::format), but the document is written to `out` instead of
+/// a new string. `out` is cleared first, so the same string can be passed for every
+/// document to reuse its allocation.
+pub fn format_into(src: &str, options: Options, out: &mut String) {
+    format_full_into(src, options, &[], &ValueHooks::default(), out, &mut String::new());
+}
+
+/// A formatter that keeps its buffers between documents, for formatting many of them
+/// with the same options.
+#[derive(Debug, Clone, Default)]
+pub struct Formatter {
+    options: Options,
+    output: String,
+    scratch: String,
+}
+
+impl Formatter {
+    pub fn new(options: Options) -> Self {
+        Self { options, output: String::new(), scratch: String::new() }
+    }
+
+    pub const fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Format a document like [`format`](super::format), the result is valid
+    /// until the next document is formatted.
+    pub fn format(&mut self, src: &str) -> &str {
+        let Self { options, output, scratch } = self;
+        format_full_into(src, options.clone(), &[], &ValueHooks::default(), output, scratch);
+        output
+    }
+
+    /// Format a document like [`format_into`] with the buffers of the formatter.
+    pub fn format_into(&mut self, src: &str, out: &mut String) {
+        let hooks = ValueHooks::default();
+        format_full_into(src, self.options.clone(), &[], &hooks, out, &mut self.scratch);
+    }
+}
@@ -0,0 +1,196 @@
+//! Formatting options scoped to keys with glob patterns.
+
+use std::collections::HashMap;
+
+use super::{Keys, Options, sections};
+use crate::{
+    ast::key_part,
+    parser::Parser,
+    syntax::{SyntaxKind::*, SyntaxNode},
+    tree::Element,
+};
+
+/// Formatting options for the entries whose keys match one of the patterns.
+///
+/// A pattern is a dotted key where bare parts can contain the `*` and `?` wildcards,
+/// e.g. `dependencies.*`. It matches the full key of an entry including its table
+/// and every entry nested below it. If several rules match, the last one is used.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormatRule {
+    /// The key patterns.
+    pub keys: Vec<String>,
+
+    /// The options that are used for the matching entries.
+    pub options: Options,
+}
+
+/// A part of a key pattern, quoted parts are matched literally.
+#[derive(Debug)]
+struct PatternPart {
+    text: String,
+    glob: bool,
+}
+
+/// Whether the pattern is a valid key pattern of a [`FormatRule`].
+pub(crate) fn is_valid_pattern(pattern: &str) -> bool {
+    parse_pattern(pattern).is_some()
+}
+
+/// Whether the key pattern of a [`FormatRule`] matches all the parts of the key,
+/// `false` if it is not a valid pattern.
+pub(crate) fn matches_pattern(pattern: &str, key: &[String]) -> bool {
+    parse_pattern(pattern)
+        .is_some_and(|pattern| pattern.len() == key.len() && matches_key(&pattern, key))
+}
+
+/// Parse a key pattern into its parts, `None` if it is not a valid key.
+fn parse_pattern(pattern: &str) -> Option<Vec<PatternPart>> {
+    let parse = Parser::new(pattern).parse_key_only();
+    if !parse.errors.is_empty() {
+        return None;
+    }
+
+    let parts = parse
+        .tree
+        .root
+        .children()
+        .iter()
+        .filter(|c| c.kind() == IDENT)
+        .map(|ident| {
+            let text = ident.text(pattern);
+            PatternPart { text: key_part(text), glob: !text.starts_with(['"', '\'']) }
+        })
+        .collect();
+    Some(parts)
+}
+
+/// Match a part of a key with `*` matching any characters and `?` a single one.
+fn matches_part(pattern: &PatternPart, part: &str) -> bool {
+    if !pattern.glob {
+        return pattern.text == part;
+    }
+
+    let pattern = pattern.text.chars().collect::<Vec<_>>();
+    let part = part.chars().collect::<Vec<_>>();
+    let (mut p, mut k) = (0, 0);
+    // The position after the last `*` and the part position it was tried at.
+    let mut backtrack = None;
+    while k < part.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, k));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == part[k] => {
+                p += 1;
+                k += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_k)) => {
+                    p = star_p;
+                    k = star_k + 1;
+                    backtrack = Some((star_p, star_k + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether the pattern matches the key or one of its prefixes.
+fn matches_key(pattern: &[PatternPart], key: &[String]) -> bool {
+    !pattern.is_empty()
+        && pattern.len() <= key.len()
+        && pattern.iter().zip(key).all(|(pattern, part)| matches_part(pattern, part))
+}
+
+/// The options of the entries that a rule applies to.
+#[derive(Debug, Default)]
+pub(super) struct RuleScopes {
+    options: Vec<Options>,
+    /// The index of the options by the start of the entries.
+    entries: HashMap<u32, usize>,
+}
+
+impl RuleScopes {
+    pub(super) fn new(root: &SyntaxNode, source: &str, rules: &[FormatRule]) -> Self {
+        let mut scopes = Self::default();
+        if rules.is_empty() {
+            return scopes;
+        }
+
+        let patterns = rules
+            .iter()
+            .map(|rule| rule.keys.iter().filter_map(|key| parse_pattern(key)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        scopes.options = rules.iter().map(|rule| rule.options.clone()).collect();
+
+        let mut table = Vec::new();
+        for node in root.children().iter().filter_map(Element::as_node) {
+            match node.kind() {
+                TABLE_HEADER | TABLE_ARRAY_HEADER => table = sections::header_key(node, source),
+                ENTRY => scopes.add_entry(node, &table, source, &patterns),
+                _ => {}
+            }
+        }
+
+        scopes
+    }
+
+    /// Find the rule of the entry and the entries nested in its value.
+    fn add_entry(
+        &mut self,
+        entry: &SyntaxNode,
+        parent: &[String],
+        source: &str,
+        patterns: &[Vec<Vec<PatternPart>>],
+    ) {
+        let mut key = parent.to_vec();
+        key.extend(Keys::from_header(entry, source).map(|keys| keys.keys).unwrap_or_default());
+
+        let rule = patterns
+            .iter()
+            .rposition(|patterns| patterns.iter().any(|pattern| matches_key(pattern, &key)));
+        if let Some(rule) = rule {
+            self.entries.insert(entry.span.start, rule);
+        }
+
+        let values = entry.children().iter().filter_map(Element::as_node);
+        self.add_values(values, &key, source, patterns);
+    }
+
+    fn add_values<'a>(
+        &mut self,
+        values: impl Iterator<Item = &'a SyntaxNode>,
+        key: &[String],
+        source: &str,
+        patterns: &[Vec<Vec<PatternPart>>],
+    ) {
+        for value in values.filter(|n| n.kind() == VALUE) {
+            for node in value.children().iter().filter_map(Element::as_node) {
+                let children = node.children().iter().filter_map(Element::as_node);
+                match node.kind() {
+                    INLINE_TABLE => {
+                        for entry in children.filter(|n| n.kind() == ENTRY) {
+                            self.add_entry(entry, key, source, patterns);
+                        }
+                    }
+                    // The elements of arrays have the key of the array.
+                    ARRAY => self.add_values(children, key, source, patterns),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// The options of the entry, `options` if no rule applies to it.
+    pub(super) fn options_for<'o>(
+        &'o self,
+        entry: &SyntaxNode,
+        options: &'o Options,
+    ) -> &'o Options {
+        self.entries.get(&entry.span.start).map_or(options, |&rule| &self.options[rule])
+    }
+}
@@ -0,0 +1,407 @@
+//! Restructuring of whole table sections.
+
+use super::{
+    Context, FormattedEntry, NewlineCount, Options, exceeds_column_width, format_entries_inline,
+    format_ident,
+};
+use crate::{
+    ast::key_part,
+    syntax::{SyntaxKind::*, SyntaxNode},
+    tree::{Element, TextRange},
+};
+use std::{cell::OnceCell, collections::HashMap, ops::Range};
+
+/// A table header and everything that belongs to it until the next section.
+struct Section<'a> {
+    header: &'a SyntaxNode,
+    /// Unescaped key of the header, e.g. `["a", "b"]` for `[a."\u0062"]`.
+    key: Vec<String>,
+    is_array: bool,
+    range: Range<usize>,
+    /// The newlines between this and the next section.
+    separator: Option<Range<usize>>,
+}
+
+/// Split the children of the root into table sections.
+///
+/// Returns the sections and the start of the blank lines at the end of the document.
+fn split_sections<'a>(children: &[&'a Element], source: &str) -> (Vec<Section<'a>>, usize) {
+    let mut starts = Vec::new();
+    let mut headers = Vec::new();
+    for (idx, c) in children.iter().enumerate() {
+        if let Element::Node(header) = *c
+            && matches!(header.kind(), TABLE_HEADER | TABLE_ARRAY_HEADER)
+        {
+            starts.push(section_start(children, idx, source));
+            headers.push(header);
+        }
+    }
+
+    if starts.is_empty() {
+        return (Vec::new(), children.len());
+    }
+
+    // Every section but the first one is preceded by a separator newline.
+    let mut separators = Vec::with_capacity(starts.len());
+    for &start in &starts[1..] {
+        let newline = children[..start].iter().rposition(|c| c.kind() == NEWLINE).unwrap_or(0);
+        separators.push(newline..start);
+    }
+
+    // The blank lines at the end of the document stay at the end.
+    let tail_start = children
+        .iter()
+        .rposition(|c| !matches!(c.kind(), NEWLINE | WHITESPACE))
+        .map_or(children.len(), |idx| idx + 1)
+        .max(starts[starts.len() - 1]);
+
+    let sections = headers
+        .into_iter()
+        .enumerate()
+        .map(|(idx, header)| {
+            let end = separators.get(idx).map_or(tail_start, |separator| separator.start);
+            Section {
+                header,
+                key: header_key(header, source),
+                is_array: header.kind() == TABLE_ARRAY_HEADER,
+                range: starts[idx]..end,
+                separator: separators.get(idx).cloned(),
+            }
+        })
+        .collect();
+
+    (sections, tail_start)
+}
+
+/// Return the children of the root with the table sections reordered
+/// according to [`Options::reorder_tables`].
+///
+/// The preamble before the first table and the newlines separating the sections
+/// are kept in place, only the sections themselves are moved.
+pub(super) fn reorder_tables<'a>(
+    children: &[&'a Element],
+    source: &str,
+    options: &Options,
+) -> Vec<&'a Element> {
+    let (sections, tail_start) = split_sections(children, source);
+    let Some(first) = sections.first() else {
+        return children.to_vec();
+    };
+    let preamble_end = first.range.start;
+
+    // Subtables of an array of tables belong to its last element,
+    // so they must be kept together.
+    let mut blocks: Vec<Vec<Section>> = Vec::new();
+    for section in sections {
+        if let Some(block) = blocks.last_mut()
+            && block[0].is_array
+            && section.key.len() > block[0].key.len()
+            && section.key.starts_with(&block[0].key)
+        {
+            block.push(section);
+        } else {
+            blocks.push(vec![section]);
+        }
+    }
+
+    // Separators between the blocks stay in place, the ones within a block are moved with it.
+    let block_separators =
+        blocks.iter().map(|block| block[block.len() - 1].separator.clone()).collect::<Vec<_>>();
+
+    // The sort is stable so the order of array of tables elements is preserved.
+    blocks
+        .sort_by_cached_key(|block| (table_priority(&block[0].key, options), block[0].key.clone()));
+
+    let mut reordered = Vec::with_capacity(children.len());
+    reordered.extend(&children[..preamble_end]);
+    for (block, block_separator) in blocks.iter().zip(block_separators) {
+        let (last, sections) = block.split_last().unwrap();
+        for section in sections {
+            reordered.extend(&children[section.range.clone()]);
+            reordered.extend(&children[section.separator.clone().unwrap()]);
+        }
+        reordered.extend(&children[last.range.clone()]);
+        if let Some(separator) = block_separator {
+            reordered.extend(&children[separator]);
+        }
+    }
+    reordered.extend(&children[tail_start..]);
+
+    reordered
+}
+
+/// A collapsed table written as an entry of its parent table.
+pub(super) enum CollapsedEntry<'a> {
+    /// The single entry of the table with the key of the table relative to its parent table.
+    Dotted(String, &'a SyntaxNode),
+    /// The whole table as an inline table.
+    Inline(FormattedEntry<'a>),
+}
+
+/// Tables that are collapsed into entries of their parent table,
+/// see [`Options::collapse_tables`] and [`Options::inline_small_tables`].
+#[derive(Default)]
+pub(super) struct CollapsedTables<'a> {
+    /// The text of the removed sections including one of their separators.
+    pub(super) removed: Vec<TextRange>,
+    /// The collapsed entries by the start of the last entry or the header of their parent table.
+    pub(super) entries: HashMap<u32, Vec<CollapsedEntry<'a>>>,
+    /// Collapsed entries of the root table, which has no entries to follow.
+    pub(super) root_entries: Vec<CollapsedEntry<'a>>,
+}
+
+impl CollapsedTables<'_> {
+    pub(super) fn is_removed(&self, range: &TextRange) -> bool {
+        self.removed.iter().any(|removed| removed.start <= range.start && range.end <= removed.end)
+    }
+}
+
+/// Find the small tables without comments and the tables they are collapsed into.
+pub(super) fn collapse_tables<'a>(
+    root: &'a SyntaxNode,
+    source: &str,
+    options: &Options,
+    context: &Context,
+) -> CollapsedTables<'a> {
+    let children = root.children().iter().collect::<Vec<_>>();
+    let (sections, _) = split_sections(&children, source);
+    let mut collapsed = CollapsedTables::default();
+    let mut removed_sections = Vec::new();
+
+    for (idx, section) in sections.iter().enumerate() {
+        // Tables with subtables are kept, so the parent of a collapsed table is never collapsed.
+        if sections
+            .iter()
+            .any(|s| s.key.len() > section.key.len() && s.key.starts_with(&section.key))
+        {
+            continue;
+        }
+
+        let parent = parent_section(&sections, idx);
+        let key = header_idents(section.header)
+            .skip(parent.map_or(0, |parent| sections[parent].key.len()))
+            .map(|ident| format_ident(ident.text(source), options))
+            .collect::<Vec<_>>()
+            .join(".");
+
+        let entry = if section.is_array {
+            // Arrays of tables are converted as a whole at their first element.
+            if !options.inline_array_tables
+                || sections[..idx].iter().any(|s| s.is_array && s.key == section.key)
+            {
+                continue;
+            }
+
+            let elements = (idx..sections.len())
+                .filter(|&element| {
+                    sections[element].is_array && sections[element].key == section.key
+                })
+                .collect::<Vec<_>>();
+            let mut values = Vec::with_capacity(elements.len());
+            for &element in &elements {
+                match section_entries(&children, &sections[element])[..] {
+                    [entry] if parent_section(&sections, element) == parent => {
+                        values.push(format_entries_inline(&[entry], source, options, context));
+                    }
+                    _ => break,
+                }
+            }
+            if values.len() != elements.len() {
+                continue;
+            }
+
+            let padding = if options.compact_arrays { "" } else { " " };
+            let value = format!("[{padding}{}{padding}]", values.join(", "));
+            if !fits_inline(&key, parent.is_some(), &value, options) {
+                continue;
+            }
+
+            removed_sections.extend(elements);
+            inline_entry(key, value, section_entries(&children, section)[0])
+        } else {
+            let entries = section_entries(&children, section);
+            if entries.is_empty() {
+                continue;
+            }
+
+            if options.collapse_tables && entries.len() == 1 {
+                removed_sections.push(idx);
+                CollapsedEntry::Dotted(key, entries[0])
+            } else if options.inline_small_tables.is_some_and(|max| entries.len() <= max) {
+                let value = format_entries_inline(&entries, source, options, context);
+                if !fits_inline(&key, parent.is_some(), &value, options) {
+                    continue;
+                }
+
+                removed_sections.push(idx);
+                inline_entry(key, value, entries[0])
+            } else {
+                continue;
+            }
+        };
+
+        let parent_range =
+            parent.map_or(0..sections[0].range.start, |parent| sections[parent].range.clone());
+        let anchor = children[parent_range]
+            .iter()
+            .rev()
+            .filter_map(|c| c.as_node())
+            .find(|n| n.kind() == ENTRY)
+            .or(parent.map(|parent| sections[parent].header));
+        match anchor {
+            Some(anchor) => {
+                collapsed.entries.entry(anchor.span.start).or_default().push(entry);
+            }
+            None => collapsed.root_entries.push(entry),
+        }
+    }
+
+    // The newline at the end of the preamble acts as the separator before the first section.
+    let preamble_separator = sections.first().and_then(|first| {
+        let newline = children[..first.range.start].iter().rposition(|c| c.kind() == NEWLINE)?;
+        Some(newline..first.range.start)
+    });
+    let mut claimed_separators = Vec::new();
+
+    removed_sections.sort_unstable();
+    for idx in removed_sections {
+        // One of the separators around the section is removed with it,
+        // the one with fewer newlines so that blank lines are kept.
+        let before =
+            if idx == 0 { preamble_separator.clone() } else { sections[idx - 1].separator.clone() };
+        let separator = [sections[idx].separator.clone(), before]
+            .into_iter()
+            .flatten()
+            .filter(|separator| !claimed_separators.contains(&separator.start))
+            .min_by_key(|separator| {
+                children[separator.clone()]
+                    .iter()
+                    .map(|c| c.text(source).newline_count())
+                    .sum::<usize>()
+            });
+        if let Some(separator) = separator {
+            claimed_separators.push(separator.start);
+            collapsed.removed.push(elements_range(&children[separator]));
+        }
+        collapsed.removed.push(elements_range(&children[sections[idx].range.clone()]));
+    }
+
+    collapsed
+}
+
+/// The index of the closest table that contains the one at `idx`,
+/// for arrays of tables it's the last element before it.
+fn parent_section(sections: &[Section], idx: usize) -> Option<usize> {
+    let section = &sections[idx];
+    sections
+        .iter()
+        .enumerate()
+        .filter(|(parent_idx, parent)| {
+            parent.key.len() < section.key.len()
+                && section.key.starts_with(&parent.key)
+                && (!parent.is_array || *parent_idx < idx)
+        })
+        .max_by_key(|(parent_idx, parent)| (parent.key.len(), *parent_idx))
+        .map(|(parent_idx, _)| parent_idx)
+}
+
+/// The entries of a section, or nothing if there are comments that would be lost.
+fn section_entries<'a>(children: &[&'a Element], section: &Section) -> Vec<&'a SyntaxNode> {
+    let elements = &children[section.range.clone()];
+    if elements.iter().any(|c| has_comments(c)) {
+        return Vec::new();
+    }
+    elements.iter().filter_map(|c| c.as_node()).filter(|n| n.kind() == ENTRY).collect()
+}
+
+/// Whether an entry with an inline value fits in `column_width` in the parent table.
+fn fits_inline(key: &str, has_parent: bool, value: &str, options: &Options) -> bool {
+    // The entries of tables are only indented if they have a parent table.
+    let indent = if has_parent && options.indent_entries {
+        options.indent_string.chars().count()
+    } else {
+        0
+    };
+    let prefix_width = indent + key.chars().count() + if options.compact_entries { 1 } else { 3 };
+    !value.contains('\n') && !exceeds_column_width(options, prefix_width, value, 0)
+}
+
+fn inline_entry<'a>(key: String, value: String, syntax: &'a SyntaxNode) -> CollapsedEntry<'a> {
+    CollapsedEntry::Inline(FormattedEntry {
+        syntax,
+        key: key.into(),
+        cleaned_key: OnceCell::new(),
+        value: value.into(),
+        comment: None,
+        leading_comments: Vec::new(),
+    })
+}
+
+fn elements_range(elements: &[&Element]) -> TextRange {
+    elements[0].text_range().start..elements[elements.len() - 1].text_range().end
+}
+
+fn has_comments(element: &Element) -> bool {
+    match element {
+        Element::Node(node) => node.children().iter().any(has_comments),
+        Element::Token(token) => token.kind() == COMMENT,
+    }
+}
+
+/// Find the start of the section with the header at `header_idx`,
+/// comments directly above the header are part of the section.
+fn section_start(children: &[&Element], header_idx: usize, source: &str) -> usize {
+    let mut start = header_idx;
+
+    let mut idx = header_idx;
+    while idx > 0 {
+        match children[idx - 1].kind() {
+            WHITESPACE => idx -= 1,
+            NEWLINE if children[idx - 1].text(source).newline_count() == 1 => {
+                let comment = children[..idx - 1].iter().rposition(|c| c.kind() != WHITESPACE);
+                match comment {
+                    Some(comment) if children[comment].kind() == COMMENT => {
+                        idx = comment;
+                        start = comment;
+                    }
+                    _ => break,
+                }
+            }
+            _ => break,
+        }
+    }
+
+    // Keep the indentation of the first line.
+    while start > 0 && children[start - 1].kind() == WHITESPACE {
+        start -= 1;
+    }
+
+    start
+}
+
+fn header_idents(header: &SyntaxNode) -> impl Iterator<Item = &Element> {
+    header
+        .children()
+        .iter()
+        .filter_map(|c| c.as_node())
+        .filter(|n| n.kind() == KEY)
+        .flat_map(|key| key.children())
+        .filter(|c| c.kind() == IDENT)
+}
+
+/// The unescaped parts of the key of the header, see [`key_part`].
+pub(super) fn header_key(header: &SyntaxNode, source: &str) -> Vec<String> {
+    header_idents(header).map(|ident| key_part(ident.text(source))).collect()
+}
+
+/// Position of the key in [`Options::table_priority`], keys that are not listed come last.
+fn table_priority(key: &[String], options: &Options) -> usize {
+    options
+        .table_priority
+        .iter()
+        .position(|priority| {
+            let priority = priority.split('.').collect::<Vec<_>>();
+            key.len() >= priority.len() && key.iter().zip(&priority).all(|(k, p)| k == p)
+        })
+        .unwrap_or(usize::MAX)
+}
@@ -0,0 +1,275 @@
+//! Formatting documents that are too large to keep in memory, see [`format_stream`].
+
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    parser::{ParseOptions, parse_root},
+    syntax::SyntaxKind::*,
+};
+
+use super::{Formatter, LineEnding, Options, directives::directive};
+
+/// The comment that is written before a section that starts where formatting is disabled,
+/// so that it stays disabled.
+const FMT_OFF: &str = "# oxc-toml: fmt off\n";
+
+/// Format a document that is read from `reader` like [`format`](super::format), but one table
+/// section at a time and write each to `writer` once it is formatted, so that neither the whole
+/// document, its syntax tree nor the output are kept in memory.
+///
+/// A section is formatted with the header of the section before it and the header line
+/// after it, which decide the blank lines between them. The options that move tables or
+/// entries between sections and `indent_tables` need the whole document, so they are ignored.
+/// With `whitespace_only`, a section is kept as it is if formatting would change more than
+/// whitespace in it. A header line inside a multi-line string or an array doesn't start a
+/// section, so a string that is never closed is kept in memory until the end of the document.
+///
+/// # Errors
+///
+/// If reading or writing fails, or the document is not valid UTF-8.
+pub fn format_stream(
+    mut reader: impl BufRead,
+    mut writer: impl Write,
+    mut options: Options,
+) -> io::Result<()> {
+    options.reorder_tables = false;
+    options.collapse_tables = false;
+    options.inline_small_tables = None;
+    options.inline_array_tables = false;
+    options.expand_inline_tables = false;
+    options.expand_dotted_keys = None;
+    options.indent_tables = false;
+    let parse_options = ParseOptions { toml_version: options.toml_version, ..Default::default() };
+
+    // Comments are removed before the directives are read.
+    let mut state = LineState { directives: !options.strip_comments, ..LineState::default() };
+    let mut section = String::new();
+    reader.read_line(&mut section)?;
+    state.scan(&section);
+    if options.line_ending == LineEnding::Preserve {
+        options.line_ending = LineEnding::detect(&section);
+    }
+    let mut formatter = Formatter::new(options);
+
+    let mut previous_header = String::new();
+    let mut section_header = String::new();
+    // Whether formatting is disabled at the start of the section.
+    let mut disabled = false;
+    let mut line = String::new();
+    let mut text = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+
+        // An `ignore` comment before the header applies to the section after it as well.
+        let header = (state.at_root() && !state.ignore)
+            .then(|| header_of_line(&line, parse_options))
+            .flatten();
+        state.scan(&line);
+        let Some(header) = header else {
+            section += &line;
+            continue;
+        };
+
+        write_section(
+            &mut formatter,
+            &previous_header,
+            disabled,
+            &section,
+            Some(&line),
+            &mut text,
+            &mut writer,
+        )?;
+
+        // The headers in disabled regions are not formatted, so they don't decide
+        // the indentation and the blank lines of the sections after them.
+        if disabled {
+            section_header = header;
+        } else {
+            previous_header = std::mem::replace(&mut section_header, header);
+        }
+        disabled = state.fmt_off_before;
+        section.clear();
+        section += &line;
+    }
+
+    write_section(
+        &mut formatter,
+        &previous_header,
+        disabled,
+        &section,
+        None,
+        &mut text,
+        &mut writer,
+    )?;
+    writer.flush()
+}
+
+/// Format a section with the header before it and the header line after it,
+/// and write it without them.
+fn write_section(
+    formatter: &mut Formatter,
+    previous_header: &str,
+    disabled: bool,
+    section: &str,
+    next_header: Option<&str>,
+    text: &mut String,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    text.clear();
+    *text += previous_header;
+    if disabled {
+        *text += FMT_OFF;
+    }
+    *text += section;
+    *text += next_header.unwrap_or_default();
+
+    let mut formatted = formatter.format(text);
+    if !previous_header.is_empty() {
+        // The blank lines before the section are written with the section before it.
+        let first_line_end = formatted.find('\n').map_or(formatted.len(), |idx| idx + 1);
+        formatted = formatted[first_line_end..].trim_start_matches(['\r', '\n']);
+    }
+    if disabled {
+        // The comment is copied as it is, like the rest of the disabled region.
+        formatted = &formatted[FMT_OFF.len()..];
+    }
+    if next_header.is_some() {
+        let end = formatted.trim_end_matches(['\r', '\n']).rfind('\n').map_or(0, |idx| idx + 1);
+        formatted = &formatted[..end];
+    }
+    writer.write_all(formatted.as_bytes())
+}
+
+/// The header of a line that is a table header on its own, without the comment after it,
+/// which may be a directive for the entries of the section.
+fn header_of_line(line: &str, options: ParseOptions) -> Option<String> {
+    if !line.trim_start().starts_with('[') {
+        return None;
+    }
+    let (root, errors) = parse_root(line, options);
+    if !errors.is_empty() {
+        return None;
+    }
+    let header = root
+        .children
+        .iter()
+        .find(|child| matches!(child.kind(), TABLE_HEADER | TABLE_ARRAY_HEADER))?;
+    let mut header = header.text(line).to_string();
+    header += "\n";
+    Some(header)
+}
+
+/// What is left open at the end of the lines that were read so far, which decides
+/// whether the next line can start a section. Only the new lines are scanned.
+#[derive(Debug, Default)]
+struct LineState {
+    /// Whether the formatter directives are followed.
+    directives: bool,
+    /// The delimiter of a multi-line string that is not closed yet.
+    string: Option<&'static str>,
+    /// The number of arrays and inline tables that are not closed yet.
+    depth: usize,
+    /// Whether formatting is disabled by a `fmt off` comment.
+    fmt_off: bool,
+    /// Whether formatting was disabled before the last line.
+    fmt_off_before: bool,
+    /// Whether the last item is an `ignore` comment, which applies to the item after it.
+    ignore: bool,
+}
+
+impl LineState {
+    /// Whether the next line is at the root of the document, i.e. not in a string or an array.
+    const fn at_root(&self) -> bool {
+        self.string.is_none() && self.depth == 0
+    }
+
+    fn scan(&mut self, line: &str) {
+        self.fmt_off_before = self.fmt_off;
+        // Whether the line has anything other than whitespace and a comment.
+        let mut item = false;
+        let mut rest = line;
+        loop {
+            if let Some(delimiter) = self.string {
+                let Some(end) = string_end(rest, delimiter) else {
+                    return;
+                };
+                self.string = None;
+                rest = &rest[end..];
+            }
+
+            let Some(idx) = rest.find(['"', '\'', '#', '[', ']', '{', '}']) else {
+                item |= !rest.trim().is_empty();
+                break;
+            };
+            item |= !rest[..idx].trim().is_empty();
+            let after = &rest[idx + 1..];
+            match rest.as_bytes()[idx] {
+                b'#' => {
+                    self.comment(&rest[idx..], item);
+                    break;
+                }
+                b'[' | b'{' => self.depth += 1,
+                b']' | b'}' => self.depth = self.depth.saturating_sub(1),
+                quote => {
+                    let delimiter = if quote == b'"' { "\"\"\"" } else { "'''" };
+                    if rest[idx..].starts_with(delimiter) {
+                        self.string = Some(delimiter);
+                        rest = &rest[idx + delimiter.len()..];
+                        item = true;
+                        continue;
+                    }
+                    // A string on a single line ends with the line if it is not closed.
+                    let end = string_end(after, &delimiter[..1]).unwrap_or(after.len());
+                    rest = &after[end..];
+                    item = true;
+                    continue;
+                }
+            }
+            item = true;
+            rest = after;
+        }
+
+        if item {
+            self.ignore = false;
+        }
+    }
+
+    fn comment(&mut self, comment: &str, item: bool) {
+        if !self.directives {
+            return;
+        }
+        match directive(comment) {
+            Some("fmt off") => self.fmt_off = true,
+            Some("fmt on") => self.fmt_off = false,
+            Some("ignore") if !item && self.depth == 0 => self.ignore = true,
+            _ => {}
+        }
+    }
+}
+
+/// The end of the delimiter that closes a string, including the quotes of a multi-line string
+/// that belong to its content.
+fn string_end(text: &str, delimiter: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let quote = delimiter.as_bytes()[0];
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'\\' && quote == b'"' {
+            idx += 2;
+            continue;
+        }
+        if bytes[idx..].starts_with(delimiter.as_bytes()) {
+            let mut end = idx + delimiter.len();
+            // Up to two quotes directly before the delimiter are part of a multi-line string.
+            if delimiter.len() == 3 {
+                end += bytes[end..].iter().take(2).take_while(|&&b| b == quote).count();
+            }
+            return Some(end);
+        }
+        idx += 1;
+    }
+    None
+}
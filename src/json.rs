@@ -0,0 +1,330 @@
+//! The tagged JSON encoding of [toml-test](https://github.com/toml-lang/toml-test).
+
+use std::collections::BTreeMap;
+
+use crate::value::{Datetime, Value};
+
+/// An error of [`Value::from_tagged_json`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct JsonError {
+    /// The byte offset of the error in the JSON.
+    pub offset: usize,
+
+    /// Human-friendly error message.
+    pub message: String,
+}
+
+impl core::fmt::Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at {})", self.message, self.offset)
+    }
+}
+impl std::error::Error for JsonError {}
+
+impl Value {
+    /// The value in the tagged JSON encoding of [toml-test](https://github.com/toml-lang/toml-test),
+    /// e.g. `{"type":"integer","value":"1"}` for `1`.
+    ///
+    /// Tables are JSON objects and arrays are JSON arrays, the other values are objects
+    /// with their type and their value written as a string.
+    pub fn to_tagged_json(&self) -> String {
+        let mut json = String::new();
+        write_tagged_json(self, &mut json);
+        json
+    }
+
+    /// Read a value from the tagged JSON encoding of toml-test, see [`Value::to_tagged_json`].
+    pub fn from_tagged_json(json: &str) -> Result<Self, JsonError> {
+        let mut reader = JsonReader { json, offset: 0 };
+        let value = reader.tagged_value()?;
+        reader.skip_whitespace();
+        if reader.offset < json.len() {
+            return Err(reader.error("expected the end of the input"));
+        }
+        Ok(value)
+    }
+}
+
+fn write_tagged_json(value: &Value, json: &mut String) {
+    let (kind, text) = match value {
+        Value::String(s) => ("string", s.clone()),
+        Value::Integer(i) => ("integer", i.to_string()),
+        Value::Float(f) if f.is_nan() => ("float", "nan".to_string()),
+        Value::Float(f) if f.is_infinite() => {
+            ("float", if *f > 0.0 { "inf" } else { "-inf" }.to_string())
+        }
+        Value::Float(f) => ("float", format!("{f:?}")),
+        Value::Bool(b) => ("bool", b.to_string()),
+        Value::Datetime(datetime) => {
+            let kind = match datetime {
+                Datetime { offset: Some(_), .. } => "datetime",
+                Datetime { date: Some(_), time: Some(_), .. } => "datetime-local",
+                Datetime { date: Some(_), .. } => "date-local",
+                Datetime { .. } => "time-local",
+            };
+            (kind, datetime.to_string())
+        }
+        Value::Array(array) => {
+            json.push('[');
+            for (idx, item) in array.iter().enumerate() {
+                if idx > 0 {
+                    json.push(',');
+                }
+                write_tagged_json(item, json);
+            }
+            json.push(']');
+            return;
+        }
+        Value::Table(table) => {
+            json.push('{');
+            for (idx, (key, value)) in table.iter().enumerate() {
+                if idx > 0 {
+                    json.push(',');
+                }
+                write_json_string(key, json);
+                json.push(':');
+                write_tagged_json(value, json);
+            }
+            json.push('}');
+            return;
+        }
+    };
+
+    json.push_str("{\"type\":");
+    write_json_string(kind, json);
+    json.push_str(",\"value\":");
+    write_json_string(&text, json);
+    json.push('}');
+}
+
+fn write_json_string(s: &str, json: &mut String) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// A JSON value with the values of the tagged encoding.
+struct Json {
+    /// The byte offset of the value, errors of its tagged value point at it.
+    offset: usize,
+
+    kind: JsonKind,
+}
+
+enum JsonKind {
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+struct JsonReader<'a> {
+    json: &'a str,
+    offset: usize,
+}
+
+impl JsonReader<'_> {
+    fn error(&self, message: &str) -> JsonError {
+        JsonError { offset: self.offset, message: message.to_string() }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.json[self.offset..];
+        self.offset += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.json.as_bytes().get(self.offset) == Some(&c);
+        if found {
+            self.offset += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), JsonError> {
+        if self.eat(c) { Ok(()) } else { Err(self.error(&format!("expected `{}`", c as char))) }
+    }
+
+    /// A value with its scalars decoded, objects with only a string `type` and `value` are scalars.
+    fn tagged_value(&mut self) -> Result<Value, JsonError> {
+        let json = self.value()?;
+        tagged(json)
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        let offset = self.offset;
+        let kind = match self.json.as_bytes().get(self.offset) {
+            Some(b'"') => JsonKind::String(self.string()?),
+            Some(b'[') => {
+                self.offset += 1;
+                let mut items = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        items.push(self.value()?);
+                        if self.eat(b']') {
+                            break;
+                        }
+                        self.expect(b',')?;
+                    }
+                }
+                JsonKind::Array(items)
+            }
+            Some(b'{') => {
+                self.offset += 1;
+                let mut entries = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        self.skip_whitespace();
+                        let key = self.string()?;
+                        self.expect(b':')?;
+                        entries.push((key, self.value()?));
+                        if self.eat(b'}') {
+                            break;
+                        }
+                        self.expect(b',')?;
+                    }
+                }
+                JsonKind::Object(entries)
+            }
+            _ => return Err(self.error("expected a string, an array or an object")),
+        };
+        Ok(Json { offset, kind })
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        if self.json.as_bytes().get(self.offset) != Some(&b'"') {
+            return Err(self.error("expected a string"));
+        }
+        self.offset += 1;
+
+        let mut s = String::new();
+        loop {
+            let rest = &self.json[self.offset..];
+            let Some(c) = rest.chars().next() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.offset += c.len_utf8();
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escape = self.json[self.offset..].chars().next();
+                    self.offset += 1;
+                    s.push(match escape {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    });
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// The four hexadecimal digits of a `\uXXXX` escape.
+    fn hex_code(&mut self) -> Result<u32, JsonError> {
+        let code = self
+            .json
+            .get(self.offset..self.offset + 4)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.offset += 4;
+        Ok(code)
+    }
+
+    /// The character of a `\uXXXX` escape, which is followed by another one for surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex_code()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.json[self.offset..].starts_with("\\u") {
+                return Err(self.error("expected the low surrogate of a surrogate pair"));
+            }
+            self.offset += 2;
+            let low = self.hex_code()?;
+            0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+}
+
+fn tagged(json: Json) -> Result<Value, JsonError> {
+    let error = |message| JsonError { offset: json.offset, message };
+    match json.kind {
+        JsonKind::String(_) => Err(error("expected an array or an object".to_string())),
+        JsonKind::Array(items) => {
+            Ok(Value::Array(items.into_iter().map(tagged).collect::<Result<_, _>>()?))
+        }
+        JsonKind::Object(entries) => {
+            if let [(k1, v1), (k2, v2)] = entries.as_slice()
+                && let (JsonKind::String(v1), JsonKind::String(v2)) = (&v1.kind, &v2.kind)
+            {
+                let tagged = match (k1.as_str(), k2.as_str()) {
+                    ("type", "value") => Some((v1, v2)),
+                    ("value", "type") => Some((v2, v1)),
+                    _ => None,
+                };
+                if let Some((kind, value)) = tagged {
+                    return scalar(kind, value).map_err(error);
+                }
+            }
+
+            let mut table = BTreeMap::new();
+            for (key, json) in entries {
+                table.insert(key, tagged(json)?);
+            }
+            Ok(Value::Table(table))
+        }
+    }
+}
+
+fn scalar(kind: &str, value: &str) -> Result<Value, String> {
+    let invalid = || format!("invalid {kind} `{value}`");
+    Ok(match kind {
+        "string" => Value::String(value.to_string()),
+        "integer" => Value::Integer(value.parse().map_err(|_| invalid())?),
+        "float" => Value::Float(match value {
+            "nan" | "+nan" | "-nan" => f64::NAN,
+            "inf" | "+inf" => f64::INFINITY,
+            "-inf" => f64::NEG_INFINITY,
+            _ => value.parse().map_err(|_| invalid())?,
+        }),
+        "bool" => Value::Bool(match value {
+            "true" => true,
+            "false" => false,
+            _ => return Err(invalid()),
+        }),
+        "datetime" | "datetime-local" | "date-local" | "time-local" => {
+            let datetime = value.parse::<Datetime>().map_err(|_| invalid())?;
+            let expected = match datetime {
+                Datetime { offset: Some(_), .. } => "datetime",
+                Datetime { date: Some(_), time: Some(_), .. } => "datetime-local",
+                Datetime { date: Some(_), .. } => "date-local",
+                Datetime { .. } => "time-local",
+            };
+            if kind != expected {
+                return Err(invalid());
+            }
+            Value::Datetime(datetime)
+        }
+        _ => return Err(format!("unknown type `{kind}`")),
+    })
+}
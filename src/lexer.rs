@@ -14,6 +14,10 @@ impl<'source, Token> Lexer<'source, Token> {
         Self { source, pos: 0, current_span: 0..0, _phantom: std::marker::PhantomData }
     }
 
+    pub const fn source(&self) -> &'source str {
+        self.source
+    }
+
     pub fn span(&self) -> Range<usize> {
         self.current_span.clone()
     }
@@ -25,6 +29,16 @@ impl<'source, Token> Lexer<'source, Token> {
     pub fn remainder(&self) -> &'source str {
         &self.source[self.pos..]
     }
+
+    /// Skip the prefix if the remaining source starts with it, returns whether it did.
+    pub fn skip_prefix(&mut self, prefix: &str) -> bool {
+        if !self.remainder().starts_with(prefix) {
+            return false;
+        }
+        self.pos += prefix.len();
+        self.current_span = self.pos - prefix.len()..self.pos;
+        true
+    }
 }
 
 impl<'source, Token: LexerToken<'source>> Iterator for Lexer<'source, Token> {
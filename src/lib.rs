@@ -1,11 +1,59 @@
 #![allow(clippy::single_match)]
 
+pub mod ast;
+mod comments;
+mod config;
+mod cursor;
+#[cfg(feature = "serde")]
+mod de;
+mod diff;
+pub mod dom;
+mod edit;
+mod emit;
+mod events;
 mod formatter;
+mod json;
 mod lexer;
+mod line_index;
+mod merge;
 mod parser;
+mod patch;
+mod reparse;
+#[cfg(feature = "serde")]
+mod ser;
 mod syntax;
 mod tree;
 mod util;
+mod value;
+mod visit;
 
-pub use formatter::{Options, format};
-pub use parser::parse;
+pub use comments::Comments;
+pub use config::{CONFIG_FILE_NAMES, Config, ConfigError, TAPLO_CONFIG_FILE_NAMES};
+pub use cursor::{Cursor, CursorElement};
+#[cfg(feature = "serde")]
+pub use de::{DeserializeError, from_str};
+pub use diff::{Change, ChangeKind, ChangeValue, diff};
+pub use edit::{ArrayEdit, DocumentMut, EditError, TablePosition};
+pub use events::{EventParse, ParseEvent, parse_events, tokenize};
+pub use formatter::{
+    ArrayLayout, DateTimeSeparator, FormatDiagnostic, FormatDiagnosticKind, FormatRule, Formatter,
+    HexCase, IntegerUnderscores, LineEnding, Options, OptionsBuilder, OptionsError, TextEdit,
+    ValueHooks, format, format_bytes, format_edits, format_incremental, format_into, format_node,
+    format_stream, format_with_diagnostics, format_with_hooks, format_with_rules,
+};
+pub use json::JsonError;
+pub use line_index::{ColumnEncoding, LineCol, LineIndex};
+pub use merge::{ArrayMerge, MergeOptions, merge};
+pub use parser::{
+    EncodingError, ErrorCategory, ErrorKind, Parse, ParseError, ParseOptions, TomlVersion, parse,
+    parse_borrowed, parse_bytes, parse_bytes_lossy, parse_with, render,
+};
+pub use patch::{PatchError, PatchOperation};
+pub use reparse::Reparse;
+#[cfg(feature = "serde")]
+pub use ser::{SerializeError, to_string};
+pub use syntax::{SyntaxKind, SyntaxNode, SyntaxToken};
+pub use tree::{Element as SyntaxElement, ErrorNode, SyntaxTree, TextRange};
+pub use util::{EscapeError, EscapeErrorKind, unescape, validate_escapes};
+pub use value::{Date, Datetime, DatetimeParseError, Offset, Time, Value};
+pub use visit::{Replacement, Rewrite, Visit, VisitFlow};
@@ -0,0 +1,138 @@
+//! Conversion between byte offsets and line and column positions.
+
+use std::collections::HashMap;
+
+/// A zero-based line and column position.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct LineCol {
+    pub line: u32,
+    pub column: u32,
+}
+
+/// The unit of columns, see [`LineIndex`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum ColumnEncoding {
+    /// Bytes of UTF-8.
+    #[default]
+    Utf8,
+
+    /// Code units of UTF-16, which the language server protocol uses by default.
+    Utf16,
+
+    /// Characters, i.e. Unicode scalar values.
+    Utf32,
+}
+
+/// A character that is longer than one byte in UTF-8.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct WideChar {
+    /// The UTF-8 column of the character.
+    start: u32,
+    len_utf8: u32,
+    len_utf16: u32,
+}
+
+impl WideChar {
+    const fn len(self, encoding: ColumnEncoding) -> u32 {
+        match encoding {
+            ColumnEncoding::Utf8 => self.len_utf8,
+            ColumnEncoding::Utf16 => self.len_utf16,
+            ColumnEncoding::Utf32 => 1,
+        }
+    }
+}
+
+/// Converts the byte offsets of a document, e.g. the spans of [`ParseError`](crate::ParseError)s,
+/// into lines and columns and back.
+///
+/// It is built once for a document, the conversions don't scan the document again.
+/// Lines are separated by `\n`, a `\r` before it belongs to the line.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LineIndex {
+    /// The offsets of the starts of the lines.
+    line_starts: Vec<u32>,
+
+    /// The characters longer than a byte by the lines they are on.
+    wide_chars: HashMap<u32, Vec<WideChar>>,
+
+    len: u32,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut wide_chars = HashMap::<u32, Vec<WideChar>>::new();
+
+        for (offset, c) in source.char_indices() {
+            let offset = offset as u32;
+            if c == '\n' {
+                line_starts.push(offset + 1);
+            } else if !c.is_ascii() {
+                let line = line_starts.len() as u32 - 1;
+                wide_chars.entry(line).or_default().push(WideChar {
+                    start: offset - line_starts[line as usize],
+                    len_utf8: c.len_utf8() as u32,
+                    len_utf16: c.len_utf16() as u32,
+                });
+            }
+        }
+
+        Self { line_starts, wide_chars, len: source.len() as u32 }
+    }
+
+    /// The number of lines, an empty document has one line.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The position of the offset, offsets past the end are clamped to the end
+    /// and offsets in the middle of a character are moved to its start.
+    pub fn line_col(&self, offset: u32, encoding: ColumnEncoding) -> LineCol {
+        let offset = offset.min(self.len);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = offset - self.line_starts[line];
+        let line = line as u32;
+
+        let mut delta = 0;
+        for c in self.wide_chars(line).iter().take_while(|c| c.start < column) {
+            if column < c.start + c.len_utf8 {
+                // The offset is in the middle of the character.
+                return LineCol { line, column: c.start - delta };
+            }
+            delta += c.len_utf8 - c.len(encoding);
+        }
+
+        LineCol { line, column: column - delta }
+    }
+
+    /// The offset of the position, `None` if the line doesn't exist
+    /// or the column is past the end of the line.
+    ///
+    /// A column in the middle of a character is moved to the start of the character.
+    pub fn offset(&self, line_col: LineCol, encoding: ColumnEncoding) -> Option<u32> {
+        let line_start = *self.line_starts.get(line_col.line as usize)?;
+        let line_end =
+            self.line_starts.get(line_col.line as usize + 1).map_or(self.len, |&start| start - 1);
+
+        let mut column = line_col.column;
+        let mut delta = 0;
+        for c in self.wide_chars(line_col.line) {
+            let start = c.start - delta;
+            if start >= column {
+                break;
+            }
+            if column < start + c.len(encoding) {
+                column = start;
+                break;
+            }
+            delta += c.len_utf8 - c.len(encoding);
+        }
+
+        let offset = line_start + column + delta;
+        (offset <= line_end).then_some(offset)
+    }
+
+    fn wide_chars(&self, line: u32) -> &[WideChar] {
+        self.wide_chars.get(&line).map_or(&[], Vec::as_slice)
+    }
+}
@@ -0,0 +1,149 @@
+//! Merging documents, see [`merge`].
+
+use std::collections::BTreeMap;
+
+use crate::{
+    dom::{Document, Item, Table},
+    edit::{DocumentMut, EditError},
+    emit::key_text,
+    value::{Value, item_value},
+};
+
+/// The options of [`merge`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MergeOptions {
+    /// How arrays and arrays of tables that are in both documents are merged.
+    pub arrays: ArrayMerge,
+
+    /// Keep the comments above the entries of the base and at the end of their lines
+    /// when their values are replaced.
+    pub keep_comments: bool,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self { arrays: ArrayMerge::Replace, keep_comments: true }
+    }
+}
+
+/// How arrays that are in both documents are merged, see [`MergeOptions`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum ArrayMerge {
+    /// The array of the overlay replaces the one of the base.
+    #[default]
+    Replace,
+
+    /// The items of the array of the overlay are added after the ones of the base.
+    Append,
+}
+
+/// Merge two documents, the keys of the overlay override the ones of the base.
+///
+/// The base is edited like with [`DocumentMut::set`], so its layout is kept and the values
+/// of the overlay are formatted with the options of the base. Tables are merged key by key,
+/// including inline tables, and values and tables replace each other. The tables of arrays
+/// of tables of the overlay are copied to the end with their comments.
+pub fn merge(
+    base: &DocumentMut,
+    overlay: &DocumentMut,
+    options: &MergeOptions,
+) -> Result<DocumentMut, EditError> {
+    let mut merged = base.clone();
+    let tree = overlay.tree();
+    let document = Document::new(&tree);
+    let mut merger = Merger { merged: &mut merged, overlay, options, path: Vec::new() };
+    merger.table(document.root())?;
+    Ok(merged)
+}
+
+struct Merger<'a> {
+    merged: &'a mut DocumentMut,
+    overlay: &'a DocumentMut,
+    options: &'a MergeOptions,
+
+    /// The parts of the key of the item that is merged.
+    path: Vec<String>,
+}
+
+impl Merger<'_> {
+    fn table(&mut self, table: &Table<'_>) -> Result<(), EditError> {
+        for (key, item) in table.iter() {
+            self.path.push(key.to_string());
+            self.item(item)?;
+            self.path.pop();
+        }
+        Ok(())
+    }
+
+    fn item(&mut self, item: &Item<'_>) -> Result<(), EditError> {
+        let key = key_text(&self.path);
+        let base = self.merged.get(&key);
+        match item {
+            Item::Table(table) => match base {
+                Some(Value::Table(_)) if !self.merged.is_array_of_tables(&key) => self.table(table),
+                _ if table.iter().next().is_none() => {
+                    self.replace(&key, &Value::Table(BTreeMap::new()))
+                }
+                Some(_) => {
+                    self.merged.remove(&key);
+                    self.table(table)
+                }
+                None => self.table(table),
+            },
+            Item::ArrayOfTables(_) => {
+                let append = self.options.arrays == ArrayMerge::Append
+                    && self.merged.is_array_of_tables(&key);
+                if base.is_some() && !append {
+                    self.merged.remove(&key);
+                }
+                self.merged.append_tables(self.overlay, &self.path);
+                Ok(())
+            }
+            Item::Array(array)
+                if self.options.arrays == ArrayMerge::Append
+                    && matches!(base, Some(Value::Array(_)))
+                    && !self.merged.is_array_of_tables(&key) =>
+            {
+                let mut edit = self.merged.array(&key)?;
+                for item in array.items() {
+                    if let Some(value) = item_value(item, self.overlay.as_str()) {
+                        edit.push(&value);
+                    }
+                }
+                Ok(())
+            }
+            item => match item_value(item, self.overlay.as_str()) {
+                Some(value) => self.replace(&key, &value),
+                None => Ok(()),
+            },
+        }
+    }
+
+    /// Set a value, replacing the values in front of it and the tables that are in the way.
+    fn replace(&mut self, key: &str, value: &Value) -> Result<(), EditError> {
+        for len in 1..self.path.len() {
+            let prefix = key_text(&self.path[..len]);
+            match self.merged.get(&prefix) {
+                Some(Value::Table(_)) if !self.merged.is_array_of_tables(&prefix) => {}
+                Some(_) => {
+                    self.merged.remove(&prefix);
+                }
+                None => break,
+            }
+        }
+
+        if self.merged.is_array_of_tables(key) {
+            self.merged.remove(key);
+        } else if !self.options.keep_comments && self.merged.get(key).is_some() {
+            self.merged.remove_comments(key);
+        }
+        match self.merged.set(key, value) {
+            // A table with a header is removed to write the value instead.
+            Err(EditError::NotAValue { .. }) => {
+                self.merged.remove(key);
+                self.merged.set(key, value)
+            }
+            result => result,
+        }
+    }
+}
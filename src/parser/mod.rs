@@ -1,32 +1,208 @@
 #![allow(clippy::useless_conversion)]
 //! TOML document to syntax tree parsing.
 
+use std::{ops::Range, str::Utf8Error};
+
 use crate::{
     lexer::Lexer,
     syntax::{SyntaxKind, SyntaxKind::*},
-    tree::{Node, SyntaxTree, TextRange, TreeBuilder, text_range},
-    util::{allowed_chars, check_escape},
+    tree::{Node, SyntaxTree, TextRange, TreeBuilder, TreeSink, text_range},
+    util::{allowed_chars, unescape, validate_escapes},
 };
 
+/// The UTF-8 byte order mark that documents can start with.
+pub(crate) const BOM: &str = "\u{FEFF}";
+
 #[macro_use]
 mod macros;
+mod options;
+mod render;
+pub(crate) mod validate;
+
+pub use options::{ParseOptions, TomlVersion};
+pub use render::render;
 
 /// A syntax error that can occur during parsing.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub struct Error {
+pub struct ParseError {
     /// The span of the error.
-    pub range: TextRange,
+    pub span: TextRange,
 
     /// Human-friendly error message.
     pub message: String,
+
+    /// The kind of the error.
+    pub kind: ErrorKind,
+
+    /// Another span that the error refers to, e.g. the first definition of a duplicate key.
+    pub related: Option<TextRange>,
+}
+
+impl core::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} ({:?})", self.kind.code(), self.message, self.span)
+    }
+}
+impl std::error::Error for ParseError {}
+
+/// A document that is not valid UTF-8, see [`parse_bytes`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct EncodingError {
+    /// The byte offset of the first invalid sequence.
+    pub offset: usize,
+
+    /// The length of the invalid sequence, `None` if the document ends in the middle of a character.
+    pub len: Option<usize>,
 }
 
-impl core::fmt::Display for Error {
+impl From<Utf8Error> for EncodingError {
+    fn from(error: Utf8Error) -> Self {
+        Self { offset: error.valid_up_to(), len: error.error_len() }
+    }
+}
+
+impl core::fmt::Display for EncodingError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} ({:?})", self.message, self.range)
+        write!(f, "invalid UTF-8 at byte {}", self.offset)
+    }
+}
+impl std::error::Error for EncodingError {}
+
+/// The kinds of [`ParseError`]s.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Text that is not a valid token.
+    InvalidToken,
+
+    /// The document ended in the middle of a key, value or table header.
+    UnexpectedEof,
+
+    /// A valid token where it is not allowed, e.g. a comma after another one.
+    UnexpectedToken,
+
+    /// A token such as `=`, `]` or a line break is missing.
+    ExpectedToken,
+
+    /// A key is missing or invalid.
+    ExpectedKey,
+
+    /// A value is missing or invalid.
+    ExpectedValue,
+
+    /// A character that is not allowed in a comment or string, e.g. a control character.
+    InvalidCharacter,
+
+    /// An invalid escape sequence in a string.
+    InvalidEscape,
+
+    /// A malformed number, e.g. with leading zeros or misplaced underscores.
+    InvalidNumber,
+
+    /// Arrays or inline tables nested deeper than [`ParseOptions::max_depth`].
+    NestingTooDeep,
+
+    /// A key that is defined more than once.
+    DuplicateKey,
+
+    /// A table that is defined again, either by a header for a table that was
+    /// already defined or by dotted keys in a table that has a header.
+    DuplicateTable,
+
+    /// An integer that doesn't fit in a signed 64-bit integer.
+    IntegerOutOfRange,
+
+    /// Bytes that are not valid UTF-8, see [`parse_bytes_lossy`].
+    InvalidEncoding,
+
+    /// A date-time with a part out of range, e.g. a minute `60`, or with more
+    /// fractional seconds than [`ParseOptions::max_fractional_digits`].
+    InvalidDateTime,
+}
+
+/// The stage of parsing that an [`ErrorKind`] belongs to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// Invalid tokens and invalid contents of tokens such as strings and numbers.
+    Lexer,
+
+    /// Tokens in the wrong order or missing tokens.
+    Parser,
+
+    /// Syntactically valid documents that are not valid TOML, e.g. duplicate keys.
+    Semantic,
+}
+
+impl ErrorKind {
+    /// All the error kinds in the order of their codes.
+    pub const ALL: &[Self] = &[
+        Self::InvalidToken,
+        Self::UnexpectedEof,
+        Self::UnexpectedToken,
+        Self::ExpectedToken,
+        Self::ExpectedKey,
+        Self::ExpectedValue,
+        Self::InvalidCharacter,
+        Self::InvalidEscape,
+        Self::InvalidNumber,
+        Self::NestingTooDeep,
+        Self::DuplicateKey,
+        Self::DuplicateTable,
+        Self::IntegerOutOfRange,
+        Self::InvalidEncoding,
+        Self::InvalidDateTime,
+    ];
+
+    /// The stable code of the error kind, e.g. `E0001`.
+    ///
+    /// Codes are never reused, so they can be used to filter or suppress errors.
+    pub const fn code(self) -> &'static str {
+        match self {
+            Self::InvalidToken => "E0001",
+            Self::UnexpectedEof => "E0002",
+            Self::UnexpectedToken => "E0003",
+            Self::ExpectedToken => "E0004",
+            Self::ExpectedKey => "E0005",
+            Self::ExpectedValue => "E0006",
+            Self::InvalidCharacter => "E0007",
+            Self::InvalidEscape => "E0008",
+            Self::InvalidNumber => "E0009",
+            Self::NestingTooDeep => "E0010",
+            Self::DuplicateKey => "E0011",
+            Self::DuplicateTable => "E0012",
+            Self::IntegerOutOfRange => "E0013",
+            Self::InvalidEncoding => "E0014",
+            Self::InvalidDateTime => "E0015",
+        }
+    }
+
+    /// The error kind with the given code, see [`ErrorKind::code`].
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.code() == code)
+    }
+
+    /// The stage of parsing that reports errors of this kind.
+    pub const fn category(self) -> ErrorCategory {
+        match self {
+            Self::InvalidToken
+            | Self::InvalidCharacter
+            | Self::InvalidEscape
+            | Self::InvalidNumber
+            | Self::InvalidEncoding
+            | Self::InvalidDateTime => ErrorCategory::Lexer,
+            Self::UnexpectedEof
+            | Self::UnexpectedToken
+            | Self::ExpectedToken
+            | Self::ExpectedKey
+            | Self::ExpectedValue
+            | Self::NestingTooDeep => ErrorCategory::Parser,
+            Self::DuplicateKey | Self::DuplicateTable | Self::IntegerOutOfRange => {
+                ErrorCategory::Semantic
+            }
+        }
     }
 }
-impl std::error::Error for Error {}
 
 /// Parse a TOML document into a syntax tree.
 ///
@@ -40,24 +216,78 @@ impl std::error::Error for Error {}
 ///
 /// This does not check for semantic errors such as duplicate keys.
 pub fn parse(source: &str) -> Parse {
-    let (root, errors) = parse_root(source);
-    Parse { tree: SyntaxTree { root, source: source.to_string() }, errors }
+    parse_with(source, ParseOptions::default())
+}
+
+/// Parse a TOML document into a syntax tree like [`parse`] with the given options,
+/// e.g. to parse documents as TOML 1.0 or to check semantic errors as well.
+pub fn parse_with(source: &str, options: ParseOptions) -> Parse {
+    let (root, errors) = parse_root(source, options);
+    Parse { tree: SyntaxTree { root, source: source.to_string().into(), options }, errors }
+}
+
+/// Parse a TOML document like [`parse_with`] into a tree that borrows the source,
+/// so the source is not copied, e.g. for large documents that are only read.
+pub fn parse_borrowed(source: &str, options: ParseOptions) -> (SyntaxTree<'_>, Vec<ParseError>) {
+    let (root, errors) = parse_root(source, options);
+    (SyntaxTree { root, source: source.into(), options }, errors)
+}
+
+/// Parse a TOML document that may not be valid UTF-8 like [`parse_with`].
+///
+/// Returns the offset of the first invalid sequence if it is not valid UTF-8,
+/// see [`parse_bytes_lossy`] to parse it anyway.
+pub fn parse_bytes(source: &[u8], options: ParseOptions) -> Result<Parse, EncodingError> {
+    Ok(parse_with(str::from_utf8(source)?, options))
+}
+
+/// Parse a TOML document that may not be valid UTF-8 like [`parse_with`],
+/// replacing each invalid sequence with U+FFFD.
+///
+/// The replacements are reported first as [`ErrorKind::InvalidEncoding`] errors,
+/// all the spans are offsets in the decoded document that the tree contains.
+pub fn parse_bytes_lossy(source: &[u8], options: ParseOptions) -> Parse {
+    let mut decoded = String::with_capacity(source.len());
+    let mut errors = Vec::new();
+    for chunk in source.utf8_chunks() {
+        decoded += chunk.valid();
+        if !chunk.invalid().is_empty() {
+            let start = decoded.len();
+            decoded.push(char::REPLACEMENT_CHARACTER);
+            errors.push(ParseError {
+                span: text_range(start, decoded.len()),
+                message: "invalid UTF-8".into(),
+                kind: ErrorKind::InvalidEncoding,
+                related: None,
+            });
+        }
+    }
+
+    let (root, parse_errors) = parse_root(&decoded, options);
+    errors.extend(parse_errors);
+    Parse { tree: SyntaxTree { root, source: decoded.into(), options }, errors }
 }
 
 /// Parse a TOML document, returning just the root node and errors without
 /// copying the source. Used internally by the formatter to avoid an unnecessary
 /// allocation when the caller already owns the source.
-pub(crate) fn parse_root(source: &str) -> (crate::tree::Node, Vec<Error>) {
-    Parser::new(source).parse()
+pub(crate) fn parse_root(
+    source: &str,
+    options: ParseOptions,
+) -> (crate::tree::Node, Vec<ParseError>) {
+    Parser::new(source).with_options(options).parse()
 }
 
 /// A hand-written parser that uses a custom lexer
 /// to tokenize the source, then constructs a syntax tree from them.
-pub(crate) struct Parser<'p> {
+pub(crate) struct Parser<'p, S = TreeBuilder> {
     skip_whitespace: bool,
     // Allow glob patterns as keys and using [] instead of dots.
     key_pattern_syntax: bool,
     current_token: Option<SyntaxKind>,
+    options: ParseOptions,
+    // The number of arrays and inline tables the current token is in.
+    depth: usize,
 
     // These tokens are not consumed on errors.
     //
@@ -76,8 +306,8 @@ pub(crate) struct Parser<'p> {
     error_whitelist: u16,
 
     lexer: Lexer<'p, SyntaxKind>,
-    builder: TreeBuilder,
-    errors: Vec<Error>,
+    builder: S,
+    errors: Vec<ParseError>,
 }
 
 impl Parser<'_> {
@@ -85,13 +315,17 @@ impl Parser<'_> {
     /// and key matches.
     ///
     /// It allows a part of glob syntax in identifiers as well.
-    #[allow(dead_code)]
-    pub(crate) fn parse_key_only(mut self, source: &str) -> Parse {
+    pub(crate) fn parse_key_only(mut self) -> Parse {
         self.key_pattern_syntax = true;
         let _ = with_node!(self.builder, KEY, self.parse_key());
 
+        let source = self.lexer.source().to_string();
         Parse {
-            tree: SyntaxTree { root: self.builder.finish_root(), source: source.to_string() },
+            tree: SyntaxTree {
+                root: self.builder.finish_root(),
+                source: source.into(),
+                options: self.options,
+            },
             errors: self.errors,
         }
     }
@@ -107,35 +341,61 @@ type ParserResult<T> = Result<T, ()>;
 // that contains minimal function calls.
 impl<'p> Parser<'p> {
     pub(crate) fn new(source: &'p str) -> Self {
+        Self::with_sink(source, TreeBuilder::new())
+    }
+
+    fn parse(self) -> (Node, Vec<ParseError>) {
+        let validate = self.options.validate;
+        let source = self.lexer.source();
+        let (builder, mut errors) = self.parse_into();
+        let root = builder.finish_root();
+        if validate {
+            validate::validate(&root, source, &mut errors);
+        }
+        (root, errors)
+    }
+}
+
+impl<'p, S: TreeSink> Parser<'p, S> {
+    /// A parser that writes the nodes and tokens to the sink instead of a tree.
+    pub(crate) fn with_sink(source: &'p str, sink: S) -> Self {
         Parser {
             current_token: None,
             skip_whitespace: true,
             key_pattern_syntax: false,
+            options: ParseOptions::default(),
+            depth: 0,
             error_whitelist: 0,
             lexer: Lexer::new(source),
-            builder: TreeBuilder::new(),
+            builder: sink,
             errors: Default::default(),
         }
     }
 
-    fn parse(mut self) -> (Node, Vec<Error>) {
-        let _ = with_node!(self.builder, ROOT, self.parse_root());
-
-        (self.builder.finish_root(), self.errors)
+    pub(crate) fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
     }
 
-    fn error(&mut self, message: &str) -> ParserResult<()> {
-        let span = self.lexer.span();
+    /// Parse the document into the sink, without checking for semantic errors.
+    pub(crate) fn parse_into(mut self) -> (S, Vec<ParseError>) {
+        let _ = with_node!(self.builder, ROOT, {
+            // A byte order mark is whitespace before the first token.
+            if self.lexer.skip_prefix(BOM) {
+                self.insert_token(WHITESPACE, BOM.len());
+            }
+            self.parse_root()
+        });
+        (self.builder, self.errors)
+    }
 
-        let err = Error { range: text_range(span.start, span.end), message: message.into() };
+    fn error(&mut self, kind: ErrorKind, message: &str) -> ParserResult<()> {
+        let span = text_range(self.lexer.span().start, self.lexer.span().end);
 
-        let same_error = self.errors.last().is_some_and(|e| e.range == err.range);
+        let same_error = self.errors.last().is_some_and(|e| e.span == span);
 
         if !same_error {
-            self.add_error(&Error {
-                range: text_range(span.start, span.end),
-                message: message.into(),
-            });
+            self.add_error(&ParseError { span, message: message.into(), kind, related: None });
             if let Some(t) = self.current_token
                 && !self.whitelisted(t)
             {
@@ -148,14 +408,108 @@ impl<'p> Parser<'p> {
         Err(())
     }
 
+    /// Report an error in a part of the current token, given relative to its start,
+    /// and mark the token as an error like [`Parser::error`].
+    fn error_at(&mut self, span: Range<usize>, kind: ErrorKind, message: &str) -> ParserResult<()> {
+        let start = self.lexer.span().start;
+        self.add_error(&ParseError {
+            span: text_range(start + span.start, start + span.end),
+            message: message.into(),
+            kind,
+            related: None,
+        });
+        if let Some(t) = self.current_token
+            && !self.whitelisted(t)
+        {
+            self.token_as(ERROR).ok();
+        }
+        Err(())
+    }
+
     // report error without consuming the current the token
-    fn report_error(&mut self, message: &str) -> ParserResult<()> {
+    fn report_error(&mut self, kind: ErrorKind, message: &str) -> ParserResult<()> {
         let span = self.lexer.span();
-        self.add_error(&Error { range: text_range(span.start, span.end), message: message.into() });
+        self.add_error(&ParseError {
+            span: text_range(span.start, span.end),
+            message: message.into(),
+            kind,
+            related: None,
+        });
         Err(())
     }
 
-    fn add_error(&mut self, e: &Error) {
+    /// Report the control characters of the current token at the indices from [`allowed_chars`].
+    fn report_control_chars(&mut self, result: Result<(), Vec<usize>>, context: &str) {
+        let Err(indices) = result else {
+            return;
+        };
+        let start = self.lexer.span().start;
+        for idx in indices {
+            // Control characters are ASCII, so they are a byte long.
+            let c = self.lexer.slice().as_bytes()[idx];
+            self.add_error(&ParseError {
+                span: text_range(start + idx, start + idx + 1),
+                message: format!("control character U+{c:04X} is not allowed in {context}"),
+                kind: ErrorKind::InvalidCharacter,
+                related: None,
+            });
+        }
+    }
+
+    /// Report the invalid escapes of the current basic string, see [`validate_escapes`].
+    fn report_invalid_escapes(&mut self) {
+        let start = self.lexer.span().start;
+        for err in validate_escapes(self.lexer.slice(), self.options.toml_version) {
+            self.add_error(&ParseError {
+                span: text_range(start + err.span.start as usize, start + err.span.end as usize),
+                message: err.kind.message().into(),
+                kind: ErrorKind::InvalidEscape,
+                related: None,
+            });
+        }
+    }
+
+    /// Report an array or inline table starting at `start` that is not closed
+    /// before the current token, the error spans the unclosed part.
+    fn report_unclosed(&mut self, start: usize, message: &str) -> ParserResult<()> {
+        let end = self.lexer.source()[..self.lexer.span().start].trim_end().len();
+        self.add_error(&ParseError {
+            span: text_range(start, end),
+            message: message.into(),
+            kind: ErrorKind::ExpectedToken,
+            related: None,
+        });
+        Err(())
+    }
+
+    /// Report a quoted key that is empty or only contains whitespace,
+    /// see [`ParseOptions::deny_blank_keys`].
+    fn report_blank_key(&mut self, key: &str) {
+        if self.options.deny_blank_keys && key.trim().is_empty() {
+            let _ = self.report_error(ErrorKind::ExpectedKey, "key is empty or only whitespace");
+        }
+    }
+
+    /// Add the current number token, or report each of its misplaced underscores
+    /// and mark it as an error like [`Parser::error_at`].
+    fn number_with_underscores(&mut self, radix: u32) -> ParserResult<()> {
+        let spans = invalid_underscores(self.lexer.slice(), radix);
+        let Some((last, spans)) = spans.split_last() else {
+            return self.token();
+        };
+        let start = self.lexer.span().start;
+        for span in spans {
+            self.add_error(&ParseError {
+                span: text_range(start + span.start, start + span.end),
+                message: "underscores must be between digits".into(),
+                kind: ErrorKind::InvalidNumber,
+                related: None,
+            });
+        }
+        self.error_at(last.clone(), ErrorKind::InvalidNumber, "underscores must be between digits")
+    }
+
+    fn add_error(&mut self, e: &ParseError) {
         if let Some(last_err) = self.errors.last_mut()
             && last_err == e
         {
@@ -180,8 +534,9 @@ impl<'p> Parser<'p> {
         self.error_whitelist & token as u16 != 0
     }
 
-    fn insert_token(&mut self, kind: SyntaxKind, s: &str) {
-        self.builder.token(kind, s)
+    /// Add a token that ends at the offset, see [`TreeBuilder::token`].
+    fn insert_token(&mut self, kind: SyntaxKind, end: usize) {
+        self.builder.token(kind, end)
     }
 
     fn must_token_or(&mut self, kind: SyntaxKind, message: &str) -> ParserResult<()> {
@@ -190,13 +545,15 @@ impl<'p> Parser<'p> {
                 if kind == t {
                     self.token()
                 } else {
-                    self.error(message)
+                    self.error(ErrorKind::ExpectedToken, message)
                 }
             }
             Err(_) => {
-                self.add_error(&Error {
-                    range: text_range(self.lexer.span().start, self.lexer.span().end),
+                self.add_error(&ParseError {
+                    span: text_range(self.lexer.span().start, self.lexer.span().end),
                     message: "unexpected EOF".into(),
+                    kind: ErrorKind::UnexpectedEof,
+                    related: None,
                 });
                 Err(())
             }
@@ -208,7 +565,7 @@ impl<'p> Parser<'p> {
         match self.get_token() {
             Err(_) => Err(()),
             Ok(token) => {
-                self.builder.token(token, self.lexer.slice());
+                self.builder.token(token, self.lexer.span().end);
                 self.current_token = None;
                 Ok(())
             }
@@ -236,7 +593,7 @@ impl<'p> Parser<'p> {
         match self.get_token() {
             Err(_) => return Err(()),
             Ok(_) => {
-                self.builder.token(kind, self.lexer.slice());
+                self.builder.token(kind, self.lexer.span().end);
             }
         }
 
@@ -249,36 +606,28 @@ impl<'p> Parser<'p> {
             let token = token.unwrap_or(ERROR);
             match token {
                 COMMENT => {
-                    match allowed_chars::comment(self.lexer.slice()) {
-                        Ok(_) => {}
-                        Err(err_indices) => {
-                            for e in err_indices {
-                                self.add_error(&Error {
-                                    range: text_range(
-                                        self.lexer.span().start + e,
-                                        self.lexer.span().start + e,
-                                    ),
-                                    message: "invalid character in comment".into(),
-                                });
-                            }
-                        }
-                    };
+                    self.report_control_chars(
+                        allowed_chars::comment(self.lexer.slice()),
+                        "comments",
+                    );
 
-                    self.insert_token(token, self.lexer.slice());
+                    self.insert_token(token, self.lexer.span().end);
                 }
                 WHITESPACE if self.skip_whitespace => {
-                    self.insert_token(token, self.lexer.slice());
+                    self.insert_token(token, self.lexer.span().end);
                 }
                 WHITESPACE => {
                     self.current_token = Some(token);
                     break;
                 }
                 ERROR => {
-                    self.insert_token(token, self.lexer.slice());
+                    self.insert_token(token, self.lexer.span().end);
                     let span = self.lexer.span();
-                    self.add_error(&Error {
-                        range: text_range(span.start, span.end),
+                    self.add_error(&ParseError {
+                        span: text_range(span.start, span.end),
                         message: "unexpected token".into(),
+                        kind: ErrorKind::InvalidToken,
+                        related: None,
                     })
                 }
                 _ => {
@@ -314,7 +663,7 @@ impl<'p> Parser<'p> {
                     }
 
                     if not_newline {
-                        let _ = self.error("expected new line");
+                        let _ = self.error(ErrorKind::ExpectedToken, "expected new line");
                         continue;
                     }
 
@@ -348,7 +697,7 @@ impl<'p> Parser<'p> {
                 }
                 _ => {
                     if not_newline {
-                        let _ = self.error("expected new line");
+                        let _ = self.error(ErrorKind::ExpectedToken, "expected new line");
                         continue;
                     }
                     if entry_started {
@@ -357,7 +706,13 @@ impl<'p> Parser<'p> {
                     not_newline = true;
                     self.builder.start_node(ENTRY);
                     entry_started = true;
-                    let _ = whitelisted!(self, NEWLINE, self.parse_entry());
+                    let entry_start = self.lexer.span().start;
+                    if whitelisted!(self, NEWLINE, self.parse_entry()).is_err()
+                        && !self.skip_line(entry_start)
+                    {
+                        // The entry ended at the start of another line.
+                        not_newline = !self.at_line_start();
+                    }
                 }
             }
         }
@@ -378,6 +733,18 @@ impl<'p> Parser<'p> {
 
     fn parse_table_array_header(&mut self) -> ParserResult<()> {
         self.skip_whitespace = false;
+        let result = self.parse_table_array_brackets();
+        // Whitespace is skipped again even if the header is malformed,
+        // so the errors don't change how the rest of the document is parsed.
+        self.skip_whitespace = true;
+        result?;
+
+        self.step();
+
+        Ok(())
+    }
+
+    fn parse_table_array_brackets(&mut self) -> ParserResult<()> {
         self.must_token_or(BRACKET_START, r#"expected "[[""#)?;
         self.must_token_or(BRACKET_START, r#"expected "[[""#)?;
         self.skip_whitespace = true;
@@ -393,12 +760,9 @@ impl<'p> Parser<'p> {
                 self.token_as_no_step(token)?;
             }
             _ => {
-                self.error(r#"expected "]]"#)?;
+                self.error(ErrorKind::ExpectedToken, r#"expected "]]"#)?;
             }
         }
-        self.skip_whitespace = true;
-
-        self.step();
 
         Ok(())
     }
@@ -412,8 +776,13 @@ impl<'p> Parser<'p> {
     }
 
     fn parse_key(&mut self) -> ParserResult<()> {
+        match self.get_token() {
+            Ok(EQ) => return self.error(ErrorKind::ExpectedKey, r#"expected key before "=""#),
+            Ok(PERIOD) => return self.error(ErrorKind::ExpectedKey, r#"expected key before ".""#),
+            _ => {}
+        }
         if self.parse_ident().is_err() {
-            return self.report_error("expected identifier");
+            return self.report_error(ErrorKind::ExpectedKey, "expected identifier");
         }
 
         let mut after_period = false;
@@ -422,13 +791,13 @@ impl<'p> Parser<'p> {
                 if !after_period {
                     return Ok(());
                 }
-                return self.error("unexpected end of input");
+                return self.error(ErrorKind::UnexpectedEof, "unexpected end of input");
             };
 
             match t {
                 PERIOD => {
                     if after_period {
-                        return self.error(r#"unexpected ".""#);
+                        return self.error(ErrorKind::ExpectedKey, "expected key between periods");
                     } else {
                         self.token()?;
                         after_period = true;
@@ -439,26 +808,32 @@ impl<'p> Parser<'p> {
 
                     match self.parse_ident() {
                         Ok(_) => {}
-                        Err(_) => return self.error("expected identifier"),
+                        Err(_) => return self.error(ErrorKind::ExpectedKey, "expected identifier"),
                     }
 
                     let token = self.get_token()?;
 
                     if !matches!(token, BRACKET_END) {
-                        self.error(r#"expected "]""#)?;
+                        self.error(ErrorKind::ExpectedToken, r#"expected "]""#)?;
                     }
                     self.step();
                     after_period = false;
                 }
+                EQ | BRACKET_END if after_period => {
+                    return self.error(ErrorKind::ExpectedKey, r#"expected key after ".""#);
+                }
                 _ => {
                     if after_period {
                         match self.parse_ident() {
                             Ok(_) => {}
-                            Err(_) => return self.report_error("expected identifier"),
+                            Err(_) => {
+                                return self
+                                    .report_error(ErrorKind::ExpectedKey, "expected identifier");
+                            }
                         }
                         after_period = false;
                     } else if self.key_pattern_syntax {
-                        return self.error("unexpected identifier");
+                        return self.error(ErrorKind::UnexpectedToken, "unexpected identifier");
                     } else {
                         break;
                     }
@@ -472,12 +847,22 @@ impl<'p> Parser<'p> {
     fn parse_ident(&mut self) -> ParserResult<()> {
         let t = self.get_token()?;
         match t {
-            IDENT => self.token(),
+            IDENT => {
+                // Non-ASCII characters were proposed for the bare keys of TOML 1.1,
+                // but the released version doesn't allow them either.
+                if !self.lexer.slice().is_ascii() {
+                    let _ = self.report_error(
+                        ErrorKind::UnexpectedToken,
+                        "non-ASCII characters are not allowed in bare keys, use a quoted key",
+                    );
+                }
+                self.token()
+            }
             IDENT_WITH_GLOB => {
-                if self.key_pattern_syntax {
+                if self.key_pattern_syntax || self.options.glob_keys {
                     self.token_as(IDENT)
                 } else {
-                    self.error("expected identifier")
+                    self.error(ErrorKind::ExpectedKey, "expected identifier")
                 }
             }
             INTEGER_HEX | INTEGER_BIN | INTEGER_OCT => self.token_as(IDENT),
@@ -489,71 +874,38 @@ impl<'p> Parser<'p> {
                 }
             }
             STRING_LITERAL => {
-                match allowed_chars::string_literal(self.lexer.slice()) {
-                    Ok(_) => {}
-                    Err(err_indices) => {
-                        for e in err_indices {
-                            self.add_error(&Error {
-                                range: text_range(
-                                    self.lexer.span().start + e,
-                                    self.lexer.span().start + e,
-                                ),
-                                message: "invalid control character in string literal".into(),
-                            });
-                        }
-                    }
-                };
+                self.report_control_chars(
+                    allowed_chars::string_literal(self.lexer.slice()),
+                    "literal strings",
+                );
+                let slice = self.lexer.slice();
+                self.report_blank_key(&slice[1..slice.len() - 1]);
 
                 self.token_as(IDENT)
             }
             STRING => {
-                match allowed_chars::string(self.lexer.slice()) {
-                    Ok(_) => {}
-                    Err(err_indices) => {
-                        for e in err_indices {
-                            self.add_error(&Error {
-                                range: text_range(
-                                    self.lexer.span().start + e,
-                                    self.lexer.span().start + e,
-                                ),
-                                message: "invalid character in string".into(),
-                            });
-                        }
-                    }
-                };
-
-                match check_escape(self.lexer.slice()) {
-                    Ok(_) => self.token_as(IDENT),
-                    Err(err_indices) => {
-                        for e in err_indices {
-                            self.add_error(&Error {
-                                range: text_range(
-                                    self.lexer.span().start + e,
-                                    self.lexer.span().start + e,
-                                ),
-                                message: "invalid escape sequence".into(),
-                            });
-                        }
+                self.report_control_chars(allowed_chars::string(self.lexer.slice()), "strings");
+                let slice = &self.lexer.slice()[1..self.lexer.slice().len() - 1];
+                self.report_blank_key(&unescape(slice).unwrap_or_else(|_| slice.to_string()));
 
-                        // We proceed normally even if
-                        // the string contains invalid escapes.
-                        // It shouldn't affect the rest of the parsing.
-                        self.token_as(IDENT)
-                    }
-                }
+                // Invalid escapes don't affect the rest of the parsing.
+                self.report_invalid_escapes();
+                self.token_as(IDENT)
             }
             FLOAT => {
-                if self.lexer.slice().starts_with('0') {
-                    self.error("zero-padded numbers are not allowed")
-                } else if self.lexer.slice().starts_with('+') {
+                // Leading zeros are fine in keys, e.g. `01.23` is the key `01` and `23`.
+                if self.lexer.slice().starts_with('+') {
                     Err(())
                 } else {
+                    let mut end = self.lexer.span().start;
                     for (i, s) in self.lexer.slice().split('.').enumerate() {
                         if i != 0 {
-                            self.insert_token(PERIOD, ".");
+                            end += 1;
+                            self.insert_token(PERIOD, end);
                         }
 
-                        self.insert_token(IDENT, s);
+                        end += s.len();
+                        self.insert_token(IDENT, end);
                     }
                     self.step();
                     Ok(())
@@ -561,51 +913,62 @@ impl<'p> Parser<'p> {
             }
             BOOL => self.token_as(IDENT),
             DATE => self.token_as(IDENT),
-            _ => self.error("expected identifier"),
+            _ => self.error(ErrorKind::ExpectedKey, "expected identifier"),
         }
     }
 
     fn parse_value(&mut self) -> ParserResult<()> {
         let Ok(t) = self.get_token() else {
-            return self.error("expected value");
+            return self.error(ErrorKind::ExpectedValue, "expected value");
         };
 
         match t {
-            BOOL | DATE_TIME_OFFSET | DATE_TIME_LOCAL | DATE | TIME => self.token(),
-            INTEGER => {
-                // Check for zero-padded integers
-                if (self.lexer.slice().starts_with('0') && self.lexer.slice() != "0")
-                    || (self.lexer.slice().starts_with("+0") && self.lexer.slice() != "+0")
-                    || (self.lexer.slice().starts_with("-0") && self.lexer.slice() != "-0")
+            BOOL | DATE => self.token(),
+            DATE_TIME_OFFSET | DATE_TIME_LOCAL | TIME => {
+                // The time starts after the date and the separator.
+                let start = if t == TIME { 0 } else { 11 };
+                let time = &self.lexer.slice()[start..];
+                // TOML 1.1.0 allows times without seconds
+                if self.options.toml_version == TomlVersion::V1_0
+                    && time.as_bytes().get(5) != Some(&b':')
                 {
-                    self.error("zero-padded integers are not allowed")
-                } else if !check_underscores(self.lexer.slice(), 10) {
-                    self.error("invalid underscores")
-                } else {
-                    self.token()
-                }
-            }
-            INTEGER_BIN => {
-                if !check_underscores(self.lexer.slice(), 2) {
-                    self.error("invalid underscores")
-                } else {
-                    self.token()
+                    let _ = self.report_error(
+                        ErrorKind::InvalidToken,
+                        "seconds are required in times before TOML 1.1",
+                    );
                 }
-            }
-            INTEGER_HEX => {
-                if !check_underscores(self.lexer.slice(), 16) {
-                    self.error("invalid underscores")
-                } else {
-                    self.token()
+                match invalid_time_part(time, self.options.max_fractional_digits) {
+                    Some((span, message)) => self.error_at(
+                        start + span.start..start + span.end,
+                        ErrorKind::InvalidDateTime,
+                        &message,
+                    ),
+                    None => self.token(),
                 }
             }
-            INTEGER_OCT => {
-                if !check_underscores(self.lexer.slice(), 8) {
-                    self.error("invalid underscores")
+            INTEGER if self.lexer.remainder().starts_with('.') => self.skip_malformed_number(
+                self.lexer.span().end,
+                "expected digits after the decimal point",
+            ),
+            PERIOD if self.lexer.remainder().starts_with(|c: char| c.is_ascii_digit()) => self
+                .skip_malformed_number(
+                    self.lexer.span().start,
+                    "expected digits before the decimal point",
+                ),
+            INTEGER => {
+                if let Some(padding) = zero_padding(self.lexer.slice()) {
+                    self.error_at(
+                        padding,
+                        ErrorKind::InvalidNumber,
+                        "zero-padded integers are not allowed",
+                    )
                 } else {
-                    self.token()
+                    self.number_with_underscores(10)
                 }
             }
+            INTEGER_BIN => self.number_with_underscores(2),
+            INTEGER_HEX => self.number_with_underscores(16),
+            INTEGER_OCT => self.number_with_underscores(8),
             FLOAT => {
                 let int_slice = if self.lexer.slice().contains('.') {
                     self.lexer.slice().split('.').next().unwrap()
@@ -613,139 +976,80 @@ impl<'p> Parser<'p> {
                     self.lexer.slice().split(['e', 'E']).next().unwrap()
                 };
 
-                if (int_slice.starts_with('0') && int_slice != "0")
-                    || (int_slice.starts_with("+0") && int_slice != "+0")
-                    || (int_slice.starts_with("-0") && int_slice != "-0")
-                {
-                    self.error("zero-padded numbers are not allowed")
-                } else if !check_underscores(self.lexer.slice(), 10) {
-                    self.error("invalid underscores")
+                if let Some(padding) = zero_padding(int_slice) {
+                    self.error_at(
+                        padding,
+                        ErrorKind::InvalidNumber,
+                        "zero-padded numbers are not allowed",
+                    )
                 } else {
-                    self.token()
+                    self.number_with_underscores(10)
                 }
             }
             STRING_LITERAL => {
-                match allowed_chars::string_literal(self.lexer.slice()) {
-                    Ok(_) => {}
-                    Err(err_indices) => {
-                        for e in err_indices {
-                            self.add_error(&Error {
-                                range: text_range(
-                                    self.lexer.span().start + e,
-                                    self.lexer.span().start + e,
-                                ),
-                                message: "invalid control character in string literal".into(),
-                            });
-                        }
-                    }
-                };
+                self.report_control_chars(
+                    allowed_chars::string_literal(self.lexer.slice()),
+                    "literal strings",
+                );
                 self.token()
             }
             MULTI_LINE_STRING_LITERAL => {
-                match allowed_chars::multi_line_string_literal(self.lexer.slice()) {
-                    Ok(_) => {}
-                    Err(err_indices) => {
-                        for e in err_indices {
-                            self.add_error(&Error {
-                                range: text_range(
-                                    self.lexer.span().start + e,
-                                    self.lexer.span().start + e,
-                                ),
-                                message: "invalid character in string".into(),
-                            });
-                        }
-                    }
-                };
+                self.report_control_chars(
+                    allowed_chars::multi_line_string_literal(self.lexer.slice()),
+                    "literal strings",
+                );
                 self.token()
             }
             STRING => {
-                match allowed_chars::string(self.lexer.slice()) {
-                    Ok(_) => {}
-                    Err(err_indices) => {
-                        for e in err_indices {
-                            self.add_error(&Error {
-                                range: text_range(
-                                    self.lexer.span().start + e,
-                                    self.lexer.span().start + e,
-                                ),
-                                message: "invalid character in string".into(),
-                            });
-                        }
-                    }
-                };
+                self.report_control_chars(allowed_chars::string(self.lexer.slice()), "strings");
 
-                match check_escape(self.lexer.slice()) {
-                    Ok(_) => self.token(),
-                    Err(err_indices) => {
-                        for e in err_indices {
-                            self.add_error(&Error {
-                                range: text_range(
-                                    self.lexer.span().start + e,
-                                    self.lexer.span().start + e,
-                                ),
-                                message: "invalid escape sequence".into(),
-                            });
-                        }
-
-                        // We proceed normally even if
-                        // the string contains invalid escapes.
-                        // It shouldn't affect the rest of the parsing.
-                        self.token()
-                    }
-                }
+                // Invalid escapes don't affect the rest of the parsing.
+                self.report_invalid_escapes();
+                self.token()
             }
             MULTI_LINE_STRING => {
-                match allowed_chars::multi_line_string(self.lexer.slice()) {
-                    Ok(_) => {}
-                    Err(err_indices) => {
-                        for e in err_indices {
-                            self.add_error(&Error {
-                                range: text_range(
-                                    self.lexer.span().start + e,
-                                    self.lexer.span().start + e,
-                                ),
-                                message: "invalid character in string".into(),
-                            });
-                        }
-                    }
-                };
+                self.report_control_chars(
+                    allowed_chars::multi_line_string(self.lexer.slice()),
+                    "strings",
+                );
 
-                match check_escape(self.lexer.slice()) {
-                    Ok(_) => self.token(),
-                    Err(err_indices) => {
-                        for e in err_indices {
-                            self.add_error(&Error {
-                                range: text_range(
-                                    self.lexer.span().start + e,
-                                    self.lexer.span().start + e,
-                                ),
-                                message: "invalid escape sequence".into(),
-                            });
-                        }
-
-                        // We proceed normally even if
-                        // the string contains invalid escapes.
-                        // It shouldn't affect the rest of the parsing.
-                        self.token()
-                    }
-                }
+                // Invalid escapes don't affect the rest of the parsing.
+                self.report_invalid_escapes();
+                self.token()
+            }
+            BRACKET_START | BRACE_START
+                if self.options.max_depth.is_some_and(|max| self.depth >= max) =>
+            {
+                self.skip_nested()
             }
             BRACKET_START => {
-                with_node!(self.builder, ARRAY, self.parse_array())
+                self.depth += 1;
+                let res = with_node!(self.builder, ARRAY, self.parse_array());
+                self.depth -= 1;
+                res
             }
             BRACE_START => {
-                with_node!(self.builder, INLINE_TABLE, self.parse_inline_table())
+                self.depth += 1;
+                let res = with_node!(self.builder, INLINE_TABLE, self.parse_inline_table());
+                self.depth -= 1;
+                res
+            }
+            IDENT if is_uppercase_radix_prefix(self.lexer.slice()) => {
+                self.report_error(ErrorKind::InvalidNumber, "integer prefixes must be lowercase")
+                    .ok();
+                Ok(())
             }
             IDENT | BRACE_END => {
                 // FIXME(bit_flags): This branch is just a workaround.
-                self.report_error("expected value").ok();
+                self.report_error(ErrorKind::ExpectedValue, "expected value").ok();
                 Ok(())
             }
-            _ => self.error("expected value"),
+            _ => self.error(ErrorKind::ExpectedValue, "expected value"),
         }
     }
 
     fn parse_inline_table(&mut self) -> ParserResult<()> {
+        let start = self.lexer.span().start;
         self.must_token_or(BRACE_START, r#"expected "{""#)?;
 
         let mut first = true;
@@ -754,16 +1058,28 @@ impl<'p> Parser<'p> {
 
         loop {
             let Ok(t) = self.get_token() else {
-                return self.report_error(r#"expected "}""#);
+                return self.report_error(ErrorKind::ExpectedToken, r#"expected "}""#);
             };
 
             match t {
                 BRACE_END => {
                     // TOML 1.1.0 allows trailing commas in inline tables
+                    if comma_last && self.options.toml_version == TomlVersion::V1_0 {
+                        let _ = self.report_error(
+                            ErrorKind::UnexpectedToken,
+                            "trailing commas in inline tables require TOML 1.1",
+                        );
+                    }
                     break self.add_token()?;
                 }
                 NEWLINE => {
                     // TOML 1.1.0 allows newlines in inline tables
+                    if self.options.toml_version == TomlVersion::V1_0 {
+                        let _ = self.report_error(
+                            ErrorKind::UnexpectedToken,
+                            "newlines in inline tables require TOML 1.1",
+                        );
+                    }
                     self.token()?;
                     was_newline = true;
                 }
@@ -774,22 +1090,26 @@ impl<'p> Parser<'p> {
                 }
                 COMMA => {
                     if comma_last {
-                        let _ = self.report_error(r#"unexpected ",""#);
+                        let _ = self.report_error(ErrorKind::UnexpectedToken, r#"unexpected ",""#);
                     }
 
                     if first {
-                        let _ = self.error(r#"unexpected ",""#);
+                        let _ = self.error(ErrorKind::UnexpectedToken, r#"unexpected ",""#);
                     } else {
                         self.token()?;
                     }
                     comma_last = true;
                     was_newline = false;
                 }
+                BRACKET_START if self.at_line_start() && self.starts_entry_or_header(t) => {
+                    // The inline table was not closed before the next table.
+                    return self.report_unclosed(start, r#"expected "}""#);
+                }
                 _ => {
                     // In TOML 1.1.0, entries can be separated by commas OR newlines
                     // Only require comma if there was no newline before this entry
                     if !comma_last && !first && !was_newline {
-                        let _ = self.error(r#"expected ",""#);
+                        let _ = self.error(ErrorKind::ExpectedToken, r#"expected ",""#);
                     }
                     let _ = whitelisted!(
                         self,
@@ -801,19 +1121,23 @@ impl<'p> Parser<'p> {
                 }
             }
 
-            first = false;
+            // A comma after a line break is still a leading comma.
+            if !matches!(t, NEWLINE | COMMENT) {
+                first = false;
+            }
         }
         Ok(())
     }
 
     fn parse_array(&mut self) -> ParserResult<()> {
+        let start = self.lexer.span().start;
         self.must_token_or(BRACKET_START, r#"expected "[""#)?;
 
         let mut first = true;
         let mut comma_last = false;
         loop {
             let Ok(t) = self.get_token() else {
-                let _ = self.report_error("unexpected EOF");
+                let _ = self.report_error(ErrorKind::UnexpectedEof, "unexpected EOF");
                 return Err(());
             };
 
@@ -825,14 +1149,18 @@ impl<'p> Parser<'p> {
                 }
                 COMMA => {
                     if first || comma_last {
-                        let _ = self.error(r#"unexpected ",""#);
+                        let _ = self.error(ErrorKind::UnexpectedToken, r#"unexpected ",""#);
                     }
                     self.token()?;
                     comma_last = true;
                 }
+                _ if self.at_line_start() && self.starts_entry_or_header(t) => {
+                    // The array was not closed, the rest of the document is parsed as usual.
+                    return self.report_unclosed(start, r#"expected "]""#);
+                }
                 _ => {
                     if !comma_last && !first {
-                        let _ = self.error(r#"expected ",""#);
+                        let _ = self.error(ErrorKind::ExpectedToken, r#"expected ",""#);
                     }
                     let _ = whitelisted!(
                         self,
@@ -847,35 +1175,207 @@ impl<'p> Parser<'p> {
         }
         Ok(())
     }
-}
 
-fn check_underscores(s: &str, radix: u32) -> bool {
-    let bytes = s.as_bytes();
-    if bytes.first() == Some(&b'_') || bytes.last() == Some(&b'_') {
-        return false;
+    /// Skip an array or inline table that is nested deeper than [`ParseOptions::max_depth`],
+    /// its tokens are put in an `ERROR` node.
+    fn skip_nested(&mut self) -> ParserResult<()> {
+        let _ = self.report_error(ErrorKind::NestingTooDeep, "too deeply nested value");
+
+        let mut depth = 0_usize;
+        with_node!(self.builder, ERROR, {
+            while let Ok(token) = self.get_token() {
+                if self.at_line_start() && self.starts_entry_or_header(token) {
+                    // The value was not closed.
+                    break;
+                }
+                match token {
+                    BRACKET_START | BRACE_START => depth += 1,
+                    BRACKET_END | BRACE_END => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                if depth == 0 {
+                    let _ = self.add_token();
+                    break;
+                }
+                let _ = self.token();
+            }
+        });
+        Err(())
     }
 
-    let mut last_byte = 0_u8;
+    /// Report a decimal point without digits on one side at `dot`, e.g. in `1.`, `1.e5` or `.5`.
+    /// The number from the current token until the next whitespace is put in an ERROR node.
+    fn skip_malformed_number(&mut self, dot: usize, message: &str) -> ParserResult<()> {
+        self.add_error(&ParseError {
+            span: text_range(dot, dot + 1),
+            message: message.into(),
+            kind: ErrorKind::InvalidNumber,
+            related: None,
+        });
 
-    for &b in bytes {
-        let is_digit = match radix {
-            2 => b == b'0' || b == b'1',
-            8 => (b'0'..=b'7').contains(&b),
-            10 => b.is_ascii_digit(),
-            16 => b.is_ascii_hexdigit(),
-            _ => unreachable!(),
-        };
+        self.skip_whitespace = false;
+        with_node!(self.builder, ERROR, {
+            while let Ok(token) = self.get_token()
+                && !matches!(
+                    token,
+                    WHITESPACE | NEWLINE | COMMENT | COMMA | BRACKET_END | BRACE_END
+                )
+            {
+                let _ = self.add_token();
+            }
+        });
+        self.skip_whitespace = true;
+        if self.current_token == Some(WHITESPACE) {
+            let _ = self.token();
+        }
+        Err(())
+    }
 
-        if b == b'_' && !is_digit_byte(last_byte, radix) {
-            return false;
+    /// Whether the current token is the first one on its line.
+    fn at_line_start(&self) -> bool {
+        let before = &self.lexer.source()[..self.lexer.span().start];
+        let before = before.trim_end_matches([' ', '\t']);
+        before.is_empty() || before.ends_with('\n')
+    }
+
+    /// Whether the current token starts an entry, e.g. `key =` or `a.b`,
+    /// or a table header such as `[table]` instead of a value.
+    fn starts_entry_or_header(&self, token: SyntaxKind) -> bool {
+        let mut rest = Lexer::<SyntaxKind>::new(self.lexer.remainder())
+            .filter(|t| !matches!(t, Ok(WHITESPACE)))
+            .map(|t| t.unwrap_or(ERROR));
+        match token {
+            IDENT => matches!(rest.next(), Some(EQ | PERIOD)),
+            BRACKET_START => rest.find(|&t| t != BRACKET_START) == Some(IDENT),
+            _ => false,
         }
-        if !is_digit && last_byte == b'_' {
+    }
+
+    /// Skip the rest of the line after a malformed entry that started at `entry_start`.
+    ///
+    /// The skipped tokens are put in an `ERROR` node without reporting more errors,
+    /// unless the entry already ended at the start of another line, e.g. because
+    /// of an array that is not closed.
+    fn skip_line(&mut self, entry_start: usize) -> bool {
+        let Ok(token) = self.get_token() else {
+            return false;
+        };
+        if token == NEWLINE || (self.at_line_start() && self.lexer.span().start > entry_start) {
             return false;
         }
-        last_byte = b;
+
+        with_node!(self.builder, ERROR, {
+            while let Ok(token) = self.get_token()
+                && token != NEWLINE
+            {
+                let _ = self.token();
+            }
+        });
+        true
+    }
+}
+
+/// The leading zeros of a decimal integer or the integer part of a float,
+/// e.g. `00` of `-001`, `None` if there are none.
+fn zero_padding(int: &str) -> Option<Range<usize>> {
+    let sign = usize::from(int.starts_with(['+', '-']));
+    let digits = int[sign..].trim_end_matches('_');
+    if !digits.starts_with('0') {
+        return None;
+    }
+
+    // A zero itself is not padding, e.g. the last zero of `00`.
+    let unpadded = digits.trim_start_matches(['0', '_']);
+    let len = if unpadded.is_empty() { digits.len() - 1 } else { digits.len() - unpadded.len() };
+    (len > 0).then_some(sign..sign + len)
+}
+
+/// The span and the error message of the first invalid part of a time
+/// without the date, e.g. an hour `24` or fractional seconds without seconds.
+///
+/// The lexer only checks the shape of times, so every part is checked here.
+/// A leap second `60` is only allowed in the last minute of an hour.
+fn invalid_time_part(time: &str, max_fraction: Option<usize>) -> Option<(Range<usize>, String)> {
+    let bytes = time.as_bytes();
+    let number = |range: Range<usize>| time[range].parse::<u32>().unwrap_or_default();
+
+    if number(0..2) > 23 {
+        return Some((0..2, "hour must be between 00 and 23".into()));
+    }
+    let minute = number(3..5);
+    if minute > 59 {
+        return Some((3..5, "minute must be between 00 and 59".into()));
+    }
+
+    let mut end = 5;
+    let has_seconds = bytes.get(5) == Some(&b':');
+    if has_seconds {
+        match number(6..8) {
+            60 if minute != 59 => {
+                return Some((6..8, "leap seconds are only allowed at minute 59".into()));
+            }
+            61.. => return Some((6..8, "second must be between 00 and 60".into())),
+            _ => {}
+        }
+        end = 8;
+    }
+
+    if bytes.get(end) == Some(&b'.') {
+        let digits = bytes[end + 1..].iter().take_while(|b| b.is_ascii_digit()).count();
+        if !has_seconds {
+            return Some((end..end + 1 + digits, "fractional seconds require seconds".into()));
+        }
+        if let Some(max) = max_fraction
+            && digits > max
+        {
+            return Some((
+                end + 1 + max..end + 1 + digits,
+                format!("more than {max} digits of fractional seconds"),
+            ));
+        }
+        end += 1 + digits;
     }
 
-    true
+    if let Some(b'+' | b'-') = bytes.get(end) {
+        if number(end + 1..end + 3) > 23 {
+            return Some((end + 1..end + 3, "offset hour must be between 00 and 23".into()));
+        }
+        if number(end + 4..end + 6) > 59 {
+            return Some((end + 4..end + 6, "offset minute must be between 00 and 59".into()));
+        }
+    }
+    None
+}
+
+/// The spans of the underscores in a number that are not between two digits,
+/// consecutive underscores are reported together.
+fn invalid_underscores(s: &str, radix: u32) -> Vec<Range<usize>> {
+    let bytes = s.as_bytes();
+    let mut spans = Vec::new();
+    let mut idx = 0;
+    while let Some(offset) = bytes[idx..].iter().position(|&b| b == b'_') {
+        let start = idx + offset;
+        let end = start + bytes[start..].iter().take_while(|&&b| b == b'_').count();
+        let after_digit = start > 0 && is_digit_byte(bytes[start - 1], radix);
+        let before_digit = bytes.get(end).is_some_and(|&b| is_digit_byte(b, radix));
+        if end - start > 1 || !after_digit || !before_digit {
+            spans.push(start..end);
+        }
+        idx = end;
+    }
+    spans
+}
+
+/// Check whether `s` is an integer with an uppercase prefix, e.g. `0XFF`.
+fn is_uppercase_radix_prefix(s: &str) -> bool {
+    let radix = match s.as_bytes() {
+        [b'0', b'X', _, ..] => 16,
+        [b'0', b'O', _, ..] => 8,
+        [b'0', b'B', _, ..] => 2,
+        _ => return false,
+    };
+
+    s.bytes().skip(2).all(|b| b == b'_' || is_digit_byte(b, radix))
 }
 
 #[inline]
@@ -894,13 +1394,18 @@ fn is_digit_byte(b: u8, radix: u32) -> bool {
 /// the errors that occurred during parsing.
 #[derive(Debug, Clone)]
 pub struct Parse {
-    pub tree: SyntaxTree,
-    pub errors: Vec<Error>,
+    pub tree: SyntaxTree<'static>,
+    pub errors: Vec<ParseError>,
 }
 
 impl Parse {
+    /// The syntax errors in the order they were found.
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
     /// Turn the parse into a syntax tree.
-    pub fn into_syntax(self) -> SyntaxTree {
+    pub fn into_syntax(self) -> SyntaxTree<'static> {
         self.tree
     }
 }
@@ -0,0 +1,53 @@
+//! Options of the parser, see [`parse_with`](super::parse_with).
+
+/// The version of the TOML specification that documents are parsed with.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TomlVersion {
+    /// [TOML 1.0.0](https://toml.io/en/v1.0.0).
+    #[cfg_attr(feature = "serde", serde(rename = "1.0"))]
+    V1_0,
+
+    /// [TOML 1.1.0](https://toml.io/en/v1.1.0), which allows newlines
+    /// and trailing commas in inline tables among others.
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "1.1"))]
+    V1_1,
+}
+
+/// All the parsing options.
+///
+/// New options are added over time, start from [`ParseOptions::default`] to set them.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct ParseOptions {
+    /// The version of TOML that is accepted.
+    pub toml_version: TomlVersion,
+
+    /// Allow the `*` and `?` wildcards in bare keys, e.g. `dependencies.*`.
+    ///
+    /// This is not valid TOML, it is meant for documents with key patterns.
+    pub glob_keys: bool,
+
+    /// Report quoted keys that are empty or only contain whitespace, e.g. `" " = 1`.
+    ///
+    /// TOML allows them, but they are usually mistakes.
+    pub deny_blank_keys: bool,
+
+    /// Check the rules that the syntax doesn't cover, e.g. that keys are not defined twice.
+    ///
+    /// The errors have the [`ErrorCategory::Semantic`](super::ErrorCategory::Semantic) category.
+    pub validate: bool,
+
+    /// The maximum depth of nested arrays and inline tables, `None` for no limit.
+    ///
+    /// Values that are nested deeper are reported as errors and not parsed further,
+    /// which protects against stack overflows with untrusted documents.
+    pub max_depth: Option<usize>,
+
+    /// The maximum number of digits of fractional seconds, `None` for no limit.
+    ///
+    /// TOML allows any precision, but implementations truncate what they can't represent,
+    /// e.g. `Some(9)` reports the digits that would be lost with nanoseconds.
+    pub max_fractional_digits: Option<usize>,
+}
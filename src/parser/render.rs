@@ -0,0 +1,92 @@
+//! Rendering of errors with the source lines they point at.
+
+use std::fmt::Write;
+
+use super::{ErrorKind, ParseError};
+use crate::line_index::{ColumnEncoding, LineCol, LineIndex};
+
+impl ErrorKind {
+    /// A hint about how to fix errors of this kind.
+    const fn help(self) -> Option<&'static str> {
+        match self {
+            Self::InvalidEscape => Some(
+                r#"the escapes are \b \t \n \f \r \" \\ \uHHHH and \UHHHHHHHH, and \e and \xHH since TOML 1.1, use a literal string to avoid escaping"#,
+            ),
+            Self::InvalidNumber => {
+                Some("numbers can't have leading zeros and underscores must be between digits")
+            }
+            Self::InvalidCharacter => Some(
+                "control characters other than tab are only allowed as escapes in basic strings",
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// Render errors for a terminal, each with its code, the line it is on
+/// and the span underlined, e.g.
+///
+/// ```text
+/// error[E0006]: expected value
+///  --> Cargo.toml:1:5
+///   |
+/// 1 | a =
+///   |     ^
+/// ```
+///
+/// The `path` is shown before the line and column if it is given.
+pub fn render(source: &str, path: Option<&str>, errors: &[ParseError]) -> String {
+    let mut rendered = String::new();
+    let index = LineIndex::new(source);
+
+    for (idx, error) in errors.iter().enumerate() {
+        if idx != 0 {
+            rendered += "\n";
+        }
+
+        let start = (error.span.start as usize).min(source.len());
+        let end = (error.span.end as usize).clamp(start, source.len());
+        let position = index.line_col(start as u32, ColumnEncoding::Utf32);
+        let line_start = index
+            .offset(LineCol { column: 0, ..position }, ColumnEncoding::Utf8)
+            .unwrap_or_default() as usize;
+        let line_end = source[start..].find('\n').map_or(source.len(), |idx| start + idx);
+        let line = source[line_start..line_end].trim_end_matches('\r');
+        let line_number = position.line + 1;
+        let column = position.column + 1;
+
+        // Spans over several lines are underlined until the end of the first one.
+        let prefix = &source[line_start..start];
+        let underlined = &source[start..end.min(line_start + line.len()).max(start)];
+        let width = line_number.to_string().len();
+
+        let _ = writeln!(rendered, "error[{}]: {}", error.kind.code(), error.message);
+        let _ = match path {
+            Some(path) => writeln!(rendered, "{:width$}--> {path}:{line_number}:{column}", ""),
+            None => writeln!(rendered, "{:width$}--> {line_number}:{column}", ""),
+        };
+        let _ = writeln!(rendered, "{:width$} |", "");
+        let _ = writeln!(rendered, "{line_number} | {line}");
+        // Tabs are kept so the carets line up with the line above.
+        let padding =
+            prefix.chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect::<String>();
+        let carets = "^".repeat(underlined.chars().count().max(1));
+        let _ = writeln!(rendered, "{:width$} | {padding}{carets}", "");
+        if let Some(help) = error.kind.help() {
+            let _ = writeln!(rendered, "{:width$} = help: {help}", "");
+        }
+        if let Some(related) = &error.related {
+            let related =
+                index.line_col(related.start.min(source.len() as u32), ColumnEncoding::Utf32);
+            let _ = writeln!(
+                rendered,
+                "{:width$} = note: see also {}:{}",
+                "",
+                related.line + 1,
+                related.column + 1
+            );
+        }
+    }
+
+    rendered
+}
@@ -0,0 +1,311 @@
+//! Checks of the rules of TOML that the syntax doesn't cover, see [`ParseOptions::validate`](super::ParseOptions::validate).
+
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    num::IntErrorKind,
+};
+
+use super::{ErrorKind, ParseError};
+use crate::{
+    ast::key_part,
+    syntax::{SyntaxKind::*, SyntaxNode},
+    tree::{Element, TextRange},
+};
+
+/// Report the semantic errors of the document.
+pub(crate) fn validate(root: &SyntaxNode, source: &str, errors: &mut Vec<ParseError>) {
+    let mut validator = Validator { source, root: Item::new(ItemKind::Table, 0..0), errors };
+    validator.validate_root(root);
+}
+
+/// How a key was defined.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum ItemKind {
+    /// A value of an entry other than an inline table.
+    Value,
+
+    /// An inline table, it can't be extended.
+    InlineTable,
+
+    /// A table with a header.
+    Table,
+
+    /// A table that was created by the header of a subtable, it can be defined later.
+    ImplicitTable,
+
+    /// A table that was created by dotted keys.
+    DottedTable,
+
+    /// An array of tables, its items are the ones of the last table.
+    ArrayOfTables,
+}
+
+impl ItemKind {
+    /// Whether tables can be defined in it with headers.
+    const fn is_table(self) -> bool {
+        !matches!(self, Self::Value | Self::InlineTable)
+    }
+}
+
+/// A defined key and the keys defined in it.
+#[derive(Debug)]
+struct Item {
+    kind: ItemKind,
+
+    /// The span of the key that defined it.
+    span: TextRange,
+
+    items: HashMap<String, Item>,
+}
+
+impl Item {
+    fn new(kind: ItemKind, span: TextRange) -> Self {
+        Self { kind, span, items: HashMap::new() }
+    }
+}
+
+struct Validator<'a> {
+    source: &'a str,
+
+    /// The root table.
+    root: Item,
+
+    errors: &'a mut Vec<ParseError>,
+}
+
+impl Validator<'_> {
+    fn validate_root(&mut self, root: &SyntaxNode) {
+        // The key of the current table, `None` if its header is invalid.
+        let mut table = Some(Vec::new());
+        for node in root.children().iter().filter_map(Element::as_node) {
+            let Some(key) = key_node(node) else {
+                continue;
+            };
+
+            match node.kind() {
+                TABLE_HEADER => table = self.define_table(key, false),
+                TABLE_ARRAY_HEADER => table = self.define_table(key, true),
+                ENTRY => {
+                    if let Some(table) = &table {
+                        self.define_entry(table, node, key);
+                    }
+                    self.check_inline_tables(node);
+                    self.check_integers(node);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Define the table of a header, returns its key if it is valid.
+    fn define_table(&mut self, key: &SyntaxNode, array: bool) -> Option<Vec<String>> {
+        let parts = self.key(key);
+        let (last, parents) = parts.split_last()?;
+        let span = key_span(key);
+
+        let mut item = &mut self.root;
+        for (idx, part) in parents.iter().enumerate() {
+            item = item
+                .items
+                .entry(part.clone())
+                .or_insert_with(|| Item::new(ItemKind::ImplicitTable, span.clone()));
+            if !item.kind.is_table() {
+                self.errors.push(ParseError {
+                    span,
+                    message: format!("`{}` is already defined as a value", parts[..=idx].join(".")),
+                    kind: ErrorKind::DuplicateKey,
+                    related: Some(item.span.clone()),
+                });
+                return None;
+            }
+        }
+
+        let (kind, related) = match item.items.entry(last.clone()) {
+            Entry::Vacant(entry) => {
+                let kind = if array { ItemKind::ArrayOfTables } else { ItemKind::Table };
+                entry.insert(Item::new(kind, span));
+                return Some(parts);
+            }
+            Entry::Occupied(entry) => {
+                let item = entry.into_mut();
+                match (item.kind, array) {
+                    (ItemKind::ImplicitTable, false) => {
+                        item.kind = ItemKind::Table;
+                        item.span = span;
+                        return Some(parts);
+                    }
+                    (ItemKind::ArrayOfTables, true) => {
+                        // The next table of the array.
+                        item.items.clear();
+                        return Some(parts);
+                    }
+                    (kind, _) => (kind, Some(item.span.clone())),
+                }
+            }
+        };
+
+        self.errors.push(if kind.is_table() {
+            ParseError {
+                span,
+                message: format!("duplicate table `{}`", parts.join(".")),
+                kind: ErrorKind::DuplicateTable,
+                related,
+            }
+        } else {
+            ParseError {
+                span,
+                message: format!("`{}` is already defined as a value", parts.join(".")),
+                kind: ErrorKind::DuplicateKey,
+                related,
+            }
+        });
+        None
+    }
+
+    /// Define the value of an entry in the table with the given key.
+    fn define_entry(&mut self, table: &[String], entry: &SyntaxNode, key: &SyntaxNode) {
+        let parts = self.key(key);
+        let mut item = &mut self.root;
+        for part in table {
+            match item.items.get_mut(part) {
+                Some(table) => item = table,
+                None => return,
+            }
+        }
+        define_key(item, table, &parts, entry, key, self.errors);
+    }
+
+    /// Report the keys that are defined more than once in the inline tables of an entry,
+    /// the keys of each inline table are only compared with each other.
+    fn check_inline_tables(&mut self, entry: &SyntaxNode) {
+        let tables = entry
+            .descendants()
+            .filter_map(Element::as_node)
+            .filter(|node| node.kind() == INLINE_TABLE);
+        for table in tables {
+            let mut item = Item::new(ItemKind::InlineTable, table.span.clone());
+            for entry in table.children().iter().filter_map(Element::as_node) {
+                if entry.kind() != ENTRY {
+                    continue;
+                }
+                if let Some(key) = key_node(entry) {
+                    let parts = self.key(key);
+                    define_key(&mut item, &[], &parts, entry, key, self.errors);
+                }
+            }
+        }
+    }
+
+    /// Report the integers in the value of an entry that don't fit in an `i64`.
+    fn check_integers(&mut self, entry: &SyntaxNode) {
+        for token in entry.descendants().filter_map(Element::as_token) {
+            let radix = match token.kind() {
+                INTEGER => 10,
+                INTEGER_HEX => 16,
+                INTEGER_OCT => 8,
+                INTEGER_BIN => 2,
+                _ => continue,
+            };
+            let text = token.text(self.source);
+            let digits = text.get(2..).filter(|_| radix != 10).unwrap_or(text).replace('_', "");
+            if let Err(err) = i64::from_str_radix(&digits, radix)
+                && matches!(err.kind(), IntErrorKind::PosOverflow | IntErrorKind::NegOverflow)
+            {
+                self.errors.push(ParseError {
+                    span: token.span.clone(),
+                    message: format!("integer `{text}` is out of range for a 64-bit integer"),
+                    kind: ErrorKind::IntegerOutOfRange,
+                    related: None,
+                });
+            }
+        }
+    }
+
+    /// The unquoted and unescaped parts of a key.
+    fn key(&self, key: &SyntaxNode) -> Vec<String> {
+        key.children()
+            .iter()
+            .filter(|c| c.kind() == IDENT)
+            .map(|ident| key_part(ident.text(self.source)))
+            .collect()
+    }
+}
+
+/// Define the value of an entry with the key `parts` in `table`,
+/// whose own key is `path`, or report why the key can't be defined.
+fn define_key(
+    table: &mut Item,
+    path: &[String],
+    parts: &[String],
+    entry: &SyntaxNode,
+    key: &SyntaxNode,
+    errors: &mut Vec<ParseError>,
+) {
+    let Some((last, parents)) = parts.split_last() else {
+        return;
+    };
+    let span = key_span(key);
+    let full_key = |len: usize| {
+        path.iter().chain(&parts[..len]).map(String::as_str).collect::<Vec<_>>().join(".")
+    };
+
+    let mut item = table;
+    for (idx, part) in parents.iter().enumerate() {
+        item = item
+            .items
+            .entry(part.clone())
+            .or_insert_with(|| Item::new(ItemKind::DottedTable, span.clone()));
+        let (kind, message) = match item.kind {
+            ItemKind::DottedTable => continue,
+            ItemKind::Value | ItemKind::InlineTable => (
+                ErrorKind::DuplicateKey,
+                format!("`{}` is already defined as a value", full_key(idx + 1)),
+            ),
+            // Tables with headers can't be extended with dotted keys.
+            ItemKind::Table | ItemKind::ImplicitTable | ItemKind::ArrayOfTables => (
+                ErrorKind::DuplicateTable,
+                format!("table `{}` is defined by a header, not by dotted keys", full_key(idx + 1)),
+            ),
+        };
+        errors.push(ParseError { span, message, kind, related: Some(item.span.clone()) });
+        return;
+    }
+
+    match item.items.entry(last.clone()) {
+        Entry::Vacant(vacant) => {
+            let kind = if is_inline_table(entry) { ItemKind::InlineTable } else { ItemKind::Value };
+            vacant.insert(Item::new(kind, span));
+        }
+        Entry::Occupied(occupied) => {
+            errors.push(ParseError {
+                span,
+                message: format!("duplicate key `{}`", full_key(parts.len())),
+                kind: ErrorKind::DuplicateKey,
+                related: Some(occupied.get().span.clone()),
+            });
+        }
+    }
+}
+
+/// Whether the value of the entry is an inline table.
+fn is_inline_table(entry: &SyntaxNode) -> bool {
+    entry
+        .children()
+        .iter()
+        .filter_map(Element::as_node)
+        .filter(|n| n.kind() == VALUE)
+        .flat_map(|value| value.children())
+        .any(|c| c.kind() == INLINE_TABLE)
+}
+
+fn key_node(node: &SyntaxNode) -> Option<&SyntaxNode> {
+    node.children().iter().find_map(|c| c.as_node().filter(|n| n.kind() == KEY))
+}
+
+/// The span of a key without the whitespace around it.
+fn key_span(key: &SyntaxNode) -> TextRange {
+    let mut idents = key.children().iter().filter(|c| c.kind() == IDENT);
+    let start = idents.clone().next().map_or(key.span.start, |ident| ident.span().start);
+    let end = idents.next_back().map_or(key.span.end, |ident| ident.span().end);
+    start..end
+}
@@ -0,0 +1,124 @@
+//! Patches of documents, see [`DocumentMut::apply_patch`].
+
+use std::collections::BTreeMap;
+
+use crate::{
+    dom::{Document, Item, path_parts},
+    edit::{DocumentMut, EditError},
+    emit::key_text,
+    tree::SyntaxTree,
+    value::Value,
+};
+
+/// An operation of a patch, addressed by a path of keys, see [`DocumentMut::apply_patch`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOperation {
+    /// Set the value of a key, see [`DocumentMut::set`].
+    Set {
+        /// The parts of the key separated by periods.
+        path: String,
+        value: Value,
+    },
+
+    /// Remove a key that exists, see [`DocumentMut::remove`].
+    Remove {
+        /// The parts of the key separated by periods.
+        path: String,
+    },
+
+    /// Rename the last part of a key, see [`DocumentMut::rename`].
+    Rename {
+        /// The parts of the key separated by periods.
+        path: String,
+
+        /// The new name of the last part.
+        name: String,
+    },
+
+    /// Add a value at the end of an array, or a table at the end of an array of tables.
+    /// The array is created if the key doesn't exist.
+    Append {
+        /// The parts of the key separated by periods.
+        path: String,
+        value: Value,
+    },
+}
+
+/// An operation of a patch that failed, see [`DocumentMut::apply_patch`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PatchError {
+    /// The index of the operation in the patch.
+    pub index: usize,
+
+    /// The reason why it failed.
+    pub error: EditError,
+}
+
+impl core::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "operation {} of the patch failed: {}", self.index, self.error)
+    }
+}
+impl std::error::Error for PatchError {}
+
+impl DocumentMut {
+    /// Apply the operations of a patch in order, each one sees the changes of the ones before.
+    ///
+    /// The document is edited like with the methods of the operations, so the parts
+    /// that are not changed keep their layout. If an operation fails, the document is
+    /// left as it was before the patch.
+    pub fn apply_patch(&mut self, patch: &[PatchOperation]) -> Result<(), PatchError> {
+        let original = self.clone();
+        for (index, operation) in patch.iter().enumerate() {
+            if let Err(error) = self.apply_operation(operation) {
+                *self = original;
+                return Err(PatchError { index, error });
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_operation(&mut self, operation: &PatchOperation) -> Result<(), EditError> {
+        match operation {
+            PatchOperation::Set { path, value } => self.set(path, value),
+            PatchOperation::Remove { path } => {
+                let parts = path_parts(path).filter(|parts| !parts.is_empty());
+                let parts = parts.ok_or(EditError::InvalidPath)?;
+                match self.remove(path) {
+                    true => Ok(()),
+                    false => Err(EditError::Missing { path: parts.join(".") }),
+                }
+            }
+            PatchOperation::Rename { path, name } => self.rename(path, name),
+            PatchOperation::Append { path, value } => self.append(path, value),
+        }
+    }
+
+    fn append(&mut self, path: &str, value: &Value) -> Result<(), EditError> {
+        let parts = path_parts(path).filter(|parts| !parts.is_empty());
+        let parts = parts.ok_or(EditError::InvalidPath)?;
+
+        let tree = self.tree();
+        let document = Document::new(&tree);
+        match document.get(path) {
+            Some(Item::Array(_)) => {
+                self.array(path)?.push(value);
+                Ok(())
+            }
+            Some(Item::ArrayOfTables(_)) => {
+                if !matches!(value, Value::Table(_)) {
+                    return Err(EditError::NotATable { path: parts.join(".") });
+                }
+                // A document with a table of the array, grafted at the end of the parent.
+                let (parent, last) = parts.split_at(parts.len() - 1);
+                let table = BTreeMap::from([(last[0].clone(), Value::Array(vec![value.clone()]))]);
+                let tree = SyntaxTree::from_value(&Value::Table(table), self.options().clone());
+                let fragment = DocumentMut::parse(tree.source(), self.options().clone())
+                    .map_err(|_| EditError::InvalidDocument)?;
+                self.graft(&key_text(parent), &fragment).map(|_| ())
+            }
+            Some(_) => Err(EditError::NotAnArray { path: parts.join(".") }),
+            None => self.set(path, &Value::Array(vec![value.clone()])),
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Parsing only the part of a document that an edit changes, see [`SyntaxTree::reparse`].
+
+use crate::{
+    formatter::TextEdit,
+    parser::{ErrorCategory, Parse, ParseError, ParseOptions, parse_root, validate},
+    syntax::SyntaxKind::*,
+    tree::{Element, Node, SyntaxTree, TextRange, text_range},
+};
+
+/// The part of a document that [`SyntaxTree::reparse`] parsed again.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Reparse {
+    /// The span in the new source whose nodes were replaced.
+    pub range: TextRange,
+
+    /// The syntax errors in the range, the spans are in the new source.
+    ///
+    /// If the tree is parsed with [`ParseOptions::validate`], they are followed by the
+    /// semantic errors of the whole document, which an edit can change anywhere.
+    pub errors: Vec<ParseError>,
+}
+
+impl SyntaxTree<'_> {
+    /// Apply the edit to the source and update the tree like parsing the new source with the
+    /// options of the tree would, but only parse the table sections that the edit touches
+    /// again and shift the spans of the nodes after them.
+    ///
+    /// The whole document is parsed again if the edit changes how the rest of it is parsed,
+    /// e.g. if it opens a multi-line string or an array that is not closed.
+    /// A borrowed source is copied to apply the edit.
+    ///
+    /// # Panics
+    ///
+    /// If the range of the edit is not in the source or not on character boundaries.
+    pub fn reparse(&mut self, edit: TextEdit) -> Reparse {
+        let (start, end) = (edit.range.start as usize, edit.range.end as usize);
+        let old_len = self.source.len();
+        let sections = section_starts(&self.root);
+        // The section before the edit is not affected if the edit starts at its end,
+        // unless the line break at its end is joined with the next ones.
+        let first = edit.new_text.chars().next().or_else(|| self.source[end..].chars().next());
+        let joins_line_break = matches!(first, Some('\n' | '\r'));
+        let region_start = sections
+            .iter()
+            .copied()
+            .filter(|&idx| idx < start || idx == start && !joins_line_break)
+            .max()
+            .unwrap_or(0);
+        let region_end = sections
+            .iter()
+            .copied()
+            .find(|&idx| idx >= end && idx > region_start)
+            .unwrap_or(old_len);
+
+        // A quote can close a string that is left open before the region.
+        let closes_string = edit.new_text.contains(['"', '\'']) && {
+            let mut before =
+                self.root.children.iter().take_while(|c| c.span().end as usize <= region_start);
+            before.any(|child| {
+                is_open_string(child, &self.source)
+                    || child.as_node().is_some_and(|node| has_open_string(node, &self.source))
+            })
+        };
+
+        let mut source = std::mem::take(&mut self.source).into_owned();
+        source.replace_range(start..end, &edit.new_text);
+        let delta = source.len() as i64 - old_len as i64;
+        let new_end = (region_end as i64 + delta) as usize;
+
+        // The header of the next section is parsed with the region to check that it is
+        // still a header, e.g. that no array is left open before it.
+        let next_header = self.root.children.iter().find_map(|child| match child {
+            Element::Node(node) if node.span.start as usize == region_end => Some(node),
+            _ => None,
+        });
+        let checked_end = next_header.map_or(new_end, |header| {
+            let line_end = header_line_end(&self.root, header) as i64;
+            (line_end + delta) as usize
+        });
+
+        // The semantic errors are checked on the whole tree once it is updated.
+        let region_options = ParseOptions { validate: false, ..self.options };
+        let (region, errors) = parse_root(&source[region_start..checked_end], region_options);
+        let header_len = checked_end - new_end;
+        if closes_string
+            || !is_self_contained(&region, &source[region_start..checked_end], header_len)
+        {
+            let (root, errors) = parse_root(&source, self.options);
+            *self = SyntaxTree { root, source: source.into(), options: self.options };
+            return Reparse { range: text_range(0, self.source.len()), errors };
+        }
+
+        let offset = region_start as i64;
+        let region_len = (new_end - region_start) as u32;
+        let mut new_children = region.children;
+        // The header after the region is already in the tree.
+        new_children.retain(|child| header_len == 0 || child.span().start < region_len);
+        for child in &mut new_children {
+            shift_element(child, offset);
+        }
+
+        let children = std::mem::take(&mut self.root.children);
+        let (before, rest): (Vec<_>, Vec<_>) =
+            children.into_iter().partition(|child| (child.span().end as usize) <= region_start);
+        let mut after = rest
+            .into_iter()
+            .filter(|child| child.span().start as usize >= region_end)
+            .collect::<Vec<_>>();
+        for child in &mut after {
+            shift_element(child, delta);
+        }
+
+        self.root.children = before;
+        self.root.children.extend(new_children);
+        self.root.children.extend(after);
+        self.root.span = text_range(0, source.len());
+        self.source = source.into();
+
+        let mut errors = errors
+            .into_iter()
+            .filter(|err| header_len == 0 || err.span.start < region_len)
+            .map(|err| shift_error(err, offset))
+            .collect();
+        if self.options.validate {
+            validate::validate(&self.root, &self.source, &mut errors);
+        }
+        Reparse { range: text_range(region_start, new_end), errors }
+    }
+}
+
+impl Parse {
+    /// Apply the edit to the tree like [`SyntaxTree::reparse`] and update the errors,
+    /// the syntax errors of the parts that are not parsed again are kept with shifted spans.
+    /// The semantic errors are replaced with the ones of the whole updated document.
+    pub fn reparse(&mut self, edit: TextEdit) {
+        let old_len = self.tree.source.len() as i64;
+        let reparse = self.tree.reparse(edit);
+        let delta = self.tree.source.len() as i64 - old_len;
+        let (start, new_end) = (reparse.range.start, reparse.range.end);
+        let old_end = (new_end as i64 - delta) as u32;
+        let is_last = old_end as i64 == old_len;
+
+        let (semantic, syntax): (Vec<_>, Vec<_>) = reparse
+            .errors
+            .into_iter()
+            .partition(|err| err.kind.category() == ErrorCategory::Semantic);
+
+        let mut errors = Vec::with_capacity(self.errors.len());
+        let mut after = Vec::new();
+        for err in std::mem::take(&mut self.errors) {
+            if err.kind.category() == ErrorCategory::Semantic {
+                continue;
+            } else if err.span.start < start {
+                errors.push(err);
+            } else if err.span.start >= old_end && !is_last {
+                after.push(shift_error(err, delta));
+            }
+        }
+        errors.extend(syntax);
+        errors.extend(after);
+        errors.extend(semantic);
+        self.errors = errors;
+    }
+}
+
+/// The starts of the table sections, a section starts with the header of a table
+/// at the start of a line and ends where the next one starts.
+pub(crate) fn section_starts(root: &Node) -> Vec<usize> {
+    let mut starts = vec![0];
+    let mut line_start = true;
+    for child in &root.children {
+        match child.kind() {
+            TABLE_HEADER | TABLE_ARRAY_HEADER if line_start => {
+                starts.push(child.span().start as usize);
+            }
+            _ => {}
+        }
+        line_start = child.kind() == NEWLINE;
+    }
+    starts
+}
+
+/// The end of the line break after a header, or of the header if it is the last line.
+pub(crate) fn header_line_end(root: &Node, header: &Node) -> usize {
+    let mut children =
+        root.children.iter().skip_while(|child| child.span().start < header.span.end);
+    match children.next() {
+        Some(newline) if newline.kind() == NEWLINE => newline.span().end as usize,
+        _ => header.span.end as usize,
+    }
+}
+
+/// Whether the region parses the same on its own as in the document, which is the case
+/// if the header after it is still a header and no string is left open.
+fn is_self_contained(region: &Node, source: &str, header_len: usize) -> bool {
+    if has_open_string(region, source) {
+        return false;
+    }
+    if header_len == 0 {
+        return true;
+    }
+
+    let header_start = (source.len() - header_len) as u32;
+    let mut children = region.children.iter().rev();
+    let mut last = children.next();
+    if last.is_some_and(|last| last.kind() == NEWLINE) {
+        last = children.next();
+    }
+    let before = children.next();
+    last.is_some_and(|header| {
+        matches!(header.kind(), TABLE_HEADER | TABLE_ARRAY_HEADER)
+            && header.span().start == header_start
+    }) && before.is_none_or(|before| before.kind() == NEWLINE)
+}
+
+/// Whether the node contains a quote that doesn't start a string because it isn't closed.
+pub(crate) fn has_open_string(node: &Node, source: &str) -> bool {
+    node.descendants().any(|element| is_open_string(element, source))
+}
+
+fn is_open_string(element: &Element, source: &str) -> bool {
+    element.kind() == ERROR && element.text(source).starts_with(['"', '\''])
+}
+
+fn shift_range(range: &TextRange, delta: i64) -> TextRange {
+    (range.start as i64 + delta) as u32..(range.end as i64 + delta) as u32
+}
+
+fn shift_error(mut err: ParseError, delta: i64) -> ParseError {
+    err.span = shift_range(&err.span, delta);
+    err.related = err.related.map(|related| shift_range(&related, delta));
+    err
+}
+
+fn shift_element(element: &mut Element, delta: i64) {
+    match element {
+        Element::Node(node) => {
+            node.span = shift_range(&node.span, delta);
+            for child in &mut node.children {
+                shift_element(child, delta);
+            }
+        }
+        Element::Token(token) => token.span = shift_range(&token.span, delta),
+    }
+}
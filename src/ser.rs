@@ -0,0 +1,375 @@
+//! Serialization of documents with serde, see [`to_string`].
+
+use serde::ser::{self, Serialize};
+
+use crate::{
+    emit::{Node, float, write_document},
+    formatter::Options,
+    value::DATETIME_NAME,
+};
+
+/// Serialize a value as a TOML document formatted with the options.
+///
+/// The value must serialize as a table, e.g. a struct or a map.
+/// Its values are written before its tables, tables that only contain other tables
+/// don't get a header and `None` values are left out.
+pub fn to_string<T: Serialize + ?Sized>(
+    value: &T,
+    options: Options,
+) -> Result<String, SerializeError> {
+    let Node::Table(entries) = value.serialize(NodeSerializer)? else {
+        return Err(ser::Error::custom("only tables can be serialized as documents"));
+    };
+
+    Ok(crate::formatter::format(&write_document(&entries), options))
+}
+
+/// An error that can occur while serializing a value, see [`to_string`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SerializeError {
+    /// Human-friendly error message.
+    pub message: String,
+}
+
+impl core::fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+impl std::error::Error for SerializeError {}
+
+impl ser::Error for SerializeError {
+    fn custom<T: core::fmt::Display>(msg: T) -> Self {
+        Self { message: msg.to_string() }
+    }
+}
+
+/// Serializes a value into a [`Node`].
+struct NodeSerializer;
+
+impl ser::Serializer for NodeSerializer {
+    type Ok = Node;
+    type Error = SerializeError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+    type SerializeMap = TableSerializer;
+    type SerializeStruct = TableSerializer;
+    type SerializeStructVariant = VariantSerializer<TableSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<Node, SerializeError> {
+        Ok(Node::Value(v.to_string()))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Node, SerializeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Node, SerializeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Node, SerializeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Node, SerializeError> {
+        Ok(Node::Value(v.to_string()))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Node, SerializeError> {
+        let v = i64::try_from(v)
+            .map_err(|_| ser::Error::custom(format!("integer `{v}` is out of range")))?;
+        self.serialize_i64(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Node, SerializeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Node, SerializeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Node, SerializeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Node, SerializeError> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Node, SerializeError> {
+        let v = i64::try_from(v)
+            .map_err(|_| ser::Error::custom(format!("integer `{v}` is out of range")))?;
+        self.serialize_i64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Node, SerializeError> {
+        // Written with the digits of the `f32` rather than the ones of the `f64` it converts to.
+        if v.is_finite() { Ok(Node::Value(format!("{v:?}"))) } else { Ok(float(v.into())) }
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Node, SerializeError> {
+        Ok(float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Node, SerializeError> {
+        Ok(Node::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Node, SerializeError> {
+        Ok(Node::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Node, SerializeError> {
+        Ok(Node::Array(v.iter().map(|b| Node::Value(b.to_string())).collect()))
+    }
+
+    fn serialize_none(self) -> Result<Node, SerializeError> {
+        Ok(Node::None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Node, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Node, SerializeError> {
+        Err(ser::Error::custom("unit values can't be serialized"))
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Node, SerializeError> {
+        Err(ser::Error::custom(format!("unit struct `{name}` can't be serialized")))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Node, SerializeError> {
+        Ok(Node::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Node, SerializeError> {
+        match value.serialize(self)? {
+            // Date-times are written without quotes.
+            Node::String(s) if name == DATETIME_NAME => Ok(Node::Value(s)),
+            node => Ok(node),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Node, SerializeError> {
+        Ok(Node::Table(vec![(variant.to_string(), value.serialize(self)?)]))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, SerializeError> {
+        Ok(SeqSerializer { items: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<SeqSerializer>, SerializeError> {
+        Ok(VariantSerializer { variant, inner: self.serialize_seq(Some(len))? })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<TableSerializer, SerializeError> {
+        Ok(TableSerializer { entries: Vec::with_capacity(len.unwrap_or(0)), key: None })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<TableSerializer, SerializeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<TableSerializer>, SerializeError> {
+        Ok(VariantSerializer { variant, inner: self.serialize_map(Some(len))? })
+    }
+}
+
+struct SeqSerializer {
+    items: Vec<Node>,
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        match value.serialize(NodeSerializer)? {
+            Node::None => Err(ser::Error::custom("arrays can't contain `None`")),
+            node => {
+                self.items.push(node);
+                Ok(())
+            }
+        }
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        Ok(Node::Array(self.items))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct TableSerializer {
+    entries: Vec<(String, Node)>,
+
+    /// The last key of a map, its value is serialized next.
+    key: Option<String>,
+}
+
+impl TableSerializer {
+    fn insert(&mut self, key: String, node: Node) {
+        if !matches!(node, Node::None) {
+            self.entries.push((key, node));
+        }
+    }
+}
+
+impl ser::SerializeMap for TableSerializer {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        self.key = Some(match key.serialize(NodeSerializer)? {
+            Node::String(key) | Node::Value(key) => key,
+            _ => return Err(ser::Error::custom("keys must be strings, numbers or booleans")),
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let key = self.key.take().ok_or_else(|| ser::Error::custom("value without a key"))?;
+        let node = value.serialize(NodeSerializer)?;
+        self.insert(key, node);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        Ok(Node::Table(self.entries))
+    }
+}
+
+impl ser::SerializeStruct for TableSerializer {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        let node = value.serialize(NodeSerializer)?;
+        self.insert(key.to_string(), node);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// Serializes the data of a variant, it is written as a table with the variant as its key.
+struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        let node = ser::SerializeSeq::end(self.inner)?;
+        Ok(Node::Table(vec![(self.variant.to_string(), node)]))
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<TableSerializer> {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        let node = ser::SerializeMap::end(self.inner)?;
+        Ok(Node::Table(vec![(self.variant.to_string(), node)]))
+    }
+}
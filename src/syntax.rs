@@ -2,7 +2,7 @@
 
 #![allow(non_camel_case_types, clippy::upper_case_acronyms)]
 
-use crate::lexer::LexerToken;
+use crate::{lexer::LexerToken, util::scan};
 
 /// Enum containing all the tokens in a syntax tree.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -51,31 +51,21 @@ pub enum SyntaxKind {
 }
 
 // Type aliases for tree types
-pub use crate::tree::{Element as SyntaxElement, Node as SyntaxNode, Token as SyntaxToken};
+pub use crate::tree::{Node as SyntaxNode, Token as SyntaxToken};
 
 // Helper functions for lexing
 fn lex_string(input: &str) -> Option<usize> {
     let bytes = input.as_bytes();
-    let mut escaped = false;
     let mut i = 0;
 
-    while i < bytes.len() {
-        let b = bytes[i];
-
-        if b == b'\\' {
-            escaped = !escaped;
-            i += 1;
-            continue;
-        }
-
-        if b == b'"' && !escaped {
+    loop {
+        i += scan::find_either(b'"', b'\\', bytes.get(i..)?)?;
+        if bytes[i] == b'"' {
             return Some(i + 1);
         }
-
-        escaped = false;
-        i += 1;
+        // The escaped character can't end the string.
+        i += 2;
     }
-    None
 }
 
 fn lex_multi_line_string(input: &str) -> Option<usize> {
@@ -91,6 +81,16 @@ fn lex_multi_line_string(input: &str) -> Option<usize> {
     let mut quotes_found = false;
 
     while i < bytes.len() {
+        // Only quotes and backslashes matter until the closing quotes.
+        if !quotes_found {
+            let skipped = scan::find_either(b'"', b'\\', &bytes[i..]).unwrap_or(bytes.len() - i);
+            if skipped > 0 {
+                i += skipped;
+                quote_count = 0;
+                escaped = false;
+                continue;
+            }
+        }
         let b = bytes[i];
 
         if quotes_found {
@@ -137,14 +137,7 @@ fn lex_multi_line_string(input: &str) -> Option<usize> {
 }
 
 fn lex_string_literal(input: &str) -> Option<usize> {
-    let bytes = input.as_bytes();
-
-    for (i, &b) in bytes.iter().enumerate() {
-        if b == b'\'' {
-            return Some(i + 1);
-        }
-    }
-    None
+    scan::find(b'\'', input.as_bytes()).map(|i| i + 1)
 }
 
 fn lex_multi_line_string_literal(input: &str) -> Option<usize> {
@@ -159,6 +152,15 @@ fn lex_multi_line_string_literal(input: &str) -> Option<usize> {
     let mut quotes_found = false;
 
     while i < bytes.len() {
+        // Only quotes matter until the closing quotes.
+        if !quotes_found {
+            let skipped = scan::find(b'\'', &bytes[i..]).unwrap_or(bytes.len() - i);
+            if skipped > 0 {
+                i += skipped;
+                quote_count = 0;
+                continue;
+            }
+        }
         let b = bytes[i];
 
         if quotes_found {
@@ -200,8 +202,29 @@ const fn is_ident_char(b: u8) -> bool {
     b.is_ascii_alphanumeric() || b == b'_' || b == b'-'
 }
 
-const fn is_ident_with_glob_char(b: u8) -> bool {
-    is_ident_char(b) || b == b'*' || b == b'?'
+/// Whether the character can be in a bare key, including the non-ASCII characters
+/// that were proposed for TOML 1.1, so that such a key is a single token.
+/// The parser rejects those, neither TOML 1.0 nor TOML 1.1 allows them.
+///
+/// The byte order mark U+FEFF is left out, it is never part of a key.
+pub(crate) const fn is_bare_key_char(c: char) -> bool {
+    if c.is_ascii() {
+        return is_ident_char(c as u8);
+    }
+    matches!(c as u32,
+        0xB2 | 0xB3 | 0xB9 | 0xBC..=0xBE
+        | 0xC0..=0xD6 | 0xD8..=0xF6 | 0xF8..=0x37D | 0x37F..=0x1FFF
+        | 0x200C..=0x200D | 0x203F..=0x2040 | 0x2070..=0x218F | 0x2460..=0x24FF
+        | 0x2C00..=0x2FEF | 0x3001..=0xD7FF | 0xF900..=0xFDCF | 0xFDF0..=0xFEFE | 0xFF00..=0xFFFD
+        | 0x10000..=0xEFFFF)
+}
+
+const fn is_ident_with_glob_char(c: char) -> bool {
+    is_bare_key_char(c) || c == '*' || c == '?'
+}
+
+fn starts_with_ident_char(input: &str) -> bool {
+    input.chars().next().is_some_and(is_bare_key_char)
 }
 
 const fn is_hex_digit(b: u8) -> bool {
@@ -210,8 +233,8 @@ const fn is_hex_digit(b: u8) -> bool {
 
 /// A digit-led run followed by an identifier character is a bare key, not a
 /// number — same word-boundary rule as `true`/`nan` above.
-fn extends_into_ident(bytes: &[u8], len: usize, first: u8) -> bool {
-    first.is_ascii_digit() && bytes.get(len).is_some_and(|&b| is_ident_char(b))
+fn extends_into_ident(input: &str, len: usize, first: u8) -> bool {
+    first.is_ascii_digit() && starts_with_ident_char(&input[len..])
 }
 
 // Lexer implementation for SyntaxKind
@@ -236,8 +259,7 @@ impl<'source> LexerToken<'source> for SyntaxKind {
 
         // Whitespace
         if is_whitespace(first) {
-            let len = bytes.iter().take_while(|&&b| is_whitespace(b)).count();
-            return Some((SyntaxKind::WHITESPACE, len));
+            return Some((SyntaxKind::WHITESPACE, scan::whitespace_len(bytes)));
         }
 
         // Newline
@@ -259,7 +281,7 @@ impl<'source> LexerToken<'source> for SyntaxKind {
 
         // Comment
         if first == b'#' {
-            let len = bytes.iter().take_while(|&&b| b != b'\n' && b != b'\r').count();
+            let len = scan::find_either(b'\n', b'\r', bytes).unwrap_or(bytes.len());
             return Some((SyntaxKind::COMMENT, len));
         }
 
@@ -293,18 +315,18 @@ impl<'source> LexerToken<'source> for SyntaxKind {
 
         // Boolean
         // Bool keywords - must check word boundaries
-        if input.starts_with("true") {
+        if let Some(rest) = input.strip_prefix("true") {
             // Check that it's not followed by identifier characters (word boundary check)
-            if bytes.len() > 4 && is_ident_char(bytes[4]) {
+            if starts_with_ident_char(rest) {
                 // Part of a longer identifier like "true_value", not a bool keyword
                 // Fall through to identifier lexing
             } else {
                 return Some((SyntaxKind::BOOL, 4));
             }
         }
-        if input.starts_with("false") {
+        if let Some(rest) = input.strip_prefix("false") {
             // Check that it's not followed by identifier characters (word boundary check)
-            if bytes.len() > 5 && is_ident_char(bytes[5]) {
+            if starts_with_ident_char(rest) {
                 // Part of a longer identifier like "false_alarm", not a bool keyword
                 // Fall through to identifier lexing
             } else {
@@ -317,7 +339,7 @@ impl<'source> LexerToken<'source> for SyntaxKind {
         if input.starts_with("nan") || input.starts_with("+nan") || input.starts_with("-nan") {
             let len = if first == b'+' || first == b'-' { 4 } else { 3 };
             // Check that it's not followed by identifier characters (word boundary check)
-            if len < bytes.len() && is_ident_char(bytes[len]) {
+            if starts_with_ident_char(&input[len..]) {
                 // Part of a longer identifier like "nan_value", not a float keyword
                 // Fall through to identifier lexing
             } else {
@@ -327,7 +349,7 @@ impl<'source> LexerToken<'source> for SyntaxKind {
         if input.starts_with("inf") || input.starts_with("+inf") || input.starts_with("-inf") {
             let len = if first == b'+' || first == b'-' { 4 } else { 3 };
             // Check that it's not followed by identifier characters (word boundary check)
-            if len < bytes.len() && is_ident_char(bytes[len]) {
+            if starts_with_ident_char(&input[len..]) {
                 // Part of a longer identifier like "infinity", not a float keyword
                 // Fall through to identifier lexing
             } else {
@@ -339,7 +361,7 @@ impl<'source> LexerToken<'source> for SyntaxKind {
         if first.is_ascii_digit() || first == b'+' || first == b'-' {
             // Try date/time first (they are more specific)
             if let Some((kind, len)) = try_lex_datetime(input)
-                && !extends_into_ident(bytes, len, first)
+                && !extends_into_ident(input, len, first)
             {
                 return Some((kind, len));
             }
@@ -348,7 +370,7 @@ impl<'source> LexerToken<'source> for SyntaxKind {
             if bytes.len() >= 2 && bytes[0] == b'0' && bytes[1] == b'x' {
                 let len =
                     2 + bytes[2..].iter().take_while(|&&b| is_hex_digit(b) || b == b'_').count();
-                if len > 2 && !extends_into_ident(bytes, len, first) {
+                if len > 2 && !extends_into_ident(input, len, first) {
                     return Some((SyntaxKind::INTEGER_HEX, len));
                 }
             }
@@ -357,7 +379,7 @@ impl<'source> LexerToken<'source> for SyntaxKind {
                     .iter()
                     .take_while(|&&b| (b'0'..=b'7').contains(&b) || b == b'_')
                     .count();
-                if len > 2 && !extends_into_ident(bytes, len, first) {
+                if len > 2 && !extends_into_ident(input, len, first) {
                     return Some((SyntaxKind::INTEGER_OCT, len));
                 }
             }
@@ -366,29 +388,32 @@ impl<'source> LexerToken<'source> for SyntaxKind {
                     .iter()
                     .take_while(|&&b| b == b'0' || b == b'1' || b == b'_')
                     .count();
-                if len > 2 && !extends_into_ident(bytes, len, first) {
+                if len > 2 && !extends_into_ident(input, len, first) {
                     return Some((SyntaxKind::INTEGER_BIN, len));
                 }
             }
 
             // Try float or integer
             if let Some((kind, len)) = try_lex_number(input)
-                && !extends_into_ident(bytes, len, first)
+                && !extends_into_ident(input, len, first)
             {
                 return Some((kind, len));
             }
         }
 
         // Identifier (lower priority than keywords)
-        if first.is_ascii_alphanumeric() || first == b'_' || first == b'-' {
-            let len = bytes.iter().take_while(|&&b| is_ident_char(b)).count();
-            return Some((SyntaxKind::IDENT, len));
-        }
-
         // Identifier with glob
-        if first == b'*' || first == b'?' {
-            let len = bytes.iter().take_while(|&&b| is_ident_with_glob_char(b)).count();
-            return Some((SyntaxKind::IDENT_WITH_GLOB, len));
+        if input.chars().next().is_some_and(is_ident_with_glob_char) {
+            // Most keys are ASCII, the other characters are checked one by one after them.
+            let ascii = scan::ascii_ident_len(bytes);
+            let len = input[ascii..]
+                .char_indices()
+                .find(|&(_, c)| !is_ident_with_glob_char(c))
+                .map_or(input.len(), |(idx, _)| ascii + idx);
+            if input[..len].contains(['*', '?']) {
+                return Some((SyntaxKind::IDENT_WITH_GLOB, len));
+            }
+            return Some((SyntaxKind::IDENT, len));
         }
 
         None
@@ -545,32 +570,19 @@ fn try_match_date(input: &str) -> Option<usize> {
 fn try_match_time(input: &str) -> Option<usize> {
     let bytes = input.as_bytes();
 
-    // HH:MM:SS with validation
-    let &[
-        h1 @ b'0'..=b'9',
-        h0 @ b'0'..=b'9',
-        b':',
-        m1 @ b'0'..=b'9',
-        m0 @ b'0'..=b'9',
-        b':',
-        s1 @ b'0'..=b'9',
-        s0 @ b'0'..=b'9',
-    ] = bytes.first_chunk()?
-    else {
+    // HH:MM, the ranges of the parts are checked by the parser
+    let &[b'0'..=b'9', b'0'..=b'9', b':', b'0'..=b'9', b'0'..=b'9'] = bytes.first_chunk()? else {
         return None;
     };
 
-    let hour = (h1 - b'0') as u32 * 10 + (h0 - b'0') as u32;
-    let minute = (m1 - b'0') as u32 * 10 + (m0 - b'0') as u32;
-    let second = (s1 - b'0') as u32 * 10 + (s0 - b'0') as u32;
-
-    // Validate ranges: hour 00-23, minute 00-59, second 00-59
-    if hour > 23 || minute > 59 || second > 59 {
-        return None;
+    // Seconds are optional since TOML 1.1.0, the parser requires them for TOML 1.0
+    let mut len = 5;
+    if let Some(&[b':', b'0'..=b'9', b'0'..=b'9']) = bytes[5..].first_chunk() {
+        len = 8;
     }
 
-    let mut len = 8;
-    // Optional fractional seconds (TOML 1.1.0 only allows '.' not ',')
+    // Optional fractional seconds (TOML 1.1.0 only allows '.' not ','),
+    // the parser reports them without seconds
     if len < bytes.len() && bytes[len] == b'.' {
         let frac_start = len + 1;
         len += 1;
@@ -586,26 +598,12 @@ fn try_match_time(input: &str) -> Option<usize> {
 }
 
 fn try_match_timezone(input: &str) -> Option<usize> {
-    // +HH:MM or -HH:MM with validation
-    let &[
-        b'+' | b'-',
-        h1 @ b'0'..=b'9',
-        h0 @ b'0'..=b'9',
-        b':',
-        m1 @ b'0'..=b'9',
-        m0 @ b'0'..=b'9',
-    ] = input.as_bytes().first_chunk()?
+    // +HH:MM or -HH:MM, the ranges of the parts are checked by the parser
+    let &[b'+' | b'-', b'0'..=b'9', b'0'..=b'9', b':', b'0'..=b'9', b'0'..=b'9'] =
+        input.as_bytes().first_chunk()?
     else {
         return None;
     };
 
-    let hour = (h1 - b'0') as u32 * 10 + (h0 - b'0') as u32;
-    let minute = (m1 - b'0') as u32 * 10 + (m0 - b'0') as u32;
-
-    // Validate timezone offset: hour 00-23, minute 00-59
-    if hour > 23 || minute > 59 {
-        return None;
-    }
-
     Some(6)
 }
@@ -3,8 +3,8 @@
 //! This module provides a custom tree structure that replaces Rowan,
 //! optimized specifically for TOML formatting needs.
 
-use crate::syntax::SyntaxKind;
-use std::ops::Range;
+use crate::{parser::ParseOptions, syntax::SyntaxKind};
+use std::{borrow::Cow, ops::Range};
 
 /// Byte offsets into the source. Limited to 4 GiB of source text.
 pub type TextRange = Range<u32>;
@@ -14,11 +14,14 @@ pub const fn text_range(start: usize, end: usize) -> TextRange {
     start as u32..end as u32
 }
 
-/// A complete syntax tree with source text
+/// A complete syntax tree with source text, which is borrowed if the tree is parsed
+/// with [`parse_borrowed`](crate::parse_borrowed) and owned otherwise.
 #[derive(Debug, Clone)]
-pub struct SyntaxTree {
+pub struct SyntaxTree<'src> {
     pub root: Node,
-    pub source: String,
+    pub source: Cow<'src, str>,
+    /// The options the tree was parsed with, [`SyntaxTree::reparse`] parses with them again.
+    pub options: ParseOptions,
 }
 
 /// A syntax tree node (e.g., ENTRY, TABLE_HEADER, etc.)
@@ -180,6 +183,66 @@ impl<'a> Iterator for DescendantsIter<'a> {
     }
 }
 
+/// A part of the source that couldn't be parsed, see [`SyntaxTree::error_nodes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorNode<'a> {
+    /// The span of the `ERROR` node or token.
+    pub span: TextRange,
+
+    /// The text that couldn't be parsed.
+    pub text: &'a str,
+
+    /// The kind of the node that contains it, e.g. `ENTRY` or `ARRAY`.
+    pub parent: SyntaxKind,
+
+    /// The span of the entry or table header that contains it,
+    /// the line of the error if it is directly in the root.
+    pub context: TextRange,
+}
+
+fn collect_errors<'a>(
+    node: &Node,
+    context: Option<&TextRange>,
+    source: &'a str,
+    errors: &mut Vec<ErrorNode<'a>>,
+) {
+    let context = match node.kind {
+        SyntaxKind::ENTRY | SyntaxKind::TABLE_HEADER | SyntaxKind::TABLE_ARRAY_HEADER => {
+            Some(context.unwrap_or(&node.span))
+        }
+        _ => context,
+    };
+
+    for child in &node.children {
+        if child.kind() == SyntaxKind::ERROR {
+            let span = child.text_range();
+            let context = context.cloned().unwrap_or_else(|| {
+                let start = source[..span.start as usize].rfind('\n').map_or(0, |idx| idx + 1);
+                let end = source[span.end as usize..]
+                    .find('\n')
+                    .map_or(source.len(), |idx| span.end as usize + idx);
+                text_range(start, end)
+            });
+            errors.push(ErrorNode { text: child.text(source), span, parent: node.kind, context });
+        } else if let Element::Node(child) = child {
+            collect_errors(child, context, source, errors);
+        }
+    }
+}
+
+/// Where the parser writes the nodes and tokens of a document in the order of the source.
+///
+/// [`TreeBuilder`] builds a [`SyntaxTree`] from them, and
+/// [`parse_events`](crate::parse_events) records them without building a tree.
+pub(crate) trait TreeSink {
+    fn start_node(&mut self, kind: SyntaxKind);
+
+    /// Add a token from the end of the previous one to the end offset in the source.
+    fn token(&mut self, kind: SyntaxKind, end: usize);
+
+    fn finish_node(&mut self);
+}
+
 /// Builder for constructing a syntax tree during parsing
 pub struct TreeBuilder {
     stack: Vec<NodeBuilder>,
@@ -192,13 +255,8 @@ struct NodeBuilder {
     children: Vec<Element>,
 }
 
-impl TreeBuilder {
-    pub fn new() -> Self {
-        // TOML nesting is shallow; 8 is enough to avoid reallocation in typical files.
-        Self { stack: Vec::with_capacity(8), current_pos: 0 }
-    }
-
-    pub fn start_node(&mut self, kind: SyntaxKind) {
+impl TreeSink for TreeBuilder {
+    fn start_node(&mut self, kind: SyntaxKind) {
         // Pre-size children based on the node kind to avoid the typical 0->4 growth.
         let cap = match kind {
             SyntaxKind::KEY | SyntaxKind::VALUE => 1,
@@ -209,8 +267,8 @@ impl TreeBuilder {
         self.stack.push(NodeBuilder { kind, start: self.current_pos, children });
     }
 
-    pub fn token(&mut self, kind: SyntaxKind, text: &str) {
-        let end = self.current_pos + text.len();
+    fn token(&mut self, kind: SyntaxKind, end: usize) {
+        debug_assert!(end >= self.current_pos, "tokens must be added in the order of the source");
         let token = Token { kind, span: self.current_pos as u32..end as u32 };
 
         if let Some(parent) = self.stack.last_mut() {
@@ -220,7 +278,7 @@ impl TreeBuilder {
         self.current_pos = end;
     }
 
-    pub fn finish_node(&mut self) {
+    fn finish_node(&mut self) {
         let builder = self.stack.pop().expect("finish_node called without start_node");
         let node = Node {
             kind: builder.kind,
@@ -239,6 +297,13 @@ impl TreeBuilder {
             });
         }
     }
+}
+
+impl TreeBuilder {
+    pub fn new() -> Self {
+        // TOML nesting is shallow; 8 is enough to avoid reallocation in typical files.
+        Self { stack: Vec::with_capacity(8), current_pos: 0 }
+    }
 
     /// Finalize the tree by returning the root node. The source is supplied separately
     /// so the builder does not need to own a copy during parsing.
@@ -253,7 +318,7 @@ impl TreeBuilder {
     }
 }
 
-impl SyntaxTree {
+impl SyntaxTree<'_> {
     /// Get the root node
     pub const fn root(&self) -> &Node {
         &self.root
@@ -263,4 +328,54 @@ impl SyntaxTree {
     pub fn source(&self) -> &str {
         &self.source
     }
+
+    /// The `ERROR` nodes and tokens in the order of the source, they hold the parts
+    /// that couldn't be parsed. The rest of the tree is valid and still contains
+    /// every other part of the source.
+    pub fn error_nodes(&self) -> Vec<ErrorNode<'_>> {
+        let mut errors = Vec::new();
+        collect_errors(&self.root, None, &self.source, &mut errors);
+        errors
+    }
+
+    /// The token at a byte offset, the one that starts at the offset if it is between two tokens
+    /// and the last one if it is the end of the source. `None` for empty documents.
+    pub fn element_at(&self, offset: u32) -> Option<&Element> {
+        let mut node = &self.root;
+        loop {
+            let child = node
+                .children
+                .iter()
+                .find(|child| child.span().contains(&offset))
+                .or_else(|| node.children.last().filter(|child| child.span().end == offset))?;
+            match child {
+                Element::Node(child) => node = child,
+                Element::Token(_) => return Some(child),
+            }
+        }
+    }
+
+    /// The smallest node that contains the whole range, the root if no other node does.
+    pub fn covering_node(&self, range: TextRange) -> &Node {
+        let mut node = &self.root;
+        while let Some(child) = node
+            .children
+            .iter()
+            .filter_map(Element::as_node)
+            .find(|child| child.span.start <= range.start && range.end <= child.span.end)
+        {
+            node = child;
+        }
+        node
+    }
+
+    /// The tree with a copy of the source if it is borrowed, e.g. to keep it
+    /// after the source is dropped.
+    pub fn into_owned(self) -> SyntaxTree<'static> {
+        SyntaxTree {
+            root: self.root,
+            source: Cow::Owned(self.source.into_owned()),
+            options: self.options,
+        }
+    }
 }
@@ -1,4 +1,8 @@
-use crate::lexer::{Lexer, LexerToken};
+use crate::{
+    lexer::{Lexer, LexerToken},
+    parser::TomlVersion,
+    tree::{TextRange, text_range},
+};
 
 /// Escaping based on:
 ///
@@ -7,8 +11,10 @@ use crate::lexer::{Lexer, LexerToken};
 /// \n         - linefeed        (U+000A)
 /// \f         - form feed       (U+000C)
 /// \r         - carriage return (U+000D)
+/// \e         - escape          (U+001B), since TOML 1.1
 /// \"         - quote           (U+0022)
 /// \\         - backslash       (U+005C)
+/// \xXX       - unicode         (U+00XX), since TOML 1.1
 /// \uXXXX     - unicode         (U+XXXX)
 /// \UXXXXXXXX - unicode         (U+XXXXXXXX)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -19,10 +25,14 @@ pub enum Escape {
     LineFeed,
     FormFeed,
     CarriageReturn,
+    Esc,
     Quote,
     Backslash,
+    Hex,
     Unicode,
     UnicodeLarge,
+    /// `\x`, `\u` or `\U` with fewer hex digits than it needs.
+    Truncated,
     Unknown,
     UnEscaped,
 }
@@ -45,6 +55,9 @@ impl<'source> LexerToken<'source> for Escape {
         if input.starts_with("\\r") {
             return Some((CarriageReturn, 2));
         }
+        if input.starts_with("\\e") {
+            return Some((Esc, 2));
+        }
         if input.starts_with("\\\"") {
             return Some((Quote, 2));
         }
@@ -57,27 +70,20 @@ impl<'source> LexerToken<'source> for Escape {
             let bytes = rest.as_bytes();
             let ws_len = bytes.iter().take_while(|&&b| b == b' ' || b == b'\t').count();
             let after_ws = &rest[ws_len..];
-            if after_ws.starts_with('\n') {
-                return Some((Newline, 1 + ws_len + 1));
-            }
-            if after_ws.starts_with("\r\n") {
-                return Some((Newline, 1 + ws_len + 2));
-            }
-        }
-
-        // Unicode escape \uXXXX
-        if input.starts_with("\\u") && input.len() >= 6 {
-            let hex_bytes = &input.as_bytes()[2..6];
-            if hex_bytes.iter().all(|&b| b.is_ascii_hexdigit() || b == b'_') {
-                return Some((Unicode, 6));
+            if after_ws.starts_with('\n') || after_ws.starts_with("\r\n") {
+                // The whitespace and line breaks up to the next character are trimmed too.
+                let trimmed = after_ws.trim_start_matches([' ', '\t', '\n', '\r']);
+                return Some((Newline, input.len() - trimmed.len()));
             }
         }
 
-        // Unicode escape \UXXXXXXXX
-        if input.starts_with("\\U") && input.len() >= 10 {
-            let hex_bytes = &input.as_bytes()[2..10];
-            if hex_bytes.iter().all(|&b| b.is_ascii_hexdigit() || b == b'_') {
-                return Some((UnicodeLarge, 10));
+        // Hex escape \xXX and Unicode escapes \uXXXX and \UXXXXXXXX
+        for (prefix, escape, digits) in
+            [("\\x", Hex, 2), ("\\u", Unicode, 4), ("\\U", UnicodeLarge, 8)]
+        {
+            if let Some(rest) = input.strip_prefix(prefix) {
+                let len = rest.bytes().take(digits).take_while(u8::is_ascii_hexdigit).count();
+                return Some(if len == digits { (escape, 2 + len) } else { (Truncated, 2 + len) });
             }
         }
 
@@ -91,53 +97,141 @@ impl<'source> LexerToken<'source> for Escape {
     }
 }
 
-/// Same as unescape, but doesn't create a new
-/// unescaped string, and returns all invalid escape indices.
-pub fn check_escape(s: &str) -> Result<(), Vec<usize>> {
+/// An invalid escape sequence in the contents of a basic string, see [`validate_escapes`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EscapeError {
+    /// The span of the escape in the contents, from the backslash to its end.
+    pub span: TextRange,
+
+    /// What is wrong with the escape.
+    pub kind: EscapeErrorKind,
+}
+
+/// The reason of an [`EscapeError`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum EscapeErrorKind {
+    /// A backslash followed by a character that is not an escape, e.g. `\q`.
+    Unknown,
+
+    /// A `\U` escape of a value past the last Unicode scalar value, e.g. `\U00110000`.
+    InvalidScalar,
+
+    /// A `\u` or `\U` escape of a surrogate, `D800` to `DFFF`, which is not a character.
+    Surrogate,
+
+    /// A `\xHH`, `\uHHHH` or `\UHHHHHHHH` escape with too few hex digits, e.g. `\u12`.
+    Truncated,
+
+    /// An escape that was added in a later TOML version, `\e` or `\xHH` before TOML 1.1.
+    NewerVersion,
+}
+
+impl EscapeErrorKind {
+    pub(crate) const fn message(self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown escape sequence",
+            Self::InvalidScalar => "escape of a value that is not a Unicode character",
+            Self::Surrogate => "escape of a surrogate, which is not a Unicode character",
+            Self::Truncated => "escape sequence with too few hex digits",
+            Self::NewerVersion => r"`\e` and `\xHH` escapes require TOML 1.1",
+        }
+    }
+}
+
+impl core::fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({:?})", self.kind.message(), self.span)
+    }
+}
+impl std::error::Error for EscapeError {}
+
+/// The character of the current escape token, `Ok(None)` if it has none like a line ending backslash.
+fn escaped_char(lexer: &Lexer<Escape>, escape: Escape) -> Result<Option<char>, EscapeErrorKind> {
+    let c = match escape {
+        Backspace => '\u{0008}',
+        Tab => '\t',
+        LineFeed => '\n',
+        FormFeed => '\u{000C}',
+        CarriageReturn => '\r',
+        Esc => '\u{001B}',
+        Quote => '"',
+        Backslash => '\\',
+        Newline | UnEscaped => return Ok(None),
+        Hex | Unicode | UnicodeLarge => {
+            let value = u32::from_str_radix(&lexer.slice()[2..], 16)
+                .map_err(|_| EscapeErrorKind::InvalidScalar)?;
+            match char::from_u32(value) {
+                Some(c) => c,
+                None if (0xD800..=0xDFFF).contains(&value) => {
+                    return Err(EscapeErrorKind::Surrogate);
+                }
+                None => return Err(EscapeErrorKind::InvalidScalar),
+            }
+        }
+        Truncated => return Err(EscapeErrorKind::Truncated),
+        Unknown => return Err(EscapeErrorKind::Unknown),
+    };
+    Ok(Some(c))
+}
+
+/// All the invalid escape sequences in the contents of a basic string without the quotes.
+///
+/// The escapes that were added in TOML 1.1, `\e` and `\xHH`, are invalid in TOML 1.0.
+pub fn validate_escapes(s: &str, version: TomlVersion) -> Vec<EscapeError> {
+    let mut lexer: Lexer<Escape> = Lexer::new(s);
+    let mut errors = Vec::new();
+
+    while let Some(t) = lexer.next() {
+        let t = t.unwrap_or(UnEscaped);
+        let result = if matches!(t, Esc | Hex) && version < TomlVersion::V1_1 {
+            Err(EscapeErrorKind::NewerVersion)
+        } else {
+            escaped_char(&lexer, t)
+        };
+        if let Err(kind) = result {
+            errors
+                .push(EscapeError { span: text_range(lexer.span().start, lexer.span().end), kind });
+        }
+    }
+
+    errors
+}
+
+/// Unescape the contents of a basic string without the quotes.
+///
+/// Returns all the invalid escapes on failure, like [`validate_escapes`].
+pub fn unescape(s: &str) -> Result<String, Vec<EscapeError>> {
     let mut lexer: Lexer<Escape> = Lexer::new(s);
-    let mut invalid = Vec::new();
+    let mut unescaped = String::with_capacity(s.len());
+    let mut errors = Vec::new();
 
     while let Some(t) = lexer.next() {
         let t = t.unwrap_or(UnEscaped);
-        match t {
-            Backspace => {}
-            Tab => {}
-            LineFeed => {}
-            FormFeed => {}
-            CarriageReturn => {}
-            Quote => {}
-            Backslash => {}
-            Newline => {}
-            Unicode => {
-                let Ok(char_val) = u32::from_str_radix(&lexer.slice()[2..], 16) else {
-                    invalid.push(lexer.span().start);
-                    continue;
-                };
-
-                match std::char::from_u32(char_val) {
-                    None => {
-                        invalid.push(lexer.span().start);
-                    }
-                    Some(_) => {}
-                };
-            }
-            UnicodeLarge => {
-                let Ok(char_val) = u32::from_str_radix(&lexer.slice()[2..], 16) else {
-                    invalid.push(lexer.span().start);
-                    continue;
-                };
-
-                match std::char::from_u32(char_val) {
-                    None => {
-                        invalid.push(lexer.span().start);
-                    }
-                    Some(_) => {}
-                };
-            }
-            Unknown => invalid.push(lexer.span().start),
-            UnEscaped => {}
+        match escaped_char(&lexer, t) {
+            Ok(Some(c)) => unescaped.push(c),
+            Ok(None) if t == UnEscaped => unescaped.push_str(lexer.slice()),
+            Ok(None) => {}
+            Err(kind) => errors
+                .push(EscapeError { span: text_range(lexer.span().start, lexer.span().end), kind }),
         }
     }
 
-    if invalid.is_empty() { Ok(()) } else { Err(invalid) }
+    if errors.is_empty() { Ok(unescaped) } else { Err(errors) }
+}
+
+/// Write the contents of a basic string with the escapes that were added in TOML 1.1
+/// replaced by the equivalent `\uXXXX` escapes.
+pub(crate) fn write_portable_escapes(s: &str, out: &mut String) {
+    let mut lexer: Lexer<Escape> = Lexer::new(s);
+    while let Some(t) = lexer.next() {
+        match t.unwrap_or(UnEscaped) {
+            Esc => out.push_str("\\u001B"),
+            Hex => {
+                out.push_str("\\u00");
+                out.push_str(&lexer.slice()[2..]);
+            }
+            _ => out.push_str(lexer.slice()),
+        }
+    }
 }
@@ -1,8 +1,10 @@
 use crate::tree::TextRange;
 
 mod escape;
+pub(crate) mod scan;
 
-pub use escape::check_escape;
+pub(crate) use escape::write_portable_escapes;
+pub use escape::{EscapeError, EscapeErrorKind, unescape, validate_escapes};
 
 pub(crate) mod allowed_chars {
     pub(crate) fn comment(s: &str) -> Result<(), Vec<usize>> {
@@ -40,10 +42,10 @@ pub(crate) mod allowed_chars {
         let bytes = s.as_bytes();
 
         for (i, &b) in bytes.iter().enumerate() {
-            // Tab (0x09), LF (0x0A), CR (0x0D) are allowed
+            // Tab (0x09), LF (0x0A) and CR (0x0D) before LF are allowed
             if b != b'\t'
                 && b != b'\n'
-                && b != b'\r'
+                && !is_crlf(bytes, i)
                 && ((0x00..=0x08).contains(&b) || (0x0A..=0x1F).contains(&b) || b == 0x7F)
             {
                 err_indices.push(i);
@@ -72,14 +74,19 @@ pub(crate) mod allowed_chars {
         let bytes = s.as_bytes();
 
         for (i, &b) in bytes.iter().enumerate() {
-            // Tab (0x09), LF (0x0A), CR (0x0D) are allowed
-            if b != b'\t' && b != b'\n' && b != b'\r' && (b < 0x20 || b == 0x7F) {
+            // Tab (0x09), LF (0x0A) and CR (0x0D) before LF are allowed
+            if b != b'\t' && b != b'\n' && !is_crlf(bytes, i) && (b < 0x20 || b == 0x7F) {
                 err_indices.push(i);
             }
         }
 
         if err_indices.is_empty() { Ok(()) } else { Err(err_indices) }
     }
+
+    /// Whether the byte at `i` is the CR of a CRLF line break, a lone CR is not allowed.
+    fn is_crlf(bytes: &[u8], i: usize) -> bool {
+        bytes[i] == b'\r' && bytes.get(i + 1) == Some(&b'\n')
+    }
 }
 
 pub const fn overlaps(range: TextRange, other: TextRange) -> bool {
@@ -0,0 +1,77 @@
+//! Byte scanning for the lexer that checks 8 bytes at a time (SWAR), which speeds up
+//! long strings, comments and runs of whitespace or identifiers in large documents.
+
+const WORD: usize = size_of::<u64>();
+const LOW_BITS: u64 = u64::from_ne_bytes([0x7F; WORD]);
+const HIGH_BITS: u64 = u64::from_ne_bytes([0x80; WORD]);
+
+const fn splat(byte: u8) -> u64 {
+    u64::from_ne_bytes([byte; WORD])
+}
+
+/// The high bit of each byte of the word that is zero, the other bits are not set.
+const fn zero_bytes(word: u64) -> u64 {
+    !(((word & LOW_BITS) + LOW_BITS) | word | LOW_BITS)
+}
+
+/// The index of the first byte of the mask from [`zero_bytes`] that is set.
+const fn first_byte(mask: u64) -> usize {
+    // The bytes are read in little-endian order, so the first one has the lowest bits.
+    (mask.trailing_zeros() / 8) as usize
+}
+
+fn read_word(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..WORD].try_into().unwrap())
+}
+
+/// The index of the first byte that is `a` or `b`.
+pub(crate) fn find_either(a: u8, b: u8, bytes: &[u8]) -> Option<usize> {
+    let (a_word, b_word) = (splat(a), splat(b));
+    let mut idx = 0;
+    while idx + WORD <= bytes.len() {
+        let word = read_word(&bytes[idx..]);
+        let found = zero_bytes(word ^ a_word) | zero_bytes(word ^ b_word);
+        if found != 0 {
+            return Some(idx + first_byte(found));
+        }
+        idx += WORD;
+    }
+    bytes[idx..].iter().position(|&byte| byte == a || byte == b).map(|pos| idx + pos)
+}
+
+/// The index of the first byte that is `byte`.
+pub(crate) fn find(byte: u8, bytes: &[u8]) -> Option<usize> {
+    find_either(byte, byte, bytes)
+}
+
+/// The number of spaces and tabs at the start of the bytes.
+pub(crate) fn whitespace_len(bytes: &[u8]) -> usize {
+    let (space, tab) = (splat(b' '), splat(b'\t'));
+    let mut idx = 0;
+    while idx + WORD <= bytes.len() {
+        let word = read_word(&bytes[idx..]);
+        let other = !(zero_bytes(word ^ space) | zero_bytes(word ^ tab)) & HIGH_BITS;
+        if other != 0 {
+            return idx + first_byte(other);
+        }
+        idx += WORD;
+    }
+    idx + bytes[idx..].iter().take_while(|&&byte| byte == b' ' || byte == b'\t').count()
+}
+
+/// The ASCII characters of bare keys and glob patterns in keys.
+static IDENT_BYTES: [bool; 256] = {
+    let mut table = [false; 256];
+    let mut byte = 0;
+    while byte < 128 {
+        let b = byte as u8;
+        table[byte] = b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'*' | b'?');
+        byte += 1;
+    }
+    table
+};
+
+/// The number of ASCII bytes at the start that can be in bare keys or glob patterns.
+pub(crate) fn ascii_ident_len(bytes: &[u8]) -> usize {
+    bytes.iter().position(|&byte| !IDENT_BYTES[byte as usize]).unwrap_or(bytes.len())
+}
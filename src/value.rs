@@ -0,0 +1,406 @@
+//! Owned values of documents, see [`SyntaxTree::to_value`].
+
+use std::{
+    collections::BTreeMap,
+    ops::{Index, IndexMut},
+};
+
+use crate::{
+    ast,
+    dom::{Document, Item, Table},
+    syntax::SyntaxKind::*,
+    tree::SyntaxTree,
+    util::unescape,
+};
+
+/// A value of a TOML document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Datetime(Datetime),
+    Array(Vec<Value>),
+    Table(BTreeMap<String, Value>),
+}
+
+/// An offset date-time, a local date-time, a local date or a local time.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Datetime {
+    /// The date, `None` for local times.
+    pub date: Option<Date>,
+
+    /// The time, `None` for local dates.
+    pub time: Option<Time>,
+
+    /// The offset from UTC, `None` for local date-times, dates and times.
+    pub offset: Option<Offset>,
+}
+
+/// The date of a [`Datetime`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+/// The time of a [`Datetime`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+
+    /// The fractional seconds, the digits after nanoseconds are truncated.
+    pub nanosecond: u32,
+}
+
+/// The offset from UTC of a [`Datetime`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Offset {
+    /// UTC, written as `Z`.
+    Z,
+
+    /// An offset in minutes, e.g. `-420` for `-07:00`.
+    Custom { minutes: i16 },
+}
+
+impl SyntaxTree<'_> {
+    /// The value of the document, a [`Value::Table`].
+    ///
+    /// The strings are unescaped and the numbers and date-times are parsed.
+    /// Invalid values and keys that are defined more than once are left out,
+    /// see [`ParseOptions::validate`](crate::ParseOptions::validate) to report them.
+    pub fn to_value(&self) -> Value {
+        let document = Document::new(self);
+        Value::Table(table_value(document.root(), &self.source))
+    }
+}
+
+fn table_value(table: &Table<'_>, source: &str) -> BTreeMap<String, Value> {
+    table
+        .iter()
+        .filter_map(|(key, item)| Some((key.to_string(), item_value(item, source)?)))
+        .collect()
+}
+
+pub(crate) fn item_value(item: &Item<'_>, source: &str) -> Option<Value> {
+    Some(match item {
+        Item::Value(value) => scalar(*value, source)?,
+        Item::Array(array) => {
+            Value::Array(array.items().iter().filter_map(|item| item_value(item, source)).collect())
+        }
+        Item::Table(table) => Value::Table(table_value(table, source)),
+        Item::ArrayOfTables(tables) => {
+            Value::Array(tables.iter().filter_map(|item| item_value(item, source)).collect())
+        }
+    })
+}
+
+/// The value of a string, number, boolean or date-time.
+pub(crate) fn scalar(value: ast::Value<'_>, source: &str) -> Option<Value> {
+    let (ast::Value::String(token)
+    | ast::Value::Integer(token)
+    | ast::Value::Float(token)
+    | ast::Value::Bool(token)
+    | ast::Value::DateTime(token)) = value
+    else {
+        return None;
+    };
+    let text = token.text(source);
+
+    Some(match token.kind() {
+        STRING => Value::String(unescape(&text[1..text.len() - 1]).ok()?),
+        MULTI_LINE_STRING => {
+            Value::String(unescape(trim_first_newline(&text[3..text.len() - 3])).ok()?)
+        }
+        STRING_LITERAL => Value::String(text[1..text.len() - 1].to_string()),
+        MULTI_LINE_STRING_LITERAL => {
+            Value::String(trim_first_newline(&text[3..text.len() - 3]).to_string())
+        }
+        INTEGER => Value::Integer(text.replace('_', "").parse().ok()?),
+        INTEGER_HEX | INTEGER_OCT | INTEGER_BIN => {
+            let radix = match token.kind() {
+                INTEGER_HEX => 16,
+                INTEGER_OCT => 8,
+                _ => 2,
+            };
+            Value::Integer(i64::from_str_radix(&text[2..].replace('_', ""), radix).ok()?)
+        }
+        FLOAT => Value::Float(text.replace('_', "").parse().ok()?),
+        BOOL => Value::Bool(text == "true"),
+        _ => Value::Datetime(text.parse().ok()?),
+    })
+}
+
+/// Multi-line strings don't contain a line break directly after the opening quotes.
+fn trim_first_newline(s: &str) -> &str {
+    s.strip_prefix('\n').or_else(|| s.strip_prefix("\r\n")).unwrap_or(s)
+}
+
+impl Value {
+    /// The value of the key if this is a table.
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        self.as_table()?.get(key)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub const fn as_integer(&self) -> Option<i64> {
+        match self {
+            Self::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub const fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    pub const fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub const fn as_datetime(&self) -> Option<&Datetime> {
+        match self {
+            Self::Datetime(datetime) => Some(datetime),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Self]> {
+        match self {
+            Self::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub const fn as_table(&self) -> Option<&BTreeMap<String, Self>> {
+        match self {
+            Self::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    const fn empty_table() -> Self {
+        Self::Table(BTreeMap::new())
+    }
+}
+
+/// The value of a key of a table, panics if it is not a table or doesn't have the key.
+impl Index<&str> for Value {
+    type Output = Self;
+
+    fn index(&self, key: &str) -> &Self {
+        match self {
+            Self::Table(table) => table.get(key).unwrap_or_else(|| panic!("no key `{key}`")),
+            _ => panic!("not a table"),
+        }
+    }
+}
+
+/// The value of a key of a table, an empty table is added for a key that doesn't exist
+/// so that its keys can be set. Panics if it is not a table.
+impl IndexMut<&str> for Value {
+    fn index_mut(&mut self, key: &str) -> &mut Self {
+        match self {
+            Self::Table(table) => table.entry(key.to_string()).or_insert_with(Self::empty_table),
+            _ => panic!("not a table"),
+        }
+    }
+}
+
+/// An item of an array, panics if it is not an array or the index is out of bounds.
+impl Index<usize> for Value {
+    type Output = Self;
+
+    fn index(&self, index: usize) -> &Self {
+        match self {
+            Self::Array(array) => &array[index],
+            _ => panic!("not an array"),
+        }
+    }
+}
+
+/// An item of an array, panics if it is not an array or the index is out of bounds.
+impl IndexMut<usize> for Value {
+    fn index_mut(&mut self, index: usize) -> &mut Self {
+        match self {
+            Self::Array(array) => &mut array[index],
+            _ => panic!("not an array"),
+        }
+    }
+}
+
+macro_rules! from_values {
+    ($($ty:ty => $variant:ident,)*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(value.into())
+                }
+            }
+        )*
+    };
+}
+
+from_values! {
+    &str => String,
+    String => String,
+    i64 => Integer,
+    i32 => Integer,
+    f64 => Float,
+    bool => Bool,
+    Datetime => Datetime,
+    Vec<Value> => Array,
+    BTreeMap<String, Value> => Table,
+}
+
+/// A date-time that is not written like TOML date-times.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct DatetimeParseError;
+
+impl core::fmt::Display for DatetimeParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid date-time")
+    }
+}
+impl std::error::Error for DatetimeParseError {}
+
+impl std::str::FromStr for Datetime {
+    type Err = DatetimeParseError;
+
+    /// Parse a date-time written like in TOML, e.g. `1979-05-27T07:32:00Z` or `07:32`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |s: &str| -> Result<u32, DatetimeParseError> {
+            if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(DatetimeParseError);
+            }
+            s.parse().map_err(|_| DatetimeParseError)
+        };
+        let part = |range: std::ops::Range<usize>| s.get(range).ok_or(DatetimeParseError);
+
+        let mut datetime = Self { date: None, time: None, offset: None };
+        let mut rest = s;
+        if s.as_bytes().get(4) == Some(&b'-') {
+            if part(7..8)? != "-" {
+                return Err(DatetimeParseError);
+            }
+            datetime.date = Some(Date {
+                year: number(part(0..4)?)? as u16,
+                month: number(part(5..7)?)? as u8,
+                day: number(part(8..10)?)? as u8,
+            });
+            rest = match s.get(10..11) {
+                None => return Ok(datetime),
+                Some("T" | "t" | " ") => &s[11..],
+                Some(_) => return Err(DatetimeParseError),
+            };
+        }
+
+        let field = |range: std::ops::Range<usize>| rest.get(range).ok_or(DatetimeParseError);
+        if field(2..3)? != ":" {
+            return Err(DatetimeParseError);
+        }
+        let mut time = Time {
+            hour: number(field(0..2)?)? as u8,
+            minute: number(field(3..5)?)? as u8,
+            second: 0,
+            nanosecond: 0,
+        };
+        rest = &rest[5..];
+        // Seconds are optional since TOML 1.1.
+        if let Some(seconds) = rest.strip_prefix(':') {
+            time.second = number(seconds.get(..2).ok_or(DatetimeParseError)?)? as u8;
+            rest = &seconds[2..];
+            if let Some(fraction) = rest.strip_prefix('.') {
+                let len = fraction.bytes().take_while(u8::is_ascii_digit).count();
+                // The digits after nanoseconds are truncated.
+                let digits = &fraction[..len.min(9)];
+                time.nanosecond = number(digits)? * 10_u32.pow(9 - digits.len() as u32);
+                rest = &fraction[len..];
+            }
+        }
+        datetime.time = Some(time);
+
+        datetime.offset = match rest {
+            "" => None,
+            "Z" | "z" => Some(Offset::Z),
+            _ => {
+                let sign = match rest.as_bytes()[0] {
+                    b'+' => 1,
+                    b'-' => -1,
+                    _ => return Err(DatetimeParseError),
+                };
+                if rest.len() != 6 || &rest[3..4] != ":" {
+                    return Err(DatetimeParseError);
+                }
+                let minutes = number(&rest[1..3])? * 60 + number(&rest[4..6])?;
+                Some(Offset::Custom { minutes: sign * minutes as i16 })
+            }
+        };
+        Ok(datetime)
+    }
+}
+
+impl core::fmt::Display for Datetime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(date) = &self.date {
+            write!(f, "{:04}-{:02}-{:02}", date.year, date.month, date.day)?;
+        }
+        if let Some(time) = &self.time {
+            if self.date.is_some() {
+                f.write_str("T")?;
+            }
+            write!(f, "{:02}:{:02}:{:02}", time.hour, time.minute, time.second)?;
+            if time.nanosecond != 0 {
+                let fraction = format!("{:09}", time.nanosecond);
+                write!(f, ".{}", fraction.trim_end_matches('0'))?;
+            }
+        }
+        match self.offset {
+            None => Ok(()),
+            Some(Offset::Z) => f.write_str("Z"),
+            Some(Offset::Custom { minutes }) => {
+                let sign = if minutes < 0 { '-' } else { '+' };
+                let minutes = minutes.unsigned_abs();
+                write!(f, "{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+            }
+        }
+    }
+}
+
+/// The name of the newtype struct that date-times are serialized as.
+#[cfg(feature = "serde")]
+pub(crate) const DATETIME_NAME: &str = "$__oxc_toml_datetime";
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Datetime {
+    /// Date-times are serialized as strings written like in TOML,
+    /// [`to_string`](crate::to_string) writes them without quotes.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(DATETIME_NAME, &self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Datetime {
+    /// Date-times are deserialized from strings written like in TOML.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
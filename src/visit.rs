@@ -0,0 +1,152 @@
+//! Traversing and rewriting syntax trees, see [`SyntaxTree::visit`] and [`SyntaxTree::rewrite`].
+
+use crate::{
+    emit::inline_value,
+    syntax::{SyntaxNode, SyntaxToken},
+    tree::{Element, SyntaxTree},
+    value::Value,
+};
+
+/// A visitor of the nodes and tokens of a tree, see [`SyntaxTree::visit`].
+///
+/// The methods get the source of the tree so they can read the text of the nodes,
+/// e.g. lint rules match the [`SyntaxKind`](crate::SyntaxKind) of the nodes they check.
+pub trait Visit {
+    /// Called before the children of a node.
+    fn enter(&mut self, node: &SyntaxNode, source: &str) -> VisitFlow {
+        let _ = (node, source);
+        VisitFlow::Continue
+    }
+
+    /// Called after the children of a node, or after [`Visit::enter`] if they are skipped.
+    fn leave(&mut self, node: &SyntaxNode, source: &str) {
+        let _ = (node, source);
+    }
+
+    /// Called for the tokens, including whitespace and comments.
+    fn token(&mut self, token: &SyntaxToken, source: &str) {
+        let _ = (token, source);
+    }
+}
+
+/// How the traversal continues after [`Visit::enter`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum VisitFlow {
+    /// Visit the children of the node.
+    #[default]
+    Continue,
+
+    /// Don't visit the children of the node, continue after it.
+    SkipChildren,
+
+    /// Stop the traversal, no other method is called.
+    Stop,
+}
+
+/// A rewriter of the nodes and tokens of a tree, see [`SyntaxTree::rewrite`].
+pub trait Rewrite {
+    /// The replacement of a node, `None` to keep it and rewrite its children.
+    fn rewrite_node(&mut self, node: &SyntaxNode, source: &str) -> Option<Replacement> {
+        let _ = (node, source);
+        None
+    }
+
+    /// The replacement of a token, `None` to keep it.
+    fn rewrite_token(&mut self, token: &SyntaxToken, source: &str) -> Option<Replacement> {
+        let _ = (token, source);
+        None
+    }
+}
+
+/// What a node or token is replaced with, see [`Rewrite`].
+#[derive(Debug, Clone)]
+pub enum Replacement {
+    /// The text is written as it is, an empty text removes the node.
+    Text(String),
+
+    /// The text of a tree that is built separately, e.g. with [`parse`](crate::parse)
+    /// or [`SyntaxTree::from_value`].
+    Node(SyntaxTree<'static>),
+
+    /// The value is written like the value of an entry, tables are inline tables.
+    Value(Value),
+}
+
+impl Replacement {
+    fn write(self, out: &mut String) {
+        match self {
+            Self::Text(text) => *out += &text,
+            Self::Node(tree) => *out += tree.source(),
+            Self::Value(value) => *out += &inline_value(&value),
+        }
+    }
+}
+
+impl SyntaxTree<'_> {
+    /// Visit the nodes and tokens of the tree depth-first in the order of the source.
+    pub fn visit(&self, visitor: &mut impl Visit) {
+        visit_node(&self.root, &self.source, visitor);
+    }
+
+    /// The source with the nodes and tokens replaced by the rewriter, the rest of the text
+    /// is kept as it is. The nodes are offered before their children, which are not offered
+    /// if the node is replaced.
+    pub fn rewrite(&self, rewriter: &mut impl Rewrite) -> String {
+        let mut out = String::with_capacity(self.source.len());
+        let mut pos = 0;
+        rewrite_node(&self.root, &self.source, rewriter, &mut pos, &mut out);
+        out += &self.source[pos..];
+        out
+    }
+}
+
+/// Visit a node and its descendants, `false` if the traversal is stopped.
+fn visit_node(node: &SyntaxNode, source: &str, visitor: &mut impl Visit) -> bool {
+    match visitor.enter(node, source) {
+        VisitFlow::Stop => return false,
+        VisitFlow::SkipChildren => {}
+        VisitFlow::Continue => {
+            for child in node.children() {
+                match child {
+                    Element::Node(child) => {
+                        if !visit_node(child, source, visitor) {
+                            return false;
+                        }
+                    }
+                    Element::Token(token) => visitor.token(token, source),
+                }
+            }
+        }
+    }
+    visitor.leave(node, source);
+    true
+}
+
+/// Write the text of a node with the replacements, `pos` is the end of
+/// what is written from the source so far.
+fn rewrite_node(
+    node: &SyntaxNode,
+    source: &str,
+    rewriter: &mut impl Rewrite,
+    pos: &mut usize,
+    out: &mut String,
+) {
+    if let Some(replacement) = rewriter.rewrite_node(node, source) {
+        *out += &source[*pos..node.span.start as usize];
+        replacement.write(out);
+        *pos = node.span.end as usize;
+        return;
+    }
+    for child in node.children() {
+        match child {
+            Element::Node(child) => rewrite_node(child, source, rewriter, pos, out),
+            Element::Token(token) => {
+                if let Some(replacement) = rewriter.rewrite_token(token, source) {
+                    *out += &source[*pos..token.span.start as usize];
+                    replacement.write(out);
+                    *pos = token.span.end as usize;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use oxc_toml::{
+    ast::{AstNode, Root, RootItem, Value},
+    parse,
+};
+
+#[test]
+fn test_ast() {
+    const SOURCE: &str = "a.\"b.c\" = [1, 'x', { d = true }]\n[t]\ne = 1979-05-27\n[[u]]\nf = \n";
+
+    let parse = parse(SOURCE);
+    let items = Root::cast(parse.tree.root()).unwrap().items().collect::<Vec<_>>();
+    assert_eq!(items.len(), 5);
+
+    let RootItem::Entry(entry) = items[0] else { panic!("expected an entry") };
+    assert_eq!(entry.key().unwrap().parts(SOURCE), ["a", "b.c"]);
+    let Some(Value::Array(array)) = entry.value() else { panic!("expected an array") };
+    let values = array.items().collect::<Vec<_>>();
+    assert!(matches!(values[..], [Value::Integer(_), Value::String(_), Value::InlineTable(_)]));
+    assert_eq!(values[1].text(SOURCE), "'x'");
+    let Value::InlineTable(table) = values[2] else { unreachable!() };
+    let entries = table.entries().collect::<Vec<_>>();
+    assert_eq!(entries[0].key().unwrap().parts(SOURCE), ["d"]);
+    assert!(matches!(entries[0].value(), Some(Value::Bool(_))));
+
+    let RootItem::Table(table) = items[1] else { panic!("expected a table") };
+    assert_eq!(table.key().unwrap().parts(SOURCE), ["t"]);
+    let RootItem::Entry(entry) = items[2] else { panic!("expected an entry") };
+    assert!(matches!(entry.value(), Some(Value::DateTime(_))));
+    assert!(matches!(items[3], RootItem::ArrayOfTables(_)));
+    let RootItem::Entry(entry) = items[4] else { panic!("expected an entry") };
+    assert!(entry.value().is_none());
+}
@@ -0,0 +1,52 @@
+use oxc_toml::{SyntaxKind, SyntaxNode, parse};
+
+const SOURCE: &str = r#"# The document
+
+# Detached
+# from the name
+
+# The name
+name = "app" # of the app
+[table] # a table
+# Above the value
+value = [
+  # The first item
+  1, # one
+  2,
+]
+"#;
+
+fn texts(tokens: &[&oxc_toml::SyntaxToken]) -> Vec<String> {
+    tokens.iter().map(|token| token.text(SOURCE).to_string()).collect()
+}
+
+#[test]
+fn test_comments() {
+    let tree = parse(SOURCE).into_syntax();
+    let nodes = tree.root().children().iter().filter_map(|c| c.as_node()).collect::<Vec<_>>();
+
+    let name = tree.comments(nodes[0]);
+    assert_eq!(texts(&name.leading), ["# The name"]);
+    assert_eq!(name.trailing.map(|t| t.text(SOURCE)), Some("# of the app"));
+    assert_eq!(texts(&name.detached), ["# The document", "# Detached", "# from the name"]);
+
+    let table = tree.comments(nodes[1]);
+    assert!(table.leading.is_empty() && table.detached.is_empty());
+    assert_eq!(table.trailing.map(|t| t.text(SOURCE)), Some("# a table"));
+
+    let value = tree.comments(nodes[2]);
+    assert_eq!(texts(&value.leading), ["# Above the value"]);
+    assert!(value.trailing.is_none());
+
+    let items = nodes[2]
+        .descendants()
+        .filter_map(|c| c.as_node())
+        .filter(|node: &&SyntaxNode| node.kind() == SyntaxKind::VALUE)
+        .skip(1)
+        .collect::<Vec<_>>();
+    let first = tree.comments(items[0]);
+    assert_eq!(texts(&first.leading), ["# The first item"]);
+    assert_eq!(first.trailing.map(|t| t.text(SOURCE)), Some("# one"));
+    let second = tree.comments(items[1]);
+    assert!(second.leading.is_empty() && second.trailing.is_none() && second.detached.is_empty());
+}
@@ -0,0 +1,223 @@
+use std::{fs, path::PathBuf};
+
+use oxc_toml::{
+    Config, ConfigError, ErrorKind, FormatRule, HexCase, LineEnding, Options, OptionsBuilder,
+    TomlVersion,
+};
+
+#[test]
+fn test_parse_config() {
+    let config = Config::parse(
+        r#"
+include = ["**/*.toml"]
+exclude = ['target/**']
+
+[formatting]
+align_entries = true
+column_width = 1_00
+indent_string = "\t"
+hex_case = "upper"
+reorder_tables = true
+table_priority = ["package", "dependencies"]
+toml_version = "1.0"
+"#,
+    )
+    .unwrap();
+
+    let options = Options::builder()
+        .align_entries(true)
+        .column_width(100)
+        .indent_string("\t")
+        .hex_case(HexCase::Upper)
+        .reorder_tables(true)
+        .table_priority(["package", "dependencies"])
+        .toml_version(TomlVersion::V1_0)
+        .build()
+        .unwrap();
+    assert_eq!(
+        config,
+        Config {
+            options,
+            include: vec!["**/*.toml".into()],
+            exclude: vec!["target/**".into()],
+            ..Config::default()
+        }
+    );
+
+    let Err(ConfigError::Invalid { range, message }) =
+        Config::parse("[formatting]\nalign_entrys = true\n")
+    else {
+        panic!("expected an unknown key");
+    };
+    assert_eq!((range, message.as_str()), (13..25, "unknown key `formatting.align_entrys`"));
+
+    let Err(ConfigError::Invalid { message, .. }) =
+        Config::parse("formatting.hex_case = \"mixed\"")
+    else {
+        panic!("expected an invalid value");
+    };
+    assert_eq!(message, "expected one of `preserve` `lower` `upper`");
+
+    assert!(matches!(Config::parse("include = ["), Err(ConfigError::Syntax(_))));
+
+    let Err(ConfigError::Syntax(errors)) =
+        Config::parse("[formatting]\ncolumn_width = 80\ncolumn_width = 120\n")
+    else {
+        panic!("expected a duplicate key");
+    };
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, ErrorKind::DuplicateKey);
+
+    // Quoted keys are unescaped.
+    let config = Config::parse("[\"formatting\"]\n\"align\\u005Fentries\" = true\n").unwrap();
+    assert!(config.options.align_entries);
+
+    // The options are validated after they are loaded.
+    let Err(ConfigError::Invalid { range, message }) =
+        Config::parse("[formatting]\ncolumn_width = 0\n")
+    else {
+        panic!("expected invalid options");
+    };
+    assert_eq!(
+        (range, message.as_str()),
+        (13..25, "invalid `column_width`: must be greater than zero")
+    );
+}
+
+#[test]
+fn test_discover_config() {
+    let root = std::env::temp_dir().join(format!("oxc-toml-discover-{}", std::process::id()));
+    let nested = root.join("crates/a");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(root.join(".oxctomlrc.toml"), "[formatting]\nalign_entries = true\n").unwrap();
+
+    let (path, config) = Options::discover(nested.join("Cargo.toml")).unwrap().unwrap();
+    assert_eq!(path, root.join(".oxctomlrc.toml"));
+    assert!(config.options.align_entries);
+
+    // The closest config file is used, `oxc-toml.toml` comes first.
+    fs::write(nested.join("oxc-toml.toml"), "").unwrap();
+    fs::write(nested.join(".oxctomlrc.toml"), "").unwrap();
+    let (path, config) = Options::discover(&nested).unwrap().unwrap();
+    assert_eq!(path, nested.join("oxc-toml.toml"));
+    assert_eq!(config, Config::default());
+
+    fs::remove_dir_all(&root).unwrap();
+    assert!(Options::discover(PathBuf::from("/")).unwrap().is_none());
+}
+
+#[test]
+fn test_taplo_config() {
+    let config = Config::parse_taplo(
+        r#"
+include = ["**/*.toml"]
+
+[schema]
+path = "schema.json"
+
+[formatting]
+align_entries = true
+crlf = true
+reorder_keys = true
+
+[[rule]]
+keys = ["dependencies"]
+formatting = { reorder_keys = false }
+"#,
+    )
+    .unwrap();
+
+    let options = Options::builder()
+        .align_entries(true)
+        .line_ending(LineEnding::Crlf)
+        .reorder_keys(true)
+        .build()
+        .unwrap();
+    let rule = FormatRule {
+        keys: vec!["dependencies".into()],
+        options: OptionsBuilder::from(options.clone()).reorder_keys(false).build().unwrap(),
+    };
+    assert_eq!(
+        config,
+        Config { options, include: vec!["**/*.toml".into()], exclude: vec![], rules: vec![rule] }
+    );
+
+    // Options that Taplo doesn't have are not accepted in Taplo config files.
+    assert!(Config::parse_taplo("[formatting]\nsort_entries = true\n").is_err());
+    assert!(Config::parse("[formatting]\ncrlf = true\n").is_err());
+
+    // Rules for some files can't be used for all of them.
+    let Err(ConfigError::Invalid { range, message }) = Config::parse_taplo(
+        "[[rule]]\nkeys = [\"a\"]\n\n[[rule]]\nexclude = [\"Cargo.toml\"]\nkeys = [\"package\"]\n",
+    ) else {
+        panic!("expected an unsupported rule");
+    };
+    assert_eq!((range, message.as_str()), (32..39, "`exclude` in rule 2 is not supported"));
+
+    let root = std::env::temp_dir().join(format!("oxc-toml-taplo-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join(".taplo.toml"), "[formatting]\ncrlf = true\n").unwrap();
+    let (path, config) = Options::discover(root.join("Cargo.toml")).unwrap().unwrap();
+    assert_eq!(path, root.join(".taplo.toml"));
+    assert_eq!(config.options.line_ending, LineEnding::Crlf);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_config_rules() {
+    let config = Config::parse(
+        r#"
+[formatting]
+column_width = 100
+
+[[rule]]
+keys = ["dependencies", "*-dependencies"]
+formatting = { reorder_keys = true }
+
+[[rule]]
+keys = ["package.metadata"]
+
+[rule.formatting]
+align_entries = true
+"#,
+    )
+    .unwrap();
+
+    let options = Options::builder().column_width(100).build().unwrap();
+    let rules = vec![
+        FormatRule {
+            keys: vec!["dependencies".into(), "*-dependencies".into()],
+            options: OptionsBuilder::from(options.clone()).reorder_keys(true).build().unwrap(),
+        },
+        FormatRule {
+            keys: vec!["package.metadata".into()],
+            options: OptionsBuilder::from(options.clone()).align_entries(true).build().unwrap(),
+        },
+    ];
+    assert_eq!(config, Config { options, rules, ..Config::default() });
+
+    let Err(ConfigError::Invalid { message, .. }) = Config::parse("[[rule]]\nkeys = [\"a.\"]\n")
+    else {
+        panic!("expected an invalid pattern");
+    };
+    assert_eq!(message, "invalid key pattern `a.`");
+
+    let Err(ConfigError::Invalid { message, .. }) =
+        Config::parse("[[rule]]\nkeys = [\"a\"]\nformatting.align = true\n")
+    else {
+        panic!("expected an unknown key");
+    };
+    assert_eq!(message, "unknown key `rule.formatting.align`");
+
+    let Err(ConfigError::Invalid { range, message }) = Config::parse("[[rule]]\n") else {
+        panic!("expected a missing key");
+    };
+    assert_eq!((range, message.as_str()), (0..8, "missing `keys` in rule"));
+
+    let Err(ConfigError::Invalid { message, .. }) =
+        Config::parse("[[rule]]\nkeys = [\"a\"]\nformatting = { column_width = 0 }\n")
+    else {
+        panic!("expected invalid options");
+    };
+    assert_eq!(message, "invalid `column_width`: must be greater than zero");
+}
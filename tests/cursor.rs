@@ -0,0 +1,39 @@
+use oxc_toml::{SyntaxKind, parse};
+
+#[test]
+fn test_cursor() {
+    const SOURCE: &str = "a = [1, { b = 2 }]\n[t]\n";
+    let tree = parse(SOURCE).into_syntax();
+
+    let b = tree.cursor_at(10).unwrap();
+    assert_eq!((b.kind(), b.text(SOURCE)), (SyntaxKind::IDENT, "b"));
+    assert_eq!(
+        b.ancestors().map(|cursor| cursor.kind()).collect::<Vec<_>>(),
+        [
+            SyntaxKind::KEY,
+            SyntaxKind::ENTRY,
+            SyntaxKind::INLINE_TABLE,
+            SyntaxKind::VALUE,
+            SyntaxKind::ARRAY,
+            SyntaxKind::VALUE,
+            SyntaxKind::ENTRY,
+            SyntaxKind::ROOT,
+        ]
+    );
+
+    let key = b.parent().unwrap();
+    assert!(key.prev_sibling().is_none());
+    let eq = key.next_sibling().unwrap();
+    assert_eq!(eq.kind(), SyntaxKind::EQ);
+    assert_eq!(eq.next_sibling().unwrap().kind(), SyntaxKind::WHITESPACE);
+    assert_eq!(eq.prev_sibling().unwrap().span(), key.span());
+
+    let root = tree.cursor();
+    assert!(root.parent().is_none() && root.next_sibling().is_none());
+    let children = root.children().map(|cursor| cursor.kind()).collect::<Vec<_>>();
+    assert_eq!(children.first(), Some(&SyntaxKind::ENTRY));
+    assert_eq!(root.last_child().map(|cursor| cursor.kind()), children.last().copied());
+    let header = root.children().find(|cursor| cursor.kind() == SyntaxKind::TABLE_HEADER);
+    assert_eq!(header.unwrap().text(SOURCE), "[t]");
+    assert!(b.first_child().is_none() && b.children().next().is_none());
+}
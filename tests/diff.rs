@@ -0,0 +1,65 @@
+use oxc_toml::{ChangeKind, diff, parse};
+
+#[test]
+fn test_diff() {
+    let old = parse(
+        r#"name = "app"
+version = 0x10
+log = { version = "0.4" }
+
+[dependencies]
+serde = "1"
+toml = "0.8"
+
+[[bin]]
+name = "a"
+"#,
+    )
+    .into_syntax();
+    let new = parse(
+        r#"# The name
+name = 'app'
+version = 16
+
+[log]
+version = "0.5"
+
+[dependencies]
+serde = { version = "1" }
+
+[[bin]]
+name = "a"
+
+[[bin]]
+name = "b"
+
+[empty]
+"#,
+    )
+    .into_syntax();
+
+    let changes = diff(&old, &new);
+    let summary = changes
+        .iter()
+        .map(|change| {
+            let old = change.old.as_ref().map(|value| value.text.as_str());
+            let new = change.new.as_ref().map(|value| value.text.as_str());
+            (change.kind, change.path.as_str(), old, new)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            (ChangeKind::Changed, "log.version", Some("\"0.4\""), Some("\"0.5\"")),
+            (ChangeKind::Changed, "dependencies.serde", Some("\"1\""), Some("{ version = \"1\" }")),
+            (ChangeKind::Removed, "dependencies.toml", Some("\"0.8\""), None),
+            (ChangeKind::Added, "bin.1.name", None, Some("\"b\"")),
+            (ChangeKind::Added, "empty", None, Some("[empty]")),
+        ]
+    );
+
+    let serde = &changes[1];
+    assert_eq!(serde.old.as_ref().unwrap().span, 78..81);
+    assert_eq!(serde.new.as_ref().unwrap().span, 84..101);
+    assert!(diff(&old, &old).is_empty());
+}
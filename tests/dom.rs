@@ -0,0 +1,87 @@
+use oxc_toml::{
+    ast::Value,
+    dom::{Document, Item, TableKind},
+    parse,
+};
+
+#[test]
+fn test_document_get() {
+    const SOURCE: &str = r#"
+[package]
+name = "crate"
+metadata.docs = { "rs" = { all-features = true } }
+
+[[bin]]
+name = "a"
+
+[[bin]]
+name = "b"
+path = ["src", "b.rs"]
+
+[x.y]
+z = 1
+[x]
+w = 2
+"#;
+
+    let tree = parse(SOURCE).into_syntax();
+    let doc = Document::new(&tree);
+    let text = |path: &str| doc.get(path)?.value().map(|value| value.text(SOURCE));
+
+    assert_eq!(text("package.name"), Some(r#""crate""#));
+    assert_eq!(text("package.metadata.docs.rs.all-features"), Some("true"));
+    assert_eq!(text("package.metadata.docs.\"rs\""), Some("{ all-features = true }"));
+    assert_eq!(text("bin.1.name"), Some(r#""b""#));
+    assert_eq!(text("bin.1.path.1"), Some(r#""b.rs""#));
+    assert_eq!(text("x.y.z"), Some("1"));
+    assert_eq!(text("x.w"), Some("2"));
+
+    assert!(matches!(doc.get("bin.0.name").and_then(Item::value), Some(Value::String(_))));
+    assert!(matches!(doc.get("bin"), Some(Item::ArrayOfTables(tables)) if tables.len() == 2));
+    let metadata = doc.get("package.metadata").and_then(Item::as_table).unwrap();
+    assert!(matches!(metadata.kind(), TableKind::Dotted));
+    let package = doc.get("package").and_then(Item::as_table).unwrap();
+    assert_eq!(package.span(), Some(2..9));
+    assert_eq!(package.iter().map(|(key, _)| key).collect::<Vec<_>>(), ["name", "metadata"]);
+    assert!(matches!(doc.get("x").and_then(Item::as_table).unwrap().kind(), TableKind::Header(_)));
+
+    assert!(doc.get("package.version").is_none());
+    assert!(doc.get("bin.2").is_none());
+    assert!(doc.get("package.name.x").is_none());
+    assert!(doc.get("package..name").is_none());
+    assert!(doc.get("").is_none());
+}
+
+#[test]
+fn test_document_keys() {
+    const SOURCE: &str = r#"a.b = { c = [{ d = 1 }] }
+[[bin]]
+name = "x"
+[[bin]]
+"name" = "y"
+[[bin.target]]
+t = 1
+[bin.meta]
+m = 2
+"#;
+
+    let tree = parse(SOURCE).into_syntax();
+    let doc = Document::new(&tree);
+    let keys = doc
+        .keys()
+        .map(|key| (key.path(), key.parts.iter().map(|part| part.span.clone()).collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        [
+            ("a.b".to_string(), vec![0..1, 2..3]),
+            ("a.b.c".to_string(), vec![0..1, 2..3, 8..9]),
+            ("a.b.c.0.d".to_string(), vec![0..1, 2..3, 8..9, 13..22, 15..16]),
+            ("bin.0.name".to_string(), vec![28..31, 28..31, 34..38]),
+            ("bin.1.name".to_string(), vec![47..50, 47..50, 53..59]),
+            ("bin.1.target.0.t".to_string(), vec![68..71, 68..78, 72..78, 68..78, 81..82]),
+            ("bin.1.meta.m".to_string(), vec![88..91, 88..96, 92..96, 98..99]),
+        ]
+    );
+    assert_eq!(doc.keys().last().unwrap().value.text(SOURCE), "2");
+}
@@ -0,0 +1,558 @@
+use std::collections::BTreeMap;
+
+use oxc_toml::{DocumentMut, EditError, Options, TablePosition, Value};
+
+const SOURCE: &str = r#"# The package
+name = "app"   # keep this comment
+[package]
+version = "0.1.0"
+
+[dependencies]
+serde = "1"
+log = { version = "0.4", features = ["std"] }
+
+[[bin]]
+name = "a"
+[[bin]]
+name = "b"
+
+[target.x86.dependencies]
+libc = "0.2"
+"#;
+
+fn document() -> DocumentMut {
+    DocumentMut::parse(SOURCE, Options::default()).unwrap()
+}
+
+fn string(s: &str) -> Value {
+    Value::String(s.into())
+}
+
+#[test]
+fn test_set_existing_values() {
+    let mut doc = document();
+    doc.set("package.version", &string("0.2.0")).unwrap();
+    doc.set("dependencies.log.features.0", &string("alloc")).unwrap();
+    doc.set("bin.1.name", &Value::Integer(1)).unwrap();
+    assert_eq!(
+        doc.as_str(),
+        SOURCE
+            .replace("0.1.0", "0.2.0")
+            .replace(r#"["std"]"#, r#"["alloc"]"#)
+            .replace(r#"name = "b""#, "name = 1")
+    );
+    assert_eq!(doc.get("dependencies.log.features.0"), Some(string("alloc")));
+}
+
+#[test]
+fn test_set_new_entries() {
+    let mut doc = document();
+    let table = Value::Table(BTreeMap::from([("version".to_string(), string("1"))]));
+    doc.set("dependencies.toml", &table).unwrap();
+    doc.set("dependencies.log.optional", &Value::Bool(true)).unwrap();
+    doc.set("package.metadata.docs", &Value::Bool(true)).unwrap();
+    doc.set("bin.0.path", &string("a.rs")).unwrap();
+    doc.set("edition", &string("2024")).unwrap();
+    doc.set("target.x86.rustflags", &Value::Array(vec![])).unwrap();
+    doc.set("target.arm.dependencies.libc", &string("0.2")).unwrap();
+    assert_eq!(
+        doc.as_str(),
+        r#"# The package
+name = "app"   # keep this comment
+edition = "2024"
+[package]
+version = "0.1.0"
+metadata.docs = true
+
+[dependencies]
+serde = "1"
+log = { version = "0.4", features = ["std"], optional = true }
+toml = { version = "1" }
+
+[[bin]]
+name = "a"
+path = "a.rs"
+[[bin]]
+name = "b"
+
+[target.x86.dependencies]
+libc = "0.2"
+
+[target.x86]
+rustflags = []
+
+[target.arm.dependencies]
+libc = "0.2"
+"#
+    );
+    assert!(DocumentMut::parse(doc.as_str(), Options::default()).is_ok());
+}
+
+#[test]
+fn test_set_errors() {
+    let mut doc = document();
+    assert_eq!(
+        doc.set("package", &Value::Integer(1)),
+        Err(EditError::NotAValue { path: "package".into() })
+    );
+    assert_eq!(
+        doc.set("bin.0", &Value::Integer(1)),
+        Err(EditError::NotAValue { path: "bin.0".into() })
+    );
+    assert_eq!(
+        doc.set("name.x", &Value::Integer(1)),
+        Err(EditError::NotATable { path: "name".into() })
+    );
+    assert_eq!(doc.set("bin.5.name", &Value::Integer(1)), Err(EditError::InvalidPath));
+    assert_eq!(doc.set("a..b", &Value::Integer(1)), Err(EditError::InvalidPath));
+    assert_eq!(doc.as_str(), SOURCE);
+    assert!(DocumentMut::parse("a = 1\na = 2\n", Options::default()).is_err());
+}
+
+#[test]
+fn test_remove() {
+    let mut doc = document();
+    assert!(doc.remove("dependencies.log.version"));
+    assert!(doc.remove("bin.0"));
+    assert!(doc.remove("target"));
+    assert!(doc.remove("name"));
+    assert!(!doc.remove("missing"));
+    assert_eq!(
+        doc.as_str(),
+        r#"[package]
+version = "0.1.0"
+
+[dependencies]
+serde = "1"
+log = { features = ["std"] }
+
+[[bin]]
+name = "b"
+"#
+    );
+
+    assert!(doc.remove("dependencies"));
+    assert_eq!(doc.as_str(), "[package]\nversion = \"0.1.0\"\n\n[[bin]]\nname = \"b\"\n");
+}
+
+#[test]
+fn test_remove_comments() {
+    let source = r#"a = 1
+
+# The b
+# entry
+b = 2 # two
+
+c = 3
+# The table
+[table]
+d = 4
+"#;
+    let mut doc = DocumentMut::parse(source, Options::default()).unwrap();
+    assert!(doc.remove("b"));
+    assert_eq!(doc.as_str(), "a = 1\n\nc = 3\n# The table\n[table]\nd = 4\n");
+    assert!(doc.remove("table"));
+    assert_eq!(doc.as_str(), "a = 1\n\nc = 3\n");
+    assert!(doc.remove("c"));
+    assert_eq!(doc.as_str(), "a = 1\n");
+}
+
+#[test]
+fn test_insert() {
+    let source = r#"[deps]
+  # The logger
+  'log'='0.4'
+  'serde'='1'
+
+[package]
+name = "app"
+"#;
+    let mut doc = DocumentMut::parse(source, Options::default()).unwrap();
+    let span = doc.insert("deps.anyhow", &string("1")).unwrap();
+    assert_eq!(&doc.as_str()[span.start as usize..span.end as usize], "'anyhow'='1'");
+    let span = doc.insert("deps.toml", &string("0.8")).unwrap();
+    assert_eq!(&doc.as_str()[span.start as usize..span.end as usize], "'toml'='0.8'");
+    doc.insert("deps.oxc", &string("it's")).unwrap();
+    doc.insert("package.edition", &string("2024")).unwrap();
+    assert_eq!(
+        doc.as_str(),
+        r#"[deps]
+  'anyhow'='1'
+  # The logger
+  'log'='0.4'
+  'oxc'="it's"
+  'serde'='1'
+  'toml'='0.8'
+
+[package]
+name = "app"
+edition = "2024"
+"#
+    );
+
+    assert_eq!(
+        doc.insert("deps.log", &string("1")),
+        Err(EditError::Exists { path: "deps.log".into() })
+    );
+}
+
+#[test]
+fn test_insert_new_table() {
+    // Like `cargo add serde` in a manifest without dependencies.
+    let mut doc = DocumentMut::parse("[package]\nname = 'a'\n", Options::default()).unwrap();
+    let span = doc.insert("dependencies.serde", &string("1")).unwrap();
+    assert_eq!(&doc.as_str()[span.start as usize..span.end as usize], "serde = \"1\"");
+    assert_eq!(doc.as_str(), "[package]\nname = 'a'\n\n[dependencies]\nserde = \"1\"\n");
+
+    // The root entries are kept as dotted keys next to the others.
+    let mut doc = DocumentMut::parse("x = 1\n[package]\n", Options::default()).unwrap();
+    doc.insert("a.b", &Value::Integer(2)).unwrap();
+    assert_eq!(doc.as_str(), "x = 1\na.b = 2\n[package]\n");
+}
+
+#[test]
+fn test_rename() {
+    let source = r#"[dependencies]
+serde = "1" # serde
+"my-log" = { version = "0.4", "features" = ["std"] }
+
+[dependencies.sys]
+os.'name' = "linux"
+
+[dependencies."sys".target]
+x = 1
+
+[target.x.dependencies]
+serde.version = "1"
+
+[[bin]]
+name = "a"
+[[bin]]
+name = "b"
+"#;
+    let mut doc = DocumentMut::parse(source, Options::default()).unwrap();
+    doc.rename("dependencies", "deps").unwrap();
+    doc.rename("deps.my-log", "log").unwrap();
+    doc.rename("deps.log.features", "default-features").unwrap();
+    doc.rename("deps.sys.os.name", "family").unwrap();
+    doc.rename("deps.sys", "system").unwrap();
+    doc.rename("target.x.dependencies.serde.version", "ver").unwrap();
+    doc.rename("bin", "bins").unwrap();
+    doc.rename("bins.1.name", "path").unwrap();
+    assert_eq!(
+        doc.as_str(),
+        r#"[deps]
+serde = "1" # serde
+"log" = { version = "0.4", "default-features" = ["std"] }
+
+[deps.system]
+os.'family' = "linux"
+
+[deps."system".target]
+x = 1
+
+[target.x.dependencies]
+serde.ver = "1"
+
+[[bins]]
+name = "a"
+[[bins]]
+path = "b"
+"#
+    );
+
+    assert_eq!(doc.rename("deps.serde", "log"), Err(EditError::Exists { path: "deps.log".into() }));
+    assert_eq!(doc.rename("deps.toml", "x"), Err(EditError::Missing { path: "deps.toml".into() }));
+    assert_eq!(doc.rename("bins.0", "x"), Err(EditError::InvalidPath));
+}
+
+#[test]
+fn test_set_formatting() {
+    let source = "[package]\n  keywords   = [\"a\"]   # keep\n  version = \"1\"\n";
+    let mut options = Options::default();
+    options.column_width = 40;
+    options.indent_entries = true;
+    let mut doc = DocumentMut::parse(source, options).unwrap();
+    let keywords = ["toml", "parser", "formatter", "syntax"].map(string);
+    doc.set("package.keywords", &Value::Array(keywords.to_vec())).unwrap();
+    doc.set("package.version", &string("2")).unwrap();
+    assert_eq!(
+        doc.as_str(),
+        r#"[package]
+  keywords   = [
+    "toml",
+    "parser",
+    "formatter",
+    "syntax",
+  ]   # keep
+  version = "2"
+"#
+    );
+}
+
+#[test]
+fn test_array_edit() {
+    let source = r#"inline = [1, 2,3]
+empty = []
+features = [
+  # The default
+  "std", # std
+  "alloc"
+]
+trailing = [
+    "a",
+    "b",
+]
+"#;
+    let mut doc = DocumentMut::parse(source, Options::default()).unwrap();
+    let mut inline = doc.array("inline").unwrap();
+    inline.push(&Value::Integer(4));
+    inline.insert(0, &Value::Integer(0)).unwrap();
+    assert_eq!(inline.remove_where(|value| *value == Value::Integer(2)), 1);
+    assert_eq!(inline.len(), 4);
+    assert_eq!(inline.insert(5, &Value::Integer(5)), Err(EditError::InvalidPath));
+    doc.array("empty").unwrap().push(&string("x"));
+
+    let mut features = doc.array("features").unwrap();
+    features.push(&string("serde"));
+    features.insert(0, &string("core")).unwrap();
+    assert_eq!(features.remove_where(|value| *value == string("std")), 1);
+    let mut trailing = doc.array("trailing").unwrap();
+    trailing.push(&string("c"));
+    trailing.insert(1, &string("a2")).unwrap();
+    assert_eq!(
+        doc.as_str(),
+        r#"inline = [0, 1, 3, 4]
+empty = ["x"]
+features = [
+  "core",
+  "alloc",
+  "serde"
+]
+trailing = [
+    "a",
+    "a2",
+    "b",
+    "c",
+]
+"#
+    );
+
+    let mut features = doc.array("features").unwrap();
+    assert_eq!(features.remove_where(|value| *value != string("core")), 2);
+    assert_eq!(features.values(), vec![string("core")]);
+    assert_eq!(doc.array("inline").unwrap().remove_where(|_| true), 4);
+    assert!(doc.array("inline").unwrap().is_empty());
+    assert_eq!(doc.array("empty.x").unwrap_err(), EditError::Missing { path: "empty.x".into() });
+    assert_eq!(
+        doc.array("trailing.0").unwrap_err(),
+        EditError::NotAnArray { path: "trailing.0".into() }
+    );
+    assert!(DocumentMut::parse(doc.as_str(), Options::default()).is_ok());
+}
+
+#[test]
+fn test_sort_table() {
+    let source = r#"[package]
+name = "app"
+edition = "2024"
+
+[dependencies]
+toml = "0.8" # toml
+# The logger
+log = "0.4"
+anyhow = "1"
+
+serde = { version = "1", features = [
+    "derive",
+] }
+base64 = "0.22"
+
+[dev-dependencies]
+b = "1"
+a = "1"
+
+[target.x.dependencies]
+z = "1"
+y = "1""#;
+    let mut doc = DocumentMut::parse(source, Options::default()).unwrap();
+    assert_eq!(doc.sort_table("*dependencies"), Ok(2));
+    assert_eq!(
+        doc.as_str(),
+        r#"[package]
+name = "app"
+edition = "2024"
+
+[dependencies]
+anyhow = "1"
+# The logger
+log = "0.4"
+toml = "0.8" # toml
+
+base64 = "0.22"
+serde = { version = "1", features = [
+    "derive",
+] }
+
+[dev-dependencies]
+a = "1"
+b = "1"
+
+[target.x.dependencies]
+z = "1"
+y = "1""#
+    );
+
+    assert_eq!(doc.sort_table("target.*.dependencies"), Ok(1));
+    assert!(doc.as_str().ends_with("[target.x.dependencies]\ny = \"1\"\nz = \"1\""));
+    assert_eq!(doc.sort_table("a..b"), Err(EditError::InvalidPath));
+}
+
+#[test]
+fn test_move_table() {
+    let source = r#"name = "app"
+
+[dependencies]
+serde = "1"
+
+# The package
+[package]
+version = "1"
+
+[[bin]]
+name = "a"
+[bin.sub]
+x = 1
+
+[[bin]]
+name = "b"
+"#;
+    let mut doc = DocumentMut::parse(source, Options::default()).unwrap();
+    doc.move_table("package", TablePosition::Before("dependencies".into())).unwrap();
+    doc.move_table("dependencies", TablePosition::After("bin.1".into())).unwrap();
+    assert_eq!(
+        doc.as_str(),
+        r#"name = "app"
+
+# The package
+[package]
+version = "1"
+
+[[bin]]
+name = "a"
+[bin.sub]
+x = 1
+
+[[bin]]
+name = "b"
+
+[dependencies]
+serde = "1"
+"#
+    );
+
+    assert_eq!(
+        doc.move_table("bin.0.sub", TablePosition::Before("bin.0".into())),
+        Err(EditError::InvalidDocument)
+    );
+    assert_eq!(
+        doc.move_table("missing", TablePosition::After("package".into())),
+        Err(EditError::Missing { path: "missing".into() })
+    );
+    doc.move_table("bin.1", TablePosition::Before("bin.0".into())).unwrap();
+    assert_eq!(doc.get("bin.0.name"), Some(string("b")));
+}
+
+#[test]
+fn test_extract_graft() {
+    let source = r#"[target.x.dependencies]
+# Serialization
+serde = "1"
+log = { version = "0.4" }
+
+[target.x.dependencies.tokio]
+version = "1"
+"#;
+    let doc = DocumentMut::parse(source, Options::default()).unwrap();
+    let fragment = doc.extract("target.x.dependencies").unwrap();
+    assert_eq!(
+        fragment.as_str(),
+        r#"[dependencies]
+# Serialization
+serde = "1"
+log = { version = "0.4" }
+
+[dependencies.tokio]
+version = "1"
+"#
+    );
+    assert_eq!(
+        doc.extract("target.x.dependencies.serde").unwrap().as_str(),
+        "# Serialization\nserde = \"1\"\n"
+    );
+    assert_eq!(
+        doc.extract("target.x.dependencies.log.version").unwrap().as_str(),
+        "version = \"0.4\"\n"
+    );
+    assert!(doc.extract("missing").is_none());
+
+    let mut target =
+        DocumentMut::parse("name = \"app\"\n\n[workspace]\nmembers = []\n", Options::default())
+            .unwrap();
+    target.graft("", &fragment).unwrap();
+    target.graft("workspace", &doc.extract("target.x.dependencies.serde").unwrap()).unwrap();
+    assert_eq!(
+        target.as_str(),
+        r#"name = "app"
+
+[workspace]
+members = []
+# Serialization
+serde = "1"
+
+[dependencies]
+# Serialization
+serde = "1"
+log = { version = "0.4" }
+
+[dependencies.tokio]
+version = "1"
+"#
+    );
+    assert_eq!(target.graft("", &fragment), Err(EditError::InvalidDocument));
+    assert_eq!(target.get("dependencies.tokio.version"), Some(string("1")));
+
+    let serde = doc.extract("target.x.dependencies.serde").unwrap();
+    target.graft("tools.deps", &serde).unwrap();
+    assert!(target.as_str().ends_with("\n\n[tools.deps]\n# Serialization\nserde = \"1\"\n"));
+}
+
+#[test]
+fn test_index() {
+    let mut doc = DocumentMut::parse("[package]\nname = 'a'\n", Options::default()).unwrap();
+    assert_eq!(doc["package"]["name"], string("a"));
+
+    // Like `cargo add serde` followed by `cargo add log`.
+    doc["dependencies"]["serde"] = Value::from("1");
+    doc["dependencies"]["log"] = Value::from("0.4");
+    doc["package"]["version"] = Value::from("0.1.0");
+    assert_eq!(
+        doc.as_str(),
+        "[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nlog = \"0.4\"\nserde = \"1\"\n"
+    );
+    assert_eq!(doc["dependencies"]["serde"], string("1"));
+
+    // The edits are written before the document is edited otherwise.
+    doc["package"]["name"] = Value::from("b");
+    assert!(doc.remove("dependencies.log"));
+    assert_eq!(
+        doc.to_string(),
+        "[package]\nname = \"b\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1\"\n"
+    );
+
+    let mut doc = document();
+    doc["bin"][1]["name"] = Value::from("c");
+    doc["dependencies"]["log"]["features"][0] = Value::from("alloc");
+    assert!(doc.as_str().contains("[[bin]]\nname = \"c\"\n"));
+    assert!(doc.as_str().contains("log = { version = \"0.4\", features = [\"alloc\"] }\n"));
+}
@@ -1,4 +1,9 @@
-use oxc_toml::{Options, format};
+use oxc_toml::{
+    ArrayLayout, DateTimeSeparator, EncodingError, FormatDiagnosticKind, FormatRule, Formatter,
+    HexCase, IntegerUnderscores, LineEnding, Options, OptionsBuilder, TextEdit, TomlVersion,
+    ValueHooks, format, format_bytes, format_edits, format_incremental, format_into, format_node,
+    format_stream, format_with_diagnostics, format_with_hooks, format_with_rules,
+};
 
 #[test]
 fn test_basic_formatting() {
@@ -54,3 +59,1400 @@ fn test_bare_key_starting_with_digit() {
         assert_eq!(formatted, input, "input: {input:?}");
     }
 }
+
+#[test]
+fn test_array_wrapping_measures_nested_arrays() {
+    const SOURCE: &str = "matrix = [[1, 2, 3], [4, 5, 6], [7, 8, 9]]\n";
+    let options = Options::builder().column_width(30).build().unwrap();
+
+    // Only the outer array overflows, the rows still fit on their own lines.
+    assert_eq!(
+        format(SOURCE, options.clone()),
+        "matrix = [\n  [1, 2, 3],\n  [4, 5, 6],\n  [7, 8, 9],\n]\n"
+    );
+
+    // Arrays that fit are collapsed.
+    const EXPANDED: &str = "matrix = [\n  [1, 2],\n  [3, 4],\n]\n";
+    assert_eq!(format(EXPANDED, options), "matrix = [[1, 2], [3, 4]]\n");
+}
+
+#[test]
+fn test_sort_entries_moves_comments() {
+    const SOURCE: &str = r#"[dependencies]
+# Serialization
+serde = "1"
+# Async runtime
+tokio = "1"
+anyhow = "1" # errors
+
+[package]
+version = "0.1.0"
+name = "test"
+inline = { b = 2, a = 1 }
+"#;
+    let options = Options::builder().sort_entries(true).build().unwrap();
+
+    assert_eq!(
+        format(SOURCE, options),
+        r#"[dependencies]
+anyhow = "1" # errors
+# Serialization
+serde = "1"
+# Async runtime
+tokio = "1"
+
+[package]
+inline = { a = 1, b = 2 }
+name = "test"
+version = "0.1.0"
+"#
+    );
+}
+
+#[test]
+fn test_reorder_tables() {
+    const SOURCE: &str = r#"name = "root"
+
+[dev-dependencies]
+insta = "1"
+
+# The manifest.
+[package]
+name = "test"
+
+[[bin]]
+name = "b"
+[bin.metadata]
+x = 1
+
+[[bin]]
+name = "a"
+
+[dependencies]
+serde = "1"
+"#;
+    let options =
+        Options::builder().reorder_tables(true).table_priority(["package"]).build().unwrap();
+
+    assert_eq!(
+        format(SOURCE, options),
+        r#"name = "root"
+
+# The manifest.
+[package]
+name = "test"
+
+[[bin]]
+name = "b"
+[bin.metadata]
+x = 1
+
+[[bin]]
+name = "a"
+
+[dependencies]
+serde = "1"
+
+[dev-dependencies]
+insta = "1"
+"#
+    );
+}
+
+#[test]
+fn test_expand_inline_tables() {
+    const SOURCE: &str = r#"[dependencies]
+# Tokio
+tokio = { version = "1", features = ["full"], default-features = false } # async
+serde = "1"
+
+[package]
+name = "test"
+"#;
+    let options = Options::builder().expand_inline_tables(true).column_width(40).build().unwrap();
+
+    assert_eq!(
+        format(SOURCE, options),
+        r#"[dependencies]
+serde = "1"
+
+# Tokio
+[dependencies.tokio] # async
+version = "1"
+features = ["full"]
+default-features = false
+
+[package]
+name = "test"
+"#
+    );
+}
+
+#[test]
+fn test_array_auto_collapse_respects_column_width() {
+    const SOURCE: &str = "short = [\n  1,\n  2,\n]\nlong = [\n  111111111,\n  222222222,\n]\n";
+    let options = Options::builder().array_auto_expand(false).column_width(20).build().unwrap();
+
+    assert_eq!(
+        format(SOURCE, options),
+        "short = [1, 2]\nlong = [\n  111111111,\n  222222222,\n]\n"
+    );
+}
+
+#[test]
+fn test_align_entries_with_multiline_values() {
+    const SOURCE: &str = "a = 1\nlong_key = [\n  1, # one\n  2,\n]\nccc = 3\n\nx = 1\nyy = 2\n";
+    let options = Options::builder().align_entries(true).build().unwrap();
+
+    assert_eq!(
+        format(SOURCE, options),
+        "a        = 1\nlong_key = [\n  1, # one\n  2,\n]\nccc      = 3\n\nx  = 1\nyy = 2\n"
+    );
+}
+
+#[test]
+fn test_align_comments_around_multiline_values() {
+    const SOURCE: &str = "a = 1 # one\nlong_key = \"value\" # two\narr = [\n  1, # x\n  2222, # y\n] # array\nccc = 3 # three\n";
+    let options = Options::builder().align_comments(true).build().unwrap();
+
+    assert_eq!(
+        format(SOURCE, options),
+        "a = 1              # one\nlong_key = \"value\" # two\narr = [\n  1,    # x\n  2222, # y\n] # array\nccc = 3            # three\n"
+    );
+}
+
+#[test]
+fn test_blank_lines_before_table() {
+    const SOURCE: &str = "[a]\nx = 1\n[b]\ny = 2\n\n\n# about c\n[c]\n[[d]]\n";
+    let options = Options::builder().blank_lines_before_table(Some(1)).build().unwrap();
+
+    assert_eq!(format(SOURCE, options), "[a]\nx = 1\n\n[b]\ny = 2\n\n# about c\n[c]\n\n[[d]]\n");
+
+    let options = Options::builder().blank_lines_before_table(Some(0)).build().unwrap();
+    assert_eq!(format(SOURCE, options), "[a]\nx = 1\n[b]\ny = 2\n# about c\n[c]\n[[d]]\n");
+}
+
+#[test]
+fn test_blank_lines_with_whitespace() {
+    const SOURCE: &str = "a = 1\n  \n\t\n  \n  \nb = 2\n[table]\n \n \n \n \nc = 3\n";
+
+    assert_eq!(format(SOURCE, Options::default()), "a = 1\n\n\nb = 2\n[table]\n\n\nc = 3\n");
+
+    let options = Options::builder().allowed_blank_lines(1).build().unwrap();
+    assert_eq!(format(SOURCE, options), "a = 1\n\nb = 2\n[table]\n\nc = 3\n");
+}
+
+#[test]
+fn test_line_ending() {
+    const SOURCE: &str = "a = \"\"\"\r\nfoo\r\nbar\"\"\"\r\nb = '''\nbaz'''\n\n[table]\r\nc = [\r\n  1, # one\r\n  2,\r\n]\r\n";
+
+    let options = Options::builder().line_ending(LineEnding::Lf).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        "a = \"\"\"\nfoo\nbar\"\"\"\nb = '''\nbaz'''\n\n[table]\nc = [\n  1, # one\n  2,\n]\n"
+    );
+
+    let crlf = "a = \"\"\"\r\nfoo\r\nbar\"\"\"\r\nb = '''\r\nbaz'''\r\n\r\n[table]\r\nc = [\r\n  1, # one\r\n  2,\r\n]\r\n";
+    let options = Options::builder().line_ending(LineEnding::Crlf).build().unwrap();
+    assert_eq!(format(SOURCE, options), crlf);
+
+    let options = Options::builder().line_ending(LineEnding::Preserve).build().unwrap();
+    assert_eq!(format(SOURCE, options.clone()), crlf);
+    assert_eq!(format("a = 1\nb = 2\r\n", options), "a = 1\nb = 2\n");
+}
+
+#[test]
+fn test_trailing_newline() {
+    assert_eq!(format("a = 1", Options::default()), "a = 1\n");
+    assert_eq!(format("a = 1\n\n\n", Options::default()), "a = 1\n");
+    assert_eq!(format("a = 1\n  \n\t\n", Options::default()), "a = 1\n");
+    assert_eq!(format("[t]\na = 1\n\n# c\r\n\r\n", Options::default()), "[t]\na = 1\n\n# c\n");
+
+    let options = Options::builder().trailing_newline(false).build().unwrap();
+    assert_eq!(format("a = 1\n\n\n", options.clone()), "a = 1");
+    assert_eq!(format("a = 1", options), "a = 1");
+}
+
+#[test]
+fn test_trim_trailing_whitespace() {
+    const SOURCE: &str = "a = 1 # comment \t\nb = \"\"\"x  \ny\"\"\"  \n  \t\n# oxc-toml: fmt off\nc  =  2   \r\n# oxc-toml: fmt on\n";
+
+    assert_eq!(
+        format(SOURCE, Options::default()),
+        "a = 1 # comment \t\nb = \"\"\"x  \ny\"\"\"\n\n# oxc-toml: fmt off\nc  =  2   \r\n# oxc-toml: fmt on\n"
+    );
+    let options = Options::builder().trim_trailing_whitespace(true).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        "a = 1 # comment\nb = \"\"\"x  \ny\"\"\"\n\n# oxc-toml: fmt off\nc  =  2\r\n# oxc-toml: fmt on\n"
+    );
+}
+
+#[test]
+fn test_format_bytes() {
+    assert_eq!(format_bytes(b"a  =  1\n", Options::default()).unwrap(), "a = 1\n");
+    assert_eq!(
+        format_bytes(b"a  =  1\n\xc3", Options::default()).unwrap_err(),
+        EncodingError { offset: 8, len: None }
+    );
+}
+
+#[test]
+fn test_preserve_bom() {
+    const SOURCE: &str = "\u{FEFF}# comment\na  =  1\n";
+
+    assert_eq!(format(SOURCE, Options::default()), "\u{FEFF}# comment\na = 1\n");
+    let options = Options::builder().preserve_bom(false).build().unwrap();
+    assert_eq!(format(SOURCE, options), "# comment\na = 1\n");
+    // Removing it would change more than whitespace.
+    let options = Options::builder().preserve_bom(false).whitespace_only(true).build().unwrap();
+    assert_eq!(format(SOURCE, options), "\u{FEFF}# comment\na = 1\n");
+}
+
+#[test]
+fn test_unquote_keys() {
+    const SOURCE: &str = r#""a" = 1
+'b-c'.d_e = 2
+"with space" = 3
+"" = 4
+"esc\u0061ped" = 5
+"ü" = 6
+c = { "x" = 1, 'y z' = 2 }
+
+["table"."sub"]
+"e" = 7
+"#;
+
+    let options = Options::builder().unquote_keys(true).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        r#"a = 1
+b-c.d_e = 2
+"with space" = 3
+"" = 4
+"esc\u0061ped" = 5
+"ü" = 6
+c = { x = 1, 'y z' = 2 }
+
+[table.sub]
+e = 7
+"#
+    );
+
+    assert_eq!(format(SOURCE, Options::default()), SOURCE);
+
+    // Bare keys with non-ASCII characters are kept as they are, but never written.
+    assert_eq!(format("ü = 1\n", Options::default()), "ü = 1\n");
+}
+
+#[test]
+fn test_integer_underscores() {
+    const SOURCE: &str = "a = 1000000\nb = -12_34_5\nc = 8080\nd = 0xdeadbeef\ne = 0o1234567\nf = 0b1_0101\ng = [+100000, 1.5e6]\n";
+
+    let options =
+        Options::builder().integer_underscores(IntegerUnderscores::Group).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        "a = 1_000_000\nb = -12_345\nc = 8080\nd = 0xdead_beef\ne = 0o1_234_567\nf = 0b1_0101\ng = [+100_000, 1.5e6]\n"
+    );
+
+    let options =
+        Options::builder().integer_underscores(IntegerUnderscores::Remove).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        "a = 1000000\nb = -12345\nc = 8080\nd = 0xdeadbeef\ne = 0o1234567\nf = 0b10101\ng = [+100000, 1.5e6]\n"
+    );
+
+    assert_eq!(format(SOURCE, Options::default()), SOURCE);
+}
+
+#[test]
+fn test_hex_case() {
+    const SOURCE: &str = "a = 0xDEADbeef\nb = 0xff_FF\nc = 0o17\nd = 0XFF\n";
+
+    let options = Options::builder().hex_case(HexCase::Lower).build().unwrap();
+    assert_eq!(format(SOURCE, options), "a = 0xdeadbeef\nb = 0xff_ff\nc = 0o17\nd = 0XFF\n");
+
+    let options = Options::builder()
+        .hex_case(HexCase::Upper)
+        .integer_underscores(IntegerUnderscores::Group)
+        .build()
+        .unwrap();
+    assert_eq!(format(SOURCE, options), "a = 0xDEAD_BEEF\nb = 0xFFFF\nc = 0o17\nd = 0XFF\n");
+}
+
+#[test]
+fn test_normalize_date_times() {
+    const SOURCE: &str = "a = 1979-05-27t07:32:00z\nb = 1979-05-27 07:32:00.5-07:00\nc = 1979-05-27T00:32:00.1234\nd = 07:32:00.12\ne = 1979-05-27\n";
+
+    let options = Options::builder().normalize_date_times(true).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        "a = 1979-05-27T07:32:00Z\nb = 1979-05-27 07:32:00.500-07:00\nc = 1979-05-27T00:32:00.123400\nd = 07:32:00.120\ne = 1979-05-27\n"
+    );
+
+    let options = Options::builder().date_time_separator(DateTimeSeparator::T).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        "a = 1979-05-27T07:32:00z\nb = 1979-05-27T07:32:00.5-07:00\nc = 1979-05-27T00:32:00.1234\nd = 07:32:00.12\ne = 1979-05-27\n"
+    );
+
+    let options = Options::builder().date_time_separator(DateTimeSeparator::Space).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        "a = 1979-05-27 07:32:00z\nb = 1979-05-27 07:32:00.5-07:00\nc = 1979-05-27 00:32:00.1234\nd = 07:32:00.12\ne = 1979-05-27\n"
+    );
+
+    assert_eq!(format(SOURCE, Options::default()), SOURCE);
+}
+
+#[test]
+fn test_expand_dotted_keys() {
+    const SOURCE: &str = r#"name = "x"
+# about c
+a.b.c = 1
+a.b.d = 2 # d
+a.x = 3
+
+[t]
+y = 1
+m."n o".p = 2
+
+[[arr]]
+k.l.m = 1
+"#;
+
+    let options = Options::builder().expand_dotted_keys(Some(3)).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        r#"name = "x"
+a.x = 3
+
+[a.b]
+# about c
+c = 1
+d = 2 # d
+
+[t]
+y = 1
+
+[t.m."n o"]
+p = 2
+
+[[arr]]
+
+[arr.k.l]
+m = 1
+"#
+    );
+
+    let options = Options::builder().expand_dotted_keys(Some(2)).build().unwrap();
+    assert_eq!(format("a.b = 1\nc = 2\n", options), "c = 2\n\n[a]\nb = 1\n");
+
+    // The new sections are separated the same way before a header and at the end.
+    let options = Options::builder().expand_dotted_keys(Some(3)).build().unwrap();
+    assert_eq!(
+        format("a.b.c = 1\nx = 2\n[t]\nq.r.s = 1\n", options),
+        "x = 2\n\n[a.b]\nc = 1\n\n[t]\n\n[t.q.r]\ns = 1\n"
+    );
+
+    // Keys that are quoted differently belong to the same table.
+    let options = Options::builder().expand_dotted_keys(Some(2)).build().unwrap();
+    assert_eq!(format("a.b = 1\n\"a\".c = 2\nx = 3\n", options), "x = 3\n\n[a]\nb = 1\nc = 2\n");
+
+    // Quotes within quoted parts are part of the key.
+    let options = Options::builder().expand_dotted_keys(Some(2)).build().unwrap();
+    assert_eq!(
+        format("\"a\\\"b\".c = 1\n'a\"b'.d = 2\nab.e = 3\n", options),
+        "[\"a\\\"b\"]\nc = 1\nd = 2\n\n[ab]\ne = 3\n"
+    );
+
+    // Sections at the start of the document are separated from the header after them.
+    let options = Options::builder().expand_dotted_keys(Some(2)).build().unwrap();
+    assert_eq!(format("a.b = 1\n[t]\nx = 1\n", options), "[a]\nb = 1\n\n[t]\nx = 1\n");
+
+    // Documents with errors are left alone.
+    let options = Options::builder().expand_dotted_keys(Some(2)).build().unwrap();
+    assert_eq!(format("a.b = 1\nc = \n", options), "a.b = 1\nc = \n");
+}
+
+#[test]
+fn test_collapse_tables() {
+    const SOURCE: &str = r#"# preamble
+
+[package]
+name = "x"
+version = "1"
+
+[profile.release]
+lto = true
+
+[workspace]
+
+[workspace.metadata]
+foo = 1
+
+[[bin]]
+name = "a"
+[bin.x]
+y = 1
+
+[[bin]]
+name = "b"
+
+# kept
+[kept]
+a = 1
+
+[commented]
+a = 1 # kept
+"#;
+
+    let options = Options::builder().collapse_tables(true).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options.clone()),
+        r#"# preamble
+
+profile.release.lto = true
+workspace.metadata.foo = 1
+
+[package]
+name = "x"
+version = "1"
+
+[[bin]]
+name = "a"
+x.y = 1
+
+[[bin]]
+name = "b"
+
+# kept
+[kept]
+a = 1
+
+[commented]
+a = 1 # kept
+"#
+    );
+
+    assert_eq!(format("r = 1\n\n[a.b]\nk = 1\n", options.clone()), "r = 1\na.b.k = 1\n");
+    assert_eq!(format("[a.b]\nk = 1\n\n[x.y]\nm = 2\n", options.clone()), "a.b.k = 1\nx.y.m = 2\n");
+
+    // Tables are compared by their unescaped keys, `'"a"'` is not the table `a`.
+    for (source, expected) in [
+        ("[a]\n[a.b]\nc = 1\n", "a.b.c = 1\n"),
+        ("[a]\nx = 1\n\n['\"a\"'.b]\ny = 2\n", "a.x = 1\n'\"a\"'.b.y = 2\n"),
+        ("[a]\n[\"\\u0061\".b]\ny = 2\n", "a.b.y = 2\n"),
+    ] {
+        let formatted = format(source, options.clone());
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted, options.clone()), formatted);
+    }
+}
+
+#[test]
+fn test_inline_small_tables() {
+    const SOURCE: &str = r#"name = "x"
+
+[server]
+host = "localhost"
+port = 8080
+
+[big]
+a = 1
+b = 2
+c = 3
+
+[one]
+x = 1
+
+[commented]
+a = 1 # kept
+
+[long]
+description = "a string that is too long to fit into the default column width of one hundred characters"
+"#;
+
+    let options = Options::builder().inline_small_tables(Some(2)).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options.clone()),
+        r#"name = "x"
+server = { host = "localhost", port = 8080 }
+one = { x = 1 }
+
+[big]
+a = 1
+b = 2
+c = 3
+
+[commented]
+a = 1 # kept
+
+[long]
+description = "a string that is too long to fit into the default column width of one hundred characters"
+"#
+    );
+
+    let options = OptionsBuilder::from(options)
+        .collapse_tables(true)
+        .compact_inline_tables(true)
+        .build()
+        .unwrap();
+    assert_eq!(
+        format("[a.b]\nk = 1\n\n[a.c]\nk = 1\nl = 2\n", options),
+        "a.b.k = 1\na.c = {k = 1, l = 2}\n"
+    );
+
+    // Tables are compared by their unescaped keys, `'"a"'` is not the table `a`.
+    let options = Options::builder().inline_small_tables(Some(2)).build().unwrap();
+    for (source, expected) in [
+        ("[a]\nx=1\n[a.b]\ny=2\n", "a = { x = 1, b = { y = 2 } }\n"),
+        ("[\"\\u0061\"]\nx = 1\n[a.b]\ny = 2\n", "\"\\u0061\" = { x = 1, b = { y = 2 } }\n"),
+        ("[a]\nx = 1\n\n['\"a\"'.b]\ny = 2\n", "a = { x = 1 }\n'\"a\"'.b = { y = 2 }\n"),
+    ] {
+        let formatted = format(source, options.clone());
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted, options.clone()), formatted);
+    }
+}
+
+#[test]
+fn test_indent_entries() {
+    const SOURCE: &str = r#"root = 1
+
+[a]
+x = 1
+
+# about b
+[a.b]
+y = [
+  1, # one
+  2,
+]
+
+[[a."c"]]
+z = 1
+
+[d]
+e = 1
+"#;
+
+    let options = Options::builder().indent_entries(true).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options.clone()),
+        r#"root = 1
+
+[a]
+  x = 1
+
+# about b
+[a.b]
+  y = [
+    1, # one
+    2,
+  ]
+
+[[a."c"]]
+  z = 1
+
+[d]
+  e = 1
+"#
+    );
+
+    let options = OptionsBuilder::from(options).indent_tables(true).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        r#"root = 1
+
+[a]
+  x = 1
+
+  # about b
+  [a.b]
+    y = [
+      1, # one
+      2,
+    ]
+
+  [[a."c"]]
+    z = 1
+
+[d]
+  e = 1
+"#
+    );
+
+    // Keys are compared unescaped, `'"a"'` is a different table than `a`.
+    let options = Options::builder().indent_tables(true).build().unwrap();
+    assert_eq!(
+        format("[a]\n[\"\\u0061\".b]\n['\"a\"'.c]\n", options),
+        "[a]\n  [\"\\u0061\".b]\n['\"a\"'.c]\n"
+    );
+}
+
+#[test]
+fn test_array_tables() {
+    const SOURCE: &str = r#"[[bin]]
+name = "a"
+[[bin]]
+name = "b"
+
+[[test]]
+name = "t"
+[test.x]
+y = 1
+[[test]]
+name = "u"
+# c
+[[test]]
+name = "v"
+"#;
+
+    let options = Options::builder().inline_array_tables(true).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        r#"bin = [{ name = "a" }, { name = "b" }]
+
+[[test]]
+name = "t"
+[test.x]
+y = 1
+[[test]]
+name = "u"
+# c
+[[test]]
+name = "v"
+"#
+    );
+
+    let options = Options::builder().separate_array_tables(true).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        r#"[[bin]]
+name = "a"
+
+[[bin]]
+name = "b"
+
+[[test]]
+name = "t"
+[test.x]
+y = 1
+
+[[test]]
+name = "u"
+
+# c
+[[test]]
+name = "v"
+"#
+    );
+}
+
+#[test]
+fn test_fmt_off() {
+    const SOURCE: &str = r#"a   =   1
+
+# oxc-toml: fmt off
+matrix = [
+  1, 0,
+  0, 1,
+]
+b   =   2
+# oxc-toml: fmt on
+c   =   3
+
+[t]
+x   =   1   # oxc-toml: fmt off
+[u]
+y   =   2
+"#;
+
+    assert_eq!(
+        format(SOURCE, Options::default()),
+        r#"a = 1
+
+# oxc-toml: fmt off
+matrix = [
+  1, 0,
+  0, 1,
+]
+b   =   2
+# oxc-toml: fmt on
+c = 3
+
+[t]
+x   =   1   # oxc-toml: fmt off
+[u]
+y   =   2
+"#
+    );
+
+    // Tables are not moved out of or into disabled regions.
+    let options = Options::builder().reorder_tables(true).build().unwrap();
+    let source = "[b]\nx = 1\n\n# oxc-toml: fmt off\n[a]\ny   =   2\n";
+    assert_eq!(format(source, options), source);
+}
+
+#[test]
+fn test_ignore_directive() {
+    const SOURCE: &str = r#"a   =   1
+# oxc-toml: ignore
+matrix = [
+  1, 0,
+  0, 1,
+]
+b   =   2
+
+# oxc-toml: ignore
+[deps]
+serde   = "1"
+tokio   = "1"
+
+[t]
+x   =   1
+"#;
+
+    assert_eq!(
+        format(SOURCE, Options::default()),
+        r#"a = 1
+# oxc-toml: ignore
+matrix = [
+  1, 0,
+  0, 1,
+]
+b = 2
+
+# oxc-toml: ignore
+[deps]
+serde   = "1"
+tokio   = "1"
+
+[t]
+x = 1
+"#
+    );
+}
+
+#[test]
+fn test_strip_comments() {
+    const SOURCE: &str = r#"# header comment
+
+a = 1   # trailing
+# about b
+b = [
+  1, # one
+  # alone
+  2,
+]
+
+# about t
+[t] # header
+x = { y = 1 } # end
+# last
+"#;
+
+    let options = Options::builder().strip_comments(true).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        r#"a = 1
+b = [1, 2]
+
+[t]
+x = { y = 1 }
+"#
+    );
+}
+
+#[test]
+fn test_array_layout_preserve() {
+    const SOURCE: &str = r#"short = [
+  1,
+  2
+]
+long = [1111111111, 2222222222, 3333333333, 4444444444, 5555555555, 6666666666, 7777777777]
+single = [  1,2  ]
+"#;
+
+    let options =
+        Options::builder().array_layout(ArrayLayout::Preserve).column_width(40).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        r#"short = [
+  1,
+  2,
+]
+long = [1111111111, 2222222222, 3333333333, 4444444444, 5555555555, 6666666666, 7777777777]
+single = [1, 2]
+"#
+    );
+
+    let options = Options::builder().column_width(40).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        r#"short = [1, 2]
+long = [
+  1111111111,
+  2222222222,
+  3333333333,
+  4444444444,
+  5555555555,
+  6666666666,
+  7777777777,
+]
+single = [1, 2]
+"#
+    );
+}
+
+#[test]
+fn test_options_builder() {
+    let options = Options::builder().indent_string("\t").indent_entries(true).build().unwrap();
+    assert_eq!(options.indent_string, "\t");
+    assert_eq!(format("[a]\nb = 1\n", options), "[a]\n\tb = 1\n");
+
+    let err = Options::builder().column_width(0).build().unwrap_err();
+    assert_eq!(err.option, "column_width");
+    let err = Options::builder().indent_string("--").build().unwrap_err();
+    assert_eq!(err.to_string(), "invalid `indent_string`: must only contain spaces and tabs");
+}
+
+#[test]
+fn test_validate_options() {
+    assert_eq!(Options::default().validate(), Ok(()));
+
+    let mut options = Options::default();
+    options.column_width = 0;
+    options.collapse_tables = true;
+    options.expand_dotted_keys = Some(2);
+    options.allowed_blank_lines = 1;
+    options.blank_lines_before_table = Some(2);
+    options.table_priority = vec!["package".into()];
+
+    let errors = options.validate().unwrap_err();
+    assert_eq!(
+        errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        [
+            "invalid `column_width`: must be greater than zero",
+            "invalid `collapse_tables`: conflicts with `expand_dotted_keys`",
+            "invalid `blank_lines_before_table`: must not be more than `allowed_blank_lines`",
+            "invalid `table_priority`: has no effect without `reorder_tables`",
+        ]
+    );
+
+    let err = OptionsBuilder::from(options).build().unwrap_err();
+    assert_eq!(err.option, "column_width");
+}
+
+#[test]
+fn test_format_rules() {
+    const SOURCE: &str = r#"[package]
+name = "a"
+edition = "2024"
+
+[dependencies]
+toml = "1"
+serde = { version = "1", features = ["derive"] }
+
+[dev-dependencies.insta]
+version = "1"
+features = ["json"]
+"#;
+
+    let options = Options::default();
+    let rules = [
+        FormatRule {
+            keys: vec!["dependencies".into()],
+            options: Options::builder().reorder_keys(true).build().unwrap(),
+        },
+        FormatRule {
+            keys: vec!["*-dependencies.*".into(), "dependencies.serde".into()],
+            options: Options::builder()
+                .reorder_keys(true)
+                .compact_inline_tables(true)
+                .build()
+                .unwrap(),
+        },
+    ];
+    assert_eq!(
+        format_with_rules(SOURCE, options, &rules),
+        r#"[package]
+name = "a"
+edition = "2024"
+
+[dependencies]
+serde = {version = "1", features = ["derive"]}
+toml = "1"
+
+[dev-dependencies.insta]
+features = ["json"]
+version = "1"
+"#
+    );
+
+    // Quoted parts are compared unescaped and keep their inner quotes.
+    let rules = [FormatRule {
+        keys: vec!["'a\"b'.*".into()],
+        options: Options::builder().compact_entries(true).build().unwrap(),
+    }];
+    assert_eq!(
+        format_with_rules("[\"a\\\"b\"]\nx = 1\n[ab]\ny = 2\n", Options::default(), &rules),
+        "[\"a\\\"b\"]\nx=1\n[ab]\ny = 2\n"
+    );
+}
+
+#[test]
+fn test_profiles() {
+    const SOURCE: &str =
+        "[package]\nname = \"a\"\n\n\n[features]\ndefault = [ \"std\" ] # comment\n";
+
+    for options in [Options::cargo(), Options::compact(), Options::taplo_default()] {
+        assert_eq!(options.validate(), Ok(()));
+    }
+
+    assert_eq!(
+        format(SOURCE, Options::cargo()),
+        "[package]\nname = \"a\"\n\n[features]\ndefault = [\"std\"] # comment\n"
+    );
+    assert_eq!(
+        format(SOURCE, Options::compact()),
+        "[package]\nname=\"a\"\n[features]\ndefault=[\"std\"] # comment\n"
+    );
+    assert_eq!(
+        format(SOURCE, Options::taplo_default()),
+        "[package]\nname = \"a\"\n\n\n[features]\ndefault = [\"std\"] # comment\n"
+    );
+}
+
+#[test]
+fn test_whitespace_only() {
+    let options = Options::builder()
+        .whitespace_only(true)
+        .reorder_keys(true)
+        .unquote_keys(true)
+        .hex_case(HexCase::Upper)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        format("\"b\"   =  0xff\na=[1,\n  2]\nc = [ 1, 2, ]   # comment\n", options.clone()),
+        "\"b\" = 0xff\na = [1, 2]\nc = [1, 2,] # comment\n"
+    );
+
+    // Trailing commas of inline tables are removed, so nothing is changed.
+    const SOURCE: &str = "a  =  1\nb = { x = 1, }\n";
+    assert_eq!(format(SOURCE, options), SOURCE);
+}
+
+#[test]
+fn test_minimal() {
+    let options = Options::builder().minimal(true).reorder_keys(true).build().unwrap();
+
+    const SOURCE: &str = r#"   # The name
+   "b"   =  0xff # comment
+
+
+a=[1 ,2,
+     [3,4],  # three
+      ]
+  [table]
+      x={ y=1,z=2 }
+  long = """
+    kept   =  as is
+"""
+"#;
+    assert_eq!(
+        format(SOURCE, options.clone()),
+        r#"# The name
+"b" = 0xff # comment
+
+
+a = [1, 2,
+  [3, 4],  # three
+]
+[table]
+x = { y = 1, z = 2 }
+long = """
+    kept   =  as is
+"""
+"#
+    );
+
+    let indented = Options::builder().minimal(true).indent_entries(true).compact_entries(true);
+    assert_eq!(
+        format("a = [[\n1],\n]\n[table]\n# x\nx = 1\n", indented.build().unwrap()),
+        "a=[[\n  1],\n]\n[table]\n  # x\n  x=1\n"
+    );
+
+    // Formatting is still disabled by directives.
+    const DISABLED: &str = "# oxc-toml: fmt off\na=[1 ,2]\n# oxc-toml: fmt on\nb=1\n";
+    assert_eq!(
+        format(DISABLED, options),
+        "# oxc-toml: fmt off\na=[1 ,2]\n# oxc-toml: fmt on\nb = 1\n"
+    );
+}
+
+#[test]
+fn test_format_with_hooks() {
+    const SOURCE: &str = r#"[server]
+token   =   "secret" # redacted
+port = 8080
+
+[dependencies]
+serde = "v1.0"
+log = { version = "v0.4", features = ["v1"] }
+tokens = ["a", "b"]
+"#;
+
+    let hooks = ValueHooks::new()
+        .hook("*.token", |_, _| Some("\"***\"".into()))
+        .hook("dependencies.*", |_, text| {
+            text.strip_prefix("\"v").map(|version| format!("\"{version}"))
+        })
+        .hook("dependencies.tokens", |key, text| {
+            assert_eq!(key, "dependencies.tokens");
+            Some(text.to_uppercase())
+        });
+    assert_eq!(
+        format_with_hooks(SOURCE, Options::default(), &hooks),
+        r#"[server]
+token = "***" # redacted
+port = 8080
+
+[dependencies]
+serde = "1.0"
+log = { version = "v0.4", features = ["v1"] }
+tokens = ["A", "B"]
+"#
+    );
+}
+
+#[test]
+fn test_format_with_diagnostics() {
+    const SOURCE: &str = "a =\n# oxc-toml: ignore\nb  =  1\n";
+
+    let options = Options::builder().reorder_tables(true).build().unwrap();
+    let rules = [FormatRule { keys: vec!["a.".into()], options: Options::default() }];
+    let (formatted, diagnostics) = format_with_diagnostics(SOURCE, options, &rules);
+    assert!(formatted.ends_with("# oxc-toml: ignore\nb  =  1\n"));
+    assert_eq!(
+        diagnostics.iter().map(|d| (d.kind, d.range.clone())).collect::<Vec<_>>(),
+        [
+            (FormatDiagnosticKind::SyntaxError, Some(3..4)),
+            (FormatDiagnosticKind::InvalidRulePattern, None),
+            (FormatDiagnosticKind::Ignored, Some(4..30)),
+            (FormatDiagnosticKind::TablesNotRestructured, None),
+        ]
+    );
+    assert_eq!(diagnostics[1].to_string(), "rule 0 has an invalid key pattern `a.`");
+
+    let (_, diagnostics) = format_with_diagnostics("a = 1\n", Options::default(), &[]);
+    assert!(diagnostics.is_empty());
+
+    // Entries after a table that start like the keys of the root table.
+    let source = "edition = 1\n[profile.release]\nlto = true\n[deps]\nx = 1\nedition = 2\nprofile.dev.lto = true\n[profile.dev]\nprofile = 1\n";
+    let (formatted, diagnostics) = format_with_diagnostics(source, Options::default(), &[]);
+    assert_eq!(formatted, source);
+    assert_eq!(
+        diagnostics.iter().map(|d| (d.kind, d.range.clone())).collect::<Vec<_>>(),
+        [(FormatDiagnosticKind::MisplacedRootEntry, Some(54..65))]
+    );
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "`edition` is in the table `deps`, not in the root table with `edition` (54..65)"
+    );
+
+    // Keys are compared unescaped.
+    let source = "\"na\\u006de\" = 1\n[t]\n'name' = 2\n";
+    let (_, diagnostics) = format_with_diagnostics(source, Options::default(), &[]);
+    assert_eq!(
+        diagnostics.iter().map(|d| (d.kind, d.range.clone())).collect::<Vec<_>>(),
+        [(FormatDiagnosticKind::MisplacedRootEntry, Some(20..30))]
+    );
+
+    // Only entries of the root table count, tables are commonly named like the keys of others.
+    for source in [
+        "[package]\nname = \"x\"\n[package.metadata.docs.rs]\nfeatures = [\"a\"]\n[features]\na = []\n",
+        "[dependencies.tokio]\nversion = \"1\"\nfeatures = [\"full\"]\n[features]\na = []\n",
+    ] {
+        let (_, diagnostics) = format_with_diagnostics(source, Options::default(), &[]);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    }
+}
+
+#[test]
+fn test_toml_version() {
+    const SOURCE: &str = "a = {\n  b = 1,\n}\nc = {d=1}\n";
+
+    assert_eq!(format(SOURCE, Options::default()), "a = { b = 1 }\nc = { d = 1 }\n");
+    // Newlines and trailing commas in inline tables are syntax errors in TOML 1.0.
+    let options = Options::builder().toml_version(TomlVersion::V1_0).build().unwrap();
+    assert_eq!(format(SOURCE, options), "a = {\n  b = 1,\n}\nc = { d = 1 }\n");
+}
+
+#[test]
+fn test_portable_escapes() {
+    const SOURCE: &str = "a = \"\\e[0m \\x41 \\\\x41\"\nb = \"\"\"\n\\xff\"\"\"\nc = '\\x41'\n";
+
+    assert_eq!(format(SOURCE, Options::default()), SOURCE);
+    let options = Options::builder().portable_escapes(true).build().unwrap();
+    assert_eq!(
+        format(SOURCE, options),
+        "a = \"\\u001B[0m \\u0041 \\\\x41\"\nb = \"\"\"\n\\u00ff\"\"\"\nc = '\\x41'\n"
+    );
+}
+
+#[test]
+fn test_normalize_line_continuations() {
+    const SOURCE: &str =
+        "[a]\nb = \"\"\"one \\  \n\n      two \\\\\nthree\\\n\"\"\"\nc = '''x \\\n  y'''\n";
+
+    assert_eq!(format(SOURCE, Options::default()), SOURCE);
+    let options =
+        Options::builder().indent_entries(true).normalize_line_continuations(true).build().unwrap();
+    let formatted = format(SOURCE, options);
+    assert_eq!(
+        formatted,
+        "[a]\n  b = \"\"\"one \\\n    two \\\\\nthree\\\n  \"\"\"\n  c = '''x \\\n  y'''\n"
+    );
+    assert_eq!(
+        toml::from_str::<toml::Value>(&formatted).unwrap(),
+        toml::from_str::<toml::Value>(SOURCE).unwrap()
+    );
+}
+
+#[test]
+fn test_format_edits() {
+    let source = "a=1\nb = 2\n\n\n\n[t]\nc   =   [1,2]\nd = \"é\"\ne=3";
+    let edits = format_edits(source, Options::default());
+    let mut edited = source.to_string();
+    for edit in edits.iter().rev() {
+        edited.replace_range(edit.range.start as usize..edit.range.end as usize, &edit.new_text);
+    }
+    assert_eq!(edited, format(source, Options::default()));
+    assert_eq!(
+        edits,
+        vec![
+            TextEdit { range: 1..2, new_text: " = ".into() },
+            TextEdit { range: 12..13, new_text: String::new() },
+            TextEdit { range: 19..28, new_text: "= [1, ".into() },
+            TextEdit { range: 41..43, new_text: " = 3\n".into() },
+        ]
+    );
+    assert!(format_edits(&format(source, Options::default()), Options::default()).is_empty());
+}
+
+#[test]
+fn test_format_node() {
+    let source = "[ table ]\nkey   =   [1,2,{a=1}]\n";
+    let tree = oxc_toml::parse(source).into_syntax();
+    let nodes: Vec<_> = tree.root().children().iter().filter_map(|c| c.as_node()).collect();
+    let options = Options::default;
+    assert_eq!(format_node(nodes[0], source, options()), "[table]");
+    assert_eq!(format_node(nodes[1], source, options()), "key = [1, 2, { a = 1 }]");
+    let value = nodes[1].children().iter().filter_map(|c| c.as_node()).nth(1).unwrap();
+    assert_eq!(format_node(value, source, options()), "[1, 2, { a = 1 }]");
+    let key = nodes[1].children().iter().find_map(|c| c.as_node()).unwrap();
+    assert_eq!(format_node(key, source, options()), "key");
+    assert_eq!(format_node(tree.root(), source, options()), format(source, options()));
+}
+
+#[test]
+fn test_format_node_context() {
+    let source = "[t]\nlong = [\"aaaaaaaa\", \"bbbbbbbb\"]\n# oxc-toml: fmt off\nkeep   =   1\n";
+    let tree = oxc_toml::parse(source).into_syntax();
+    let nodes: Vec<_> = tree.root().children().iter().filter_map(|c| c.as_node()).collect();
+    let options = || Options::builder().indent_entries(true).column_width(24).build().unwrap();
+    assert_eq!(
+        format(source, options()),
+        "[t]\n  long = [\n    \"aaaaaaaa\",\n    \"bbbbbbbb\",\n  ]\n# oxc-toml: fmt off\nkeep   =   1\n"
+    );
+
+    // The entry is indented like in its table section, its first line is not.
+    assert_eq!(
+        format_node(nodes[1], source, options()),
+        "long = [\n    \"aaaaaaaa\",\n    \"bbbbbbbb\",\n  ]"
+    );
+    let value = nodes[1].children().iter().filter_map(|c| c.as_node()).nth(1).unwrap();
+    assert_eq!(
+        format_node(value, source, options()),
+        "[\n    \"aaaaaaaa\",\n    \"bbbbbbbb\",\n  ]"
+    );
+    assert_eq!(format_node(nodes[2], source, options()), "keep   =   1");
+}
+
+#[test]
+fn test_format_into() {
+    let sources = ["a=1\n[t]\nb  =  [1,2]", "\u{FEFF}c = 'x'   \n\n\n\n", "d = \"unclosed\n"];
+
+    let mut out = String::from("previous text");
+    for source in sources {
+        format_into(source, Options::default(), &mut out);
+        assert_eq!(out, format(source, Options::default()));
+    }
+
+    let options = Options::builder().trim_trailing_whitespace(true).minimal(true).build().unwrap();
+    let mut formatter = Formatter::new(options.clone());
+    for source in sources {
+        assert_eq!(formatter.format(source), format(source, options.clone()));
+        formatter.format_into(source, &mut out);
+        assert_eq!(out, format(source, options.clone()));
+    }
+    assert_eq!(formatter.options(), &options);
+}
+
+/// Format after every edit at every offset of a formatted document and compare with
+/// formatting the new document.
+#[test]
+fn test_format_incremental() {
+    const SOURCE: &str = r#"# The package
+name = "app"
+tags = ["a", "b"]
+
+[dependencies]
+serde = { version = "1" }
+text = """
+[not.a.table]
+"""
+
+# The binaries
+[[bin]]
+name = 'x'
+[bin.extra]
+list = [1, [2]]
+
+[[bin]]
+name = 'y'
+"#;
+    let option_sets = [
+        Options::default(),
+        Options::builder().align_entries(true).compact_entries(true).build().unwrap(),
+        Options::builder().indent_entries(true).separate_array_tables(true).build().unwrap(),
+        Options::builder().blank_lines_before_table(Some(2)).build().unwrap(),
+        Options::builder().reorder_tables(true).build().unwrap(),
+    ];
+    let texts = ["", "\n", "\n\n", "x=1\n", "  ", "[t]\n", "[[bin]]\n", "# c\n", "[", "\""];
+    for options in option_sets {
+        let source = format(SOURCE, options.clone());
+        let tree = oxc_toml::parse(&source).into_syntax();
+        for offset in (0..=source.len()).filter(|&idx| source.is_char_boundary(idx)) {
+            for len in [0, 1, 5] {
+                let end = (offset + len).min(source.len());
+                for text in texts {
+                    if len == 0 && text.is_empty() {
+                        continue;
+                    }
+                    let mut new_source = source.clone();
+                    new_source.replace_range(offset..end, text);
+                    let edit = TextEdit { range: offset as u32..end as u32, new_text: text.into() };
+
+                    let mut result = new_source.clone();
+                    let mut tree = tree.clone();
+                    for edit in format_incremental(&mut tree, edit, options.clone()).iter().rev() {
+                        let range = edit.range.start as usize..edit.range.end as usize;
+                        result.replace_range(range, &edit.new_text);
+                    }
+                    let expected = format(&new_source, options.clone());
+                    assert_eq!(result, expected, "{new_source:?}");
+
+                    // The tree is the one of the formatted document for the next edit.
+                    assert_eq!(tree.source(), expected);
+                    assert_eq!(
+                        format!("{:?}", tree.root()),
+                        format!("{:?}", oxc_toml::parse(&expected).tree.root()),
+                    );
+                }
+            }
+        }
+    }
+
+    // Only the sections of the edit are formatted, not the unformatted ones before them.
+    let source = format!("a=1\n{}", format(SOURCE, Options::default()));
+    let offset = source.find("name = 'y'").unwrap() as u32;
+    let edit = TextEdit { range: offset + 4..offset + 7, new_text: "=".into() };
+    let mut tree = oxc_toml::parse(&source).into_syntax();
+    let edits = format_incremental(&mut tree, edit, Options::default());
+    assert_eq!(edits, [TextEdit { range: offset + 4..offset + 5, new_text: " = ".into() }]);
+
+    // The tree is kept up to date across edits.
+    let edit = TextEdit { range: offset..offset, new_text: "x=1\n".into() };
+    let edits = format_incremental(&mut tree, edit, Options::default());
+    assert_eq!(edits, [TextEdit { range: offset + 1..offset + 2, new_text: " = ".into() }]);
+    assert!(tree.source().ends_with("[[bin]]\nx = 1\nname = 'y'\n"));
+}
+
+#[test]
+fn test_format_stream() {
+    const SOURCE: &str = r#"# The package
+name="app"
+
+
+[dependencies]
+serde={version="1"}
+text = """
+[not.a.table]
+"""
+list = [
+[1],
+  [2] ,
+]
+# oxc-toml: ignore
+[[bin]]
+name    =    'x'
+  [bin.extra]
+a=1 # oxc-toml: fmt off
+[[bin]]
+name    =    'y'
+# oxc-toml: fmt on
+[[bin]]
+name='z'
+[broken
+k=1
+"#;
+    let option_sets = [
+        Options::default(),
+        Options::builder().align_entries(true).compact_entries(true).build().unwrap(),
+        Options::builder().indent_entries(true).separate_array_tables(true).build().unwrap(),
+        Options::builder().blank_lines_before_table(Some(2)).strip_comments(true).build().unwrap(),
+        Options::builder().trailing_newline(false).build().unwrap(),
+    ];
+    for source in [SOURCE.to_string(), SOURCE.replace('\n', "\r\n"), format!("\u{FEFF}{SOURCE}")] {
+        for options in &option_sets {
+            let mut output = Vec::new();
+            format_stream(source.as_bytes(), &mut output, options.clone()).unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), format(&source, options.clone()));
+        }
+    }
+
+    // Tables are not moved between sections.
+    let options = Options::builder().reorder_tables(true).build().unwrap();
+    let mut output = Vec::new();
+    format_stream("[b]\nx=1\n[a]\n".as_bytes(), &mut output, options).unwrap();
+    assert_eq!(output, b"[b]\nx = 1\n[a]\n");
+
+    // Formatting stays disabled across sections and strings and arrays span their header lines.
+    let source = "a=1\n# oxc-toml: fmt off\n[x]\nb  =  2\n\n[y]\nc  =  3\n# oxc-toml: fmt on\nd=4\n[z]\n\
+                  s = \"\"\"\n[t]\n\"\"\"\"\nl = [\n[1]\n]\n# oxc-toml: ignore\n[w]\ne  =  5\n[v]\nf=6\n";
+    for options in &option_sets {
+        let mut output = Vec::new();
+        format_stream(source.as_bytes(), &mut output, options.clone()).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), format(source, options.clone()));
+    }
+
+    // The lines of a long string are not scanned again for every header line in it.
+    let source = format!(
+        "s = \"\"\"\n{}\"\"\"\n",
+        (0..8000).map(|idx| format!("[t{idx}]\n")).collect::<String>()
+    );
+    let mut output = Vec::new();
+    let start = std::time::Instant::now();
+    format_stream(source.as_bytes(), &mut output, Options::default()).unwrap();
+    assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    assert_eq!(String::from_utf8(output).unwrap(), source);
+
+    let error =
+        format_stream(&b"a = '\xFF'\n"[..], &mut Vec::new(), Options::default()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,31 @@
+use oxc_toml::{ColumnEncoding, LineCol, LineIndex};
+
+#[test]
+fn test_line_index() {
+    // `é` is 2 bytes in UTF-8 and `𝄞` is 4 bytes and 2 UTF-16 code units.
+    const SOURCE: &str = "a = 1\r\nb = \"é𝄞x\"\n\nc";
+    let index = LineIndex::new(SOURCE);
+    assert_eq!(index.line_count(), 4);
+
+    let x = SOURCE.find('x').unwrap() as u32;
+    let position = |line, column| LineCol { line, column };
+    for (encoding, column) in
+        [(ColumnEncoding::Utf8, 11), (ColumnEncoding::Utf16, 8), (ColumnEncoding::Utf32, 7)]
+    {
+        assert_eq!(index.line_col(x, encoding), position(1, column));
+        assert_eq!(index.offset(position(1, column), encoding), Some(x));
+    }
+
+    assert_eq!(index.line_col(0, ColumnEncoding::Utf16), position(0, 0));
+    assert_eq!(index.line_col(5, ColumnEncoding::Utf16), position(0, 5));
+    assert_eq!(index.line_col(SOURCE.len() as u32 + 10, ColumnEncoding::Utf8), position(3, 1));
+
+    // Offsets and columns in the middle of a character are moved to its start.
+    let clef = SOURCE.find('𝄞').unwrap() as u32;
+    assert_eq!(index.line_col(clef + 1, ColumnEncoding::Utf16), position(1, 6));
+    assert_eq!(index.offset(position(1, 7), ColumnEncoding::Utf16), Some(clef));
+
+    assert_eq!(index.offset(position(2, 0), ColumnEncoding::Utf8), Some(21));
+    assert_eq!(index.offset(position(2, 1), ColumnEncoding::Utf8), None);
+    assert_eq!(index.offset(position(4, 0), ColumnEncoding::Utf8), None);
+}
@@ -0,0 +1,84 @@
+use oxc_toml::{ArrayMerge, DocumentMut, MergeOptions, Options, merge};
+
+fn document(source: &str) -> DocumentMut {
+    DocumentMut::parse(source, Options::default()).unwrap()
+}
+
+const BASE: &str = r#"# The name
+name = "app" # old
+features = ["a"]
+
+[dependencies]
+serde = "1"
+log = { version = "0.4" }
+
+[[bin]]
+name = "a"
+"#;
+
+const OVERLAY: &str = r#"name = "lib"
+features = ["b"]
+
+[dependencies]
+log = { features = ["std"] }
+toml = "0.8"
+
+# The second binary
+[[bin]]
+name = "b"
+"#;
+
+#[test]
+fn test_merge() {
+    let merged = merge(&document(BASE), &document(OVERLAY), &MergeOptions::default()).unwrap();
+    assert_eq!(
+        merged.as_str(),
+        r#"# The name
+name = "lib" # old
+features = ["b"]
+
+[dependencies]
+serde = "1"
+log = { version = "0.4", features = ["std"] }
+toml = "0.8"
+
+# The second binary
+[[bin]]
+name = "b"
+"#
+    );
+}
+
+#[test]
+fn test_merge_options() {
+    let options = MergeOptions { arrays: ArrayMerge::Append, keep_comments: false };
+    let merged = merge(&document(BASE), &document(OVERLAY), &options).unwrap();
+    assert_eq!(
+        merged.as_str(),
+        r#"name = "lib"
+features = ["a", "b"]
+
+[dependencies]
+serde = "1"
+log = { version = "0.4", features = ["std"] }
+toml = "0.8"
+
+[[bin]]
+name = "a"
+
+# The second binary
+[[bin]]
+name = "b"
+"#
+    );
+}
+
+#[test]
+fn test_merge_conflicts() {
+    let base = document("a = 1\n\n[b]\nc = 2\n");
+    let overlay = document("b = 3\n\n[a]\nd = 4\n");
+    let merged = merge(&base, &overlay, &MergeOptions::default()).unwrap();
+    assert_eq!(merged.get("a.d"), Some(oxc_toml::Value::Integer(4)));
+    assert_eq!(merged.get("b"), Some(oxc_toml::Value::Integer(3)));
+    assert!(DocumentMut::parse(merged.as_str(), Options::default()).is_ok());
+}
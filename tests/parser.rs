@@ -0,0 +1,770 @@
+use oxc_toml::{
+    EncodingError, ErrorCategory, ErrorKind, EscapeError, EscapeErrorKind, ParseEvent,
+    ParseOptions, SyntaxKind, TomlVersion, dom::Document, parse, parse_borrowed, parse_bytes,
+    parse_bytes_lossy, parse_events, parse_with, render, tokenize, unescape, validate_escapes,
+};
+
+fn error_messages(src: &str) -> Vec<String> {
+    parse(src).errors.into_iter().map(|err| err.message).collect()
+}
+
+#[test]
+fn test_uppercase_integer_prefix() {
+    assert_eq!(
+        error_messages("a = 0XFF\nb = 0O17\nc = 0B1_0\n"),
+        vec!["integer prefixes must be lowercase"; 3]
+    );
+    assert_eq!(error_messages("a = 0XYZ\n"), vec!["expected value"]);
+    assert!(error_messages("a = 0xFF\n").is_empty());
+}
+
+#[test]
+fn test_error_kinds() {
+    let parse = parse("a = 01\nb \"c\"\nd = \"\\q\"\ne = [1,,2]\n");
+    assert_eq!(
+        parse
+            .errors()
+            .iter()
+            .map(|err| (err.kind, err.span.clone(), err.message.as_str()))
+            .collect::<Vec<_>>(),
+        [
+            (ErrorKind::InvalidNumber, 4..5, "zero-padded integers are not allowed"),
+            (ErrorKind::ExpectedToken, 9..12, r#"expected "=""#),
+            (ErrorKind::InvalidEscape, 18..20, "unknown escape sequence"),
+            (ErrorKind::UnexpectedToken, 29..30, r#"unexpected ",""#),
+        ]
+    );
+}
+
+#[test]
+fn test_error_codes() {
+    for (idx, kind) in ErrorKind::ALL.iter().enumerate() {
+        assert_eq!(kind.code(), format!("E{:04}", idx + 1));
+        assert_eq!(ErrorKind::from_code(kind.code()), Some(*kind));
+    }
+    assert_eq!(ErrorKind::from_code("E9999"), None);
+
+    assert_eq!(ErrorKind::InvalidEscape.category(), ErrorCategory::Lexer);
+    assert_eq!(ErrorKind::ExpectedValue.category(), ErrorCategory::Parser);
+
+    let errors = parse("a = \n").errors;
+    assert_eq!(errors[0].to_string(), "E0006: expected value (4..5)");
+}
+
+#[test]
+fn test_render_errors() {
+    const SOURCE: &str = "a = 1\n\tb = 01\nc = \"\\q\"\n";
+
+    let errors = parse(SOURCE).errors;
+    assert_eq!(
+        render(SOURCE, Some("Cargo.toml"), &errors),
+        r#"error[E0009]: zero-padded integers are not allowed
+ --> Cargo.toml:2:6
+  |
+2 | 	b = 01
+  | 	    ^
+  = help: numbers can't have leading zeros and underscores must be between digits
+
+error[E0008]: unknown escape sequence
+ --> Cargo.toml:3:6
+  |
+3 | c = "\q"
+  |      ^^
+  = help: the escapes are \b \t \n \f \r \" \\ \uHHHH and \UHHHHHHHH, and \e and \xHH since TOML 1.1, use a literal string to avoid escaping
+"#
+    );
+    assert_eq!(render(SOURCE, None, &[]), "");
+}
+
+#[test]
+fn test_error_recovery() {
+    // The rest of the line is skipped without more errors.
+    let errors = parse("a = \nb = 1\nc = = 2\nd = 3\n");
+    assert_eq!(
+        errors
+            .errors()
+            .iter()
+            .map(|err| (err.span.clone(), err.message.as_str()))
+            .collect::<Vec<_>>(),
+        [(4..5, "expected value"), (15..16, "expected value")]
+    );
+
+    // Unclosed arrays and inline tables end before the next entry or table.
+    let src = "f = [1, 2\ng = 5\n[t]\nh = {x = 1\n[[u]]\ni = [\n  [2,\nj = 6\n";
+    let errors = parse(src);
+    assert_eq!(
+        errors
+            .errors()
+            .iter()
+            .map(|err| (err.span.clone(), err.message.as_str()))
+            .collect::<Vec<_>>(),
+        [
+            (4..9, r#"expected "]""#),
+            (24..30, r#"expected "}""#),
+            (45..48, r#"expected "]""#),
+            (41..48, r#"expected "]""#),
+        ]
+    );
+    assert_eq!(
+        oxc_toml::format(src, Default::default()),
+        "f = [1, 2\ng = 5\n[t]\nh = {x = 1\n[[u]]\ni = [\n  [2,\nj = 6\n"
+    );
+    assert_eq!(
+        oxc_toml::format("f = [1, 2\ng=5\n[t]\nh={x = 1\n[t.u]\nj=6\n", Default::default()),
+        "f = [1, 2\ng = 5\n[t]\nh={x = 1\n[t.u]\nj = 6\n"
+    );
+
+    // A malformed header doesn't change how the lines after it are parsed.
+    let errors = parse("[[t]\n[u]\nk = [1]\n");
+    assert_eq!(errors.errors().iter().map(|err| err.span.start).collect::<Vec<_>>(), [4]);
+}
+
+#[test]
+fn test_error_nodes() {
+    let parse = parse("a = 1\nb = = 2 3\n[t\nc = [1 % 2]\n%\n");
+    let errors = parse
+        .tree
+        .error_nodes()
+        .into_iter()
+        .map(|node| (node.text, node.parent, node.context))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            ("= 2 3", SyntaxKind::ENTRY, 6..15),
+            ("%", SyntaxKind::VALUE, 19..30),
+            ("2", SyntaxKind::ARRAY, 19..30),
+            ("%", SyntaxKind::ROOT, 31..32),
+        ]
+    );
+}
+
+#[test]
+fn test_element_at() {
+    const SOURCE: &str = "a = [1, { b = 2 }]\n";
+    let tree = parse(SOURCE).into_syntax();
+    let element = |offset| tree.element_at(offset).map(|e| (e.kind(), e.text(SOURCE)));
+
+    assert_eq!(element(0), Some((SyntaxKind::IDENT, "a")));
+    assert_eq!(element(1), Some((SyntaxKind::WHITESPACE, " ")));
+    assert_eq!(element(5), Some((SyntaxKind::INTEGER, "1")));
+    assert_eq!(element(10), Some((SyntaxKind::IDENT, "b")));
+    assert_eq!(element(19), Some((SyntaxKind::NEWLINE, "\n")));
+    assert_eq!(element(20), None);
+    assert_eq!(parse("").tree.element_at(0).map(|e| e.kind()), None);
+
+    let node = |start, end| tree.covering_node(start..end).kind();
+    assert_eq!(node(9, 16), SyntaxKind::INLINE_TABLE);
+    assert_eq!(node(10, 15), SyntaxKind::ENTRY);
+    assert_eq!(node(10, 11), SyntaxKind::KEY);
+    assert_eq!(node(5, 10), SyntaxKind::ARRAY);
+    assert_eq!(node(0, 5), SyntaxKind::ENTRY);
+    assert_eq!(node(0, 20), SyntaxKind::ROOT);
+}
+
+#[test]
+fn test_parse_options() {
+    let errors = |src: &str, options: ParseOptions| {
+        parse_with(src, options)
+            .errors
+            .into_iter()
+            .map(|err| (err.kind, err.span, err.message))
+            .collect::<Vec<_>>()
+    };
+
+    let src = "a = { b = 1, }\nc = {\n  d = 1\n}\n";
+    assert_eq!(errors(src, ParseOptions::default()), []);
+    let mut options = ParseOptions::default();
+    options.toml_version = TomlVersion::V1_0;
+    assert_eq!(
+        errors(src, options),
+        [
+            (
+                ErrorKind::UnexpectedToken,
+                13..14,
+                "trailing commas in inline tables require TOML 1.1".into()
+            ),
+            (
+                ErrorKind::UnexpectedToken,
+                20..21,
+                "newlines in inline tables require TOML 1.1".into()
+            ),
+            (
+                ErrorKind::UnexpectedToken,
+                28..29,
+                "newlines in inline tables require TOML 1.1".into()
+            ),
+        ]
+    );
+
+    // A leading comma is an error in both versions.
+    assert_eq!(
+        errors("t = {\n,\n}\n", ParseOptions::default()),
+        [(ErrorKind::UnexpectedToken, 6..7, r#"unexpected ",""#.into())]
+    );
+
+    let src = "dependencies.* = 1\n";
+    assert!(!errors(src, ParseOptions::default()).is_empty());
+    let mut options = ParseOptions::default();
+    options.glob_keys = true;
+    assert_eq!(errors(src, options), []);
+
+    let src = "a = 1\n'a' = 2\n[t]\nb.c = 1\n\"b\".c = 2\n[[u]]\nd = 1\n[[u]]\nd = 1\n";
+    assert_eq!(errors(src, ParseOptions::default()), []);
+    let mut options = ParseOptions::default();
+    options.validate = true;
+    assert_eq!(
+        errors(src, options),
+        [
+            (ErrorKind::DuplicateKey, 6..9, "duplicate key `a`".into()),
+            (ErrorKind::DuplicateKey, 26..31, "duplicate key `t.b.c`".into()),
+        ]
+    );
+    assert_eq!(ErrorKind::DuplicateKey.category(), ErrorCategory::Semantic);
+
+    let src = "a = [[1], {b = [2]}]\nc = [[[3, [4]]]]\nd = 5\n";
+    let mut options = ParseOptions::default();
+    options.max_depth = Some(2);
+    assert_eq!(
+        errors(src, options),
+        [
+            (ErrorKind::NestingTooDeep, 15..16, "too deeply nested value".into()),
+            (ErrorKind::NestingTooDeep, 27..28, "too deeply nested value".into()),
+        ]
+    );
+    assert_eq!(parse_with(src, options).tree.error_nodes()[1].text, "[3, [4]]");
+}
+
+#[test]
+fn test_malformed_keys() {
+    const SOURCE: &str = "a..b = 1\n= 2\nc. = 3\n.d = 4\n\"  \" = 5\n'' = 6\n";
+
+    assert_eq!(
+        parse(SOURCE)
+            .errors
+            .into_iter()
+            .map(|err| (err.kind, err.span, err.message))
+            .collect::<Vec<_>>(),
+        [
+            (ErrorKind::ExpectedKey, 2..3, "expected key between periods".into()),
+            (ErrorKind::ExpectedKey, 9..10, r#"expected key before "=""#.into()),
+            (ErrorKind::ExpectedKey, 16..17, r#"expected key after ".""#.into()),
+            (ErrorKind::ExpectedKey, 20..21, r#"expected key before ".""#.into()),
+        ]
+    );
+
+    let mut options = ParseOptions::default();
+    options.deny_blank_keys = true;
+    assert_eq!(
+        parse_with(SOURCE, options)
+            .errors
+            .into_iter()
+            .skip(4)
+            .map(|err| (err.span, err.message))
+            .collect::<Vec<_>>(),
+        [
+            (27..31, "key is empty or only whitespace".into()),
+            (36..38, "key is empty or only whitespace".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_validate_tables() {
+    let errors = |src: &str| {
+        let mut options = ParseOptions::default();
+        options.validate = true;
+        parse_with(src, options)
+            .errors
+            .into_iter()
+            .map(|err| (err.kind, err.span, err.message))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        errors("[a.b]\n[a]\n[a]\n[[c]]\n[[c]]\n[c]\n"),
+        [
+            (ErrorKind::DuplicateTable, 11..12, "duplicate table `a`".into()),
+            (ErrorKind::DuplicateTable, 27..28, "duplicate table `c`".into()),
+        ]
+    );
+    assert_eq!(
+        errors("[t]\nu.v = 1\n[t.u]\n[t.u.w]\n"),
+        [(ErrorKind::DuplicateTable, 13..16, "duplicate table `t.u`".into())]
+    );
+    assert_eq!(
+        errors("a = 1\nb = { c = 1 }\n[a.x]\n[b]\n[[b]]\n"),
+        [
+            (ErrorKind::DuplicateKey, 21..24, "`a` is already defined as a value".into()),
+            (ErrorKind::DuplicateKey, 27..28, "`b` is already defined as a value".into()),
+            (ErrorKind::DuplicateKey, 32..33, "`b` is already defined as a value".into()),
+        ]
+    );
+    assert_eq!(errors("[[a]]\nb = 1\n[a.c]\n[[a]]\nb = 1\n[a.c]\n[t.u]\n[t]\n"), []);
+}
+
+#[test]
+fn test_validate_dotted_keys() {
+    let errors = |src: &str| {
+        let mut options = ParseOptions::default();
+        options.validate = true;
+        parse_with(src, options)
+            .errors
+            .into_iter()
+            .map(|err| (err.kind, err.span, err.message))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        errors("a = 1\na.b = 2\nc.d = 1\nc.d.e = 2\nc.f = {}\nc.f.g = 3\n"),
+        [
+            (ErrorKind::DuplicateKey, 6..9, "`a` is already defined as a value".into()),
+            (ErrorKind::DuplicateKey, 22..27, "`c.d` is already defined as a value".into()),
+            (ErrorKind::DuplicateKey, 41..46, "`c.f` is already defined as a value".into()),
+        ]
+    );
+    assert_eq!(
+        errors("[a.b.c]\nz = 9\n[a]\nb.c.t = 1\n[[t.d]]\n[t]\nd.y = 2\n"),
+        [
+            (
+                ErrorKind::DuplicateTable,
+                18..23,
+                "table `a.b` is defined by a header, not by dotted keys".into()
+            ),
+            (
+                ErrorKind::DuplicateTable,
+                40..43,
+                "table `t.d` is defined by a header, not by dotted keys".into()
+            ),
+        ]
+    );
+    assert_eq!(
+        errors("[fruit]\napple.color = 1\napple.taste.sweet = true\n[fruit.apple.texture]\n"),
+        []
+    );
+}
+
+#[test]
+fn test_validate_inline_tables() {
+    let errors = |src: &str| {
+        let mut options = ParseOptions::default();
+        options.validate = true;
+        parse_with(src, options)
+            .errors
+            .into_iter()
+            .map(|err| (err.span, err.related, err.message))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        errors(
+            "a = { b = 1, b = 2 }
+c = [{ d.e = 1, d = 2 }, { d.e = 1 }]
+"
+        ),
+        [
+            (13..14, Some(6..7), "duplicate key `b`".into()),
+            (37..38, Some(28..31), "duplicate key `d`".into()),
+        ]
+    );
+    assert_eq!(
+        errors(
+            "a = { b = { c = 1 }, b.d = 2, e = { f = 1, f = 2 } }
+"
+        ),
+        [
+            (21..24, Some(6..7), "`b` is already defined as a value".into()),
+            (43..44, Some(36..37), "duplicate key `f`".into()),
+        ]
+    );
+    assert_eq!(
+        errors(
+            "a = { b.c = 1, b.d = 2 }
+b = { b = 1 }
+"
+        ),
+        []
+    );
+
+    const SOURCE: &str = "a = { b = 1, b = 2 }
+";
+    let mut options = ParseOptions::default();
+    options.validate = true;
+    assert_eq!(
+        render(SOURCE, None, &parse_with(SOURCE, options).errors),
+        "error[E0011]: duplicate key `b`\n --> 1:14\n  |\n1 | a = { b = 1, b = 2 }\n  |              ^\n  = note: see also 1:7\n"
+    );
+}
+
+#[test]
+fn test_parse_bytes() {
+    let source = b"a = \"\xff\"\nb = 1\n\xe2\x82";
+
+    let error = parse_bytes(source, ParseOptions::default()).unwrap_err();
+    assert_eq!(error, EncodingError { offset: 5, len: Some(1) });
+    assert_eq!(error.to_string(), "invalid UTF-8 at byte 5");
+    assert!(parse_bytes(b"a = 1\n", ParseOptions::default()).unwrap().errors.is_empty());
+
+    let parse = parse_bytes_lossy(source, ParseOptions::default());
+    assert_eq!(parse.tree.source(), "a = \"\u{FFFD}\"\nb = 1\n\u{FFFD}");
+    assert_eq!(
+        parse.errors.iter().map(|err| (err.kind, err.span.clone())).collect::<Vec<_>>(),
+        [
+            (ErrorKind::InvalidEncoding, 5..8),
+            (ErrorKind::InvalidEncoding, 16..19),
+            (ErrorKind::UnexpectedToken, 16..19),
+            (ErrorKind::UnexpectedEof, 16..19),
+        ]
+    );
+}
+
+#[test]
+fn test_byte_order_mark() {
+    let parse = parse("\u{FEFF}a = 1\n");
+    assert!(parse.errors.is_empty());
+    assert_eq!(parse.tree.root().children()[0].kind(), SyntaxKind::WHITESPACE);
+    assert_eq!(parse.tree.root().children()[0].span(), &(0..3));
+
+    // Only a byte order mark at the start is skipped.
+    assert_eq!(error_messages("a = 1\n\u{FEFF}b = 2\n"), ["unexpected token"]);
+}
+
+#[test]
+fn test_control_characters() {
+    const SOURCE: &str = "a = \"x\u{1}y\" # \u{7F}\n'k\u{0}' = '''\u{1B}'''\n";
+
+    let errors = parse(SOURCE).errors;
+    assert_eq!(
+        errors.iter().map(|err| (err.span.clone(), err.message.as_str())).collect::<Vec<_>>(),
+        [
+            (6..7, "control character U+0001 is not allowed in strings"),
+            (12..13, "control character U+007F is not allowed in comments"),
+            (16..17, "control character U+0000 is not allowed in literal strings"),
+            (24..25, "control character U+001B is not allowed in literal strings"),
+        ]
+    );
+    assert!(errors.iter().all(|err| err.kind == ErrorKind::InvalidCharacter));
+    assert!(parse("a = \"\t\" # \t\n").errors.is_empty());
+}
+
+#[test]
+fn test_bare_carriage_return() {
+    assert!(parse("a = \"\"\"\r\nx\r\n\"\"\"\r\nb = '''x\r\n'''\r\n").errors.is_empty());
+    assert_eq!(
+        parse("a = \"\"\"x\ry\"\"\"\nb = '''\r'''\nc = 1\r# d\n")
+            .errors
+            .into_iter()
+            .map(|err| (err.span, err.message))
+            .collect::<Vec<_>>(),
+        [
+            (8..9, "control character U+000D is not allowed in strings".into()),
+            (21..22, "control character U+000D is not allowed in literal strings".into()),
+            (31..32, "unexpected token".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_malformed_numbers() {
+    const SOURCE: &str = "a = 01\nb = -001.5\nc = 00\nd = 1.\ne = [1.e5, .5]\n01.23 = 0.0\n";
+
+    assert_eq!(
+        parse(SOURCE).errors.into_iter().map(|err| (err.span, err.message)).collect::<Vec<_>>(),
+        [
+            (4..5, "zero-padded integers are not allowed".into()),
+            (12..14, "zero-padded numbers are not allowed".into()),
+            (22..23, "zero-padded integers are not allowed".into()),
+            (30..31, "expected digits after the decimal point".into()),
+            (38..39, "expected digits after the decimal point".into()),
+            (43..44, "expected digits before the decimal point".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_misplaced_underscores() {
+    const SOURCE: &str = "a = 1_2\nb = 1__2\nc = 1_\nd = 0x_f_\ne = 0b1__0_1\nf = 1_.5_e3\n";
+
+    assert_eq!(
+        parse(SOURCE).errors.into_iter().map(|err| (err.kind, err.span)).collect::<Vec<_>>(),
+        [
+            (ErrorKind::InvalidNumber, 13..15),
+            (ErrorKind::InvalidNumber, 22..23),
+            (ErrorKind::InvalidNumber, 30..31),
+            (ErrorKind::InvalidNumber, 32..33),
+            (ErrorKind::InvalidNumber, 41..43),
+            (ErrorKind::InvalidNumber, 52..53),
+            (ErrorKind::InvalidNumber, 55..56),
+        ]
+    );
+    assert_eq!(error_messages("a = 1_\n"), ["underscores must be between digits"]);
+}
+
+#[test]
+fn test_validate_integers() {
+    const SOURCE: &str = "a = 9_223_372_036_854_775_807\nb = -9223372036854775808\nc = 9223372036854775808\nd = [0x8000000000000000, { e = -9223372036854775809 }]\nf = 0o777777777777777777777\n";
+
+    assert!(parse(SOURCE).errors.is_empty());
+    let mut options = ParseOptions::default();
+    options.validate = true;
+    assert_eq!(
+        parse_with(SOURCE, options)
+            .errors
+            .into_iter()
+            .map(|err| (err.kind, err.span, err.message))
+            .collect::<Vec<_>>(),
+        [
+            (
+                ErrorKind::IntegerOutOfRange,
+                59..78,
+                "integer `9223372036854775808` is out of range for a 64-bit integer".into()
+            ),
+            (
+                ErrorKind::IntegerOutOfRange,
+                84..102,
+                "integer `0x8000000000000000` is out of range for a 64-bit integer".into()
+            ),
+            (
+                ErrorKind::IntegerOutOfRange,
+                110..130,
+                "integer `-9223372036854775809` is out of range for a 64-bit integer".into()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_validate_escapes() {
+    assert_eq!(
+        validate_escapes(
+            r"a\tbé\U0001F600\e\x41\
+    c",
+            TomlVersion::V1_1
+        ),
+        []
+    );
+    assert_eq!(
+        validate_escapes(r"\e \x41", TomlVersion::V1_0),
+        [
+            EscapeError { span: 0..2, kind: EscapeErrorKind::NewerVersion },
+            EscapeError { span: 3..7, kind: EscapeErrorKind::NewerVersion },
+        ]
+    );
+    assert_eq!(
+        validate_escapes(r"\q \uD800 \U00110000 \u12 \x4", TomlVersion::V1_1),
+        [
+            EscapeError { span: 0..2, kind: EscapeErrorKind::Unknown },
+            EscapeError { span: 3..9, kind: EscapeErrorKind::Surrogate },
+            EscapeError { span: 10..20, kind: EscapeErrorKind::InvalidScalar },
+            EscapeError { span: 21..25, kind: EscapeErrorKind::Truncated },
+            EscapeError { span: 26..29, kind: EscapeErrorKind::Truncated },
+        ]
+    );
+    assert_eq!(
+        validate_escapes(r"\u12", TomlVersion::V1_1).first().map(ToString::to_string).as_deref(),
+        Some("escape sequence with too few hex digits (0..4)")
+    );
+
+    assert_eq!(unescape(r"a\tbé\e\x41\\").unwrap(), "a\tb\u{E9}\u{1B}A\\");
+    assert_eq!(
+        unescape(r"ok \uDFFF").unwrap_err(),
+        [EscapeError { span: 3..9, kind: EscapeErrorKind::Surrogate }]
+    );
+}
+
+#[test]
+fn test_toml_1_1_escapes() {
+    const SOURCE: &str = "a = \"\\e[0m \\x41\"\nb = \"\"\"\\xff\"\"\"\nc = \"\\x4\"\n";
+
+    assert_eq!(error_messages(SOURCE), ["escape sequence with too few hex digits"]);
+
+    let mut options = ParseOptions::default();
+    options.toml_version = TomlVersion::V1_0;
+    assert_eq!(
+        parse_with(SOURCE, options)
+            .errors
+            .into_iter()
+            .map(|err| (err.span, err.message))
+            .collect::<Vec<_>>(),
+        [
+            (5..7, r"`\e` and `\xHH` escapes require TOML 1.1".into()),
+            (11..15, r"`\e` and `\xHH` escapes require TOML 1.1".into()),
+            (24..28, r"`\e` and `\xHH` escapes require TOML 1.1".into()),
+            (37..40, "escape sequence with too few hex digits".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_optional_seconds() {
+    const SOURCE: &str = "a = 07:32\nb = 1979-05-27T07:32Z\nc = 1979-05-27 07:32:00.5\n";
+
+    assert!(parse(SOURCE).errors.is_empty());
+
+    let mut options = ParseOptions::default();
+    options.toml_version = TomlVersion::V1_0;
+    assert_eq!(
+        parse_with(SOURCE, options)
+            .errors
+            .into_iter()
+            .map(|err| (err.span, err.message))
+            .collect::<Vec<_>>(),
+        [
+            (4..9, "seconds are required in times before TOML 1.1".into()),
+            (14..31, "seconds are required in times before TOML 1.1".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_date_times() {
+    const SOURCE: &str = "a = 24:00\nb = 07:60:00\nc = 07:32:60\nd = 1979-05-27T23:59:60Z\n\
+                          e = 07:32.5\nf = 1979-05-27T07:32:00+24:00\ng = 07:32:61\n";
+
+    assert_eq!(
+        parse(SOURCE)
+            .errors
+            .into_iter()
+            .map(|err| (err.kind, err.span, err.message))
+            .collect::<Vec<_>>(),
+        [
+            (ErrorKind::InvalidDateTime, 4..6, "hour must be between 00 and 23".into()),
+            (ErrorKind::InvalidDateTime, 17..19, "minute must be between 00 and 59".into()),
+            (
+                ErrorKind::InvalidDateTime,
+                33..35,
+                "leap seconds are only allowed at minute 59".into()
+            ),
+            (ErrorKind::InvalidDateTime, 70..72, "fractional seconds require seconds".into()),
+            (ErrorKind::InvalidDateTime, 97..99, "offset hour must be between 00 and 23".into()),
+            (ErrorKind::InvalidDateTime, 113..115, "second must be between 00 and 60".into()),
+        ]
+    );
+
+    const FRACTION: &str = "a = 07:32:00.1234567891\nb = 1979-05-27T07:32:00.123Z\n";
+    assert!(parse(FRACTION).errors.is_empty());
+
+    let mut options = ParseOptions::default();
+    options.max_fractional_digits = Some(9);
+    assert_eq!(
+        parse_with(FRACTION, options)
+            .errors
+            .into_iter()
+            .map(|err| (err.span, err.message))
+            .collect::<Vec<_>>(),
+        [(22..23, "more than 9 digits of fractional seconds".into())]
+    );
+}
+
+#[test]
+fn test_unicode_bare_keys() {
+    const SOURCE: &str = "ü = 1\nκλειδί.日本 = 2\n[Δ]\n";
+    const MESSAGE: &str = "non-ASCII characters are not allowed in bare keys, use a quoted key";
+
+    // Neither TOML 1.0 nor TOML 1.1 allows them, each key is a single error.
+    for version in [TomlVersion::V1_0, TomlVersion::V1_1] {
+        let mut options = ParseOptions::default();
+        options.toml_version = version;
+        assert_eq!(
+            parse_with(SOURCE, options)
+                .errors
+                .into_iter()
+                .map(|err| (err.span, err.message))
+                .collect::<Vec<_>>(),
+            [
+                (0..2, MESSAGE.into()),
+                (7..19, MESSAGE.into()),
+                (20..26, MESSAGE.into()),
+                (32..34, MESSAGE.into()),
+            ]
+        );
+    }
+    assert_eq!(error_messages("μ = \"greek small letter mu\"\n"), [MESSAGE]);
+    assert!(error_messages("\"ü\" = 1\n'日本' = 2\n").is_empty());
+    // The ideographic space is not a key character.
+    assert_eq!(error_messages("\u{3000}a = 1\n"), ["unexpected token"]);
+}
+
+#[test]
+fn test_parse_borrowed() {
+    let source = String::from("a = 1\n[table]\nb = [\n");
+    let (tree, errors) = parse_borrowed(&source, ParseOptions::default());
+    let owned = parse(&source);
+    assert_eq!(errors, owned.errors);
+    assert!(std::ptr::eq(tree.source(), source.as_str()));
+    assert_eq!(tree.error_nodes(), owned.tree.error_nodes());
+    assert_eq!(tree.element_at(4).map(|e| e.kind()), Some(SyntaxKind::INTEGER));
+    assert_eq!(tree.covering_node(0..5).kind(), SyntaxKind::ENTRY);
+
+    // The borrowed tree is the same type as the owned one.
+    let document = Document::new(&tree);
+    assert!(document.get("a").is_some());
+    assert_eq!(tree.to_value(), owned.tree.to_value());
+    let owned_tree = tree.into_owned();
+    assert!(!std::ptr::eq(owned_tree.source(), source.as_str()));
+    assert_eq!(owned_tree.source(), source);
+}
+
+#[test]
+fn test_long_tokens() {
+    // The lengths cross the 8-byte words that the lexer scans at a time.
+    for len in 1..20 {
+        let text = "x".repeat(len);
+        for src in [
+            format!("a = \"{text}\\\"{text}\\\\\" # {text}\n"),
+            format!("a = '{text}'{}# {text}\n", " \t".repeat(len)),
+            format!("a = \"\"\"{text}\"\"{text}\\\"\"\"\"\"\"\n"),
+            format!("a = '''{text}''{text}'''''\n"),
+            format!("{text}é{text}-_ = 1\r\n"),
+        ] {
+            let parse = parse(&src);
+            // The non-ASCII bare key is still a single token.
+            let errors = usize::from(!src.is_ascii());
+            assert_eq!(parse.errors.len(), errors, "{src:?}: {:?}", parse.errors);
+            let tokens = parse.tree.root().descendants().filter_map(|e| e.as_token());
+            assert_eq!(tokens.map(|t| t.text(&src)).collect::<String>(), src);
+        }
+    }
+
+    let tokens = |src: &str| {
+        let parse = parse(src);
+        let tokens = parse.tree.root().descendants().filter_map(|e| e.as_token());
+        tokens.map(|t| (t.kind(), t.text(src).to_string())).collect::<Vec<_>>()
+    };
+    let src = "a = \"abcdefgh\\\\\" # 12345678\n";
+    assert!(tokens(src).contains(&(SyntaxKind::STRING, "\"abcdefgh\\\\\"".into())));
+    assert!(tokens(src).contains(&(SyntaxKind::COMMENT, "# 12345678".into())));
+}
+
+#[test]
+fn test_tokenize_and_parse_events() {
+    use SyntaxKind::*;
+
+    let src = "\u{FEFF}a = [1, \"b\"] # c\n[t]\n1.2 = 3\n";
+    let tokens = tokenize(src);
+    assert_eq!(
+        tokens
+            .iter()
+            .map(|(_, span)| &src[span.start as usize..span.end as usize])
+            .collect::<String>(),
+        src
+    );
+    assert_eq!(
+        tokens.iter().map(|(kind, _)| *kind).take(8).collect::<Vec<_>>(),
+        [WHITESPACE, IDENT, WHITESPACE, EQ, WHITESPACE, BRACKET_START, INTEGER, COMMA]
+    );
+    assert!(tokens.contains(&(FLOAT, 24..27)));
+    assert_eq!(tokenize("a = ~").last(), Some(&(ERROR, 4..5)));
+
+    let mut options = ParseOptions::default();
+    options.validate = true;
+    for src in [src, "a = 1\na = 2\nb = [1,,\n", ""] {
+        let events = parse_events(src, options);
+        let parse = parse_with(src, options);
+        assert_eq!(events.errors, parse.errors);
+        assert_eq!(events.events.first(), Some(&ParseEvent::Start(ROOT)));
+        assert_eq!(events.events.last(), Some(&ParseEvent::Finish));
+        let tree = events.build_tree(src);
+        assert_eq!(format!("{:?}", tree.root()), format!("{:?}", parse.tree.root()));
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+
+use oxc_toml::{DocumentMut, EditError, Options, PatchError, PatchOperation, Value};
+
+fn string(s: &str) -> Value {
+    Value::String(s.to_string())
+}
+
+const SOURCE: &str = r#"[package]
+name = "app" # The name
+version = "0.1.0"
+keywords = ["cli"]
+
+[dependencies]
+serde = "1.0.100"
+# Logging
+log = "0.4"
+
+[[bin]]
+name = "app"
+"#;
+
+#[test]
+fn test_apply_patch() {
+    let mut doc = DocumentMut::parse(SOURCE, Options::default()).unwrap();
+    let table = BTreeMap::from([("name".to_string(), string("tool"))]);
+    doc.apply_patch(&[
+        PatchOperation::Set { path: "dependencies.serde".into(), value: string("1.0.200") },
+        PatchOperation::Remove { path: "dependencies.log".into() },
+        PatchOperation::Rename { path: "package.name".into(), name: "title".into() },
+        PatchOperation::Append { path: "package.keywords".into(), value: string("toml") },
+        PatchOperation::Append { path: "package.categories".into(), value: string("parsing") },
+        PatchOperation::Append { path: "bin".into(), value: Value::Table(table) },
+    ])
+    .unwrap();
+    assert_eq!(
+        doc.as_str(),
+        r#"[package]
+title = "app" # The name
+version = "0.1.0"
+keywords = ["cli", "toml"]
+categories = ["parsing"]
+
+[dependencies]
+serde = "1.0.200"
+
+[[bin]]
+name = "app"
+
+[[bin]]
+name = "tool"
+"#
+    );
+}
+
+#[test]
+fn test_apply_patch_errors() {
+    let mut doc = DocumentMut::parse(SOURCE, Options::default()).unwrap();
+    let result = doc.apply_patch(&[
+        PatchOperation::Set { path: "package.version".into(), value: string("0.2.0") },
+        PatchOperation::Remove { path: "package.missing".into() },
+    ]);
+    assert_eq!(
+        result,
+        Err(PatchError { index: 1, error: EditError::Missing { path: "package.missing".into() } })
+    );
+    assert_eq!(doc.as_str(), SOURCE);
+
+    let result = doc
+        .apply_patch(&[PatchOperation::Append { path: "package.name".into(), value: string("x") }]);
+    assert_eq!(
+        result,
+        Err(PatchError { index: 0, error: EditError::NotAnArray { path: "package.name".into() } })
+    );
+    let result = doc.apply_patch(&[PatchOperation::Rename { path: "".into(), name: "x".into() }]);
+    assert_eq!(result, Err(PatchError { index: 0, error: EditError::InvalidPath }));
+}
@@ -0,0 +1,102 @@
+use oxc_toml::{ParseOptions, TextEdit, TomlVersion, parse, parse_with};
+
+const SOURCE: &str = r#"# The package
+name = "app"
+tags = ["a", "b"]
+
+[dependencies]
+serde = { version = "1" }
+text = """
+[not.a.table]
+"""
+
+[[bin]]
+name = 'x'
+[bin.extra]
+list = [
+  1,
+  [2],
+]
+"#;
+
+/// Reparse after every edit at every offset and compare with parsing the new source.
+#[test]
+fn test_reparse_matches_parse() {
+    let texts = [
+        "", "\"", "'''", "\"\"\"", "[", "]", "]]", "{", "}", "\n", "\r\n", "#", "a.b", "= ",
+        "x = 1\n", "[t]\n", "[[t]]\n",
+    ];
+    for offset in 0..=SOURCE.len() {
+        for len in [0, 1, 2, 10] {
+            let end = (offset + len).min(SOURCE.len());
+            for text in texts {
+                if len == 0 && text.is_empty() {
+                    continue;
+                }
+                let mut new_source = SOURCE.to_string();
+                new_source.replace_range(offset..end, text);
+
+                let mut reparsed = parse(SOURCE);
+                let range = offset as u32..end as u32;
+                reparsed.reparse(TextEdit { range, new_text: text.into() });
+                let expected = parse(&new_source);
+                assert_eq!(reparsed.tree.source(), new_source);
+                assert_eq!(
+                    format!("{:?}", reparsed.tree.root()),
+                    format!("{:?}", expected.tree.root()),
+                    "{new_source:?}"
+                );
+                assert_eq!(reparsed.errors, expected.errors, "{new_source:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_reparse_section() {
+    let mut tree = parse(SOURCE).into_syntax();
+    let offset = SOURCE.find("'x'").unwrap() as u32;
+    let reparse = tree.reparse(TextEdit { range: offset..offset + 3, new_text: "= 2".into() });
+
+    // Only the section of the edit is parsed again.
+    let start = SOURCE.find("[[bin]]").unwrap() as u32;
+    let end = SOURCE.find("[bin.extra]").unwrap() as u32;
+    assert_eq!(reparse.range, start..end);
+    assert_eq!(reparse.errors.len(), 1);
+    assert_eq!(reparse.errors[0].message, "expected value");
+    assert!(tree.source().contains("name = = 2\n[bin.extra]"));
+
+    // An array that is not closed changes the sections after it.
+    let offset = SOURCE.find("\"b\"]").unwrap() as u32 + 3;
+    let reparse = tree.reparse(TextEdit { range: offset..offset + 1, new_text: String::new() });
+    assert_eq!(reparse.range, 0..tree.source().len() as u32);
+}
+
+/// Reparse with the options of the tree and check the semantic errors again.
+#[test]
+fn test_reparse_options() {
+    let mut options = ParseOptions::default();
+    options.toml_version = TomlVersion::V1_0;
+    let source = "a = 1\n\n[t]\nb = 2\n";
+    let mut reparsed = parse_with(source, options);
+    let offset = source.find("2").unwrap() as u32 + 1;
+    reparsed.reparse(TextEdit { range: offset..offset, new_text: "\nc = { q = 1, }".into() });
+    let expected = parse_with(reparsed.tree.source(), options);
+    assert_eq!(reparsed.tree.options, options);
+    assert_eq!(reparsed.errors.len(), 1);
+    assert_eq!(reparsed.errors, expected.errors);
+
+    let mut options = ParseOptions::default();
+    options.validate = true;
+    let source = "[a]\nx = 1\n\n[b]\ny = 2\n";
+    let mut reparsed = parse_with(source, options);
+    let offset = source.find("[b]").unwrap() as u32;
+    reparsed.reparse(TextEdit { range: offset..offset + 3, new_text: "[a]".into() });
+    let expected = parse_with(reparsed.tree.source(), options);
+    assert_eq!(reparsed.errors.len(), 1);
+    assert_eq!(reparsed.errors, expected.errors);
+
+    // The duplicate is gone again after the next edit.
+    reparsed.reparse(TextEdit { range: offset..offset + 3, new_text: "[c]".into() });
+    assert!(reparsed.errors.is_empty());
+}
@@ -0,0 +1,193 @@
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use oxc_toml::{Datetime, LineEnding, Options, TomlVersion};
+
+#[test]
+fn test_deserialize_options() {
+    let options: Options = toml::from_str(
+        r#"
+align_entries = true
+line_ending = "crlf"
+expand_dotted_keys = 3
+reorder_tables = true
+table_priority = ["package"]
+toml_version = "1.0"
+"#,
+    )
+    .unwrap();
+
+    let expected = Options::builder()
+        .align_entries(true)
+        .line_ending(LineEnding::Crlf)
+        .expand_dotted_keys(Some(3))
+        .reorder_tables(true)
+        .table_priority(["package"])
+        .toml_version(TomlVersion::V1_0)
+        .build()
+        .unwrap();
+    assert_eq!(options, expected);
+
+    let serialized = toml::to_string(&options).unwrap();
+    assert_eq!(toml::from_str::<Options>(&serialized).unwrap(), options);
+}
+
+#[test]
+fn test_unknown_fields() {
+    let deserializer =
+        toml::Deserializer::parse("align_entrys = true\ncolumn_width = 100\n").unwrap();
+    let (options, unknown_fields) = Options::deserialize_with_unknown_fields(deserializer).unwrap();
+    assert_eq!(options, Options::default());
+    assert_eq!(unknown_fields, ["align_entrys"]);
+}
+
+#[test]
+fn test_from_str() {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Kind {
+        Lib,
+        Bin { name: String },
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Target {
+        kind: Kind,
+        path: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Manifest {
+        name: String,
+        version: (u8, u8, u8),
+        released: Datetime,
+        features: BTreeMap<String, Vec<String>>,
+        target: Vec<Target>,
+    }
+
+    let manifest: Manifest = oxc_toml::from_str(
+        r#"
+name = "app"
+version = [1, 2, 3]
+released = 1979-05-27T07:32:00Z
+features.default = ["std"]
+
+[[target]]
+kind = "lib"
+path = 'src/lib.rs'
+
+[[target]]
+kind = { bin = { name = "app" } }
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(manifest.name, "app");
+    assert_eq!(manifest.version, (1, 2, 3));
+    assert_eq!(manifest.released.to_string(), "1979-05-27T07:32:00Z");
+    assert_eq!(manifest.features["default"], ["std"]);
+    assert_eq!(
+        manifest.target,
+        [
+            Target { kind: Kind::Lib, path: Some("src/lib.rs".into()) },
+            Target { kind: Kind::Bin { name: "app".into() }, path: None },
+        ]
+    );
+}
+
+#[test]
+fn test_from_str_errors() {
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        width: u8,
+        name: String,
+    }
+
+    let error = oxc_toml::from_str::<Config>("width = 300\nname = \"a\"\n").unwrap_err();
+    assert_eq!(error.span, Some(8..11));
+    assert!(error.message.contains("300"), "{error}");
+
+    let error = oxc_toml::from_str::<Config>("width = 1\nname = [\"a\"]\n").unwrap_err();
+    assert_eq!(error.span, Some(17..22));
+
+    let error = oxc_toml::from_str::<Config>("width = 1\nwidth = 2\n").unwrap_err();
+    assert_eq!(error.span, Some(10..15));
+    assert_eq!(error.message, "duplicate key `width`");
+
+    let error = oxc_toml::from_str::<Config>("width = 1\n").unwrap_err();
+    assert_eq!(error.span, None);
+    assert_eq!(error.message, "missing field `name`");
+}
+
+#[test]
+fn test_to_string() {
+    #[derive(serde::Serialize)]
+    struct Dependency {
+        version: &'static str,
+        features: Vec<&'static str>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Package {
+        name: &'static str,
+        released: Datetime,
+        description: Option<&'static str>,
+        ratio: f32,
+        metadata: BTreeMap<&'static str, BTreeMap<&'static str, bool>>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Manifest {
+        package: Package,
+        dependencies: BTreeMap<&'static str, Dependency>,
+        bin: Vec<BTreeMap<&'static str, &'static str>>,
+    }
+
+    let manifest = Manifest {
+        package: Package {
+            name: "app \"x\"",
+            released: "1979-05-27T07:32:00Z".parse().unwrap(),
+            description: None,
+            ratio: 0.1,
+            metadata: BTreeMap::from([("docs.rs", BTreeMap::from([("all-features", true)]))]),
+        },
+        dependencies: BTreeMap::from([(
+            "serde",
+            Dependency { version: "1", features: vec!["derive", "std"] },
+        )]),
+        bin: vec![BTreeMap::from([("name", "a")]), BTreeMap::from([("name", "b")])],
+    };
+
+    let options = Options::cargo();
+    let serialized = oxc_toml::to_string(&manifest, options.clone()).unwrap();
+    assert_eq!(
+        serialized,
+        r#"[package]
+name = "app \"x\""
+released = 1979-05-27T07:32:00Z
+ratio = 0.1
+
+[package.metadata."docs.rs"]
+all-features = true
+
+[dependencies.serde]
+version = "1"
+features = ["derive", "std"]
+
+[[bin]]
+name = "a"
+
+[[bin]]
+name = "b"
+"#
+    );
+    assert_eq!(oxc_toml::format(&serialized, options), serialized);
+
+    let error = oxc_toml::to_string(&[1, 2], Options::default()).unwrap_err();
+    assert_eq!(error.message, "only tables can be serialized as documents");
+    let error = oxc_toml::to_string(&BTreeMap::from([("a", [None, Some(1)])]), Options::default())
+        .unwrap_err();
+    assert_eq!(error.message, "arrays can't contain `None`");
+}
@@ -1,7 +1,9 @@
 use std::fs;
 use std::path::Path;
 
-use oxc_toml::{Options, format, parse};
+use oxc_toml::{
+    Options, ParseOptions, SyntaxTree, TomlVersion, Value, format, format_stream, parse, parse_with,
+};
 use walkdir::WalkDir;
 
 const TOML_TEST_DIR: &str = "toml-test/tests";
@@ -31,89 +33,42 @@ fn values_equal(a: &toml::Value, b: &toml::Value) -> bool {
 }
 
 /// Files that the parser accepts but shouldn't according to the spec
-/// These require semantic validation which is not implemented:
-/// - Duplicate key detection
-/// - Table redefinition/overwrite detection  
-/// - Dotted key vs table conflict detection
-///
-/// Some files are TOML 1.1 features that were invalid in TOML 1.0
 const SKIP_INVALID: &[&str] = &[
-    "array/extend-defined-aot.toml",
-    "array/extending-table.toml",
-    "array/tables-01.toml",
-    "array/tables-02.toml",
-    "control/multi-cr.toml",
-    "control/rawmulti-cr.toml",
-    "inline-table/duplicate-key-01.toml",
-    "inline-table/duplicate-key-02.toml",
-    "inline-table/duplicate-key-03.toml",
-    "inline-table/duplicate-key-04.toml",
-    "inline-table/overwrite-01.toml",
-    "inline-table/overwrite-02.toml",
-    "inline-table/overwrite-03.toml",
-    "inline-table/overwrite-04.toml",
-    "inline-table/overwrite-05.toml",
-    "inline-table/overwrite-06.toml",
-    "inline-table/overwrite-07.toml",
-    "inline-table/overwrite-08.toml",
-    "inline-table/overwrite-09.toml",
-    "inline-table/overwrite-10.toml",
-    // TOML 1.1.0 allows these features that were invalid in 1.0
-    "inline-table/empty-03.toml", // Empty inline tables with newlines
-    "inline-table/linebreak-01.toml", // Newlines in inline tables
-    "inline-table/linebreak-02.toml", // Newlines in inline tables
-    "inline-table/linebreak-03.toml", // Newlines in inline tables
-    "inline-table/linebreak-04.toml", // Newlines in inline tables
-    "inline-table/trailing-comma.toml", // Trailing commas in inline tables
-    "key/dotted-redefine-table-01.toml",
-    "key/dotted-redefine-table-02.toml",
-    "key/duplicate-keys-01.toml",
-    "key/duplicate-keys-02.toml",
-    "key/duplicate-keys-03.toml",
-    "key/duplicate-keys-04.toml",
-    "key/duplicate-keys-05.toml",
-    "key/duplicate-keys-06.toml",
-    "key/duplicate-keys-07.toml",
-    "key/duplicate-keys-08.toml",
-    "key/duplicate-keys-09.toml",
     "spec-1.0.0/inline-table-2-0.toml",
     "spec-1.0.0/inline-table-3-0.toml",
     "spec-1.0.0/table-9-0.toml",
     "spec-1.0.0/table-9-1.toml",
-    "spec-1.1.0/common-46-0.toml",
-    "spec-1.1.0/common-46-1.toml",
-    "spec-1.1.0/common-49-0.toml",
-    "spec-1.1.0/common-50-0.toml",
-    "table/append-with-dotted-keys-01.toml",
-    "table/append-with-dotted-keys-02.toml",
-    "table/append-with-dotted-keys-03.toml",
-    "table/append-with-dotted-keys-04.toml",
-    "table/append-with-dotted-keys-05.toml",
-    "table/append-with-dotted-keys-06.toml",
     "table/append-with-dotted-keys-07.toml",
     "table/append-with-dotted-keys-08.toml",
-    "table/array-implicit.toml",
-    "table/duplicate-key-01.toml",
-    "table/duplicate-key-02.toml",
-    "table/duplicate-key-03.toml",
-    "table/duplicate-key-04.toml",
-    "table/duplicate-key-05.toml",
-    "table/duplicate-key-06.toml",
-    "table/duplicate-key-07.toml",
-    "table/duplicate-key-08.toml",
-    "table/duplicate-key-09.toml",
-    "table/duplicate-key-10.toml",
     "table/duplicate-key-11.toml",
     "table/duplicate-key-12.toml",
     "table/duplicate-key-13.toml",
     "table/duplicate-key-14.toml",
-    "table/overwrite-array-in-parent.toml",
-    "table/overwrite-bool-with-array.toml",
-    "table/overwrite-with-deep-table.toml",
-    "table/redefine-01.toml",
-    "table/redefine-02.toml",
-    "table/redefine-03.toml",
-    "table/super-twice.toml",
+];
+
+/// Files of the TOML 1.0 test suite with features that TOML 1.1 allows,
+/// they are parsed as TOML 1.0
+const INVALID_1_0: &[&str] = &[
+    "inline-table/linebreak-01.toml",   // Newlines in inline tables
+    "inline-table/linebreak-02.toml",   // Newlines in inline tables
+    "inline-table/linebreak-03.toml",   // Newlines in inline tables
+    "inline-table/linebreak-04.toml",   // Newlines in inline tables
+    "inline-table/trailing-comma.toml", // Trailing commas in inline tables
+];
+
+/// Valid files with features of TOML 1.1, they must not parse as TOML 1.0
+const VALID_1_1: &[&str] = &[
+    "datetime/no-seconds.toml",
+    "inline-table/newline-comment.toml",
+    "inline-table/newline.toml",
+    "key/empty-05.toml",
+    "spec-1.1.0/common-12.toml",
+    "spec-1.1.0/common-29.toml",
+    "spec-1.1.0/common-31.toml",
+    "spec-1.1.0/common-34.toml",
+    "spec-1.1.0/common-47.toml",
+    "string/escape-esc.toml",
+    "string/hex-escape.toml",
 ];
 
 fn should_skip(path: &Path, skip_list: &[&str]) -> bool {
@@ -136,6 +91,20 @@ fn toml_files(dir: &str) -> impl Iterator<Item = walkdir::DirEntry> {
 
 #[test]
 fn test_valid_idempotent() {
+    check_valid_idempotent(&Options::default());
+}
+
+#[test]
+fn test_valid_idempotent_collapsed() {
+    check_valid_idempotent(&Options::builder().collapse_tables(true).build().unwrap());
+    check_valid_idempotent(
+        &Options::builder().inline_small_tables(Some(4)).inline_array_tables(true).build().unwrap(),
+    );
+}
+
+/// Format the valid files twice and check that the second pass changes nothing
+/// and that the first one keeps the values.
+fn check_valid_idempotent(options: &Options) {
     let mut idempotent_failures = Vec::new();
     let mut semantic_failures = Vec::new();
     let mut panics = Vec::new();
@@ -146,8 +115,8 @@ fn test_valid_idempotent() {
         let source = fs::read_to_string(path).unwrap();
 
         let result = std::panic::catch_unwind(|| {
-            let first = format(&source, Options::default());
-            let second = format(&first, Options::default());
+            let first = format(&source, options.clone());
+            let second = format(&first, options.clone());
 
             // Test 1: Idempotency
             let is_idempotent = first == second;
@@ -200,6 +169,59 @@ fn test_valid_idempotent() {
     assert!(panics.is_empty() && idempotent_failures.is_empty() && semantic_failures.is_empty());
 }
 
+/// Convert a value to the value of the `toml` crate to compare them.
+fn to_toml(value: &Value) -> toml::Value {
+    match value {
+        Value::String(s) => toml::Value::String(s.clone()),
+        Value::Integer(i) => toml::Value::Integer(*i),
+        Value::Float(f) => toml::Value::Float(*f),
+        Value::Bool(b) => toml::Value::Boolean(*b),
+        Value::Datetime(datetime) => toml::Value::Datetime(datetime.to_string().parse().unwrap()),
+        Value::Array(array) => toml::Value::Array(array.iter().map(to_toml).collect()),
+        Value::Table(table) => toml::Value::Table(
+            table.iter().map(|(key, value)| (key.clone(), to_toml(value))).collect(),
+        ),
+    }
+}
+
+/// Write the omitted seconds of date-times as zero, which is what they mean.
+fn with_seconds(value: toml::Value) -> toml::Value {
+    match value {
+        toml::Value::Datetime(datetime) => {
+            let datetime = datetime.to_string().parse::<oxc_toml::Datetime>().unwrap();
+            toml::Value::Datetime(datetime.to_string().parse().unwrap())
+        }
+        toml::Value::Array(array) => {
+            toml::Value::Array(array.into_iter().map(with_seconds).collect())
+        }
+        toml::Value::Table(table) => toml::Value::Table(
+            table.into_iter().map(|(key, value)| (key, with_seconds(value))).collect(),
+        ),
+        value => value,
+    }
+}
+
+#[test]
+fn test_valid_to_value() {
+    let mut failures = Vec::new();
+
+    for entry in toml_files("valid") {
+        let path = entry.path();
+        let source = fs::read_to_string(path).unwrap();
+        // Skip the files that the toml crate can't parse.
+        let Ok(expected) = toml::from_str::<toml::Value>(&source) else {
+            continue;
+        };
+
+        let value = to_toml(&parse(&source).into_syntax().to_value());
+        if !values_equal(&value, &with_seconds(expected)) {
+            failures.push(path.to_path_buf());
+        }
+    }
+
+    assert!(failures.is_empty(), "Values differ from the toml crate for:\n{failures:#?}");
+}
+
 #[test]
 fn test_invalid_parse_failure() {
     let mut failures = Vec::new();
@@ -214,7 +236,12 @@ fn test_invalid_parse_failure() {
             continue; // Skip non-UTF-8 files
         };
 
-        let result = parse(&source);
+        let mut options = ParseOptions::default();
+        options.validate = true;
+        if should_skip(path, INVALID_1_0) {
+            options.toml_version = TomlVersion::V1_0;
+        }
+        let result = parse_with(&source, options);
 
         if result.errors.is_empty() {
             failures.push(path.to_path_buf());
@@ -223,3 +250,64 @@ fn test_invalid_parse_failure() {
 
     assert!(failures.is_empty(), "Expected parse errors for:\n{failures:#?}");
 }
+
+#[test]
+fn test_valid_1_1_parse_failure_in_1_0() {
+    let mut options = ParseOptions::default();
+    options.toml_version = TomlVersion::V1_0;
+
+    let mut failures = Vec::new();
+    for entry in toml_files("valid") {
+        let path = entry.path();
+        if !should_skip(path, VALID_1_1) {
+            continue;
+        }
+
+        let source = fs::read_to_string(path).unwrap();
+        if parse_with(&source, options).errors.is_empty() {
+            failures.push(path.to_path_buf());
+        }
+    }
+
+    assert!(failures.is_empty(), "Expected TOML 1.0 parse errors for:\n{failures:#?}");
+}
+
+#[test]
+fn test_valid_from_value() {
+    let mut failures = Vec::new();
+
+    for entry in toml_files("valid") {
+        let path = entry.path();
+        let source = fs::read_to_string(path).unwrap();
+        let value = parse(&source).into_syntax().to_value();
+
+        let tree = SyntaxTree::from_value(&value, Options::default());
+        // The tagged JSON compares NaN like other floats.
+        if tree.to_value().to_tagged_json() != value.to_tagged_json() {
+            failures.push(path.to_path_buf());
+        }
+    }
+
+    assert!(failures.is_empty(), "Values of written documents differ for:\n{failures:#?}");
+}
+
+#[test]
+fn test_format_stream() {
+    let mut failures = Vec::new();
+
+    for entry in toml_files("valid").chain(toml_files("invalid")) {
+        let path = entry.path();
+        let Ok(source) = fs::read_to_string(path) else {
+            continue;
+        };
+
+        let mut output = Vec::new();
+        let reader = std::io::BufReader::with_capacity(7, source.as_bytes());
+        format_stream(reader, &mut output, Options::default()).unwrap();
+        if output != format(&source, Options::default()).as_bytes() {
+            failures.push(path.to_path_buf());
+        }
+    }
+
+    assert!(failures.is_empty(), "Streamed output differs for:\n{failures:#?}");
+}
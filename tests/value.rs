@@ -0,0 +1,100 @@
+use oxc_toml::{Datetime, Offset, Options, SyntaxTree, Value, parse};
+
+#[test]
+fn test_to_value() {
+    const SOURCE: &str = "a.b = \"\\u00e9\\t\"\nc = \"\"\"\nx \\\n\n   y\"\"\"\nd = 0xff_ff\ne = -1_000.5e1\n\
+                          f = 1979-05-27 07:32:00.1234567891-07:30\ng = 99999999999999999999\n\
+                          [[h]]\ni = [true, 07:32]\n";
+
+    let value = parse(SOURCE).into_syntax().to_value();
+    assert_eq!(value.get("a").and_then(|a| a.get("b")), Some(&Value::String("é\t".into())));
+    assert_eq!(value.get("c").and_then(Value::as_str), Some("x y"));
+    assert_eq!(value.get("d").and_then(Value::as_integer), Some(0xffff));
+    assert_eq!(value.get("e").and_then(Value::as_float), Some(-10_005.0));
+    assert_eq!(value.get("g"), None);
+
+    let f = value.get("f").and_then(Value::as_datetime).unwrap();
+    assert_eq!(f.time.unwrap().nanosecond, 123_456_789);
+    assert_eq!(f.offset, Some(Offset::Custom { minutes: -450 }));
+    assert_eq!(f.to_string(), "1979-05-27T07:32:00.123456789-07:30");
+
+    let h = value.get("h").and_then(Value::as_array).unwrap();
+    let i = h[0].get("i").and_then(Value::as_array).unwrap();
+    assert_eq!(i[0], Value::Bool(true));
+    assert_eq!(i[1].as_datetime().map(Datetime::to_string).as_deref(), Some("07:32:00"));
+
+    assert!("1979-05-27X07:32".parse::<Datetime>().is_err());
+    assert!("07:3".parse::<Datetime>().is_err());
+}
+
+#[test]
+fn test_to_tagged_json() {
+    const SOURCE: &str = "a = \"x\\\"\\n\"\nb = [1, 2.5, inf]\nc = { d = true }\n\
+                          e = [1979-05-27T07:32:00Z, 1979-05-27T07:32:00, 1979-05-27, 07:32:00]\n";
+
+    let json = parse(SOURCE).into_syntax().to_value().to_tagged_json();
+    assert_eq!(
+        json,
+        concat!(
+            r#"{"a":{"type":"string","value":"x\"\n"},"#,
+            r#""b":[{"type":"integer","value":"1"},{"type":"float","value":"2.5"},{"type":"float","value":"inf"}],"#,
+            r#""c":{"d":{"type":"bool","value":"true"}},"#,
+            r#""e":[{"type":"datetime","value":"1979-05-27T07:32:00Z"},{"type":"datetime-local","value":"1979-05-27T07:32:00"},"#,
+            r#"{"type":"date-local","value":"1979-05-27"},{"type":"time-local","value":"07:32:00"}]}"#,
+        )
+    );
+}
+
+#[test]
+fn test_from_tagged_json() {
+    let json = r#" {
+        "a": {"type": "string", "value": "x\"é😀"},
+        "b": [{"value": "-inf", "type": "float"}, {"type": "integer", "value": "1"}],
+        "c": {"type": {"type": "bool", "value": "true"}, "value": []}
+    } "#;
+    let value = Value::from_tagged_json(json).unwrap();
+    assert_eq!(value.get("a").and_then(Value::as_str), Some("x\"é😀"));
+    assert_eq!(value.get("b").and_then(Value::as_array).unwrap()[1], Value::Integer(1));
+    assert_eq!(value.get("c").and_then(|c| c.get("type")), Some(&Value::Bool(true)));
+    assert_eq!(Value::from_tagged_json(&value.to_tagged_json()), Ok(value));
+
+    let error = Value::from_tagged_json(r#"{"a": {"type": "integer", "value": "x"}}"#).unwrap_err();
+    assert_eq!((error.offset, error.message.as_str()), (6, "invalid integer `x`"));
+    let error = Value::from_tagged_json(r#"{"a": {"type": "date-local", "value": "07:32:00"}}"#)
+        .unwrap_err();
+    assert_eq!(error.message, "invalid date-local `07:32:00`");
+    assert_eq!(Value::from_tagged_json("[] x").unwrap_err().offset, 3);
+}
+
+#[test]
+fn test_from_value() {
+    let json = r#"{
+        "name": {"type": "string", "value": "a b"},
+        "owner": {"dob": {"type": "datetime", "value": "1979-05-27T07:32:00-08:00"}},
+        "servers": {"alpha": {"ip": {"type": "string", "value": "10.0.0.1"}}},
+        "points": [[{"x": {"type": "integer", "value": "1"}}]],
+        "products": [{"sku": {"type": "integer", "value": "738594937"}}, {}],
+        "key with spaces": {"type": "bool", "value": "false"}
+    }"#;
+    let value = Value::from_tagged_json(json).unwrap();
+    let tree = SyntaxTree::from_value(&value, Options::default());
+    assert_eq!(
+        tree.source,
+        r#""key with spaces" = false
+name = "a b"
+points = [[{ x = 1 }]]
+
+[owner]
+dob = 1979-05-27T07:32:00-08:00
+
+[[products]]
+sku = 738594937
+
+[[products]]
+
+[servers.alpha]
+ip = "10.0.0.1"
+"#
+    );
+    assert_eq!(tree.to_value(), value);
+}
@@ -0,0 +1,85 @@
+use oxc_toml::{
+    Replacement, Rewrite, SyntaxKind, SyntaxNode, SyntaxToken, Value, Visit, VisitFlow, parse,
+};
+
+const SOURCE: &str = r#"# The package
+name = "app"
+tags = ["a", "b"] # tags
+
+[dependencies]
+serde = { version = "1" }
+"#;
+
+#[derive(Default)]
+struct Collect {
+    events: Vec<String>,
+    comments: usize,
+}
+
+impl Visit for Collect {
+    fn enter(&mut self, node: &SyntaxNode, source: &str) -> VisitFlow {
+        match node.kind() {
+            SyntaxKind::KEY => self.events.push(format!("key {}", node.text(source).trim())),
+            // The entries of the inline table are skipped.
+            SyntaxKind::INLINE_TABLE => return VisitFlow::SkipChildren,
+            SyntaxKind::TABLE_HEADER => return VisitFlow::Stop,
+            _ => {}
+        }
+        VisitFlow::Continue
+    }
+
+    fn leave(&mut self, node: &SyntaxNode, _: &str) {
+        if node.kind() == SyntaxKind::ARRAY {
+            self.events.push("array".into());
+        }
+    }
+
+    fn token(&mut self, token: &SyntaxToken, _: &str) {
+        if token.kind() == SyntaxKind::COMMENT {
+            self.comments += 1;
+        }
+    }
+}
+
+#[test]
+fn test_visit() {
+    let tree = parse(SOURCE).into_syntax();
+    let mut visitor = Collect::default();
+    tree.visit(&mut visitor);
+    assert_eq!(visitor.events, ["key name", "key tags", "array"]);
+    assert_eq!(visitor.comments, 2);
+}
+
+struct Bump;
+
+impl Rewrite for Bump {
+    fn rewrite_node(&mut self, node: &SyntaxNode, source: &str) -> Option<Replacement> {
+        match node.kind() {
+            SyntaxKind::ARRAY => Some(Replacement::Value(Value::Array(Vec::new()))),
+            SyntaxKind::INLINE_TABLE => Some(Replacement::Node(parse("\"2\"").into_syntax())),
+            SyntaxKind::KEY if node.text(source) == "name " => {
+                Some(Replacement::Text("title ".into()))
+            }
+            _ => None,
+        }
+    }
+
+    fn rewrite_token(&mut self, token: &SyntaxToken, _: &str) -> Option<Replacement> {
+        (token.kind() == SyntaxKind::COMMENT).then(|| Replacement::Text("#".into()))
+    }
+}
+
+#[test]
+fn test_rewrite() {
+    let tree = parse(SOURCE).into_syntax();
+    assert_eq!(
+        tree.rewrite(&mut Bump),
+        r#"#
+title = "app"
+tags = [] #
+
+[dependencies]
+serde = "2"
+"#
+    );
+}